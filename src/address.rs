@@ -0,0 +1,1045 @@
+use crate::{
+    cashaddr, coins,
+    error::HdiFinderError,
+    ethereum,
+    models::{AddressTarget, Coin, DerivedAddress},
+};
+use bitcoin::{
+    bech32::{self, u5, FromBase32, ToBase32, Variant},
+    blockdata::script::Script,
+    hashes::{
+        hex::{FromHex, ToHex},
+        Hash,
+    },
+    network::constants::Network,
+    schnorr,
+    util::address::{self as btc_address, Payload},
+    util::base58,
+    util::bip32::ExtendedPubKey,
+    util::taproot::TapTweakHash,
+    Address,
+};
+use hdpath::Purpose;
+use secp256k1::{All, Secp256k1};
+use std::str::FromStr;
+use crate::derivation::ZERO_WIDTH_CHARS;
+#[cfg(test)]
+use {
+    crate::derivation::{coin_type, coin_type_for, get_mnemonic, get_private_key, get_public_key},
+    crate::models::CoinDefinition,
+    hdpath::AccountHDPath,
+};
+
+/**
+ * Retrieves the address type standard for a derivation purpose:
+ * p2pkh for BIP44, p2shwpkh for BIP49, p2wpkh for BIP84 and p2tr for BIP86
+ */
+pub fn address_type(purpose: &Purpose) -> &'static str {
+    match purpose {
+        Purpose::ScriptHash => "p2shwpkh",
+        Purpose::Witness => "p2wpkh",
+        Purpose::Custom(86) => "p2tr",
+        _ => "p2pkh",
+    }
+}
+
+/**
+ * Type of the p2pkh addresses of uncompressed public keys, the ones of the
+ * wallets predating key compression
+ */
+pub const P2PKH_UNCOMPRESSED: &str = "p2pkh-uncompressed";
+
+/**
+ * Retrieves the derivation purpose an address type is standard for
+ */
+pub fn type_purpose(address_type: &str) -> Option<Purpose> {
+    match address_type {
+        "p2pkh" => Some(Purpose::Pubkey),
+        "p2shwpkh" => Some(Purpose::ScriptHash),
+        "p2wpkh" => Some(Purpose::Witness),
+        "p2tr" => Some(Purpose::Custom(86)),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_type_purpose(){
+    for purpose in [Purpose::Pubkey, Purpose::ScriptHash, Purpose::Witness, Purpose::Custom(86)] {
+        assert_eq!(type_purpose(address_type(&purpose)), Some(purpose));
+    }
+    assert_eq!(type_purpose("p2wsh"), None);
+}
+
+/**
+ * Builds the address of a public key in the type standard for a derivation purpose.
+ * Only hashes the key, the address still has to be encoded.
+ */
+pub fn build_address(secp: &Secp256k1<All>, pubkey: ExtendedPubKey, purpose: &Purpose, network: Network) -> Result<Address, HdiFinderError> {
+    match purpose {
+        Purpose::ScriptHash => Address::p2shwpkh(&pubkey.public_key, network)
+            .map_err(|e| HdiFinderError::InvalidAddress(format!("Unable to build a p2shwpkh address: {}", e))),
+        Purpose::Witness => Address::p2wpkh(&pubkey.public_key, network)
+            .map_err(|e| HdiFinderError::InvalidAddress(format!("Unable to build a p2wpkh address: {}", e))),
+        Purpose::Custom(86) => self::taproot_address(secp, pubkey, network),
+        _ => Ok(Address::p2pkh(&pubkey.public_key, network)),
+    }
+}
+
+/**
+ * Computes the address of a public key in the type standard for a derivation purpose
+ */
+pub fn address_compute(secp: &Secp256k1<All>, pubkey: ExtendedPubKey, purpose: &Purpose, network: Network) -> Result<DerivedAddress, HdiFinderError> {
+    self::coin_address_compute(secp, pubkey, purpose, &self::address_params(Coin::Bitcoin, network))
+}
+
+/**
+ * Same as `address_compute`, encoding the address with the parameters of a
+ * coin, which may not have the address type of the purpose
+ */
+pub fn coin_address_compute(
+    secp: &Secp256k1<All>,
+    pubkey: ExtendedPubKey,
+    purpose: &Purpose,
+    params: &AddressParams,
+) -> Result<DerivedAddress, HdiFinderError> {
+    if !params.supports(purpose) {
+        return Err(HdiFinderError::InvalidAddress(format!("The coin has no {} addresses", self::address_type(purpose))));
+    }
+    if params.encoding == Encoding::Ethereum {
+        return Ok(DerivedAddress {
+            address_type: self::coin_address_type(purpose, params),
+            address: ethereum::address(&pubkey.public_key.key),
+        });
+    }
+    // The network of the payload is left aside, the parameters telling its encoding
+    Ok(DerivedAddress {
+        address_type: self::address_type(purpose),
+        address: self::encode_address(&self::build_address(secp, pubkey, purpose, Network::Bitcoin)?, params),
+    })
+}
+
+/**
+ * Retrieves the address type a coin derives for a purpose, an Ethereum
+ * account being the one type of its coin
+ */
+pub fn coin_address_type(purpose: &Purpose, params: &AddressParams) -> &'static str {
+    match params.encoding {
+        Encoding::Script => self::address_type(purpose),
+        Encoding::Ethereum => "ethereum",
+    }
+}
+
+/**
+ * What the addresses of a coin are computed from: the output script they
+ * pay to, or the public key itself for the Ethereum accounts, which have
+ * no script
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Script,
+    Ethereum,
+}
+
+/**
+ * Encoding of the addresses of a coin on a network: the versions of its
+ * base58 addresses, a version above 0xff being encoded on two bytes as the
+ * Zcash ones, the human readable part of its bech32 ones when it
+ * has segwit, the purposes of the address types it has, and the prefix of
+ * its cashaddr ones when it encodes its base58 types so
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AddressParams {
+    pub p2pkh: u16,
+    pub p2sh: u16,
+    pub hrp: Option<&'static str>,
+    pub purposes: &'static [Purpose],
+    pub cashaddr: Option<&'static str>,
+    pub encoding: Encoding,
+}
+
+impl AddressParams {
+    /**
+     * Tells whether the coin has the address type standard for a purpose
+     */
+    pub fn supports(&self, purpose: &Purpose) -> bool {
+        self.purposes.contains(purpose)
+    }
+}
+
+const ALL_PURPOSES: &[Purpose] = &[Purpose::Pubkey, Purpose::ScriptHash, Purpose::Witness, Purpose::Custom(86)];
+
+const SEGWIT_PURPOSES: &[Purpose] = &[Purpose::Pubkey, Purpose::ScriptHash, Purpose::Witness];
+
+const LEGACY_PURPOSES: &[Purpose] = &[Purpose::Pubkey];
+
+/**
+ * Retrieves the encoding of the addresses of a coin on a network. Signet
+ * shares the encoding of testnet, and regtest reuses its base58 prefixes.
+ */
+pub fn address_params(coin: Coin, network: Network) -> AddressParams {
+    let (p2pkh, p2sh, hrp) = match (coin, network) {
+        (Coin::Bitcoin, Network::Bitcoin) => (0x00, 0x05, Some("bc")),
+        (Coin::Bitcoin, Network::Regtest) => (0x6f, 0xc4, Some("bcrt")),
+        (Coin::Bitcoin, _) => (0x6f, 0xc4, Some("tb")),
+        (Coin::Litecoin, Network::Bitcoin) => (0x30, 0x32, Some("ltc")),
+        (Coin::Litecoin, Network::Regtest) => (0x6f, 0x3a, Some("rltc")),
+        (Coin::Litecoin, _) => (0x6f, 0x3a, Some("tltc")),
+        (Coin::Dogecoin, Network::Bitcoin) => (0x1e, 0x16, None),
+        (Coin::Dogecoin, Network::Regtest) => (0x6f, 0xc4, None),
+        (Coin::Dogecoin, _) => (0x71, 0xc4, None),
+        // The legacy encoding of Bitcoin Cash, still read from targets
+        (Coin::BitcoinCash, Network::Bitcoin) => (0x00, 0x05, None),
+        (Coin::BitcoinCash, _) => (0x6f, 0xc4, None),
+        // No base58 encoding, the version bytes are left unused
+        (Coin::Ethereum, _) => (0x00, 0x05, None),
+        (Coin::Defined(definition), _) => (definition.p2pkh, definition.p2sh, definition.hrp),
+    };
+    let purposes = match coin {
+        Coin::Bitcoin => ALL_PURPOSES,
+        Coin::Litecoin => SEGWIT_PURPOSES,
+        Coin::Dogecoin | Coin::BitcoinCash | Coin::Ethereum => LEGACY_PURPOSES,
+        Coin::Defined(definition) => definition.purposes,
+    };
+    let cashaddr = match (coin, network) {
+        (Coin::BitcoinCash, Network::Bitcoin) => Some("bitcoincash"),
+        (Coin::BitcoinCash, Network::Regtest) => Some("bchreg"),
+        (Coin::BitcoinCash, _) => Some("bchtest"),
+        _ => None,
+    };
+    AddressParams {
+        p2pkh,
+        p2sh,
+        hrp,
+        purposes,
+        cashaddr,
+        encoding: if coin == Coin::Ethereum { Encoding::Ethereum } else { Encoding::Script },
+    }
+}
+
+/**
+ * Retrieves the bytes a base58 version is encoded in, two of them for the
+ * versions above 0xff
+ */
+fn version_bytes(version: u16) -> Vec<u8> {
+    if version > 0xff {
+        version.to_be_bytes().to_vec()
+    } else {
+        vec![version as u8]
+    }
+}
+
+/**
+ * Encodes an address with the parameters of a coin, its payload being the
+ * same for every coin
+ */
+pub fn encode_address(address: &Address, params: &AddressParams) -> String {
+    match (&address.payload, params.cashaddr) {
+        (Payload::PubkeyHash(hash), Some(prefix)) => return cashaddr::encode(prefix, cashaddr::P2PKH, &hash[..]),
+        (Payload::ScriptHash(hash), Some(prefix)) => return cashaddr::encode(prefix, cashaddr::P2SH, &hash[..]),
+        _ => {}
+    }
+    match &address.payload {
+        Payload::PubkeyHash(hash) => base58::check_encode_slice(&[&self::version_bytes(params.p2pkh)[..], &hash[..]].concat()),
+        Payload::ScriptHash(hash) => base58::check_encode_slice(&[&self::version_bytes(params.p2sh)[..], &hash[..]].concat()),
+        Payload::WitnessProgram { version, program } => {
+            let variant = if version.to_u8() == 0 { Variant::Bech32 } else { Variant::Bech32m };
+            let mut data = vec![*version];
+            data.extend(program.to_base32());
+            let hrp = params.hrp.expect("the coin of a segwit address has a human readable part");
+            bech32::encode(hrp, data, variant).expect("the human readable part is valid")
+        }
+    }
+}
+
+/**
+ * Decodes the payload of an address encoded with the parameters of a coin.
+ * A cashaddr can be given without its prefix, and the legacy base58
+ * encoding of the coin is read as well.
+ */
+fn decode_with(address: &str, params: &AddressParams) -> Option<Payload> {
+    if params.encoding == Encoding::Ethereum {
+        return None;
+    }
+    if let Some(Ok((kind, hash))) = params.cashaddr.map(|prefix| cashaddr::decode(address, prefix)) {
+        return match kind {
+            cashaddr::P2PKH => Some(Payload::PubkeyHash(bitcoin::PubkeyHash::from_slice(&hash).ok()?)),
+            cashaddr::P2SH => Some(Payload::ScriptHash(bitcoin::ScriptHash::from_slice(&hash).ok()?)),
+            _ => None,
+        };
+    }
+    if let Ok(bytes) = base58::from_check(address) {
+        let hash = |version: u16| bytes.strip_prefix(&self::version_bytes(version)[..]).filter(|hash| hash.len() == 20);
+        if let Some(hash) = hash(params.p2pkh) {
+            return Some(Payload::PubkeyHash(bitcoin::PubkeyHash::from_slice(hash).ok()?));
+        }
+        return Some(Payload::ScriptHash(bitcoin::ScriptHash::from_slice(hash(params.p2sh)?).ok()?));
+    }
+    let (hrp, data, _) = bech32::decode(address).ok()?;
+    if Some(hrp.as_str()) != params.hrp {
+        return None;
+    }
+    let (version, program) = data.split_first()?;
+    Some(Payload::WitnessProgram {
+        version: *version,
+        program: Vec::<u8>::from_base32(program).ok()?,
+    })
+}
+
+/**
+ * Decodes an address of any of the supported coins, along with the coin it
+ * is encoded for. The prefixes shared between coins are read as Bitcoin
+ * ones, and the error is the one of the Bitcoin decoding.
+ */
+pub fn decode_address(address: &str) -> Result<(Address, Coin), btc_address::Error> {
+    let e = match Address::from_str(address) {
+        Ok(decoded) => return Ok((decoded, Coin::Bitcoin)),
+        Err(e) => e,
+    };
+    for coin in [Coin::Litecoin, Coin::Dogecoin, Coin::BitcoinCash] {
+        for network in [Network::Bitcoin, Network::Testnet, Network::Regtest] {
+            if let Some(payload) = self::decode_with(address, &self::address_params(coin, network)) {
+                return Ok((Address { payload, network }, coin));
+            }
+        }
+    }
+    Err(e)
+}
+
+#[test]
+fn test_litecoin_addresses(){
+    let secp = Secp256k1::new();
+    // First receiving addresses of the BIP39 test vector under the Litecoin coin type
+    let test_seed = get_mnemonic("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about", None).unwrap().to_seed("");
+    let expected_results = [
+        (Purpose::Pubkey, "LUWPbpM43E2p7ZSh8cyTBEkvpHmr3cB8Ez"),
+        (Purpose::ScriptHash, "M7wtsL7wSHDBJVMWWhtQfTMSYYkyooAAXM"),
+        (Purpose::Witness, "ltc1qjmxnz78nmc8nq77wuxh25n2es7rzm5c2rkk4wh"),
+    ];
+    for (purpose, expected) in expected_results.iter(){
+        let hd_path = AccountHDPath::new(purpose.clone(), coin_type_for(Coin::Litecoin, Network::Bitcoin), 0).address_at(0, 0).unwrap();
+        let public_key = get_public_key(&secp, get_private_key(test_seed, &hd_path, Network::Bitcoin).unwrap());
+        let address = coin_address_compute(&secp, public_key, purpose, &address_params(Coin::Litecoin, Network::Bitcoin)).unwrap();
+        assert_eq!(&address.address, expected);
+
+        // decoded back to the same payload
+        let (decoded, coin) = decode_address(expected).unwrap();
+        assert_eq!(coin, Coin::Litecoin);
+        assert_eq!(decoded, build_address(&secp, public_key, purpose, Network::Bitcoin).unwrap());
+    }
+    let hd_path = AccountHDPath::new(Purpose::Custom(86), 2, 0).address_at(0, 0).unwrap();
+    let public_key = get_public_key(&secp, get_private_key(test_seed, &hd_path, Network::Bitcoin).unwrap());
+    assert!(coin_address_compute(&secp, public_key, &Purpose::Custom(86), &address_params(Coin::Litecoin, Network::Bitcoin)).is_err());
+
+    assert_eq!(decode_address("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK").unwrap().1, Coin::Bitcoin);
+    assert_eq!(decode_target("ltc1qjmxnz78nmc8nq77wuxh25n2es7rzm5c2rkk4wh").unwrap().address_type, Some("p2wpkh"));
+    // a broken checksum stays an error
+    assert!(decode_address("LUWPbpM43E2p7ZSh8cyTBEkvpHmr3cB8Ey").is_err());
+    assert!(decode_address("ltc1qjmxnz78nmc8nq77wuxh25n2es7rzm5c2rkk4wq").is_err());
+}
+
+#[test]
+fn test_dogecoin_addresses(){
+    let secp = Secp256k1::new();
+    let test_seed = get_mnemonic("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about", None).unwrap().to_seed("");
+    let params = address_params(Coin::Dogecoin, Network::Bitcoin);
+    let hd_path = AccountHDPath::new(Purpose::Pubkey, coin_type_for(Coin::Dogecoin, Network::Bitcoin), 0).address_at(0, 0).unwrap();
+    let public_key = get_public_key(&secp, get_private_key(test_seed, &hd_path, Network::Bitcoin).unwrap());
+    assert_eq!(coin_address_compute(&secp, public_key, &Purpose::Pubkey, &params).unwrap().address, "DBus3bamQjgJULBJtYXpEzDWQRwF5iwxgC");
+    // no segwit
+    for purpose in [Purpose::ScriptHash, Purpose::Witness, Purpose::Custom(86)] {
+        assert!(!params.supports(&purpose));
+        assert_eq!(
+            coin_address_compute(&secp, public_key, &purpose, &params).unwrap_err().to_string(),
+            format!("The coin has no {} addresses", address_type(&purpose))
+        );
+    }
+    assert_eq!(standard_addresses(&secp, public_key, Network::Bitcoin, Coin::Dogecoin).unwrap().len(), 1);
+
+    let (decoded, coin) = decode_address("DBus3bamQjgJULBJtYXpEzDWQRwF5iwxgC").unwrap();
+    assert_eq!((decoded.network, coin), (Network::Bitcoin, Coin::Dogecoin));
+    // a p2sh address, which no derived type is encoded in
+    assert_eq!(decode_address("9yD3AjCTjHyHvs4chBsGMz3DNPshJXyL3i").unwrap().1, Coin::Dogecoin);
+    assert_eq!(decode_target("9yD3AjCTjHyHvs4chBsGMz3DNPshJXyL3i").unwrap().address_type, Some("p2sh"));
+    assert_eq!(decode_address("naxvmcKgLi92MJkVvNBGVPooeJKY4wHDxY").unwrap().0.network, Network::Testnet);
+}
+
+/**
+ * Rewrites a target in the encoding the search derives for the coin, which
+ * compares the addresses as strings: a legacy or unprefixed Bitcoin Cash
+ * address becomes the cashaddr of the same payload. The other targets are
+ * kept as given.
+ */
+pub fn canonical_target(address: &str, params: &AddressParams) -> String {
+    if params.encoding == Encoding::Ethereum {
+        return ethereum::checksummed(address).unwrap_or_else(|_| address.to_string());
+    }
+    match (params.cashaddr, self::decode_with(address, params)) {
+        (Some(_), Some(payload)) => self::encode_address(
+            &Address {
+                payload,
+                network: Network::Bitcoin,
+            },
+            params,
+        ),
+        // The segwit addresses of a defined coin, which `normalize_target` doesn't know
+        (None, Some(Payload::WitnessProgram { .. })) => address.to_lowercase(),
+        _ => address.to_string(),
+    }
+}
+
+#[test]
+fn test_bitcoin_cash_addresses(){
+    let secp = Secp256k1::new();
+    let test_seed = get_mnemonic("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about", None).unwrap().to_seed("");
+    let params = address_params(Coin::BitcoinCash, Network::Bitcoin);
+    let hd_path = AccountHDPath::new(Purpose::Pubkey, coin_type_for(Coin::BitcoinCash, Network::Bitcoin), 0).address_at(0, 0).unwrap();
+    let public_key = get_public_key(&secp, get_private_key(test_seed, &hd_path, Network::Bitcoin).unwrap());
+    let cashaddr = "bitcoincash:qqyx49mu0kkn9ftfj6hje6g2wfer34yfnq5tahq3q6";
+    assert_eq!(coin_address_compute(&secp, public_key, &Purpose::Pubkey, &params).unwrap().address, cashaddr);
+    assert!(!params.supports(&Purpose::Witness));
+
+    // The prefixless, uppercase and legacy forms of the address stand for the same payload
+    assert_eq!(canonical_target("qqyx49mu0kkn9ftfj6hje6g2wfer34yfnq5tahq3q6", &params), cashaddr);
+    assert_eq!(canonical_target(&normalize_target("BITCOINCASH:QQYX49MU0KKN9FTFJ6HJE6G2WFER34YFNQ5TAHQ3Q6"), &params), cashaddr);
+    assert_eq!(canonical_target("1mW6fDEMjKrDHvLvoEsaeLxSCzZBf3Bfg", &params), cashaddr);
+    assert_eq!(canonical_target("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", &address_params(Coin::Bitcoin, Network::Bitcoin)), "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+
+    let (decoded, coin) = decode_address(cashaddr).unwrap();
+    assert_eq!((decoded.network, coin), (Network::Bitcoin, Coin::BitcoinCash));
+    assert_eq!(decode_address("bchtest:pr6m7j9njldwwzlg9v7v53unlr4jkmx6eyvwc0uz5t").unwrap().0.network, Network::Testnet);
+    assert_eq!(decode_target(cashaddr).unwrap().address_type, Some("p2pkh"));
+    assert!(is_address_on_network("1mW6fDEMjKrDHvLvoEsaeLxSCzZBf3Bfg", Network::Bitcoin, Coin::BitcoinCash));
+    assert!(!is_address_on_network(cashaddr, Network::Bitcoin, Coin::Bitcoin));
+    assert!(!is_address_on_network(cashaddr, Network::Testnet, Coin::BitcoinCash));
+}
+
+/**
+ * Tells the coin an address is encoded for, if it decodes at all
+ */
+pub fn address_coin(address: &str) -> Option<Coin> {
+    if ethereum::is_address(address) {
+        return Some(Coin::Ethereum);
+    }
+    self::decode_address(address).ok().map(|(_, coin)| coin)
+}
+
+#[test]
+fn test_ethereum_addresses(){
+    let secp = Secp256k1::new();
+    let test_seed = get_mnemonic("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about", None).unwrap().to_seed("");
+    let params = address_params(Coin::Ethereum, Network::Bitcoin);
+    let hd_path = AccountHDPath::new(Purpose::Pubkey, coin_type_for(Coin::Ethereum, Network::Bitcoin), 0).address_at(0, 0).unwrap();
+    let public_key = get_public_key(&secp, get_private_key(test_seed, &hd_path, Network::Bitcoin).unwrap());
+    let derived = coin_address_compute(&secp, public_key, &Purpose::Pubkey, &params).unwrap();
+    assert_eq!(derived.address, "0x9858EfFD232B4033E47d90003D41EC34EcaEda94");
+    assert_eq!(derived.address_type, "ethereum");
+    assert!(coin_address_compute(&secp, public_key, &Purpose::Witness, &params).is_err());
+
+    // Targets are compared checksummed, whatever their case
+    assert_eq!(canonical_target("0x9858effd232b4033e47d90003d41ec34ecaeda94", &params), derived.address);
+    assert_eq!(decode_target("0x9858EFFD232B4033E47D90003D41EC34ECAEDA94").unwrap().address_type, Some("ethereum"));
+    assert_eq!(
+        decode_target("0x9858EfFD232B4033E47d90003D41EC34EcaEda9A").unwrap_err().to_string(),
+        "Invalid address 0x9858EfFD232B4033E47d90003D41EC34EcaEda9A: EIP-55 checksum mismatch, expected 0x9858EFFd232B4033E47D90003d41ec34eCaeDA9a"
+    );
+    assert_eq!(address_coin(&derived.address), Some(Coin::Ethereum));
+    assert!(is_address_on_network(&derived.address, Network::Testnet, Coin::Ethereum));
+    assert!(!is_address_on_network(&derived.address, Network::Bitcoin, Coin::Bitcoin));
+    assert!(!is_address_on_network("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", Network::Bitcoin, Coin::Ethereum));
+}
+
+#[test]
+fn test_address_compute(){
+    let secp = Secp256k1::new();
+let test_mnemonic_phrase: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let test_seed = get_mnemonic(test_mnemonic_phrase, None).unwrap().to_seed("");
+
+    let hd_path= AccountHDPath::new(Purpose::Pubkey, 0, 0).address_at(0,5).unwrap();
+    let private_key = get_private_key(test_seed, &hd_path, Network::Bitcoin).unwrap();
+
+    let public_key = get_public_key(&secp, private_key);
+    
+    let expected_results = [
+        [
+            "p2pkh",
+            "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"
+        ],
+        [
+            "p2wpkh",
+            "bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y"
+        ],
+        [
+            "p2shwpkh",
+            "39gFyg2s6bp5AwwqtCrH7iNqRBh664LnZg"
+
+        ]
+    ];
+    let purposes = [Purpose::Pubkey, Purpose::Witness, Purpose::ScriptHash];
+    for (i,purpose) in purposes.iter().enumerate(){
+        let address = address_compute(&secp, public_key, purpose, Network::Bitcoin).unwrap();
+        assert_eq!(expected_results[i][0],address.address_type);
+        assert_eq!(expected_results[i][1],address.address);
+         
+    }
+}
+
+#[test]
+fn test_build_address_uncompressed(){
+    let secp = Secp256k1::new();
+    let test_mnemonic_phrase: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let test_seed = get_mnemonic(test_mnemonic_phrase, None).unwrap().to_seed("");
+    let hd_path = AccountHDPath::new(Purpose::Pubkey, 0, 0).address_at(0, 5).unwrap();
+    let mut public_key = get_public_key(&secp, get_private_key(test_seed, &hd_path, Network::Bitcoin).unwrap());
+    let compressed = address_compute(&secp, public_key, &Purpose::Pubkey, Network::Bitcoin).unwrap();
+    assert_eq!(compressed.address, "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    public_key.public_key.compressed = false;
+
+    // The same key hashed uncompressed pays to another address
+    let uncompressed = address_compute(&secp, public_key, &Purpose::Pubkey, Network::Bitcoin).unwrap();
+    assert_eq!(uncompressed.address, "1J4Lx1AMWEhp1jMFNLZ8jNgmxHW2ibwDxJ");
+    assert_eq!(uncompressed.address_type, "p2pkh");
+
+    // segwit outputs can't commit to an uncompressed key
+    for purpose in [Purpose::ScriptHash, Purpose::Witness] {
+        match build_address(&secp, public_key, &purpose, Network::Bitcoin) {
+            Err(HdiFinderError::InvalidAddress(reason)) => assert!(reason.starts_with("Unable to build a p2")),
+            _ => panic!("expected an invalid address error"),
+        }
+    }
+}
+
+#[test]
+fn test_address_compute_testnet(){
+    let secp = Secp256k1::new();
+    let test_mnemonic_phrase: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let test_seed = get_mnemonic(test_mnemonic_phrase, None).unwrap().to_seed("");
+
+    let hd_path= AccountHDPath::new(Purpose::Pubkey, coin_type(Network::Testnet), 0).address_at(0,5).unwrap();
+    let private_key = get_private_key(test_seed, &hd_path, Network::Testnet).unwrap();
+
+    let public_key = get_public_key(&secp, private_key);
+
+    assert_eq!(address_compute(&secp, public_key, &Purpose::Pubkey, Network::Testnet).unwrap().address, "mpj5LUAeqPiLH6shh8KuQoymnVVNMc6yPe");
+    assert_eq!(address_compute(&secp, public_key, &Purpose::Witness, Network::Testnet).unwrap().address, "tb1qv5zqqm8plkx9hzp485pnze0aljx6eg9js46dzq");
+    assert_eq!(address_compute(&secp, public_key, &Purpose::ScriptHash, Network::Testnet).unwrap().address, "2N4hE9AuxQKdATK3nKfqq7QfyQKYUG1ksTR");
+}
+
+/**
+ * Computes the BIP86 taproot address of a public key.
+ * The internal key is tweaked without script tree as described in BIP341.
+ */
+pub fn taproot_address(secp: &Secp256k1<All>, pubkey: ExtendedPubKey, network: Network) -> Result<Address, HdiFinderError> {
+    let serialized = pubkey.public_key.key.serialize();
+    let mut output_key = schnorr::PublicKey::from_slice(&serialized[1..])
+        .map_err(|e| HdiFinderError::InvalidAddress(format!("Invalid taproot internal key: {}", e)))?;
+    let tweak = TapTweakHash::hash(&output_key.serialize());
+    output_key
+        .tweak_add_assign(secp, &tweak)
+        .map_err(|e| HdiFinderError::InvalidAddress(format!("Unable to tweak the taproot internal key: {}", e)))?;
+
+    Ok(Address {
+        payload: Payload::WitnessProgram {
+            version: u5::try_from_u8(1)
+                .map_err(|e| HdiFinderError::InvalidAddress(format!("Invalid witness version: {}", e)))?,
+            program: output_key.serialize().to_vec(),
+        },
+        network,
+    })
+}
+
+#[test]
+fn test_taproot_address(){
+    let secp = Secp256k1::new();
+    // Test vectors from BIP86, the internal key of each address along with the
+    // output key its script pays to. BIP86 stops at the first change address,
+    // the second one being checked against an independent implementation.
+    let test_mnemonic_phrase: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    let test_seed = get_mnemonic(test_mnemonic_phrase, None).unwrap().to_seed("");
+
+    let expected_results = [
+        (
+            0,
+            0,
+            "cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115",
+            "a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c",
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr",
+        ),
+        (
+            0,
+            1,
+            "83dfe85a3151d2517290da461fe2815591ef69f2b18a2ce63f01697a8b313145",
+            "a82f29944d65b86ae6b5e5cc75e294ead6c59391a1edc5e016e3498c67fc7bbb",
+            "bc1p4qhjn9zdvkux4e44uhx8tc55attvtyu358kutcqkudyccelu0was9fqzwh",
+        ),
+        (
+            1,
+            0,
+            "399f1b2f4393f29a18c937859c5dd8a77350103157eb880f02e8c08214277cef",
+            "882d74e5d0572d5a816cef0041a96b6c1de832f6f9676d9605c44d5e9a97d3dc",
+            "bc1p3qkhfews2uk44qtvauqyr2ttdsw7svhkl9nkm9s9c3x4ax5h60wqwruhk7",
+        ),
+        (
+            1,
+            1,
+            "da01548c72c04619d079ec0118a48bda801cc9391b00de5c846bfdc9807905bd",
+            "5b51a7a072b157100300c08355fc8488abe20cc59dfe39436e8e814ef95fbb47",
+            "bc1ptdg60grjk9t3qqcqczp4tlyy3z47yrx9nhlrjsmw36q5a72lhdrs9f00nj",
+        ),
+    ];
+    for (change, index, internal_key, output_key, expected) in expected_results.iter(){
+        let hd_path = AccountHDPath::new(Purpose::Custom(86), 0, 0).address_at(*change, *index).unwrap();
+        let private_key = get_private_key(test_seed, &hd_path, Network::Bitcoin).unwrap();
+        let public_key = get_public_key(&secp, private_key);
+        assert_eq!(public_key.public_key.key.serialize()[1..].to_hex(), *internal_key);
+
+        let address = taproot_address(&secp, public_key, Network::Bitcoin).unwrap();
+        assert_eq!(address.script_pubkey().to_hex(), format!("5120{}", output_key));
+        assert_eq!(&address.to_string(), expected);
+        assert_eq!(address_compute(&secp, public_key, &Purpose::Custom(86), Network::Bitcoin).unwrap().address, *expected);
+    }
+}
+
+#[test]
+fn test_address_compute_purposes(){
+    let secp = Secp256k1::new();
+    let test_mnemonic_phrase: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    let test_seed = get_mnemonic(test_mnemonic_phrase, None).unwrap().to_seed("");
+
+    // First receiving address of each standard from BIP44, BIP49, BIP84 and BIP86
+    let expected_results = [
+        (Purpose::Pubkey, "p2pkh", "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA"),
+        (Purpose::ScriptHash, "p2shwpkh", "37VucYSaXLCAsxYyAPfbSi9eh4iEcbShgf"),
+        (Purpose::Witness, "p2wpkh", "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"),
+        (Purpose::Custom(86), "p2tr", "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"),
+    ];
+    for (purpose, address_type, expected) in expected_results.iter(){
+        let hd_path = AccountHDPath::new(purpose.clone(), 0, 0).address_at(0, 0).unwrap();
+        let private_key = get_private_key(test_seed, &hd_path, Network::Bitcoin).unwrap();
+        let public_key = get_public_key(&secp, private_key);
+
+        let address = address_compute(&secp, public_key, purpose, Network::Bitcoin).unwrap();
+        assert_eq!(&address.address_type, address_type);
+        assert_eq!(&address.address, expected);
+    }
+
+    // First change address from BIP84
+    let hd_path = AccountHDPath::new(Purpose::Witness, 0, 0).address_at(1, 0).unwrap();
+    let public_key = get_public_key(&secp, get_private_key(test_seed, &hd_path, Network::Bitcoin).unwrap());
+    assert_eq!(address_compute(&secp, public_key, &Purpose::Witness, Network::Bitcoin).unwrap().address, "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el");
+}
+
+/**
+ * Checks the provided address can be derived on the selected network of a coin,
+ * that is it decodes with the encoding of the coin on that network.
+ * Addresses that can't be decoded are left to the search itself.
+ */
+pub fn is_address_on_network(address: &str, network: Network, coin: Coin) -> bool {
+    // An Ethereum address is the same on every network
+    if ethereum::is_address(address) {
+        return coin == Coin::Ethereum;
+    }
+    if self::decode_address(address).is_err() {
+        return true;
+    }
+    self::decode_with(address, &self::address_params(coin, network)).is_some()
+}
+
+#[test]
+fn test_is_address_on_network(){
+    assert!(is_address_on_network("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", Network::Bitcoin, Coin::Bitcoin));
+    assert!(is_address_on_network("bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y", Network::Bitcoin, Coin::Bitcoin));
+    assert!(!is_address_on_network("bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y", Network::Testnet, Coin::Bitcoin));
+    assert!(!is_address_on_network("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", Network::Regtest, Coin::Bitcoin));
+
+    assert!(is_address_on_network("tb1qv5zqqm8plkx9hzp485pnze0aljx6eg9js46dzq", Network::Testnet, Coin::Bitcoin));
+    assert!(is_address_on_network("tb1qv5zqqm8plkx9hzp485pnze0aljx6eg9js46dzq", Network::Signet, Coin::Bitcoin));
+    assert!(!is_address_on_network("tb1qv5zqqm8plkx9hzp485pnze0aljx6eg9js46dzq", Network::Regtest, Coin::Bitcoin));
+    assert!(!is_address_on_network("tb1qv5zqqm8plkx9hzp485pnze0aljx6eg9js46dzq", Network::Bitcoin, Coin::Bitcoin));
+
+    assert!(is_address_on_network("mjKaX8gzTZM5UXVgP6LGp8tCPLvudvk6EY", Network::Testnet, Coin::Bitcoin));
+    assert!(is_address_on_network("mjKaX8gzTZM5UXVgP6LGp8tCPLvudvk6EY", Network::Regtest, Coin::Bitcoin));
+    assert!(is_address_on_network("bcrt1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk554dfr7", Network::Regtest, Coin::Bitcoin));
+    assert!(!is_address_on_network("bcrt1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk554dfr7", Network::Testnet, Coin::Bitcoin));
+
+    assert!(is_address_on_network("ltc1qjmxnz78nmc8nq77wuxh25n2es7rzm5c2rkk4wh", Network::Bitcoin, Coin::Litecoin));
+    assert!(!is_address_on_network("ltc1qjmxnz78nmc8nq77wuxh25n2es7rzm5c2rkk4wh", Network::Bitcoin, Coin::Bitcoin));
+    assert!(!is_address_on_network("LUWPbpM43E2p7ZSh8cyTBEkvpHmr3cB8Ez", Network::Testnet, Coin::Litecoin));
+    assert!(!is_address_on_network("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", Network::Bitcoin, Coin::Litecoin));
+    assert!(is_address_on_network("mjKaX8gzTZM5UXVgP6LGp8tCPLvudvk6EY", Network::Testnet, Coin::Litecoin));
+
+    assert!(is_address_on_network("DBus3bamQjgJULBJtYXpEzDWQRwF5iwxgC", Network::Bitcoin, Coin::Dogecoin));
+    assert!(!is_address_on_network("DBus3bamQjgJULBJtYXpEzDWQRwF5iwxgC", Network::Bitcoin, Coin::Litecoin));
+    assert!(!is_address_on_network("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", Network::Bitcoin, Coin::Dogecoin));
+    assert!(is_address_on_network("naxvmcKgLi92MJkVvNBGVPooeJKY4wHDxY", Network::Testnet, Coin::Dogecoin));
+    assert!(!is_address_on_network("mjKaX8gzTZM5UXVgP6LGp8tCPLvudvk6EY", Network::Testnet, Coin::Dogecoin));
+}
+
+/**
+ * Brings a target address as pasted by a user to the form the derived
+ * addresses are compared in: without surrounding whitespace or invisible
+ * characters, and lowercase when it is an all-uppercase bech32 address, as
+ * QR codes encode them. Mixed case is left as is, bech32 rejecting it.
+ */
+pub fn normalize_target(address: &str) -> String {
+    let address: String = address.chars().filter(|c| !ZERO_WIDTH_CHARS.contains(c)).collect();
+    let address = address.trim();
+    match self::decode_address(address) {
+        Ok((Address { payload: Payload::WitnessProgram { .. }, .. }, _)) | Ok((_, Coin::BitcoinCash)) => address.to_lowercase(),
+        _ => address.to_string(),
+    }
+}
+
+#[test]
+fn test_normalize_target(){
+    assert_eq!(normalize_target("BC1Q9XUUQJDZ920RKCS0KVNMQH0T4ANMGTK5U60H0Y"), "bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y");
+    assert_eq!(normalize_target(" 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK\u{200B}\n"), "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    assert_eq!(normalize_target("\u{FEFF}TB1QV5ZQQM8PLKX9HZP485PNZE0ALJX6EG9JS46DZQ\t"), "tb1qv5zqqm8plkx9hzp485pnze0aljx6eg9js46dzq");
+    // Mixed case stays an error
+    assert_eq!(normalize_target("bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtK5u60h0y"), "bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtK5u60h0y");
+    assert!(decode_target(&normalize_target("bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtK5u60h0y")).is_err());
+}
+
+/**
+ * Decodes a target address, telling the address type the search computes
+ * for it, if any, and the network its encoding stands for.
+ * A mistyped address is rejected along with the decoding error, a scan
+ * for it could never succeed.
+ */
+pub fn decode_target(address: &str) -> Result<AddressTarget, HdiFinderError> {
+    if ethereum::is_address(address) {
+        ethereum::checksummed(address).map_err(|e| HdiFinderError::InvalidAddress(format!("Invalid address {}: {}", address, e)))?;
+        return Ok(AddressTarget {
+            address_type: Some("ethereum"),
+            network: Network::Bitcoin,
+            label: None,
+            script: None,
+        });
+    }
+    let (decoded, coin) = self::decode_address(address).map_err(|e| HdiFinderError::InvalidAddress(format!("Invalid address {}: {}", address, e)))?;
+    Ok(AddressTarget {
+        address_type: self::payload_type(&decoded.payload, &self::address_params(coin, decoded.network)),
+        network: decoded.network,
+        label: None,
+        script: None,
+    })
+}
+
+/**
+ * Tells the address type of a payload of a coin, the search computing it
+ * unless it is p2sh
+ */
+fn payload_type(payload: &Payload, params: &AddressParams) -> Option<&'static str> {
+    match payload {
+        Payload::PubkeyHash(_) => Some("p2pkh"),
+        // The only script hash the search derives is the BIP49 nested segwit
+        // one, which the coins without it can't pay to
+        Payload::ScriptHash(_) if params.supports(&Purpose::ScriptHash) => Some("p2shwpkh"),
+        Payload::ScriptHash(_) => Some("p2sh"),
+        Payload::WitnessProgram { version, program } => match (version.to_u8(), program.len()) {
+            (0, 20) => Some("p2wpkh"),
+            (1, 32) => Some("p2tr"),
+            _ => None,
+        },
+    }
+}
+
+/**
+ * Decodes a target address as `decode_target` does, along with the
+ * addresses of a defined coin, whose encoding only the search knows. Those
+ * stand for the network searched. A shielded Zcash address is rejected as
+ * such, only the transparent t1 ones being derived from the keys.
+ */
+pub fn decode_coin_target(address: &str, coin: Coin, network: Network) -> Result<AddressTarget, HdiFinderError> {
+    if let Coin::Defined(_) = coin {
+        let params = self::address_params(coin, network);
+        if let Some(payload) = self::decode_with(address, &params) {
+            return Ok(AddressTarget {
+                address_type: self::payload_type(&payload, &params),
+                network,
+                label: None,
+                script: None,
+            });
+        }
+    }
+    if coins::is_zcash_shielded(address) {
+        return Err(HdiFinderError::InvalidAddress(format!(
+            "Invalid address {}: shielded Zcash addresses aren't supported, only the transparent t1 ones are searched",
+            address
+        )));
+    }
+    self::decode_target(address)
+}
+
+#[test]
+fn test_decode_coin_target(){
+    let vtc = Coin::Defined(CoinDefinition {
+        name: "vtc",
+        coin_type: 28,
+        p2pkh: 0x47,
+        p2sh: 0x05,
+        hrp: Some("vtc"),
+        purposes: &[Purpose::Pubkey, Purpose::ScriptHash, Purpose::Witness],
+    });
+    let params = address_params(vtc, Network::Bitcoin);
+    let test_seed = get_mnemonic("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about", None).unwrap().to_seed("");
+    let secp = Secp256k1::new();
+    for (purpose, expected) in [(Purpose::Pubkey, "Vce16eJifb7HpuoTFEBJyKNLsBJPo7fM83"), (Purpose::Witness, "vtc1qfe8v6c4r39fq8xnjgcpunt5spdfcxw63zzfwru")] {
+        let hd_path = AccountHDPath::new(purpose.clone(), coin_type_for(vtc, Network::Bitcoin), 0).address_at(0, 0).unwrap();
+        let public_key = get_public_key(&secp, get_private_key(test_seed, &hd_path, Network::Bitcoin).unwrap());
+        let address = coin_address_compute(&secp, public_key, &purpose, &params).unwrap().address;
+        assert_eq!(address, expected);
+        assert_eq!(decode_coin_target(&address, vtc, Network::Bitcoin).unwrap().address_type, Some(address_type(&purpose)));
+    }
+    assert!(!params.supports(&Purpose::Custom(86)));
+    assert_eq!(canonical_target("VTC1QFE8V6C4R39FQ8XNJGCPUNT5SPDFCXW63ZZFWRU", &params), "vtc1qfe8v6c4r39fq8xnjgcpunt5spdfcxw63zzfwru");
+    // Only the search for the coin knows its addresses
+    assert!(decode_coin_target("Vce16eJifb7HpuoTFEBJyKNLsBJPo7fM83", Coin::Bitcoin, Network::Bitcoin).is_err());
+    assert!(is_address_on_network("Vce16eJifb7HpuoTFEBJyKNLsBJPo7fM83", Network::Bitcoin, vtc));
+}
+
+#[test]
+fn test_zcash_addresses(){
+    let zec = Coin::Defined(coins::find_definition("zec", &[]).unwrap());
+    let params = address_params(zec, Network::Bitcoin);
+    let test_seed = get_mnemonic("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about", None).unwrap().to_seed("");
+    let secp = Secp256k1::new();
+    let hd_path = AccountHDPath::new(Purpose::Pubkey, coin_type_for(zec, Network::Bitcoin), 0).address_at(0, 0).unwrap();
+    let public_key = get_public_key(&secp, get_private_key(test_seed, &hd_path, Network::Bitcoin).unwrap());
+    let t_address = "t1XVXWCvpMgBvUaed4XDqWtgQgJSu1Ghz7F";
+    assert_eq!(coin_address_compute(&secp, public_key, &Purpose::Pubkey, &params).unwrap().address, t_address);
+
+    // The two bytes versions decode back to the payload type
+    assert_eq!(decode_coin_target(t_address, zec, Network::Bitcoin).unwrap().address_type, Some("p2pkh"));
+    assert_eq!(decode_coin_target("t3YBYRkRFuaWJZkM4BctRwX3LppjcVcRAoJ", zec, Network::Bitcoin).unwrap().address_type, Some("p2sh"));
+    assert!(decode_coin_target("t1XVXWCvpMgBvUaed4XDqWtgQgJSu1Ghz7G", zec, Network::Bitcoin).is_err());
+    assert_eq!(
+        decode_coin_target("zs1zr3lvrvcsvyyekegkza2znprnx808vyufrgf9nfa9ukksa2ss3tczx9efycg75wedv5s774jxwv", zec, Network::Bitcoin).unwrap_err().to_string(),
+        "Invalid address zs1zr3lvrvcsvyyekegkza2znprnx808vyufrgf9nfa9ukksa2ss3tczx9efycg75wedv5s774jxwv: shielded Zcash addresses aren't supported, only the transparent t1 ones are searched"
+    );
+}
+
+#[test]
+fn test_decode_target(){
+    let target = decode_target("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK").unwrap();
+    assert_eq!(target.address_type, Some("p2pkh"));
+    assert_eq!(target.network, Network::Bitcoin);
+    assert_eq!(decode_target("bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y").unwrap().address_type, Some("p2wpkh"));
+    assert_eq!(decode_target("tb1qv5zqqm8plkx9hzp485pnze0aljx6eg9js46dzq").unwrap().network, Network::Testnet);
+    // p2wsh
+    assert_eq!(decode_target("bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3").unwrap().address_type, None);
+
+    // A base58 checksum broken by the last character
+    assert_eq!(
+        decode_target("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgL").unwrap_err().to_string(),
+        "Invalid address 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgL: base58: base58ck checksum 0x911bd685 does not match expected 0x901bd685"
+    );
+    // Bech32 strings are either lowercase or uppercase
+    assert_eq!(
+        decode_target("bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtK5u60h0y").unwrap_err().to_string(),
+        "Invalid address bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtK5u60h0y: bech32: mixed-case strings not allowed"
+    );
+    assert!(decode_target("bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0b").is_err());
+}
+
+/**
+ * Decodes an output script (scriptPubKey) given as hex into the address it
+ * pays to on the network searched. A script is a target as its address
+ * is, the derived scripts matching it exactly when their addresses do.
+ * Scripts no derived address type produces, e.g p2wsh or OP_RETURN, are
+ * rejected.
+ */
+pub fn script_target(hex: &str, network: Network, coin: Coin) -> Result<(String, AddressTarget), HdiFinderError> {
+    let bytes = Vec::<u8>::from_hex(hex.trim()).map_err(|e| HdiFinderError::InvalidAddress(format!("Invalid script {}: {}", hex, e)))?;
+    let script = Script::from(bytes);
+    let unsupported = || {
+        HdiFinderError::InvalidAddress(format!(
+            "Unsupported script {}. Only p2pkh, p2sh, p2wpkh and p2tr output scripts can be searched",
+            hex
+        ))
+    };
+    let address = Address::from_script(&script, network).ok_or_else(unsupported)?;
+    let params = self::address_params(coin, network);
+    if params.hrp.is_none() && matches!(address.payload, Payload::WitnessProgram { .. }) {
+        return Err(unsupported());
+    }
+    let address = self::encode_address(&address, &params);
+    let target = self::decode_target(&address)?;
+    if target.address_type.is_none() {
+        return Err(unsupported());
+    }
+    Ok((
+        address,
+        AddressTarget {
+            script: Some(script.as_bytes().to_hex()),
+            ..target
+        },
+    ))
+}
+
+#[test]
+fn test_script_target(){
+    let script = Address::from_str("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK").unwrap().script_pubkey().as_bytes().to_hex();
+    assert!(script.starts_with("76a914") && script.ends_with("88ac"));
+    let (address, target) = script_target(&script, Network::Bitcoin, Coin::Bitcoin).unwrap();
+    assert_eq!(address, "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    assert_eq!(target.address_type, Some("p2pkh"));
+    assert_eq!(target.script, Some(script));
+
+    // The script of an address gives the address back
+    let script = Address::from_str("bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y").unwrap().script_pubkey().as_bytes().to_hex();
+    let (address, target) = script_target(&script.to_uppercase(), Network::Bitcoin, Coin::Bitcoin).unwrap();
+    assert_eq!(address, "bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y");
+    assert_eq!(target.address_type, Some("p2wpkh"));
+    assert_eq!(script_target(&script, Network::Testnet, Coin::Bitcoin).unwrap().0, "tb1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5ku5y5h");
+
+    assert_eq!(
+        script_target("6a0b68656c6c6f20776f726c64", Network::Bitcoin, Coin::Bitcoin).unwrap_err().to_string(),
+        "Unsupported script 6a0b68656c6c6f20776f726c64. Only p2pkh, p2sh, p2wpkh and p2tr output scripts can be searched"
+    );
+    // p2wsh
+    assert!(script_target("0020701a8d401c84fb13e6baf169d59684e17abd9fa216c8cc5b9fc63d622ff8c58d", Network::Bitcoin, Coin::Bitcoin).is_err());
+    assert!(script_target("76a9zz", Network::Bitcoin, Coin::Bitcoin).unwrap_err().to_string().starts_with("Invalid script 76a9zz: "));
+    assert_eq!(script_target(&script, Network::Bitcoin, Coin::Litecoin).unwrap().0, "ltc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5cx4nh5");
+}
+
+/**
+ * Parses a public key target given as hex, either compressed (33 bytes) or
+ * uncompressed (65 bytes). The key is normalized to its compressed hex
+ * encoding, the one of the keys BIP32 derives, which the search compares
+ * the derived keys against.
+ */
+pub fn pubkey_target(hex: &str, network: Network) -> Result<(String, AddressTarget), HdiFinderError> {
+    let invalid = |reason: String| HdiFinderError::InvalidAddress(format!("Invalid public key {}: {}", hex, reason));
+    let bytes = Vec::<u8>::from_hex(hex.trim()).map_err(|e| invalid(e.to_string()))?;
+    if bytes.len() != 33 && bytes.len() != 65 {
+        return Err(invalid(format!("expected 33 or 65 bytes, got {}", bytes.len())));
+    }
+    let key = secp256k1::PublicKey::from_slice(&bytes).map_err(|e| invalid(e.to_string()))?;
+    Ok((
+        key.serialize().to_hex(),
+        AddressTarget {
+            address_type: None,
+            network,
+            label: None,
+            script: None,
+        },
+    ))
+}
+
+#[test]
+fn test_pubkey_target(){
+    let compressed = "02016653fa405f3ecedb3dc88a378dabf7cd4c1c1acf1430515e854a630254cbbe";
+    assert_eq!(pubkey_target(compressed, Network::Bitcoin).unwrap().0, compressed);
+    // The uncompressed encoding of the same key
+    assert_eq!(
+        pubkey_target("04016653fa405f3ecedb3dc88a378dabf7cd4c1c1acf1430515e854a630254cbbeada7e09891c8b3a36234d45dffcff8e4b49b6f68c69396ad39fe3ea8cb9356fa", Network::Bitcoin).unwrap().0,
+        compressed
+    );
+    assert_eq!(pubkey_target(&compressed.to_uppercase(), Network::Bitcoin).unwrap().0, compressed);
+    assert!(is_pubkey_target(compressed));
+    assert!(!is_pubkey_target("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"));
+    assert_eq!(
+        pubkey_target("02016653", Network::Bitcoin).unwrap_err().to_string(),
+        "Invalid public key 02016653: expected 33 or 65 bytes, got 4"
+    );
+    assert!(pubkey_target(&compressed.replacen("02", "05", 1), Network::Bitcoin).is_err());
+}
+
+/**
+ * Tells whether a target is a public key, as normalized by `pubkey_target`,
+ * rather than an address. No address encoding is made of hex digits only.
+ */
+pub fn is_pubkey_target(target: &str) -> bool {
+    target.len() == 66 && (target.starts_with("02") || target.starts_with("03")) && target.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+/**
+ * Computes the address of a public key in each of the standard types
+ */
+pub fn standard_addresses(secp: &Secp256k1<All>, pubkey: ExtendedPubKey, network: Network, coin: Coin) -> Result<Vec<DerivedAddress>, HdiFinderError> {
+    let params = self::address_params(coin, network);
+    [Purpose::Pubkey, Purpose::ScriptHash, Purpose::Witness, Purpose::Custom(86)]
+        .iter()
+        .filter(|purpose| params.supports(purpose))
+        .map(|purpose| self::coin_address_compute(secp, pubkey, purpose, &params))
+        .collect()
+}
+
+/**
+ * Parses a hash160 target given as hex, the 20 bytes explorers give for the
+ * key of a p2pkh or p2wpkh output. Both address types share this payload,
+ * the search compares the hash160 of the derived keys against it.
+ */
+pub fn hash160_target(hex: &str, network: Network) -> Result<(String, AddressTarget), HdiFinderError> {
+    let invalid = |reason: String| HdiFinderError::InvalidAddress(format!("Invalid hash160 {}: {}", hex, reason));
+    let bytes = Vec::<u8>::from_hex(hex.trim()).map_err(|e| invalid(e.to_string()))?;
+    if bytes.len() != 20 {
+        return Err(invalid(format!("expected 20 bytes, got {}", bytes.len())));
+    }
+    Ok((
+        bytes.to_hex(),
+        AddressTarget {
+            address_type: None,
+            network,
+            label: None,
+            script: None,
+        },
+    ))
+}
+
+#[test]
+fn test_hash160_target(){
+    let hash = "29b9c049a22a9e3b620fb327b05debaf67b42ed4";
+    assert_eq!(hash160_target(hash, Network::Bitcoin).unwrap().0, hash);
+    assert_eq!(hash160_target(&hash.to_uppercase(), Network::Bitcoin).unwrap().0, hash);
+    assert!(is_hash160_target(hash));
+    assert!(!is_hash160_target("02016653fa405f3ecedb3dc88a378dabf7cd4c1c1acf1430515e854a630254cbbe"));
+    assert!(!is_hash160_target("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"));
+    assert_eq!(
+        hash160_target("29b9c049", Network::Bitcoin).unwrap_err().to_string(),
+        "Invalid hash160 29b9c049: expected 20 bytes, got 4"
+    );
+    assert!(hash160_target("29b9c0zz", Network::Bitcoin).unwrap_err().to_string().starts_with("Invalid hash160 29b9c0zz: "));
+
+    let addresses = hash160_addresses(&Vec::<u8>::from_hex(hash).unwrap(), Network::Bitcoin, Coin::Bitcoin);
+    assert_eq!(addresses[0].address, "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    assert_eq!(addresses[1].address, "bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y");
+}
+
+/**
+ * Tells whether a target is a hash160, as normalized by `hash160_target`,
+ * rather than an address or a public key
+ */
+pub fn is_hash160_target(target: &str) -> bool {
+    target.len() == 40 && target.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+/**
+ * Renders a hash160 as the p2pkh and p2wpkh addresses it is the payload of,
+ * the latter only for the coins with segwit
+ */
+pub fn hash160_addresses(hash: &[u8], network: Network, coin: Coin) -> Vec<DerivedAddress> {
+    let p2pkh = Address {
+        payload: Payload::PubkeyHash(bitcoin::PubkeyHash::from_slice(hash).expect("a hash160 is 20 bytes")),
+        network,
+    };
+    let p2wpkh = Address {
+        payload: Payload::WitnessProgram {
+            version: u5::try_from_u8(0).expect("0 is a valid witness version"),
+            program: hash.to_vec(),
+        },
+        network,
+    };
+    let params = self::address_params(coin, network);
+    let mut addresses = vec![DerivedAddress {
+        address_type: "p2pkh",
+        address: self::encode_address(&p2pkh, &params),
+    }];
+    if params.supports(&Purpose::Witness) {
+        addresses.push(DerivedAddress {
+            address_type: "p2wpkh",
+            address: self::encode_address(&p2wpkh, &params),
+        });
+    }
+    addresses
+}
@@ -0,0 +1,363 @@
+use crate::error::HdiFinderError;
+use aes::{hazmat::cipher_round, Block};
+use bip39::Language;
+use blake2::{digest::consts::U48, Blake2b, Digest};
+use std::convert::TryInto;
+
+/**
+ * LND backs its wallets up with aezeed cipher seeds: 24 words of the BIP39
+ * English wordlist encoding a version byte, the entropy and the wallet
+ * birthday enciphered with the AEZ cipher, the scrypt salt of the passphrase
+ * and a CRC32C checksum, instead of a BIP39 entropy and checksum.
+ * The entropy deciphered is the BIP32 seed of the wallet.
+ */
+pub const WORD_COUNT: usize = 24;
+
+/**
+ * Passphrase LND enciphers the seed with when the user sets none
+ */
+pub const DEFAULT_PASSPHRASE: &str = "aezeed";
+
+const ENCODED_LEN: usize = 33;
+const CIPHER_SEED_VERSION: u8 = 0;
+const CHECKSUM_LEN: usize = 4;
+const SALT_LEN: usize = 5;
+const ENTROPY_LEN: usize = 16;
+
+/**
+ * Zero bytes appended to the message before enciphering it, which only
+ * decipher back to zeros with the right key
+ */
+const EXPANSION_LEN: usize = 4;
+
+/**
+ * Version, birthday and entropy, followed by the expansion
+ */
+const CIPHERTEXT_LEN: usize = 1 + 2 + ENTROPY_LEN + EXPANSION_LEN;
+
+/**
+ * scrypt parameters of the key the seed is enciphered with, N being 2^15
+ */
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+const KEY_LEN: usize = 32;
+
+/**
+ * Timestamp of the genesis block, the birthday counting the days since it
+ */
+const GENESIS_TIMESTAMP: i64 = 1_231_006_505;
+
+/**
+ * The secret of a deciphered aezeed seed
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct CipherSeed {
+    pub entropy: [u8; ENTROPY_LEN],
+    // Days from the genesis block to the creation of the wallet
+    pub birthday: u16,
+}
+
+impl CipherSeed {
+    /**
+     * Retrieves the day the wallet has been created, e.g 2018-03-22
+     */
+    pub fn birthday_date(&self) -> String {
+        let days = (GENESIS_TIMESTAMP + self.birthday as i64 * 86_400).div_euclid(86_400);
+        // Civil date of a count of days since 1970-01-01, in 400 years eras
+        let shifted = days + 719_468;
+        let era = shifted.div_euclid(146_097);
+        let day_of_era = shifted - era * 146_097;
+        let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+#[test]
+fn test_birthday_date(){
+    let date = |birthday| CipherSeed { entropy: [0; ENTROPY_LEN], birthday }.birthday_date();
+    assert_eq!(date(0), "2009-01-03");
+    assert_eq!(date(3365), "2018-03-22");
+    assert_eq!(date(4074), "2020-02-29");
+}
+
+/**
+ * Computes the CRC-32C (Castagnoli) checksum of the data
+ */
+fn crc32c(data: &[u8]) -> u32 {
+    let mut crc: u32 = !0;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0x82F6_3B78 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+#[test]
+fn test_crc32c(){
+    assert_eq!(crc32c(b"123456789"), 0xE306_9283);
+    assert_eq!(crc32c(b""), 0);
+}
+
+/**
+ * Decodes the 24 words of a phrase into the 33 bytes they encode, 11 bits
+ * per word
+ */
+fn decode(phrase: &str) -> Result<Vec<u8>, HdiFinderError> {
+    let words: Vec<String> = phrase.split_whitespace().map(|word| word.to_lowercase()).collect();
+    if words.len() != WORD_COUNT {
+        return Err(HdiFinderError::Mnemonic {
+            reason: format!("an aezeed cipher seed has {} words, not {}", WORD_COUNT, words.len()),
+            error: bip39::Error::BadWordCount(words.len()),
+        });
+    }
+    let wordlist = Language::English.words_by_prefix("");
+    let mut bits: Vec<bool> = Vec::with_capacity(WORD_COUNT * 11);
+    for (position, word) in words.iter().enumerate() {
+        let index = wordlist.iter().position(|candidate| candidate == word).ok_or_else(|| HdiFinderError::Mnemonic {
+            reason: format!("word {} '{}' is not in the BIP39 english wordlist", position + 1, word),
+            error: bip39::Error::UnknownWord(position),
+        })?;
+        bits.extend((0..11).rev().map(|bit| index >> bit & 1 == 1));
+    }
+    Ok(bits.chunks(8).map(|byte| byte.iter().fold(0, |value, bit| value << 1 | *bit as u8)).collect())
+}
+
+/**
+ * Tells whether the encoded seed is of the supported version and its
+ * checksum matches
+ */
+fn has_valid_checksum(bytes: &[u8]) -> bool {
+    let (payload, checksum) = bytes.split_at(ENCODED_LEN - CHECKSUM_LEN);
+    bytes[0] == CIPHER_SEED_VERSION && self::crc32c(payload).to_be_bytes() == checksum
+}
+
+/**
+ * Tells whether a phrase is an aezeed cipher seed: 24 English words with
+ * the aezeed version byte and a valid CRC32C checksum
+ */
+pub fn is_cipher_seed(phrase: &str) -> bool {
+    match self::decode(phrase) {
+        Ok(bytes) => self::has_valid_checksum(&bytes),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+fn encode(bytes: &[u8]) -> String {
+    let bits: Vec<bool> = bytes.iter().flat_map(|byte| (0..8).rev().map(move |bit| byte >> bit & 1 == 1)).collect();
+    let wordlist = Language::English.words_by_prefix("");
+    let words: Vec<&str> = bits
+        .chunks(11)
+        .map(|word| wordlist[word.iter().fold(0usize, |index, bit| index << 1 | *bit as usize)])
+        .collect();
+    words.join(" ")
+}
+
+#[test]
+fn test_is_cipher_seed(){
+    // Encodes a version 0 payload with its checksum
+    let mut bytes: Vec<u8> = (0..29).map(|i| (i * 37 + 11) as u8).collect();
+    bytes[0] = CIPHER_SEED_VERSION;
+    bytes.extend(crc32c(&bytes).to_be_bytes());
+    let phrase = encode(&bytes);
+    assert!(is_cipher_seed(&phrase));
+    assert_eq!(decode(&phrase).unwrap(), bytes);
+
+    // A word changed breaks the checksum
+    let words: Vec<&str> = phrase.split(' ').collect();
+    let altered = phrase.replacen(words[3], if words[3] == "zoo" { "abandon" } else { "zoo" }, 1);
+    assert!(!is_cipher_seed(&altered));
+    assert!(!is_cipher_seed("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb"));
+}
+
+fn xor(a: &[u8; 16], b: &[u8; 16]) -> [u8; 16] {
+    let mut result = *a;
+    result.iter_mut().zip(b).for_each(|(x, y)| *x ^= y);
+    result
+}
+
+/**
+ * Multiplies a block by 2 in GF(2^128), the blocks being big-endian
+ */
+fn double(block: &[u8; 16]) -> [u8; 16] {
+    let value = u128::from_be_bytes(*block);
+    let reduction = if value >> 127 == 1 { 0x87 } else { 0 };
+    (value << 1 ^ reduction).to_be_bytes()
+}
+
+/**
+ * Multiplies a block by a small integer in GF(2^128)
+ */
+fn multiply(mut factor: u32, block: &[u8; 16]) -> [u8; 16] {
+    let mut result = [0; 16];
+    let mut power = *block;
+    while factor != 0 {
+        if factor & 1 == 1 {
+            result = self::xor(&result, &power);
+        }
+        power = self::double(&power);
+        factor >>= 1;
+    }
+    result
+}
+
+/**
+ * The AEZ v5 cipher, limited to what deciphering an aezeed seed takes: the
+ * hash of the associated data into the tweak, and AEZ-tiny, the seed being
+ * shorter than 32 bytes. The key is split into I, J and L.
+ */
+struct Aez {
+    i: [u8; 16],
+    j: [u8; 16],
+    l: [u8; 16],
+}
+
+impl Aez {
+    fn new(key: &[u8]) -> Aez {
+        let extracted = Blake2b::<U48>::digest(key);
+        Aez {
+            i: extracted[..16].try_into().expect("the extracted key is 48 bytes"),
+            j: extracted[16..32].try_into().expect("the extracted key is 48 bytes"),
+            l: extracted[32..].try_into().expect("the extracted key is 48 bytes"),
+        }
+    }
+
+    /**
+     * The tweakable block cipher E(j, i), four AES rounds of the block
+     * offset by jJ + 2^ceil(i/8)I + (i mod 8)L
+     */
+    fn encipher_block(&self, j: u32, i: u32, block: &[u8; 16]) -> [u8; 16] {
+        let offset = self::xor(
+            &self::xor(&self::multiply(j, &self.j), &self::multiply(1 << ((i + 7) / 8), &self.i)),
+            &self::multiply(i % 8, &self.l),
+        );
+        let mut state = Block::from(self::xor(block, &offset));
+        for round_key in [self.j, self.i, self.l, [0; 16]] {
+            cipher_round(&mut state, &Block::from(round_key));
+        }
+        state.into()
+    }
+
+    /**
+     * Hashes the expansion, the empty nonce and the associated data, shorter
+     * than a block, into the tweak of the Feistel network
+     */
+    fn hash(&self, associated_data: &[u8]) -> [u8; 16] {
+        let padded = |data: &[u8]| {
+            let mut block = [0; 16];
+            block[..data.len()].copy_from_slice(data);
+            block[data.len()] = 0x80;
+            block
+        };
+        let mut expansion_bits = [0; 16];
+        expansion_bits[12..].copy_from_slice(&(EXPANSION_LEN as u32 * 8).to_be_bytes());
+        let sum = self::xor(&self.encipher_block(3, 1, &expansion_bits), &self.encipher_block(4, 0, &padded(&[])));
+        self::xor(&sum, &self.encipher_block(5, 0, &padded(associated_data)))
+    }
+
+    /**
+     * Deciphers a message of 16 to 31 bytes with AEZ-tiny: 8 rounds of a
+     * Feistel network over the two halves of the message, which split a
+     * byte in the middle for an odd length
+     */
+    fn decipher_tiny(&self, tweak: &[u8; 16], ciphertext: &[u8]) -> Vec<u8> {
+        let half = ciphertext.len() * 4;
+        let bit = |position: usize| (ciphertext[position / 8] >> (7 - position % 8) & 1) as u128;
+        let mut left: u128 = (0..half).fold(0, |value, position| value << 1 | bit(position));
+        let mut right: u128 = (half..2 * half).fold(0, |value, position| value << 1 | bit(position));
+        let tweak = u128::from_be_bytes(*tweak);
+        for round in (0..8u128).rev() {
+            // The half followed by a one bit fills a block
+            let block = (right << 1 | 1) << (127 - half) ^ tweak ^ round;
+            let mask = u128::from_be_bytes(self.encipher_block(0, 6, &block.to_be_bytes())) >> (128 - half);
+            let next = left ^ mask;
+            left = right;
+            right = next;
+        }
+        let mut message = vec![0; ciphertext.len()];
+        for position in 0..2 * half {
+            let value = if position < half { right >> (half - 1 - position) } else { left >> (2 * half - 1 - position) };
+            message[position / 8] |= ((value & 1) as u8) << (7 - position % 8);
+        }
+        message
+    }
+}
+
+/**
+ * Deciphers an aezeed cipher seed with its passphrase, the default one of
+ * LND when empty. A mistyped word is told by the checksum, while a wrong
+ * passphrase only shows once deciphered.
+ */
+pub fn decipher(phrase: &str, passphrase: &str) -> Result<CipherSeed, HdiFinderError> {
+    self::decipher_with(phrase, passphrase, SCRYPT_LOG_N)
+}
+
+fn decipher_with(phrase: &str, passphrase: &str, scrypt_log_n: u8) -> Result<CipherSeed, HdiFinderError> {
+    let bytes = self::decode(phrase)?;
+    if bytes[0] != CIPHER_SEED_VERSION {
+        return Err(HdiFinderError::Mnemonic {
+            reason: format!("the aezeed cipher seed is of version {}, only version {} is supported", bytes[0], CIPHER_SEED_VERSION),
+            error: bip39::Error::InvalidChecksum,
+        });
+    }
+    if !self::has_valid_checksum(&bytes) {
+        return Err(HdiFinderError::Mnemonic {
+            reason: "the CRC32C checksum of the aezeed cipher seed doesn't match. A word may be wrong".to_string(),
+            error: bip39::Error::InvalidChecksum,
+        });
+    }
+    let ciphertext = &bytes[1..1 + CIPHERTEXT_LEN];
+    let salt = &bytes[1 + CIPHERTEXT_LEN..1 + CIPHERTEXT_LEN + SALT_LEN];
+
+    let passphrase = if passphrase.is_empty() { DEFAULT_PASSPHRASE } else { passphrase };
+    let params = scrypt::Params::new(scrypt_log_n, SCRYPT_R, SCRYPT_P, KEY_LEN).expect("the scrypt parameters are valid");
+    let mut key = [0; KEY_LEN];
+    scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut key).expect("the key length is valid");
+
+    let mut associated_data = [CIPHER_SEED_VERSION; 1 + SALT_LEN];
+    associated_data[1..].copy_from_slice(salt);
+    let aez = Aez::new(&key);
+    let deciphered = aez.decipher_tiny(&aez.hash(&associated_data), ciphertext);
+    let (message, expansion) = deciphered.split_at(CIPHERTEXT_LEN - EXPANSION_LEN);
+    if expansion.iter().any(|byte| *byte != 0) || message[0] != CIPHER_SEED_VERSION {
+        return Err(HdiFinderError::InvalidArgument(
+            "Unable to decipher the aezeed cipher seed, the passphrase is wrong. Give the one set when creating the LND wallet with --aezeed-passphrase".to_string(),
+        ));
+    }
+    Ok(CipherSeed {
+        birthday: u16::from_be_bytes([message[1], message[2]]),
+        entropy: message[3..].try_into().expect("the message ends with the 16 bytes of entropy"),
+    })
+}
+
+#[test]
+fn test_decipher(){
+    let entropy = [0x81, 0xb6, 0x37, 0xd8, 0x63, 0x59, 0xe6, 0x96, 0x0d, 0xe7, 0x95, 0xe4, 0x1e, 0x0b, 0x4c, 0xfd];
+    // The LND test vector, enciphered with scrypt N lowered to 16
+    let lnd_vector = "ability liquid travel stem barely drastic pact cupboard apple thrive morning oak feature tissue couch old math inform success suggest drink motion know royal";
+    assert_eq!(decipher_with(lnd_vector, "", 4).unwrap(), CipherSeed { entropy, birthday: 0 });
+
+    // The same entropy enciphered as LND does, with the default passphrase
+    // and with another one
+    let default_passphrase = "absorb original enlist once climb erode kid thrive kitchen giant define tube orange leader harbor comfort olive fatal success suggest drink penalty chimney ritual";
+    assert_eq!(decipher(default_passphrase, "").unwrap(), CipherSeed { entropy, birthday: 3365 });
+    assert_eq!(decipher(default_passphrase, DEFAULT_PASSPHRASE).unwrap(), CipherSeed { entropy, birthday: 3365 });
+    let passphrase = "absent roof siege awesome milk book wish basket bullet donor cable strategy explain prepare mystery chest stamp bean success suggest drip burden identify unit";
+    assert_eq!(decipher(passphrase, "!very_safe_55345_password*").unwrap(), CipherSeed { entropy, birthday: 3365 });
+
+    assert!(matches!(decipher(passphrase, ""), Err(HdiFinderError::InvalidArgument(_))));
+    let mistyped = passphrase.replace("milk", "million");
+    assert!(matches!(
+        decipher(&mistyped, "!very_safe_55345_password*"),
+        Err(HdiFinderError::Mnemonic { error: bip39::Error::InvalidChecksum, .. })
+    ));
+    assert!(matches!(decipher("erupt quit", ""), Err(HdiFinderError::Mnemonic { error: bip39::Error::BadWordCount(2), .. })));
+}
@@ -0,0 +1,337 @@
+use hdifinder::{
+    address, derivation,
+    models::{ChainKey, ExecutionConf, WalletConf},
+    progress::Progress,
+    search::{self, DerivationTrace},
+    HdiFinderError,
+};
+use bip39::Language;
+use bitcoin::network::constants::Network;
+use hdpath::{AccountHDPath, Purpose, StandardHDPath};
+use secp256k1::{All, Secp256k1};
+use std::{
+    collections::HashSet,
+    hint,
+    sync::atomic::AtomicBool,
+    time::{Duration, Instant},
+};
+
+/**
+ * Mnemonic the keys of a benchmark are derived from, fixed so that runs
+ * on different machines do the exact same work
+ */
+const BENCH_MNEMONIC: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+
+/**
+ * Time spent computing the addresses of one type, split by stage:
+ * the key derivation, the hashing of the public key into a payload
+ * and the encoding of the payload into an address string
+ */
+pub struct TypeBench {
+    pub address_type: &'static str,
+    pub keys: usize,
+    pub derivation: Duration,
+    pub hashing: Duration,
+    pub encoding: Duration,
+}
+
+impl TypeBench {
+    pub fn elapsed(&self) -> Duration {
+        self.derivation + self.hashing + self.encoding
+    }
+
+    pub fn keys_per_second(&self) -> f64 {
+        self::keys_per_second(self.keys, self.elapsed())
+    }
+}
+
+fn keys_per_second(keys: usize, elapsed: Duration) -> f64 {
+    if elapsed.as_secs_f64() > 0.0 {
+        keys as f64 / elapsed.as_secs_f64()
+    } else {
+        0.0
+    }
+}
+
+/**
+ * Derives `keys` addresses spread evenly over the standard address types,
+ * going through the same steps as the executor, on a single thread
+ */
+pub fn run(secp: &Secp256k1<All>, keys: usize) -> Result<Vec<TypeBench>, HdiFinderError> {
+    let seed = derivation::get_mnemonic(BENCH_MNEMONIC, Some(Language::English))?.to_seed("");
+    let master = derivation::get_master_key(seed, Network::Bitcoin)?;
+    let purposes = [Purpose::Pubkey, Purpose::ScriptHash, Purpose::Witness, Purpose::Custom(86)];
+
+    purposes
+        .iter()
+        .enumerate()
+        .map(|(position, purpose)| {
+            let count = keys / purposes.len() + usize::from(position < keys % purposes.len());
+            let account = AccountHDPath::new(purpose.clone(), 0, 0);
+            let chain_key = derivation::chain_key(secp, &master, &account, 0)?;
+            let mut result = TypeBench {
+                address_type: address::address_type(purpose),
+                keys: count,
+                derivation: Duration::ZERO,
+                hashing: Duration::ZERO,
+                encoding: Duration::ZERO,
+            };
+
+            for index in 0..count as u32 {
+                let started = Instant::now();
+                let private_key = derivation::index_key(secp, &chain_key, index)?;
+                let public_key = derivation::get_public_key(secp, private_key);
+                let derived = Instant::now();
+                let address = address::build_address(secp, public_key, purpose, Network::Bitcoin)?;
+                let hashed = Instant::now();
+                hint::black_box(address.to_string());
+                let finished = Instant::now();
+
+                result.derivation += derived - started;
+                result.hashing += hashed - derived;
+                result.encoding += finished - hashed;
+            }
+            Ok(result)
+        })
+        .collect()
+}
+
+#[test]
+fn test_run(){
+    let secp = Secp256k1::new();
+    let results = run(&secp, 10).unwrap();
+    let types: Vec<&str> = results.iter().map(|result| result.address_type).collect();
+    assert_eq!(types, vec!["p2pkh", "p2shwpkh", "p2wpkh", "p2tr"]);
+    let keys: Vec<usize> = results.iter().map(|result| result.keys).collect();
+    assert_eq!(keys, vec![3, 3, 2, 2]);
+}
+
+/**
+ * Time the executor takes to scan the same indexes without a hook, and
+ * with the derivation trace hooked while its log level is disabled
+ */
+pub struct HookBench {
+    pub keys: usize,
+    pub without: Duration,
+    pub with: Duration,
+}
+
+impl HookBench {
+    /**
+     * Extra time taken with the hook, in percent, negative when within the noise
+     */
+    pub fn overhead(&self) -> f64 {
+        if self.without.as_secs_f64() > 0.0 {
+            (self.with.as_secs_f64() / self.without.as_secs_f64() - 1.0) * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/**
+ * Scans `keys` p2wpkh indexes through the executor, for an address that
+ * isn't among them, once without and once with the derivation trace
+ */
+pub fn hook(secp: &Secp256k1<All>, keys: usize) -> Result<HookBench, HdiFinderError> {
+    let seed = derivation::get_mnemonic(BENCH_MNEMONIC, Some(Language::English))?.to_seed("");
+    let master = derivation::get_master_key(seed, Network::Bitcoin)?;
+    let account = AccountHDPath::new(Purpose::Witness, 0, 0);
+    let wallet = WalletConf::new(ChainKey::Private(derivation::chain_key(secp, &master, &account, 0)?), account, 0, Network::Bitcoin);
+    // The first address of the BIP84 test vector
+    let targets = HashSet::from(["bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu".to_string()]);
+    let trace = DerivationTrace::new(1);
+    let scan = |observer: Option<&dyn search::Observer>| {
+        let started = Instant::now();
+        let execution_config = ExecutionConf { start: 0, end: keys };
+        search::scan_range_with(secp, &targets, &wallet, execution_config, false, &Progress::new(keys), &AtomicBool::new(false), observer)
+            .map(|_| started.elapsed())
+    };
+    Ok(HookBench {
+        keys,
+        without: scan(None)?,
+        with: scan(Some(&trace))?,
+    })
+}
+
+#[test]
+fn test_hook(){
+    let secp = Secp256k1::new();
+    let result = hook(&secp, 10).unwrap();
+    assert_eq!(result.keys, 10);
+    assert!(result.without > Duration::ZERO);
+    assert_eq!(HookBench { keys: 10, without: Duration::from_millis(200), with: Duration::from_millis(201) }.overhead().round(), 1.0);
+}
+
+/**
+ * Time taken to derive the same index keys from the seed, the master key
+ * being rebuilt for each of them, and from the chain key derived once from
+ * the cached master key, as the executor does
+ */
+pub struct MasterKeyBench {
+    pub keys: usize,
+    pub from_seed: Duration,
+    pub cached: Duration,
+}
+
+impl MasterKeyBench {
+    /**
+     * How many times faster the keys are derived from the cached master key
+     */
+    pub fn speedup(&self) -> f64 {
+        if self.cached.as_secs_f64() > 0.0 {
+            self.from_seed.as_secs_f64() / self.cached.as_secs_f64()
+        } else {
+            0.0
+        }
+    }
+}
+
+/**
+ * Derives `keys` m/44'/0'/0'/0/i keys once from the seed through
+ * `get_private_key`, and once from the chain key of the cached master key
+ */
+pub fn master_key(secp: &Secp256k1<All>, keys: usize) -> Result<MasterKeyBench, HdiFinderError> {
+    let seed = derivation::get_mnemonic(BENCH_MNEMONIC, Some(Language::English))?.to_seed("");
+    let account = AccountHDPath::new(Purpose::Pubkey, 0, 0);
+
+    let started = Instant::now();
+    for index in 0..keys as u32 {
+        let path = StandardHDPath::new(Purpose::Pubkey, 0, 0, 0, index);
+        hint::black_box(derivation::get_private_key(seed, &path, Network::Bitcoin)?);
+    }
+    let from_seed = started.elapsed();
+
+    let started = Instant::now();
+    let master = derivation::get_master_key(seed, Network::Bitcoin)?;
+    let chain_key = derivation::chain_key(secp, &master, &account, 0)?;
+    for index in 0..keys as u32 {
+        hint::black_box(derivation::index_key(secp, &chain_key, index)?);
+    }
+    let cached = started.elapsed();
+
+    Ok(MasterKeyBench { keys, from_seed, cached })
+}
+
+#[test]
+fn test_master_key(){
+    let secp = Secp256k1::new();
+    let result = master_key(&secp, 10).unwrap();
+    assert_eq!(result.keys, 10);
+    assert!(result.from_seed > Duration::ZERO);
+    assert_eq!(MasterKeyBench { keys: 10, from_seed: Duration::from_millis(300), cached: Duration::from_millis(100) }.speedup().round(), 3.0);
+}
+
+fn total_keys(results: &[TypeBench]) -> usize {
+    results.iter().map(|result| result.keys).sum()
+}
+
+fn total_elapsed(results: &[TypeBench]) -> Duration {
+    results.iter().map(|result| result.elapsed()).sum()
+}
+
+/**
+ * Formats the results of a benchmark as a human readable report
+ */
+pub fn text_report(results: &[TypeBench], hook: &HookBench, master_key: &MasterKeyBench) -> String {
+    let keys = total_keys(results);
+    let elapsed = total_elapsed(results);
+    let mut report = format!(
+        "{} keys in {:.2}s: {:.0} keys/s on one thread\n",
+        keys,
+        elapsed.as_secs_f64(),
+        self::keys_per_second(keys, elapsed)
+    );
+    for result in results {
+        report.push_str(&format!(
+            "{:<9}{:>8} keys {:>8.0} keys/s  derivation {:>8.2}ms  hashing {:>8.2}ms  encoding {:>8.2}ms\n",
+            result.address_type,
+            result.keys,
+            result.keys_per_second(),
+            result.derivation.as_secs_f64() * 1000.0,
+            result.hashing.as_secs_f64() * 1000.0,
+            result.encoding.as_secs_f64() * 1000.0
+        ));
+    }
+    report.push_str(&format!("derivation hook disabled: {:+.1}% over {} keys\n", hook.overhead(), hook.keys));
+    report.push_str(&format!(
+        "cached master key: {:.1}x faster than from the seed over {} keys ({:.2}ms instead of {:.2}ms)\n",
+        master_key.speedup(),
+        master_key.keys,
+        master_key.cached.as_secs_f64() * 1000.0,
+        master_key.from_seed.as_secs_f64() * 1000.0
+    ));
+    report
+}
+
+/**
+ * Formats the results of a benchmark as a JSON object
+ */
+pub fn json_report(results: &[TypeBench], hook: &HookBench, master_key: &MasterKeyBench) -> String {
+    let keys = total_keys(results);
+    let elapsed = total_elapsed(results);
+    let types: Vec<String> = results
+        .iter()
+        .map(|result| {
+            format!(
+                "{{\"type\":\"{}\",\"keys\":{},\"keys_per_second\":{:.0},\"derivation_ms\":{:.3},\"hashing_ms\":{:.3},\"encoding_ms\":{:.3}}}",
+                result.address_type,
+                result.keys,
+                result.keys_per_second(),
+                result.derivation.as_secs_f64() * 1000.0,
+                result.hashing.as_secs_f64() * 1000.0,
+                result.encoding.as_secs_f64() * 1000.0
+            )
+        })
+        .collect();
+    format!(
+        "{{\"keys\":{},\"elapsed_ms\":{},\"keys_per_second\":{:.0},\"types\":[{}],\"hook_overhead_percent\":{:.1},\"master_key_speedup\":{:.1}}}",
+        keys,
+        elapsed.as_millis(),
+        self::keys_per_second(keys, elapsed),
+        types.join(","),
+        hook.overhead(),
+        master_key.speedup()
+    )
+}
+
+#[test]
+fn test_json_report(){
+    let results = vec![
+        TypeBench {
+            address_type: "p2pkh",
+            keys: 100,
+            derivation: Duration::from_millis(400),
+            hashing: Duration::from_millis(50),
+            encoding: Duration::from_millis(50),
+        },
+        TypeBench {
+            address_type: "p2tr",
+            keys: 100,
+            derivation: Duration::from_millis(400),
+            hashing: Duration::from_millis(500),
+            encoding: Duration::from_millis(100),
+        },
+    ];
+    let hook = HookBench {
+        keys: 200,
+        without: Duration::from_millis(1000),
+        with: Duration::from_millis(1010),
+    };
+    let master_key = MasterKeyBench {
+        keys: 200,
+        from_seed: Duration::from_millis(900),
+        cached: Duration::from_millis(100),
+    };
+    assert_eq!(
+        json_report(&results, &hook, &master_key),
+        "{\"keys\":200,\"elapsed_ms\":1500,\"keys_per_second\":133,\"types\":[\
+        {\"type\":\"p2pkh\",\"keys\":100,\"keys_per_second\":200,\"derivation_ms\":400.000,\"hashing_ms\":50.000,\"encoding_ms\":50.000},\
+        {\"type\":\"p2tr\",\"keys\":100,\"keys_per_second\":100,\"derivation_ms\":400.000,\"hashing_ms\":500.000,\"encoding_ms\":100.000}],\
+        \"hook_overhead_percent\":1.0,\"master_key_speedup\":9.0}"
+    );
+    let report = text_report(&results, &hook, &master_key);
+    assert!(report.starts_with("200 keys in 1.50s: 133 keys/s on one thread\n"));
+    assert!(report.contains("derivation hook disabled: +1.0% over 200 keys\n"));
+    assert!(report.ends_with("cached master key: 9.0x faster than from the seed over 200 keys (100.00ms instead of 900.00ms)\n"));
+}
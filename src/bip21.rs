@@ -0,0 +1,137 @@
+use crate::{address, error::HdiFinderError};
+use bitcoin::Address;
+use std::str::FromStr;
+
+/**
+ * Payment URIs (BIP21) wallets share addresses with, e.g
+ * `bitcoin:bc1q...?amount=0.1&label=Alice`. Only the address and the label
+ * matter to a search, the amount and the message are ignored.
+ */
+const SCHEME: &str = "bitcoin:";
+
+/**
+ * The address of a payment URI, along with its label
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct PaymentUri {
+    pub address: String,
+    pub label: Option<String>,
+}
+
+/**
+ * Tells whether a target is given as a payment URI rather than a bare
+ * address. The scheme is case insensitive, QR codes using uppercase.
+ */
+pub fn is_payment_uri(target: &str) -> bool {
+    target.len() >= SCHEME.len() && target[..SCHEME.len()].eq_ignore_ascii_case(SCHEME)
+}
+
+/**
+ * Decodes the percent-encoded characters of a URI component
+ */
+fn percent_decode(component: &str) -> Result<String, String> {
+    let bytes = component.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = component
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| format!("invalid percent-encoding in '{}'", component))?;
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| format!("'{}' doesn't decode to UTF-8", component))
+}
+
+#[test]
+fn test_percent_decode(){
+    assert_eq!(percent_decode("Luke-Jr").unwrap(), "Luke-Jr");
+    assert_eq!(percent_decode("Donation%20for%20project%20xyz").unwrap(), "Donation for project xyz");
+    assert_eq!(percent_decode("caf%C3%A9").unwrap(), "café");
+    assert!(percent_decode("100%").is_err());
+    assert!(percent_decode("%zz").is_err());
+    assert!(percent_decode("%ff").is_err());
+}
+
+/**
+ * Parses a payment URI into its address and label. Uppercase bech32
+ * addresses, which QR codes encode more compactly, are lowercased as the
+ * derived addresses are. Parameters prefixed with `req-` are required to be
+ * understood by BIP21, the URI is rejected when there is one.
+ */
+pub fn parse_payment_uri(uri: &str) -> Result<PaymentUri, HdiFinderError> {
+    let invalid = |reason: String| HdiFinderError::PaymentUri {
+        uri: uri.to_string(),
+        reason,
+    };
+    if !self::is_payment_uri(uri) {
+        return Err(invalid("the scheme isn't bitcoin:".to_string()));
+    }
+    let (address, query) = match uri[SCHEME.len()..].split_once('?') {
+        Some((address, query)) => (address, Some(query)),
+        None => (&uri[SCHEME.len()..], None),
+    };
+    let address = address::normalize_target(&self::percent_decode(address).map_err(invalid)?);
+    if address.is_empty() {
+        return Err(invalid("no address is given".to_string()));
+    }
+    if let Err(e) = Address::from_str(&address) {
+        return Err(invalid(format!("invalid address {}: {}", address, e)));
+    }
+
+    let mut label: Option<String> = None;
+    for parameter in query.unwrap_or_default().split('&').filter(|parameter| !parameter.is_empty()) {
+        let (key, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+        let key = self::percent_decode(key).map_err(invalid)?;
+        if key.eq_ignore_ascii_case("label") {
+            label = Some(self::percent_decode(value).map_err(invalid)?);
+        } else if key.to_lowercase().starts_with("req-") {
+            return Err(invalid(format!("the required parameter {} isn't supported", key)));
+        }
+    }
+    Ok(PaymentUri { address, label })
+}
+
+#[test]
+fn test_parse_payment_uri(){
+    assert_eq!(
+        parse_payment_uri("bitcoin:14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK").unwrap(),
+        PaymentUri {
+            address: "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK".to_string(),
+            label: None,
+        }
+    );
+    assert_eq!(
+        parse_payment_uri("bitcoin:bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y?amount=0.1&label=Cold%20storage&message=x").unwrap(),
+        PaymentUri {
+            address: "bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y".to_string(),
+            label: Some("Cold storage".to_string()),
+        }
+    );
+    // QR codes are uppercase
+    assert_eq!(
+        parse_payment_uri("BITCOIN:BC1Q9XUUQJDZ920RKCS0KVNMQH0T4ANMGTK5U60H0Y?AMOUNT=0.1").unwrap().address,
+        "bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y"
+    );
+    assert_eq!(parse_payment_uri("bitcoin:%31%34odE5c1eXuphR24fXMtzDfsXMLCmFTFgK").unwrap().address, "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+
+    assert_eq!(parse_payment_uri("bitcoin:?amount=1").unwrap_err().to_string(), "Invalid BIP21 URI bitcoin:?amount=1: no address is given");
+    assert_eq!(
+        parse_payment_uri("bitcoin:14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK?label=100%").unwrap_err().to_string(),
+        "Invalid BIP21 URI bitcoin:14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK?label=100%: invalid percent-encoding in '100%'"
+    );
+    assert_eq!(
+        parse_payment_uri("bitcoin:14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK?req-somethingyoudontunderstand=50").unwrap_err().to_string(),
+        "Invalid BIP21 URI bitcoin:14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK?req-somethingyoudontunderstand=50: the required parameter req-somethingyoudontunderstand isn't supported"
+    );
+    assert!(parse_payment_uri("bitcoin:14odE5c1eXuphR24fXMtzDfsXMLCmFTFgL")
+        .unwrap_err()
+        .to_string()
+        .starts_with("Invalid BIP21 URI bitcoin:14odE5c1eXuphR24fXMtzDfsXMLCmFTFgL: invalid address 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgL: base58"));
+}
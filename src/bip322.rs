@@ -0,0 +1,189 @@
+use crate::{error::HdiFinderError, message};
+use bitcoin::{
+    blockdata::{opcodes, script::Builder, transaction::SigHashType},
+    consensus::encode,
+    hashes::{sha256, Hash, HashEngine},
+    util::{
+        address::AddressType,
+        bip143::SigHashCache,
+        ecdsa::{PrivateKey, PublicKey},
+    },
+    Address, OutPoint, Script, Transaction, TxIn, TxOut, Txid,
+};
+use secp256k1::{All, Message, Secp256k1, Signature};
+#[cfg(test)]
+use {bitcoin::network::constants::Network, std::str::FromStr};
+
+/**
+ * Generic signed messages (BIP322): the message is committed to by a virtual
+ * transaction paying to the address, "to_spend", and the signature is the
+ * witness of the virtual transaction spending it, "to_sign". The simple format
+ * gives the witness stack alone, which p2wpkh addresses are signed with.
+ * p2pkh addresses keep the legacy format, which BIP322 accepts for them.
+ */
+const MESSAGE_TAG: &[u8] = b"BIP0322-signed-message";
+
+/**
+ * Tells whether addresses of a type can be signed for, p2shwpkh needing the
+ * full format and p2tr a taproot signature hash
+ */
+pub fn is_supported(address_type: &str) -> bool {
+    matches!(address_type, "p2pkh" | "p2wpkh")
+}
+
+/**
+ * Hashes the message with the BIP340 tagged hash of BIP322
+ */
+pub fn message_hash(message: &str) -> sha256::Hash {
+    let tag = sha256::Hash::hash(MESSAGE_TAG);
+    let mut engine = sha256::Hash::engine();
+    engine.input(&tag[..]);
+    engine.input(&tag[..]);
+    engine.input(message.as_bytes());
+    sha256::Hash::from_engine(engine)
+}
+
+/**
+ * Builds the virtual transaction committing to the message, paying to the
+ * script of the address
+ */
+pub fn to_spend(address: &Address, message: &str) -> Transaction {
+    Transaction {
+        version: 0,
+        lock_time: 0,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(Txid::default(), 0xFFFFFFFF),
+            script_sig: Builder::new()
+                .push_opcode(opcodes::all::OP_PUSHBYTES_0)
+                .push_slice(&message_hash(message)[..])
+                .into_script(),
+            sequence: 0,
+            witness: Vec::new(),
+        }],
+        output: vec![TxOut {
+            value: 0,
+            script_pubkey: address.script_pubkey(),
+        }],
+    }
+}
+
+/**
+ * Builds the virtual transaction spending `to_spend`, unsigned
+ */
+pub fn to_sign(to_spend: &Transaction) -> Transaction {
+    Transaction {
+        version: 0,
+        lock_time: 0,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(to_spend.txid(), 0),
+            script_sig: Script::new(),
+            sequence: 0,
+            witness: Vec::new(),
+        }],
+        output: vec![TxOut {
+            value: 0,
+            script_pubkey: Builder::new().push_opcode(opcodes::all::OP_RETURN).into_script(),
+        }],
+    }
+}
+
+/**
+ * Computes the signature hash of the p2wpkh input of `to_sign`
+ */
+fn p2wpkh_sighash(to_sign: &Transaction, key: &PublicKey) -> Message {
+    let script_code = Script::new_p2pkh(&key.pubkey_hash());
+    let sighash = SigHashCache::new(to_sign).signature_hash(0, &script_code, 0, SigHashType::All);
+    Message::from_slice(&sighash[..]).expect("a signature hash is 32 bytes long")
+}
+
+/**
+ * Signs a message for an address with its key, giving the proof in base64:
+ * the witness stack of `to_sign` for p2wpkh addresses, the legacy signature
+ * for p2pkh ones
+ */
+pub fn sign(secp: &Secp256k1<All>, key: &PrivateKey, address: &Address, message: &str) -> Result<String, HdiFinderError> {
+    match address.address_type() {
+        Some(AddressType::P2pkh) => Ok(message::sign_message(secp, key, "p2pkh", message).expect("p2pkh addresses have a legacy format")),
+        Some(AddressType::P2wpkh) => {
+            let public_key = key.public_key(secp);
+            let to_sign = self::to_sign(&self::to_spend(address, message));
+            let mut signature = secp.sign_low_r(&self::p2wpkh_sighash(&to_sign, &public_key), &key.key).serialize_der().to_vec();
+            signature.push(SigHashType::All.as_u32() as u8);
+            let witness: Vec<Vec<u8>> = vec![signature, public_key.to_bytes()];
+            Ok(message::to_base64(&encode::serialize(&witness)))
+        }
+        _ => Err(HdiFinderError::InvalidAddress(format!(
+            "{} can't be signed with BIP322, only p2wpkh and p2pkh addresses are supported",
+            address
+        ))),
+    }
+}
+
+/**
+ * Verifies the proof of a signed message for an address, false when it
+ * doesn't sign the message or the address type isn't supported
+ */
+pub fn verify(secp: &Secp256k1<All>, address: &Address, message: &str, proof: &str) -> bool {
+    match address.address_type() {
+        Some(AddressType::P2pkh) => message::verify_message(secp, address, message, proof),
+        Some(AddressType::P2wpkh) => self::verify_p2wpkh(secp, address, message, proof).unwrap_or(false),
+        _ => false,
+    }
+}
+
+fn verify_p2wpkh(secp: &Secp256k1<All>, address: &Address, message: &str, proof: &str) -> Option<bool> {
+    let witness: Vec<Vec<u8>> = encode::deserialize(&message::from_base64(proof)?).ok()?;
+    let (signature, key) = match witness.as_slice() {
+        [signature, key] => (signature, PublicKey::from_slice(key).ok()?),
+        _ => return Some(false),
+    };
+    let (sighash_type, signature) = signature.split_last()?;
+    if *sighash_type != SigHashType::All.as_u32() as u8 || Address::p2wpkh(&key, address.network).ok()? != *address {
+        return Some(false);
+    }
+    let signature = Signature::from_der(signature).ok()?;
+    let to_sign = self::to_sign(&self::to_spend(address, message));
+    Some(secp.verify(&self::p2wpkh_sighash(&to_sign, &key), &signature, &key.key).is_ok())
+}
+
+#[test]
+fn test_message_hash(){
+    // The test vectors of BIP322
+    assert_eq!(message_hash("").to_string(), "c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1");
+    assert_eq!(message_hash("Hello World").to_string(), "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a");
+}
+
+#[test]
+fn test_transactions(){
+    let address = Address::from_str("bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l").unwrap();
+    let to_spend = self::to_spend(&address, "");
+    assert_eq!(to_spend.txid().to_string(), "c5680aa69bb8d860bf82d4e9cd3504b55dde018de765a91bb566283c545a99a7");
+    assert_eq!(to_sign(&to_spend).txid().to_string(), "1e9654e951a5ba44c8604c4de6c67fd78a27e81dcadcfe1edf638ba3aaebaed6");
+    let to_spend = self::to_spend(&address, "Hello World");
+    assert_eq!(to_spend.txid().to_string(), "b79d196740ad5217771c1098fc4a4b51e0535c32236c71f1ea4d61a2d603352b");
+    assert_eq!(to_sign(&to_spend).txid().to_string(), "88737ae86f2077145f93cc4b153ae9a1cb8d56afa511988c149c5c8c9d93bddf");
+}
+
+#[test]
+fn test_sign(){
+    let secp = Secp256k1::new();
+    // The key and the signatures of the test vectors of BIP322
+    let key = PrivateKey::from_wif("L3VFeEujGtevx9w18HD1fhRbCH67Az2dpCymeRE1SoPK6XQtaN2k").unwrap();
+    let address = Address::p2wpkh(&key.public_key(&secp), Network::Bitcoin).unwrap();
+    assert_eq!(address.to_string(), "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l");
+    let empty = "AkcwRAIgM2gBAQqvZX15ZiysmKmQpDrG83avLIT492QBzLnQIxYCIBaTpOaD20qRlEylyxFSeEA2ba9YOixpX8z46TSDtS40ASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=";
+    let hello = "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=";
+    assert!(verify(&secp, &address, "", empty));
+    assert!(verify(&secp, &address, "Hello World", hello));
+    assert!(!verify(&secp, &address, "Hello World", empty));
+    assert_eq!(sign(&secp, &key, &address, "").unwrap(), empty);
+    assert_eq!(sign(&secp, &key, &address, "Hello World").unwrap(), hello);
+
+    let address = Address::p2pkh(&key.public_key(&secp), Network::Bitcoin);
+    let proof = sign(&secp, &key, &address, "Hello World").unwrap();
+    assert!(verify(&secp, &address, "Hello World", &proof));
+    assert!(!verify(&secp, &address, "", &proof));
+
+    let address = Address::p2shwpkh(&key.public_key(&secp), Network::Bitcoin).unwrap();
+    assert!(sign(&secp, &key, &address, "Hello World").is_err());
+}
@@ -0,0 +1,179 @@
+/**
+ * Bitcoin Cash addresses (cashaddr), e.g `bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a`.
+ * The payload is the version byte telling the type and the size of the
+ * hash, followed by the hash, in base32 with a 40 bits BCH checksum
+ * covering the prefix.
+ */
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+const GENERATORS: [u64; 5] = [0x98f2bc8e61, 0x79b76d99e2, 0xf33e5fb3c4, 0xae2eabe2a8, 0x1e4f43e470];
+
+/**
+ * Type of a p2pkh payload
+ */
+pub const P2PKH: u8 = 0;
+
+/**
+ * Type of a p2sh payload
+ */
+pub const P2SH: u8 = 1;
+
+/**
+ * Sizes of the hash a version byte can tell, in bytes
+ */
+const SIZES: [usize; 8] = [20, 24, 28, 32, 40, 48, 56, 64];
+
+fn polymod(values: &[u8]) -> u64 {
+    let mut c: u64 = 1;
+    for value in values {
+        let c0 = c >> 35;
+        c = ((c & 0x07_ffff_ffff) << 5) ^ u64::from(*value);
+        for (i, generator) in GENERATORS.iter().enumerate() {
+            if (c0 >> i) & 1 == 1 {
+                c ^= generator;
+            }
+        }
+    }
+    c ^ 1
+}
+
+/**
+ * Computes the checksum of a payload, the prefix taking part in it by the
+ * lower 5 bits of its characters
+ */
+fn checksum(prefix: &str, payload: &[u8]) -> u64 {
+    let mut values: Vec<u8> = prefix.bytes().map(|byte| byte & 0x1f).collect();
+    values.push(0);
+    values.extend_from_slice(payload);
+    values.extend_from_slice(&[0; 8]);
+    self::polymod(&values)
+}
+
+/**
+ * Regroups bits, from bytes to 5 bits values or back. Padding is only
+ * added when encoding, the decoding rejecting padding bits set.
+ */
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let mut converted = Vec::new();
+    let max = (1 << to) - 1;
+    for value in data {
+        acc = (acc << from) | u32::from(*value);
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            converted.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            converted.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || (acc << (to - bits)) & max != 0 {
+        return None;
+    }
+    Some(converted)
+}
+
+/**
+ * Encodes a hash of a type, P2PKH or P2SH, into an address with its prefix
+ */
+pub fn encode(prefix: &str, kind: u8, hash: &[u8]) -> String {
+    let size = SIZES.iter().position(|size| *size == hash.len()).expect("the hash has a cashaddr size") as u8;
+    let mut data = vec![(kind << 3) | size];
+    data.extend_from_slice(hash);
+    let mut payload = self::convert_bits(&data, 8, 5, true).expect("padding always converts");
+    let checksum = self::checksum(prefix, &payload);
+    payload.extend((0..8).map(|i| ((checksum >> (5 * (7 - i))) & 0x1f) as u8));
+    let encoded: String = payload.iter().map(|value| CHARSET[*value as usize] as char).collect();
+    format!("{}:{}", prefix, encoded)
+}
+
+/**
+ * Decodes an address into the type and the hash of its payload. The
+ * prefix can be left out, the address being then checked against the
+ * expected one, and the address is either lowercase or uppercase.
+ */
+pub fn decode(address: &str, prefix: &str) -> Result<(u8, Vec<u8>), String> {
+    if address.chars().any(|c| c.is_ascii_lowercase()) && address.chars().any(|c| c.is_ascii_uppercase()) {
+        return Err("mixed-case strings not allowed".to_string());
+    }
+    let address = address.to_lowercase();
+    let encoded = match address.split_once(':') {
+        Some((given, encoded)) if given == prefix => encoded,
+        Some((given, _)) => return Err(format!("prefix {} where {} is expected", given, prefix)),
+        None => &address,
+    };
+    let values = encoded
+        .bytes()
+        .map(|byte| CHARSET.iter().position(|c| *c == byte).map(|value| value as u8))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| "invalid character".to_string())?;
+    if values.len() < 8 {
+        return Err("too short".to_string());
+    }
+    let (payload, _) = values.split_at(values.len() - 8);
+    let mut checked: Vec<u8> = prefix.bytes().map(|byte| byte & 0x1f).collect();
+    checked.push(0);
+    checked.extend_from_slice(&values);
+    if self::polymod(&checked) != 0 {
+        return Err("invalid checksum".to_string());
+    }
+    let data = self::convert_bits(payload, 5, 8, false).ok_or_else(|| "invalid padding".to_string())?;
+    let (version, hash) = data.split_first().ok_or_else(|| "empty payload".to_string())?;
+    if version & 0x80 != 0 || SIZES[(version & 0x07) as usize] != hash.len() {
+        return Err(format!("invalid version byte {:#04x}", version));
+    }
+    Ok((version >> 3, hash.to_vec()))
+}
+
+#[test]
+fn test_encode(){
+    use bitcoin::hashes::hex::FromHex;
+
+    // Vectors of the cashaddr specification
+    let hash = Vec::<u8>::from_hex("76a04053bda0a88bda5177b86a15c3b29f559873").unwrap();
+    assert_eq!(encode("bitcoincash", P2PKH, &hash), "bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a");
+    assert_eq!(encode("bitcoincash", P2SH, &hash), "bitcoincash:ppm2qsznhks23z7629mms6s4cwef74vcwvn0h829pq");
+    let hash = Vec::<u8>::from_hex("f5bf48b397dae70be82b3cca4793f8eb2b6cdac9").unwrap();
+    assert_eq!(encode("bitcoincash", P2PKH, &hash), "bitcoincash:qr6m7j9njldwwzlg9v7v53unlr4jkmx6eylep8ekg2");
+    assert_eq!(encode("bchtest", P2SH, &hash), "bchtest:pr6m7j9njldwwzlg9v7v53unlr4jkmx6eyvwc0uz5t");
+    assert_eq!(encode("pref", P2SH, &hash), "pref:pr6m7j9njldwwzlg9v7v53unlr4jkmx6ey65nvtks5");
+    assert_eq!(encode("prefix", 15, &hash), "prefix:0r6m7j9njldwwzlg9v7v53unlr4jkmx6ey3qnjwsrf");
+}
+
+#[test]
+fn test_decode(){
+    use bitcoin::hashes::hex::ToHex;
+
+    let (kind, hash) = decode("bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a", "bitcoincash").unwrap();
+    assert_eq!((kind, hash.to_hex().as_str()), (P2PKH, "76a04053bda0a88bda5177b86a15c3b29f559873"));
+    // The prefix can be left out, and the address can be uppercase
+    assert_eq!(decode("ppm2qsznhks23z7629mms6s4cwef74vcwvn0h829pq", "bitcoincash").unwrap().0, P2SH);
+    assert_eq!(decode("BCHTEST:PR6M7J9NJLDWWZLG9V7V53UNLR4JKMX6EYVWC0UZ5T", "bchtest").unwrap().0, P2SH);
+    assert_eq!(decode("prefix:0r6m7j9njldwwzlg9v7v53unlr4jkmx6ey3qnjwsrf", "prefix").unwrap().0, 15);
+
+    // The checksum covers the prefix
+    assert_eq!(
+        decode("bchtest:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a", "bchtest").unwrap_err(),
+        "invalid checksum"
+    );
+    assert_eq!(
+        decode("qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a", "bchtest").unwrap_err(),
+        "invalid checksum"
+    );
+    assert_eq!(
+        decode("bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a", "bchtest").unwrap_err(),
+        "prefix bitcoincash where bchtest is expected"
+    );
+    assert_eq!(
+        decode("bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6c", "bitcoincash").unwrap_err(),
+        "invalid checksum"
+    );
+    assert_eq!(
+        decode("bitcoincash:qpm2qsznhks23z7629mmS6s4cwef74vcwvy22gdx6a", "bitcoincash").unwrap_err(),
+        "mixed-case strings not allowed"
+    );
+    assert_eq!(decode("bitcoincash:qpm2qsznhks23z7629mmb6s4cwef74vcwvy22gdx6a", "bitcoincash").unwrap_err(), "invalid character");
+}
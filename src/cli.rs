@@ -0,0 +1,3001 @@
+use crate::{
+    config::{self, CoinTable, ConfigFile},
+    prompt,
+};
+use bip39::{Language, Mnemonic};
+use bitcoin::{network::constants::Network, util::bip32::{ExtendedPrivKey, ExtendedPubKey}};
+use clap::{
+    builder::{PossibleValuesParser, TypedValueParser},
+    error::ErrorKind,
+    parser::ValueSource,
+    value_parser, Arg, ArgAction, ArgMatches, ColorChoice, Command, ValueHint,
+};
+use hdifinder::{
+    address::{self, Encoding},
+    aezeed, bip21, bip322, coins,
+    derivation::{self, MAX_INDEX},
+    descriptor,
+    electrum::{self, SeedVersion},
+    models::{AddressTarget, Coin, CoinDefinition, Multisig, OutputFormat, SearchConfig, Seed, Swaps},
+    multisig, HdiFinderError,
+    presets::{self, Layout, NamedAccount, Preset},
+    psbt,
+    slip132, slip39,
+};
+use hdpath::{PathValue, Purpose};
+use secp256k1::Secp256k1;
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    env,
+    ffi::OsString,
+    fs,
+    io::{self, IsTerminal},
+    ops::{Range, RangeInclusive},
+    process,
+    time::Duration,
+};
+
+pub const EXIT_FOUND: i32 = 0;
+pub const EXIT_USAGE: i32 = 1;
+pub const EXIT_NOT_FOUND: i32 = 2;
+pub const EXIT_INVALID_MNEMONIC: i32 = 3;
+pub const EXIT_INVALID_ADDRESS: i32 = 4;
+
+/**
+ * Retrieves the exit code reporting an error
+ */
+pub fn exit_code(error: &HdiFinderError) -> i32 {
+    match error {
+        HdiFinderError::Mnemonic { .. } | HdiFinderError::Shares(_) => EXIT_INVALID_MNEMONIC,
+        HdiFinderError::InvalidAddress(_) | HdiFinderError::PaymentUri { .. } | HdiFinderError::Descriptor { .. } => EXIT_INVALID_ADDRESS,
+        HdiFinderError::Derivation { .. }
+        | HdiFinderError::InvalidPath(_)
+        | HdiFinderError::InvalidRange(_)
+        | HdiFinderError::InvalidArgument(_)
+        | HdiFinderError::Io { .. }
+        | HdiFinderError::Internal(_) => EXIT_USAGE,
+    }
+}
+
+#[test]
+fn test_exit_code(){
+    let mnemonic_error = hdifinder::derivation::get_mnemonic("bananaa", None).unwrap_err();
+    assert_eq!(exit_code(&mnemonic_error), EXIT_INVALID_MNEMONIC);
+    assert_eq!(exit_code(&HdiFinderError::InvalidAddress("p2wpkh".to_string())), EXIT_INVALID_ADDRESS);
+    assert_eq!(exit_code(&HdiFinderError::InvalidRange("end".to_string())), EXIT_USAGE);
+}
+
+/**
+ * Splits the free arguments of the command line into the mnemonic and the
+ * targets. The mnemonic is either quoted, as the first argument, or given as
+ * separate words on either side of the targets. An argument decoding as a
+ * target is one, and of the others, those made of letters only are words.
+ * When the mnemonic is left out to be prompted for, every argument is a
+ * target.
+ */
+fn free_args<'a>(args: &'a ArgMatches) -> (Option<String>, Vec<&'a str>) {
+    let mut free: Vec<&str> = args
+        .get_one::<String>("mnemonic")
+        .into_iter()
+        .chain(args.get_many::<String>("address").into_iter().flatten())
+        .map(String::as_str)
+        .collect();
+    if free.first().is_some_and(|first| first.trim().contains(char::is_whitespace)) {
+        let mnemonic = free.remove(0);
+        return (Some(mnemonic.to_string()), free);
+    }
+    let (words, targets): (Vec<&str>, Vec<&str>) =
+        free.into_iter().partition(|arg| !self::is_target(arg) && !arg.is_empty() && arg.chars().all(char::is_alphabetic));
+    let mnemonic = if words.is_empty() { None } else { Some(words.join(" ")) };
+    (mnemonic, targets)
+}
+
+/**
+ * Tells whether an argument is a target, an address of one of the coins, a
+ * payment URI or a descriptor
+ */
+fn is_target(arg: &str) -> bool {
+    address::address_coin(&address::normalize_target(arg)).is_some() || bip21::is_payment_uri(arg.trim()) || descriptor::is_descriptor(arg)
+}
+
+/**
+ * Retrieves the mnemonic given on the command line
+ */
+pub fn mnemonic_arg(args: &ArgMatches) -> Option<String> {
+    self::free_args(args).0
+}
+
+#[test]
+fn test_mnemonic_arg(){
+    let args = app().get_matches_from(vec!["hdifinder", "erupt quit sphere", "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"]);
+    assert_eq!(mnemonic_arg(&args).as_deref(), Some("erupt quit sphere"));
+    let args = app().get_matches_from(vec!["hdifinder", "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"]);
+    assert_eq!(mnemonic_arg(&args), None);
+    assert!(load_config(&args).unwrap().addresses.contains("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"));
+
+    // Unquoted, the words are joined whichever side of the targets they are on
+    let args = app().get_matches_from(vec!["hdifinder", "erupt", "quit", "sphere", "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", "bc1q2dq975h7y93xplagze42exmzflxl4uqhl435qd"]);
+    assert_eq!(mnemonic_arg(&args).as_deref(), Some("erupt quit sphere"));
+    assert_eq!(free_args(&args).1, ["14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", "bc1q2dq975h7y93xplagze42exmzflxl4uqhl435qd"]);
+    let args = app().get_matches_from(vec!["hdifinder", "bc1q2dq975h7y93xplagze42exmzflxl4uqhl435qd", "erupt", "quit", "sphere"]);
+    assert_eq!(free_args(&args), (Some("erupt quit sphere".to_string()), vec!["bc1q2dq975h7y93xplagze42exmzflxl4uqhl435qd"]));
+    // A mistyped address is still a target, to be reported as such
+    let args = app().get_matches_from(vec!["hdifinder", "erupt", "quit", "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgL"]);
+    assert_eq!(free_args(&args), (Some("erupt quit".to_string()), vec!["14odE5c1eXuphR24fXMtzDfsXMLCmFTFgL"]));
+    // An address made of letters only is still a target
+    let args = app().get_matches_from(vec!["hdifinder", "erupt", "quit", "DTALakiLbPFGHrWgodUSVMhVEoRzGptKax"]);
+    assert_eq!(free_args(&args), (Some("erupt quit".to_string()), vec!["DTALakiLbPFGHrWgodUSVMhVEoRzGptKax"]));
+}
+
+/**
+ * Environment variables the secrets are read from when they aren't given
+ * on the command line, for jobs that can neither prompt nor pass arguments
+ */
+pub const MNEMONIC_VAR: &str = "HDIFINDER_MNEMONIC";
+pub const PASSPHRASE_VAR: &str = "HDIFINDER_PASSPHRASE";
+
+pub fn env_secret(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/**
+ * Retrieves the mnemonic from, by order of precedence, the command line,
+ * --mnemonic-file, the environment, or else a prompt or stdin
+ */
+pub fn read_mnemonic(args: &ArgMatches, config: &SearchConfig) -> Result<String, HdiFinderError> {
+    let machine_output = config.format != OutputFormat::Text;
+    // load_config refuses a mnemonic argument along with --mnemonic-file
+    let (mnemonic, source) = match (self::mnemonic_arg(args), args.get_one::<String>("mnemonic-file").map(String::as_str)) {
+        (None, Some(path)) => (prompt::read_secret_file(path)?.trim().to_string(), format!("the file {}", path)),
+        (Some(r), _) => {
+            // Scripts usually have no other way, people typing the command have the prompt
+            if io::stdin().is_terminal() {
+                warning!(true, "Warning: a mnemonic passed as an argument is kept in the shell history and visible to other users in the process list. Leave it out to be prompted for it");
+            }
+            (r, "the command line".to_string())
+        }
+        (None, None) => match self::env_secret(MNEMONIC_VAR) {
+            Some(r) => (r, format!("the {} environment variable", MNEMONIC_VAR)),
+            None => (prompt::read_secret("Enter mnemonic")?, "stdin".to_string()),
+        },
+    };
+    if config.verbose > 0 {
+        notice!(machine_output, "Mnemonic read from {}", source);
+    }
+    Ok(mnemonic)
+}
+
+/**
+ * Bounds of the chunksize picked when --chunksize isn't given. Chunks are
+ * kept large enough to amortize the scheduling and small enough for the
+ * stop flag, the time limit and the checkpoints to be honored quickly.
+ */
+const MIN_CHUNKSIZE: usize = 100;
+const MAX_CHUNKSIZE: usize = 100000;
+const CHUNKS_PER_THREAD: usize = 8;
+
+/**
+ * End of the range scanned when trying swapped words and --end isn't given,
+ * as every swap giving a valid checksum is a wallet of its own
+ */
+const SWAPS_DEFAULT_END: usize = 1000;
+
+/**
+ * Options giving what the wallet is derived from in place of the mnemonic
+ * words, a single one of which can be given. The SLIP-39 shares of the
+ * command line and of a file are combined together.
+ */
+const SOURCE_OPTIONS: [&str; 8] = ["mnemonic-file", "mnemonics-file", "seed", "slip39-share", "slip39-file", "xprv", "xpub", "entropy"];
+
+/**
+ * Lists the sources an option giving the wallet conflicts with
+ */
+fn other_sources(id: &str) -> Vec<&'static str> {
+    SOURCE_OPTIONS
+        .iter()
+        .copied()
+        .filter(|other| *other != id && !(id.starts_with("slip39-") && other.starts_with("slip39-")))
+        .collect()
+}
+
+/**
+ * Passphrase options, refused along with a seed or a key the passphrase is
+ * part of already
+ */
+const PASSPHRASE_OPTIONS: [&str; 4] = ["passphrase", "ask-passphrase", "passphrase-file", "passphrase-list"];
+
+/**
+ * Number of addresses a ranged descriptor target is derived over when
+ * --descriptor-range isn't given, the gap limit of BIP44, and the most it
+ * can be given, every address being held as a target through the scan
+ */
+const DESCRIPTOR_DEFAULT_RANGE: u32 = 20;
+const MAX_DESCRIPTOR_RANGE: u32 = 100000;
+
+/**
+ * Mnemonics read from a mnemonics file, along with their line
+ */
+pub struct MnemonicsList {
+    pub valid: Vec<(usize, Mnemonic)>,
+    pub skipped: Vec<SkippedLine>,
+}
+
+/**
+ * Parses a list of mnemonics, one per line. Empty lines and comments,
+ * starting with a '#', are ignored. A phrase found on several lines is only
+ * scanned for the first one.
+ */
+fn parse_mnemonics_list(content: &str, language: Option<Language>) -> MnemonicsList {
+    let mut valid: Vec<(usize, Mnemonic)> = Vec::new();
+    let mut skipped: Vec<SkippedLine> = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let phrase = line.split('#').next().unwrap_or_default().trim();
+        if phrase.is_empty() {
+            continue;
+        }
+        match derivation::get_mnemonic(phrase, language) {
+            Ok(mnemonic) => match valid.iter().find(|(_, known)| *known == mnemonic) {
+                Some((first, _)) => skipped.push(SkippedLine {
+                    line: i + 1,
+                    reason: format!("same mnemonic as line {}", first),
+                }),
+                None => valid.push((i + 1, mnemonic)),
+            },
+            Err(e) => skipped.push(SkippedLine {
+                line: i + 1,
+                reason: e.to_string().replacen("Invalid mnemonic", "invalid mnemonic", 1),
+            }),
+        }
+    }
+
+    MnemonicsList { valid, skipped }
+}
+
+#[test]
+fn test_parse_mnemonics_list(){
+    let content = "# estate backups\n\
+        erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb\n\
+        \n\
+        abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about  # paper wallet\n\
+        erupt quit sphere\n\
+        ERUPT quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb\n";
+    let list = parse_mnemonics_list(content, None);
+
+    assert_eq!(list.valid.iter().map(|(line, _)| *line).collect::<Vec<usize>>(), vec![2, 4]);
+    assert_eq!(list.valid[1].1.word_count(), 12);
+    let skipped: Vec<(usize, &str)> = list.skipped.iter().map(|skipped| (skipped.line, skipped.reason.as_str())).collect();
+    assert_eq!(
+        skipped,
+        vec![
+            (5, "invalid mnemonic: the phrase has 3 words, a mnemonic has 12, 15, 18, 21 or 24 words"),
+            (6, "same mnemonic as line 2"),
+        ]
+    );
+}
+
+/**
+ * Reads the mnemonics of --mnemonics-file, reporting the lines skipped.
+ * Fails only when none of the lines holds a valid mnemonic.
+ */
+pub fn read_mnemonics_file(args: &ArgMatches, config: &SearchConfig, path: &str) -> Result<MnemonicsList, HdiFinderError> {
+    if self::mnemonic_arg(args).is_some() || args.contains_id("mnemonic-file") {
+        return Err(HdiFinderError::InvalidArgument(
+            "--mnemonics-file can't be used along with another mnemonic".to_string(),
+        ));
+    }
+    let list = self::parse_mnemonics_list(&prompt::read_secret_list(path)?, config.language);
+    for skipped in &list.skipped {
+        notice!(config.format != OutputFormat::Text, "Skipping line {} of {}: {}", skipped.line, path, skipped.reason);
+    }
+    if list.valid.is_empty() {
+        return Err(HdiFinderError::InvalidArgument(format!("No valid mnemonic found in {}", path)));
+    }
+    if config.verbose > 0 {
+        notice!(config.format != OutputFormat::Text, "Scanning {} mnemonics from {}", list.valid.len(), path);
+    }
+    Ok(list)
+}
+
+/**
+ * Reads the phrase of an Electrum seed, which tells by its version the paths
+ * and the address type of the wallet
+ */
+pub fn read_electrum_seed(args: &ArgMatches, config: &SearchConfig) -> Result<(String, SeedVersion), HdiFinderError> {
+    let phrase = self::read_mnemonic(args, config)?;
+    let version = match electrum::seed_version(&phrase) {
+        Some(version) => version,
+        None if derivation::get_mnemonic(&phrase, None).is_ok() => {
+            return Err(HdiFinderError::Mnemonic {
+                reason: "the phrase is a BIP39 mnemonic, not an Electrum seed. Leave --seed-type electrum out".to_string(),
+                error: bip39::Error::InvalidChecksum,
+            })
+        }
+        None => {
+            return Err(HdiFinderError::Mnemonic {
+                reason: "the phrase isn't an Electrum seed, its hash doesn't tell a seed version. A word may be wrong".to_string(),
+                error: bip39::Error::InvalidChecksum,
+            })
+        }
+    };
+    if electrum::purpose(version).is_none() {
+        return Err(HdiFinderError::InvalidArgument(
+            "The phrase is a two-factor Electrum seed, whose multisig wallets aren't supported".to_string(),
+        ));
+    }
+    if config.verbose > 0 {
+        notice!(
+            config.format != OutputFormat::Text,
+            "Using an Electrum {} seed",
+            if version == SeedVersion::Segwit { "segwit" } else { "standard" }
+        );
+    }
+    Ok((phrase, version))
+}
+
+/**
+ * Reads an LND aezeed cipher seed and deciphers it with --aezeed-passphrase
+ * into its entropy, the seed of the wallet. LND derives the keys of its
+ * p2wpkh addresses at m/84'/0'/0', the only purpose scanned unless the
+ * purposes or the address type are given.
+ */
+pub fn read_aezeed(args: &ArgMatches, config: &mut SearchConfig) -> Result<Seed, HdiFinderError> {
+    let phrase = self::read_mnemonic(args, config)?;
+    let cipher_seed = aezeed::decipher(&phrase, args.get_one::<String>("aezeed-passphrase").map(String::as_str).unwrap_or(aezeed::DEFAULT_PASSPHRASE))?;
+    notice!(
+        config.format != OutputFormat::Text,
+        "aezeed wallet birthday: {}, {} days after the genesis block. The wallet has no address older than that",
+        cipher_seed.birthday_date(),
+        cipher_seed.birthday
+    );
+    if !args.contains_id("purposes") && !args.contains_id("type") {
+        config.purposes = vec![Purpose::Witness];
+    }
+    Ok(Seed::from_slice(&cipher_seed.entropy).expect("an aezeed entropy is 16 bytes, a valid seed length"))
+}
+
+/**
+ * Reads the seed of --seed, given in place of a mnemonic
+ */
+pub fn read_seed(config: &SearchConfig, hex: &str) -> Result<Seed, HdiFinderError> {
+    let seed = derivation::parse_seed(hex)?;
+    if io::stdin().is_terminal() {
+        warning!(true, "Warning: a seed passed as an argument is kept in the shell history and visible to other users in the process list");
+    }
+    if config.verbose > 0 {
+        notice!(config.format != OutputFormat::Text, "Using a {} bytes seed", seed.len());
+    }
+    Ok(seed)
+}
+
+/**
+ * Reads the SLIP-39 shares of --slip39-share and --slip39-file and combines
+ * them with the passphrase into the master secret, used as the seed
+ */
+pub fn read_slip39_shares(args: &ArgMatches, config: &SearchConfig) -> Result<Seed, HdiFinderError> {
+    let mut phrases: Vec<String> = args.get_many::<String>("slip39-share").map_or(Vec::new(), |values| values.map(String::from).collect());
+    if !phrases.is_empty() && io::stdin().is_terminal() {
+        warning!(true, "Warning: shares passed as arguments are kept in the shell history and visible to other users in the process list");
+    }
+    if let Some(path) = args.get_one::<String>("slip39-file").map(String::as_str) {
+        let content = prompt::read_secret_file(path)?;
+        phrases.extend(
+            content
+                .lines()
+                .map(|line| line.split('#').next().unwrap_or_default().trim())
+                .filter(|line| !line.is_empty())
+                .map(String::from),
+        );
+    }
+    let mut shares: Vec<slip39::Share> = Vec::with_capacity(phrases.len());
+    for (position, phrase) in phrases.iter().enumerate() {
+        shares.push(slip39::parse_share(phrase).map_err(|error| match error {
+            HdiFinderError::Shares(reason) => HdiFinderError::Shares(format!("share {}: {}", position + 1, reason)),
+            error => error,
+        })?);
+    }
+    let secret = slip39::combine(&shares, &config.passphrase)?;
+    let seed = Seed::from_slice(&secret).ok_or_else(|| HdiFinderError::Shares(format!("the master secret is {} bytes long", secret.len())))?;
+    if config.verbose > 0 {
+        notice!(
+            config.format != OutputFormat::Text,
+            "Using the {} bytes master secret of {} SLIP-39 shares",
+            seed.len(),
+            shares.len()
+        );
+    }
+    Ok(seed)
+}
+
+/**
+ * Reads the entropy of --entropy and builds the mnemonic it encodes, shown
+ * only with --show-mnemonic
+ */
+pub fn read_entropy(args: &ArgMatches, config: &SearchConfig, hex: &str) -> Result<Mnemonic, HdiFinderError> {
+    let mnemonic = derivation::mnemonic_from_entropy(hex, config.language)?;
+    if io::stdin().is_terminal() {
+        warning!(true, "Warning: an entropy passed as an argument is kept in the shell history and visible to other users in the process list");
+    }
+    if args.get_flag("show-mnemonic") {
+        notice!(config.format != OutputFormat::Text, "Mnemonic: {}", mnemonic);
+    }
+    Ok(mnemonic)
+}
+
+/**
+ * Scans only the purpose a SLIP-132 key prefix stands for (e.g. BIP84 for
+ * zpub), unless the purposes or the address type are given
+ */
+fn apply_key_purpose(args: &ArgMatches, config: &mut SearchConfig, purpose: Option<Purpose>) {
+    let purpose = match purpose {
+        Some(purpose) => purpose,
+        None => return,
+    };
+    let machine_output = config.format != OutputFormat::Text;
+    if args.contains_id("purposes") || args.contains_id("type") {
+        if !config.purposes.contains(&purpose) {
+            warning!(
+                machine_output,
+                "Warning: the key prefix stands for {} addresses, which aren't among the types scanned",
+                address::address_type(&purpose)
+            );
+        }
+        return;
+    }
+    if config.verbose > 0 {
+        notice!(machine_output, "Scanning {} addresses as the key prefix stands for", address::address_type(&purpose));
+    }
+    config.purposes = vec![purpose];
+}
+
+/**
+ * Reads the extended private key of --xprv, given in place of a mnemonic.
+ * An account level key holds a single account, whose index it tells.
+ */
+pub fn read_xprv(args: &ArgMatches, config: &mut SearchConfig, key: &str) -> Result<ExtendedPrivKey, HdiFinderError> {
+    let (key, purpose) = slip132::to_bip32(key, true)?;
+    let key = derivation::parse_xprv(&key, config.network)?;
+    self::apply_key_purpose(args, config, purpose);
+    if io::stdin().is_terminal() {
+        warning!(true, "Warning: a key passed as an argument is kept in the shell history and visible to other users in the process list");
+    }
+    let machine_output = config.format != OutputFormat::Text;
+    if key.depth == derivation::ACCOUNT_DEPTH {
+        if args.contains_id("accounts") {
+            return Err(HdiFinderError::InvalidArgument(
+                "--accounts can't be used along with an account level key, which holds a single account".to_string(),
+            ));
+        }
+        if config.verbose > 0 {
+            notice!(machine_output, "Using the account level key of account {}", derivation::child_index(key.child_number));
+        }
+    } else if config.verbose > 0 {
+        notice!(machine_output, "Using a master key");
+    }
+    Ok(key)
+}
+
+/**
+ * Reads the extended public key of --xpub for a watch-only scan. The key
+ * fixes the account, and the chain for a chain level key.
+ */
+pub fn read_xpub(args: &ArgMatches, config: &mut SearchConfig, key: &str) -> Result<ExtendedPubKey, HdiFinderError> {
+    for flag in ["with-private", "show-private", "sign-message", "sign-message-bip322", "psbt", "hardened-index"] {
+        if self::is_given(args, flag) {
+            return Err(HdiFinderError::InvalidArgument(format!(
+                "--{} can't be used along with --xpub, which holds no private key",
+                flag
+            )));
+        }
+    }
+    let (key, purpose) = slip132::to_bip32(key, false)?;
+    let key = derivation::parse_xpub(&key, config.network)?;
+    self::apply_key_purpose(args, config, purpose);
+    let index = derivation::child_index(key.child_number);
+    if key.depth == derivation::ACCOUNT_DEPTH && args.contains_id("accounts") {
+        return Err(HdiFinderError::InvalidArgument(
+            "--accounts can't be used along with an account level key, which holds a single account".to_string(),
+        ));
+    }
+    if key.depth == derivation::CHAIN_DEPTH {
+        // The account of a chain level key is unknown, --accounts only tells
+        // which one to report
+        if config.accounts.start() != config.accounts.end() {
+            return Err(HdiFinderError::InvalidArgument(
+                "A chain level key belongs to a single account, --accounts can't be a range along with it".to_string(),
+            ));
+        }
+        if (args.contains_id("chains") || args.get_flag("change")) && config.chains != [index] {
+            return Err(HdiFinderError::InvalidArgument(format!(
+                "The extended public key is the key of chain {}, --chains and --change can't select other chains",
+                index
+            )));
+        }
+    }
+    notice!(
+        config.format != OutputFormat::Text,
+        "Watch-only mode: the addresses are derived from the {} level public key of {} {}, no private key is involved",
+        if key.depth == derivation::CHAIN_DEPTH { "chain" } else { "account" },
+        if key.depth == derivation::CHAIN_DEPTH { "chain" } else { "account" },
+        index
+    );
+    Ok(key)
+}
+
+/**
+ * Configuration builder
+ */
+pub fn load_config(args: &ArgMatches) -> Result<SearchConfig, HdiFinderError> {
+    let mut format: OutputFormat = OutputFormat::Text;
+    if args.get_flag("json") {
+        format = OutputFormat::Json;
+    }
+    if let Some(r) = args.get_one::<OutputFormat>("format") {
+        format = *r;
+    }
+    // The results are written to the file of --output in the format asked for,
+    // stdout getting the text output
+    let output = args.get_one::<String>("output").cloned();
+    let output_format = format;
+    if output.is_some() {
+        format = OutputFormat::Text;
+    } else if args.get_flag("quiet") && (args.get_flag("json") || args.contains_id("format")) {
+        return Err(HdiFinderError::InvalidArgument("--quiet can't be used with --json or --format, unless the results are written to --output".to_string()));
+    }
+    if args.get_flag("quiet") {
+        format = match args.get_one::<String>("print").map(String::as_str) {
+            Some("path") => OutputFormat::Path,
+            _ => OutputFormat::Index,
+        };
+    }
+    let machine_output = format != OutputFormat::Text;
+
+    let verbose = u64::from(args.get_count("verbose"));
+
+    let passphrase_flags: Vec<String> = PASSPHRASE_OPTIONS
+        .iter()
+        .filter(|flag| self::is_given(args, flag))
+        .map(|flag| format!("--{}", flag))
+        .collect();
+    let passphrase = match (args.get_one::<String>("passphrase").map(String::as_str), self::env_secret(PASSPHRASE_VAR)) {
+        (Some(r), _) => r.to_string(),
+        (None, _) if args.get_flag("ask-passphrase") => prompt::ask_passphrase()?,
+        (None, _) if args.contains_id("passphrase-file") => prompt::read_passphrase_file(
+            args.get_one::<String>("passphrase-file").map(String::as_str).unwrap_or_default(),
+            args.get_flag("passphrase-keep-trailing-newline"),
+        )?,
+        (None, Some(r)) => {
+            if verbose > 0 {
+                notice!(machine_output, "Passphrase read from the {} environment variable", PASSPHRASE_VAR);
+            }
+            r
+        }
+        (None, None) => String::new(),
+    };
+
+    // The words of the command line share their positions with the targets,
+    // which clap can't tell apart, the other sources conflicting in clap
+    if self::mnemonic_arg(args).is_some() {
+        if let Some(source) = SOURCE_OPTIONS.iter().find(|source| self::is_given(args, source)) {
+            return Err(HdiFinderError::InvalidArgument(format!("--{} can't be used along with a mnemonic argument", source)));
+        }
+    }
+
+    // The passphrase has been applied when computing the seed or the key. The
+    // passphrase options conflict in clap, the environment variable is left
+    for (source, secret) in [("seed", "seed"), ("xprv", "key"), ("xpub", "key")] {
+        if !self::is_given(args, source) {
+            continue;
+        }
+        if !passphrase.is_empty() {
+            return Err(HdiFinderError::InvalidArgument(format!(
+                "--{} can't be used along with a passphrase, the passphrase is part of the {}. Unset {}",
+                source, secret, PASSPHRASE_VAR
+            )));
+        }
+    }
+
+    // Electrum seeds tell their paths and address type, and aren't made of
+    // BIP39 words
+    if args.get_one::<String>("seed-type").map(String::as_str) == Some("electrum") {
+        for flag in [
+            "seed", "entropy", "xprv", "xpub", "slip39-share", "slip39-file", "mnemonics-file", "fix-checksum", "try-swaps", "language",
+            "purposes", "type", "preset", "accounts",
+        ] {
+            if self::is_given(args, flag) {
+                return Err(HdiFinderError::InvalidArgument(format!("--seed-type electrum can't be used along with --{}", flag)));
+            }
+        }
+    }
+
+    // aezeed seeds are enciphered with a passphrase of their own, and aren't
+    // BIP39 mnemonics either
+    if args.get_one::<String>("seed-type").map(String::as_str) == Some("aezeed") {
+        for flag in [
+            "seed", "entropy", "xprv", "xpub", "slip39-share", "slip39-file", "mnemonics-file", "fix-checksum", "try-swaps", "language",
+        ] {
+            if self::is_given(args, flag) {
+                return Err(HdiFinderError::InvalidArgument(format!("--seed-type aezeed can't be used along with --{}", flag)));
+            }
+        }
+        if let Some(flag) = passphrase_flags.first() {
+            return Err(HdiFinderError::InvalidArgument(format!(
+                "--seed-type aezeed can't be used along with {}, the passphrase of an aezeed seed is given with --aezeed-passphrase",
+                flag
+            )));
+        }
+    } else if args.contains_id("aezeed-passphrase") {
+        return Err(HdiFinderError::InvalidArgument("--aezeed-passphrase is only used along with --seed-type aezeed".to_string()));
+    }
+
+    // The child mnemonics are BIP39 ones, derived from a master seed
+    if args.contains_id("bip85-children") && args.get_one::<String>("seed-type").map(String::as_str) == Some("electrum") {
+        return Err(HdiFinderError::InvalidArgument(
+            "--bip85-children can't be used along with --seed-type electrum".to_string(),
+        ));
+    }
+    let bip85_children: Option<u32> = match args.get_one::<u32>("bip85-children").copied() {
+        Some(count) if count == 0 || count > MAX_INDEX + 1 => {
+            return Err(HdiFinderError::InvalidArgument(format!(
+                "Invalid BIP85 children count {}. Expected a number of child mnemonics from 1 to {}",
+                count,
+                MAX_INDEX as u64 + 1
+            )))
+        }
+        count => count,
+    };
+
+    let mut passphrase_candidates = Vec::new();
+    if let Some(path) = args.get_one::<String>("passphrase-list").map(String::as_str) {
+        passphrase_candidates = prompt::read_passphrase_list(path)?;
+        if verbose > 0 {
+            notice!(machine_output, "Trying {} passphrase candidates from {}", passphrase_candidates.len(), path);
+        }
+    }
+
+    let try_swaps = if !args.contains_id("try-swaps") {
+        None
+    } else if args.get_one::<String>("try-swaps").map(String::as_str) == Some("all") {
+        Some(Swaps::All)
+    } else {
+        Some(Swaps::Adjacent)
+    };
+
+    let start = args.get_one::<usize>("start").copied().unwrap_or(0);
+    let mut end = args.get_one::<usize>("end").copied().unwrap_or(if try_swaps.is_some() { SWAPS_DEFAULT_END } else { 10000000 });
+    let chunksize = args.get_one::<usize>("chunksize").copied();
+
+    let max_duration = args.get_one::<Duration>("max-duration").copied();
+
+    // 0 lets rayon pick one thread per core
+    let threads = args.get_one::<usize>("threads").copied().unwrap_or(0);
+
+    let trace_derivations = args.get_one::<usize>("trace-derivations").copied();
+    if trace_derivations == Some(0) {
+        return Err(HdiFinderError::InvalidArgument("Invalid trace interval 0. Expected a positive number".to_string()));
+    }
+
+    let language = args.get_one::<Language>("language").copied();
+
+    let PathOptions {
+        network,
+        mut purposes,
+        accounts,
+        chains,
+        preset_end,
+        layout,
+        mut named_accounts,
+    } = self::read_path_options(args)?;
+    // Only the supported address types are scanned by default when signing
+    // with BIP322, the others being refused when asked for
+    if args.contains_id("sign-message-bip322") {
+        let unsupported: Vec<&str> = purposes.iter().map(address::address_type).filter(|t| !bip322::is_supported(t)).collect();
+        if !unsupported.is_empty() && ["purposes", "type", "preset"].iter().any(|flag| self::is_given(args, flag)) {
+            return Err(HdiFinderError::InvalidArgument(format!(
+                "--sign-message-bip322 only signs p2wpkh and p2pkh addresses, not {}",
+                unsupported.join(" or ")
+            )));
+        }
+        purposes.retain(|purpose| bip322::is_supported(address::address_type(purpose)));
+    }
+    let definitions = match args.get_one::<String>("coin-config").map(String::as_str) {
+        Some(path) => {
+            let text = fs::read_to_string(path).map_err(|source| HdiFinderError::Io {
+                path: path.to_string(),
+                source,
+            })?;
+            self::coin_definitions(&text).map_err(|reason| HdiFinderError::InvalidArgument(format!("Invalid coin config {}: {}", path, reason)))?
+        }
+        None => Vec::new(),
+    };
+    let coin = match args.get_one::<String>("coin").map(String::as_str) {
+        Some(name) => self::parse_coin(name, &definitions).ok_or_else(|| {
+            HdiFinderError::InvalidArgument(format!("Unknown coin {}. Supported coins are: {}", name, self::coin_names(&definitions).join(", ")))
+        })?,
+        None => Coin::Bitcoin,
+    };
+    let coin_params = address::address_params(coin, network);
+    if coin != Coin::Bitcoin {
+        // Only the address types of the coin are scanned by default
+        let unsupported: Vec<&str> = purposes.iter().filter(|purpose| !coin_params.supports(purpose)).map(address::address_type).collect();
+        if !unsupported.is_empty() && ["purposes", "type", "preset"].iter().any(|flag| self::is_given(args, flag)) {
+            return Err(HdiFinderError::InvalidArgument(format!(
+                "--coin {} can't scan {} addresses, which {} doesn't have",
+                self::coin_name(coin),
+                unsupported.join(" or "),
+                coin
+            )));
+        }
+        purposes.retain(|purpose| coin_params.supports(purpose));
+        // The keys and the signatures they report are encoded for bitcoin,
+        // and an Ethereum account has neither a script nor a hash160
+        let mut bitcoin_only = vec!["xprv", "xpub", "show-private", "show-xpub", "emit-descriptor", "sign-message", "sign-message-bip322", "psbt"];
+        if coin_params.encoding == Encoding::Ethereum {
+            bitcoin_only.extend(["hash160", "script", "include-uncompressed"]);
+            if layout.is_legacy() {
+                bitcoin_only.push("preset");
+            }
+        }
+        let bitcoin_only: Vec<String> = bitcoin_only
+            .iter()
+            .filter(|flag| self::is_given(args, flag))
+            .map(|flag| format!("--{}", flag))
+            .collect();
+        if !bitcoin_only.is_empty() {
+            return Err(HdiFinderError::InvalidArgument(format!(
+                "--coin {} can't be used along with {}",
+                self::coin_name(coin),
+                bitcoin_only.join(", ")
+            )));
+        }
+    }
+    // Segwit outputs can't commit to an uncompressed key, the legacy
+    // layouts scanning both forms already
+    if args.get_flag("include-uncompressed") && !layout.is_legacy() && !purposes.contains(&Purpose::Pubkey) {
+        return Err(HdiFinderError::InvalidArgument(
+            "--include-uncompressed only applies to p2pkh addresses, which the scanned purposes don't have".to_string(),
+        ));
+    }
+    // The cosigners give their BIP48 account keys, the seed holding the key
+    // of one more cosigner
+    let multisig = match args.get_many::<String>("cosigner-xpub") {
+        Some(keys) => {
+            let cosigners = keys.map(|key| multisig::parse_cosigner(key, network)).collect::<Result<Vec<_>, _>>()?;
+            let threshold = args.get_one::<usize>("threshold").copied().unwrap_or(0);
+            multisig::check_threshold(threshold, cosigners.len() + 1)
+                .map_err(|reason| HdiFinderError::InvalidArgument(format!("Invalid --threshold: {}", reason)))?;
+            // Counted from 1, as the keys of multi() read
+            let key_position = args.get_one::<usize>("key-position").copied().unwrap_or(1);
+            if key_position > cosigners.len() + 1 {
+                return Err(HdiFinderError::InvalidArgument(format!(
+                    "Invalid --key-position: {} out of the 1 to {} keys of the script",
+                    key_position,
+                    cosigners.len() + 1
+                )));
+            }
+            Some(Multisig {
+                threshold,
+                cosigners,
+                sorted: !args.get_flag("no-sort-keys"),
+                key_position: key_position - 1,
+                nested: args.get_flag("p2sh-wrapped"),
+            })
+        }
+        None => None,
+    };
+    // The named accounts of a purpose no longer scanned are left out as well
+    named_accounts.retain(|named| purposes.contains(&named.purpose));
+    if let Some(preset_end) = preset_end {
+        if !args.contains_id("end") {
+            end = preset_end;
+        }
+    }
+
+    let auto_chunksize = chunksize.is_none();
+    let workers = if threads == 0 { rayon::current_num_threads() } else { threads };
+    let requested_chunksize = chunksize.unwrap_or_else(|| self::default_chunksize(end.saturating_sub(start), workers));
+    let chunksize = self::check_range(start, end, requested_chunksize)?;
+    if chunksize != requested_chunksize && !auto_chunksize {
+        notice!(machine_output, "Chunksize {} is larger than the range, using {}", requested_chunksize, chunksize);
+    }
+    if auto_chunksize && verbose > 0 {
+        notice!(machine_output, "Using a chunksize of {} indexes for {} threads", chunksize, workers);
+    }
+
+    let (_, given) = self::free_args(args);
+
+    let ignore_network_mismatch = args.get_flag("ignore-network-mismatch");
+    let mut addresses: HashSet<String> = HashSet::new();
+    let mut targets: HashMap<String, AddressTarget> = HashMap::new();
+    let mut given_targets: Vec<(String, AddressTarget)> = Vec::new();
+    let descriptor_range = args.get_one::<u32>("descriptor-range").copied().unwrap_or(DESCRIPTOR_DEFAULT_RANGE);
+    if descriptor_range > MAX_DESCRIPTOR_RANGE {
+        return Err(HdiFinderError::InvalidArgument(format!(
+            "--descriptor-range {} is larger than the {} addresses a ranged descriptor can be derived over",
+            descriptor_range, MAX_DESCRIPTOR_RANGE
+        )));
+    }
+    for r in given {
+        if descriptor::is_descriptor(r) {
+            given_targets.extend(self::descriptor_targets(r, 0..descriptor_range, network)?);
+        } else {
+            given_targets.push(self::read_target(r, coin, network)?);
+        }
+    }
+    for (address, target) in given_targets {
+        if let Some(reason) = self::network_mismatch(&address, &target, network, coin) {
+            if !ignore_network_mismatch {
+                return Err(HdiFinderError::InvalidAddress(format!("Target {}", reason)));
+            }
+            warning!(machine_output, "Warning: target {}. Scanning anyway as --ignore-network-mismatch is given", reason);
+        }
+        if target.address_type == Some("p2sh") {
+            return Err(HdiFinderError::InvalidAddress(format!(
+                "Target {} is a p2sh address, which can't be derived: it pays to a script rather than to a key of the wallet",
+                address
+            )));
+        }
+        if let Some(address_type) = target.address_type {
+            if address::type_purpose(address_type).map_or(false, |purpose| !coin_params.supports(&purpose)) {
+                return Err(HdiFinderError::InvalidAddress(format!(
+                    "Target {} is a {} address, which {} doesn't have",
+                    address, address_type, coin
+                )));
+            }
+        }
+        let address = address::canonical_target(&address, &coin_params);
+        addresses.insert(address.clone());
+        targets.insert(address, target);
+    }
+
+    if let Some(r) = args.get_many::<String>("pubkey") {
+        for hex in r {
+            let (key, target) = address::pubkey_target(hex, network)?;
+            addresses.insert(key.clone());
+            targets.insert(key, target);
+        }
+    }
+
+    if let Some(r) = args.get_many::<String>("hash160") {
+        for hex in r {
+            let (hash, target) = address::hash160_target(hex, network)?;
+            addresses.insert(hash.clone());
+            targets.insert(hash, target);
+        }
+    }
+
+    if let Some(r) = args.get_many::<String>("script") {
+        for hex in r {
+            let (address, target) = address::script_target(hex, network, coin)?;
+            addresses.insert(address.clone());
+            targets.insert(address, target);
+        }
+    }
+
+    if let Some(r) = args.get_one::<String>("addresses-file").map(String::as_str) {
+        let content = fs::read_to_string(r).map_err(|source| HdiFinderError::Io {
+            path: r.to_string(),
+            source,
+        })?;
+        let list = self::parse_addresses_list(&content, network, coin, ignore_network_mismatch);
+        for skipped in &list.skipped {
+            notice!(machine_output, "Skipping line {} of {}: {}", skipped.line, r, skipped.reason);
+        }
+        for (address, target) in list.valid {
+            addresses.insert(address.clone());
+            targets.insert(address, target);
+        }
+    }
+
+    if addresses.is_empty() {
+        return Err(HdiFinderError::InvalidArgument("No address provided".to_string()));
+    }
+
+    // A list of targets is usually not owned by a single wallet,
+    // so every match is reported instead of stopping at the first one.
+    let stop_at_first = !args.get_flag("all") && !args.contains_id("addresses-file");
+
+    // The progress bar would only get in the way of scripts and pipes
+    let progress = !machine_output && !args.get_flag("no-progress") && io::stdout().is_terminal();
+
+    Ok(SearchConfig {
+        passphrase,
+        passphrase_candidates,
+        redact: args.get_flag("redact"),
+        fix_checksum: args.get_flag("fix-checksum"),
+        try_swaps,
+        language,
+        start,
+        end,
+        chunksize,
+        addresses,
+        targets,
+        stop_at_first,
+        format,
+        network,
+        coin,
+        purposes,
+        accounts,
+        chains,
+        layout,
+        named_accounts,
+        hardened_index: args.get_flag("hardened-index"),
+        include_uncompressed: args.get_flag("include-uncompressed"),
+        multisig,
+        progress,
+        threads,
+        pubkey_only: args.get_flag("pubkey-only"),
+        state_file: args.get_one::<String>("state-file").cloned(),
+        max_duration,
+        dry_run: args.get_flag("dry-run"),
+        verbose,
+        trace_derivations,
+        bip85_children,
+        bip85_words: args.get_one::<String>("bip85-words").map(String::as_str).and_then(|words| words.parse().ok()).unwrap_or(12),
+        emit_descriptor: args.get_flag("emit-descriptor"),
+        with_private: args.get_flag("with-private"),
+        show_private: args.get_flag("show-private"),
+        show_xpub: args.get_flag("show-xpub"),
+        sign_message: args.get_one::<String>("sign-message").cloned(),
+        sign_message_bip322: args.get_one::<String>("sign-message-bip322").cloned(),
+        psbt: args.get_one::<String>("psbt").map(String::as_str).map(psbt::read_psbt).transpose()?,
+        psbt_out: args.get_one::<String>("psbt-out").cloned(),
+        sign_all_known: args.get_flag("sign-all-known"),
+        // Builds without the qr feature have none of its options
+        qr: cfg!(feature = "qr") && args.get_flag("qr"),
+        qr_private: cfg!(feature = "qr") && args.get_flag("qr-private"),
+        qr_file: if cfg!(feature = "qr") { args.get_one::<String>("qr-file").cloned() } else { None },
+        output,
+        output_format,
+        force_output: args.get_flag("force"),
+    })
+}
+
+#[test]
+fn test_load_config_targets(){
+    let args = app().get_matches_from(vec!["hdifinder", "erupt quit", "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", "bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y"]);
+    let config = load_config(&args).unwrap();
+    assert_eq!(config.targets["14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"].address_type, Some("p2pkh"));
+    assert_eq!(config.targets["bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y"].address_type, Some("p2wpkh"));
+    assert_eq!(config.targets["bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y"].network, Network::Bitcoin);
+
+    // Mistyped addresses are rejected before any scan
+    let args = app().get_matches_from(vec!["hdifinder", "erupt quit", "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgL"]);
+    assert!(matches!(load_config(&args), Err(HdiFinderError::InvalidAddress(reason)) if reason.contains("base58ck checksum")));
+    let args = app().get_matches_from(vec!["hdifinder", "erupt quit", "bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtK5u60h0y"]);
+    assert!(matches!(load_config(&args), Err(HdiFinderError::InvalidAddress(reason)) if reason.ends_with("bech32: mixed-case strings not allowed")));
+}
+
+/**
+ * Where the addresses are derived: the network along with the purposes,
+ * accounts and chains, shared by the search and the derive subcommand
+ */
+pub struct PathOptions {
+    pub network: Network,
+    pub purposes: Vec<Purpose>,
+    pub accounts: RangeInclusive<u32>,
+    pub chains: Vec<u32>,
+    // The end of the range the preset scans by default
+    pub preset_end: Option<usize>,
+    // Where the chains sit, the legacy layouts of a preset having no purpose level
+    pub layout: Layout,
+    // The accounts of the preset scanned besides the range of --accounts
+    pub named_accounts: Vec<NamedAccount>,
+}
+
+impl PathOptions {
+    /**
+     * Lists the accounts to derive along with their purpose, the ones of the
+     * range under each purpose followed by the named accounts
+     */
+    pub fn account_purposes(&self) -> Vec<(u32, Purpose)> {
+        let mut accounts: Vec<(u32, Purpose)> = Vec::new();
+        for account in self.accounts.clone() {
+            accounts.extend(self.purposes.iter().map(|purpose| (account, purpose.clone())));
+        }
+        accounts.extend(self.named_accounts.iter().map(|named| (named.index, named.purpose.clone())));
+        accounts
+    }
+}
+
+/**
+ * Reads --network, --purposes, --type, --accounts, --change, --chains and
+ * --preset, the explicit options taking precedence over the preset
+ */
+pub fn read_path_options(args: &ArgMatches) -> Result<PathOptions, HdiFinderError> {
+    let network = args.get_one::<Network>("network").copied().unwrap_or(Network::Bitcoin);
+
+    let mut purposes: Vec<Purpose> = vec![
+        Purpose::Pubkey,
+        Purpose::ScriptHash,
+        Purpose::Witness,
+        Purpose::Custom(86),
+    ];
+    if let Some(r) = args.get_one::<String>("purposes").map(String::as_str) {
+        match self::parse_purposes(r) {
+            Some(p) => purposes = p,
+            None => {
+                return Err(HdiFinderError::InvalidArgument(format!("Invalid purposes {}. Supported purposes are 44, 49, 84 and 86", r)));
+            }
+        }
+    }
+    // Only the purpose the address type is standard for needs to be derived
+    if let Some(r) = args.get_one::<String>("type").map(String::as_str).filter(|r| *r != "all") {
+        match address::type_purpose(r) {
+            Some(p) => purposes = vec![p],
+            None => {
+                return Err(HdiFinderError::InvalidArgument(format!("Invalid type {}. Supported types are p2pkh, p2shwpkh, p2wpkh and p2tr", r)));
+            }
+        }
+    }
+
+    let mut accounts: RangeInclusive<u32> = 0..=0;
+    if let Some(r) = args.get_one::<String>("accounts").map(String::as_str) {
+        match self::parse_accounts(r) {
+            Some(a) => accounts = a,
+            None => {
+                return Err(HdiFinderError::InvalidArgument(format!("Invalid accounts range {}. Expected an account index or a range like 0-500", r)));
+            }
+        }
+    }
+
+    let mut chains: Vec<u32> = vec![0];
+    if args.get_flag("change") {
+        chains = vec![1];
+    }
+    if let Some(r) = args.get_one::<String>("chains").map(String::as_str) {
+        match self::parse_chains(r) {
+            Some(c) => chains = c,
+            None => {
+                return Err(HdiFinderError::InvalidArgument(format!("Invalid chains {}. Supported chains are 0 (external) and 1 (internal)", r)));
+            }
+        }
+    }
+
+    // Explicit options always take precedence over the preset values
+    let mut preset_end: Option<usize> = None;
+    let mut layout = Layout::Bip44;
+    let mut named_accounts: Vec<NamedAccount> = Vec::new();
+    if let Some(r) = args.get_one::<String>("preset").map(String::as_str) {
+        let preset: &Preset = match presets::find_preset(r) {
+            Some(p) => p,
+            None => {
+                return Err(HdiFinderError::InvalidArgument(format!("Unknown preset {}. Supported presets are: {}", r, presets::preset_names().join(", "))));
+            }
+        };
+        if !args.contains_id("purposes") && !args.contains_id("type") {
+            purposes = preset.purposes.to_vec();
+        }
+        if !args.contains_id("accounts") {
+            accounts = preset.accounts.clone();
+            named_accounts = preset.named_accounts.iter().filter(|named| purposes.contains(&named.purpose)).cloned().collect();
+        }
+        if !args.contains_id("chains") && !args.get_flag("change") {
+            chains = preset.chains.to_vec();
+        }
+        preset_end = preset.end;
+        layout = preset.layout;
+        if layout.is_legacy() && purposes != [Purpose::Pubkey] {
+            return Err(HdiFinderError::InvalidArgument(format!(
+                "--preset {} only derives p2pkh addresses, its paths having no purpose level",
+                r
+            )));
+        }
+    }
+
+    Ok(PathOptions {
+        network,
+        purposes,
+        accounts,
+        chains,
+        preset_end,
+        layout,
+        named_accounts,
+    })
+}
+
+/**
+ * Options of the bench subcommand
+ */
+pub struct BenchOptions {
+    pub keys: usize,
+    pub json: bool,
+}
+
+/**
+ * Reads the options of the bench subcommand
+ */
+pub fn bench_options(args: &ArgMatches) -> Result<BenchOptions, HdiFinderError> {
+    let keys = args.get_one::<usize>("keys").copied().unwrap_or_default();
+    if keys == 0 {
+        return Err(HdiFinderError::InvalidArgument(format!("Invalid number of keys {}. Expected a positive number", keys)));
+    }
+    Ok(BenchOptions {
+        keys,
+        json: args.get_flag("json") || args.get_one::<OutputFormat>("format") == Some(&OutputFormat::Json),
+    })
+}
+
+#[test]
+fn test_bench_options(){
+    let args = app().get_matches_from(vec!["hdifinder", "bench", "--keys", "10"]);
+    let options = bench_options(args.subcommand_matches("bench").unwrap()).unwrap();
+    assert_eq!(options.keys, 10);
+    assert!(!options.json);
+    let args = app().get_matches_from(vec!["hdifinder", "--format", "json", "bench"]);
+    assert!(bench_options(args.subcommand_matches("bench").unwrap()).unwrap().json);
+    let args = app().get_matches_from(vec!["hdifinder", "bench", "--keys", "0"]);
+    assert!(bench_options(args.subcommand_matches("bench").unwrap()).is_err());
+}
+
+/**
+ * Options of the generate subcommand
+ */
+pub struct GenerateOptions {
+    pub words: usize,
+    pub network: Network,
+    pub purposes: Vec<Purpose>,
+    pub count: u32,
+    pub json: bool,
+}
+
+/**
+ * Reads the options of the generate subcommand. The mnemonic is only printed
+ * to a terminal unless told otherwise, a pipe or a file possibly ending up
+ * in logs.
+ */
+pub fn generate_options(args: &ArgMatches) -> Result<GenerateOptions, HdiFinderError> {
+    if !io::stdout().is_terminal() && !args.get_flag("i-know-what-im-doing") {
+        return Err(HdiFinderError::InvalidArgument(
+            "Refusing to print a new mnemonic to a pipe or a file, where it may be logged. Run it in a terminal or give --i-know-what-im-doing".to_string(),
+        ));
+    }
+    let words = args.get_one::<String>("words").map(String::as_str).and_then(|words| words.parse().ok()).unwrap_or(12);
+    let network = args.get_one::<Network>("network").copied().unwrap_or(Network::Bitcoin);
+    let purposes = args.get_one::<String>("purposes").map(String::as_str).unwrap_or("44,49,84,86");
+    let purposes = self::parse_purposes(purposes).ok_or_else(|| {
+        HdiFinderError::InvalidArgument(format!("Invalid purposes {}. Supported purposes are 44, 49, 84 and 86", purposes))
+    })?;
+    let count = args.get_one::<u32>("count").copied().unwrap_or(5);
+    if count > MAX_INDEX {
+        return Err(HdiFinderError::InvalidArgument(format!("Invalid count {}. Expected a number of addresses", count)));
+    }
+    Ok(GenerateOptions {
+        words,
+        network,
+        purposes,
+        count,
+        json: args.get_flag("json") || args.get_one::<OutputFormat>("format") == Some(&OutputFormat::Json),
+    })
+}
+
+/**
+ * Options of the derive subcommand
+ */
+pub struct DeriveOptions {
+    pub paths: PathOptions,
+    pub start: u32,
+    pub end: u32,
+    pub format: OutputFormat,
+    pub show_xpub: bool,
+}
+
+/**
+ * Reads the options of the derive subcommand, the paths being read as for
+ * the search
+ */
+pub fn derive_options(args: &ArgMatches) -> Result<DeriveOptions, HdiFinderError> {
+    let start = args.get_one::<usize>("start").copied().unwrap_or(0);
+    let end = args.get_one::<usize>("end").copied().unwrap_or(20);
+    self::check_range(start, end, 1)?;
+    let format = match args.get_one::<OutputFormat>("format") {
+        Some(OutputFormat::Json) => OutputFormat::Json,
+        Some(OutputFormat::Csv) => OutputFormat::Csv,
+        Some(OutputFormat::Ndjson) => return Err(HdiFinderError::InvalidArgument("The addresses are listed as text, json or csv, not ndjson".to_string())),
+        _ => OutputFormat::Text,
+    };
+    Ok(DeriveOptions {
+        paths: self::read_path_options(args)?,
+        start: start as u32,
+        end: end as u32,
+        format,
+        show_xpub: args.get_flag("show-xpub"),
+    })
+}
+
+#[test]
+fn test_derive_options(){
+    let args = app().get_matches_from(vec!["hdifinder", "derive", "erupt quit", "--end", "50", "--type", "all", "--change"]);
+    let options = derive_options(args.subcommand_matches("derive").unwrap()).unwrap();
+    assert_eq!((options.start, options.end), (0, 50));
+    assert_eq!(options.paths.purposes.len(), 4);
+    assert_eq!(options.paths.chains, vec![1]);
+    let args = app().get_matches_from(vec!["hdifinder", "derive", "erupt quit", "--type", "p2wpkh", "--format", "csv"]);
+    let options = derive_options(args.subcommand_matches("derive").unwrap()).unwrap();
+    assert_eq!(options.paths.purposes, vec![Purpose::Witness]);
+    assert!(options.format == OutputFormat::Csv);
+    let args = app().get_matches_from(vec!["hdifinder", "derive", "erupt quit", "--start", "50", "--end", "50"]);
+    assert!(matches!(derive_options(args.subcommand_matches("derive").unwrap()), Err(HdiFinderError::InvalidRange(_))));
+}
+
+/**
+ * Options of the verify subcommand
+ */
+pub struct VerifyOptions {
+    pub paths: PathOptions,
+    pub index: u32,
+    pub address: String,
+}
+
+/**
+ * Reads the options of the verify subcommand. The address is checked
+ * against the network first, a mismatch there telling nothing about the
+ * wallet.
+ */
+pub fn verify_options(args: &ArgMatches) -> Result<VerifyOptions, HdiFinderError> {
+    let paths = self::read_path_options(args)?;
+    let index = args.get_one::<u32>("index").copied().unwrap_or_default();
+    if index > MAX_INDEX {
+        return Err(HdiFinderError::InvalidRange(format!(
+            "Invalid --index {}. Expected a non-hardened index from 0 to {}",
+            index, MAX_INDEX
+        )));
+    }
+    let (address, target) = self::read_target(args.get_one::<String>("address").map(String::as_str).unwrap_or_default(), Coin::Bitcoin, paths.network)?;
+    if let Some(reason) = self::network_mismatch(&address, &target, paths.network, Coin::Bitcoin) {
+        return Err(HdiFinderError::InvalidAddress(format!("Target {}", reason)));
+    }
+    Ok(VerifyOptions { paths, index, address })
+}
+
+#[test]
+fn test_verify_options(){
+    let args = app().get_matches_from(vec!["hdifinder", "verify", "erupt quit", "--index", "5", "--address", "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"]);
+    let options = verify_options(args.subcommand_matches("verify").unwrap()).unwrap();
+    assert_eq!(options.index, 5);
+    assert_eq!(options.paths.purposes.len(), 4);
+    let args = app().get_matches_from(vec!["hdifinder", "verify", "erupt quit", "--index", "2147483648", "--address", "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"]);
+    assert!(matches!(verify_options(args.subcommand_matches("verify").unwrap()), Err(HdiFinderError::InvalidRange(_))));
+    let args = app().get_matches_from(vec![
+        "hdifinder", "verify", "erupt quit", "--index", "5", "--address", "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", "--network", "testnet",
+    ]);
+    assert_eq!(
+        verify_options(args.subcommand_matches("verify").unwrap()).err().unwrap().to_string(),
+        "Target 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK is a bitcoin mainnet address but the search network is testnet; pass --network mainnet"
+    );
+}
+
+/**
+ * Checks the index range and the chunksize against each other.
+ * Returns the chunksize clamped to the size of the range.
+ */
+fn check_range(start: usize, end: usize, chunksize: usize) -> Result<usize, HdiFinderError> {
+    if chunksize == 0 {
+        return Err(HdiFinderError::InvalidRange("Invalid --chunksize 0. The chunksize must be at least 1".to_string()));
+    }
+    if start >= end {
+        return Err(HdiFinderError::InvalidRange(format!(
+            "Invalid --start {}. The start index must be lower than the end index (--end {})",
+            start, end
+        )));
+    }
+    // end is exclusive, so it may be one past the last index
+    if end > MAX_INDEX as usize + 1 {
+        return Err(HdiFinderError::InvalidRange(format!(
+            "Invalid --end {}. Non-hardened BIP32 indexes can't exceed {}",
+            end, MAX_INDEX
+        )));
+    }
+    Ok(chunksize.min(end - start))
+}
+
+#[test]
+fn test_check_range(){
+    assert_eq!(check_range(0, 10000, 2500).unwrap(), 2500);
+    assert_eq!(check_range(0, 10, 2500).unwrap(), 10);
+    assert_eq!(check_range(500, 1000, 500).unwrap(), 500);
+    assert_eq!(check_range(0, 10, 0).unwrap_err().to_string(), "Invalid --chunksize 0. The chunksize must be at least 1");
+    assert_eq!(
+        check_range(100, 50, 10).unwrap_err().to_string(),
+        "Invalid --start 100. The start index must be lower than the end index (--end 50)"
+    );
+    assert!(check_range(50, 50, 10).is_err());
+    assert_eq!(check_range(0, 0x80000000, 2500).unwrap(), 2500);
+    assert_eq!(
+        check_range(0, 3000000000, 2500).unwrap_err().to_string(),
+        "Invalid --end 3000000000. Non-hardened BIP32 indexes can't exceed 2147483647"
+    );
+}
+
+#[test]
+fn test_load_config_range(){
+    let args = app().get_matches_from(vec![
+        "hdifinder", "--start", "10", "--end", "50", "--chunksize", "100",
+        "mnemonic", "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK",
+    ]);
+    let config = load_config(&args).unwrap();
+    assert_eq!(config.start, 10);
+    assert_eq!(config.end, 50);
+    assert_eq!(config.chunksize, 40);
+
+    let args = app().get_matches_from(vec!["hdifinder", "--end", "7", "mnemonic", "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"]);
+    assert_eq!(load_config(&args).unwrap().chunksize, 7);
+}
+
+#[test]
+fn test_load_config_multisig(){
+    let cosigner = "xpub6DkFAXWQ2dHxq2vatrt9qyA3bXYU4ToWQwCHbf5XB2mSTexcHZCeKS1VZYcPoBd5X8yVcbXFHJR9R8UCVpt82VX1VhR28mCyxUFL4r6KFrf";
+    let target = "bc1qcz9tjvwf307awl3ax77u4r7r27t0dva4m30428ac5c2fvmlfy0nskhqsjz";
+    // The same key given with its SLIP-132 multisig prefix
+    let args = app().get_matches_from(vec![
+        "hdifinder", "--threshold", "1", "--p2sh-wrapped", "--cosigner-xpub",
+        "Zpub74Jru6aftwwHxCUCWEvP6DgrfFsdA4U6ZRtQ5i8qJpMcC39yZGv3egBhQfV3MS9pZtH5z8iV5qWkJsK6ESs6mSzt4qvGhzJxPeeVS2e1zUG",
+        "mnemonic", target,
+    ]);
+    let multisig = load_config(&args).unwrap().multisig.unwrap();
+    assert_eq!(multisig.threshold, 1);
+    assert_eq!(multisig.cosigners[0].to_string(), cosigner);
+    assert!(multisig.sorted);
+    assert_eq!(multisig.key_position, 0);
+    assert!(multisig.nested);
+
+    let args = app().get_matches_from(vec![
+        "hdifinder", "--threshold", "1", "--no-sort-keys", "--key-position", "2", "--cosigner-xpub", cosigner, "mnemonic", target,
+    ]);
+    assert_eq!(load_config(&args).unwrap().multisig.unwrap().key_position, 1);
+    let args = app().get_matches_from(vec![
+        "hdifinder", "--threshold", "1", "--no-sort-keys", "--key-position", "3", "--cosigner-xpub", cosigner, "mnemonic", target,
+    ]);
+    assert!(matches!(load_config(&args), Err(HdiFinderError::InvalidArgument(reason)) if reason == "Invalid --key-position: 3 out of the 1 to 2 keys of the script"));
+    let error = app().color(ColorChoice::Never).try_get_matches_from(vec![
+        "hdifinder", "--threshold", "1", "--key-position", "1", "--cosigner-xpub", cosigner, "mnemonic", target,
+    ]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::MissingRequiredArgument);
+
+    let args = app().get_matches_from(vec!["hdifinder", "--threshold", "0", "--cosigner-xpub", cosigner, "mnemonic", target]);
+    assert!(matches!(load_config(&args), Err(HdiFinderError::InvalidArgument(reason)) if reason == "Invalid --threshold: threshold 0 out of the 1 to 2 keys of the script"));
+    let error = app().color(ColorChoice::Never).try_get_matches_from(vec!["hdifinder", "--cosigner-xpub", cosigner, "mnemonic", target]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::MissingRequiredArgument);
+}
+
+/**
+ * Picks a chunksize giving every worker thread several chunks of the range,
+ * so that the load stays balanced when some chunks end up slower than others
+ */
+fn default_chunksize(range: usize, threads: usize) -> usize {
+    range
+        .div_ceil(threads.max(1) * CHUNKS_PER_THREAD)
+        .clamp(MIN_CHUNKSIZE, MAX_CHUNKSIZE)
+}
+
+#[test]
+fn test_default_chunksize(){
+    // (range, threads, chunksize)
+    for (range, threads, expected) in [
+        (10000000, 1, 100000),
+        (10000000, 32, 39063),
+        (10000000, 128, 9766),
+        (100000, 4, 3125),
+        (100000, 32, 391),
+        (10000, 32, 100),
+        (20, 8, 100),
+        (1000, 0, 125),
+        (0x80000000, 64, 100000),
+    ] {
+        let chunksize = default_chunksize(range, threads);
+        assert_eq!(chunksize, expected, "range {} threads {}", range, threads);
+        // every thread gets work as long as chunks aren't too small to be worth it
+        let chunks = range.div_ceil(chunksize);
+        assert!(chunks >= threads.max(1) || chunksize == MIN_CHUNKSIZE);
+    }
+}
+
+/**
+ * Whether an option has been given on the command line, rather than left to
+ * its default value
+ */
+fn is_given(args: &ArgMatches, id: &str) -> bool {
+    args.value_source(id) == Some(ValueSource::CommandLine)
+}
+
+#[test]
+fn test_numeric_options(){
+    let parse = |args: Vec<&str>| app().color(ColorChoice::Never).try_get_matches_from(args);
+    let error = parse(vec!["hdifinder", "--start", "ten", "erupt quit"]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::ValueValidation);
+    assert!(error.to_string().starts_with("error: invalid value 'ten' for '--start <start>': invalid digit found in string"));
+    let error = parse(vec!["hdifinder", "--threads", "many", "erupt quit"]).unwrap_err();
+    assert!(error.to_string().starts_with("error: invalid value 'many' for '--threads <threads>': invalid digit found in string"));
+    let error = parse(vec!["hdifinder", "derive", "erupt quit", "--end=-5"]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::ValueValidation);
+    let error = parse(vec!["hdifinder", "verify", "erupt quit", "--index", "5th", "--address", "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"]).unwrap_err();
+    assert!(error.to_string().starts_with("error: invalid value '5th' for '--index <index>': invalid digit found in string"));
+
+    let args = parse(vec!["hdifinder", "--start", "5", "--end", "10", "erupt quit"]).unwrap();
+    assert_eq!(args.get_one::<usize>("start"), Some(&5));
+    assert_eq!(args.get_one::<usize>("threads"), None);
+
+    // Options that can't go together are rejected along with them
+    for flags in [
+        vec!["--passphrase", "secret", "--ask-passphrase"],
+        vec!["--passphrase-file", "file", "--passphrase-list", "list"],
+        vec!["--mnemonics-file", "file", "--try-swaps"],
+        vec!["--state-file", "state", "--fix-checksum"],
+        vec!["--entropy", "00", "--fix-checksum"],
+        vec!["--seed", "00", "--try-swaps"],
+        vec!["--slip39-file", "shares", "--passphrase-list", "list"],
+        vec!["--bip85-children", "2", "--xpub", "xpub"],
+        vec!["--seed", "00", "--entropy", "00"],
+        vec!["--mnemonic-file", "file", "--mnemonics-file", "file"],
+        vec!["--xprv", "xprv", "--xpub", "xpub"],
+        vec!["--slip39-share", "share", "--xprv", "xprv"],
+        vec!["--slip39-file", "shares", "--mnemonic-file", "file"],
+        vec!["--seed", "00", "--passphrase", "secret"],
+        vec!["--xpub", "xpub", "--ask-passphrase"],
+    ] {
+        let args = ["hdifinder"].iter().chain(flags.iter()).chain(["erupt quit"].iter()).copied().collect();
+        assert_eq!(parse(args).unwrap_err().kind(), ErrorKind::ArgumentConflict, "{:?}", flags);
+    }
+    // The shares of the command line and of a file go together
+    assert!(parse(vec!["hdifinder", "--slip39-share", "share", "--slip39-file", "shares", "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"]).is_ok());
+}
+
+/**
+ * Converts a human duration provided through the CLI, e.g 90s, 30m or 1h30m.
+ * A bare number is a number of seconds.
+ */
+fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    if value.is_empty() {
+        return None;
+    }
+
+    let mut seconds: u64 = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit: u64 = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return None,
+        };
+        seconds = seconds.checked_add(number.parse::<u64>().ok()?.checked_mul(unit)?)?;
+        number.clear();
+    }
+    if !number.is_empty() {
+        return None;
+    }
+    Some(Duration::from_secs(seconds))
+}
+
+#[test]
+fn test_parse_duration(){
+    assert_eq!(parse_duration("90"), Some(Duration::from_secs(90)));
+    assert_eq!(parse_duration("90s"), Some(Duration::from_secs(90)));
+    assert_eq!(parse_duration("30m"), Some(Duration::from_secs(1800)));
+    assert_eq!(parse_duration("1h30m"), Some(Duration::from_secs(5400)));
+    assert_eq!(parse_duration("2d"), Some(Duration::from_secs(172800)));
+    assert_eq!(parse_duration("m"), None);
+    assert_eq!(parse_duration("1h30"), None);
+    assert_eq!(parse_duration("10 minutes"), None);
+    assert_eq!(parse_duration(""), None);
+}
+
+/**
+ * Converts a comma separated list of chains provided through the CLI
+ */
+fn parse_chains(list: &str) -> Option<Vec<u32>> {
+    let mut chains: Vec<u32> = Vec::new();
+    for value in list.split(',') {
+        let chain = match value.trim() {
+            "0" => 0,
+            "1" => 1,
+            _ => return None,
+        };
+        if !chains.contains(&chain) {
+            chains.push(chain);
+        }
+    }
+    Some(chains)
+}
+
+#[test]
+fn test_parse_chains(){
+    assert_eq!(parse_chains("0"), Some(vec![0]));
+    assert_eq!(parse_chains("0,1"), Some(vec![0, 1]));
+    assert_eq!(parse_chains("1, 0, 1"), Some(vec![1, 0]));
+    assert_eq!(parse_chains("2"), None);
+    assert_eq!(parse_chains(""), None);
+}
+
+/**
+ * Converts an account index or an inclusive range of account indexes
+ * provided through the CLI (e.g 0-500)
+ */
+fn parse_accounts(range: &str) -> Option<RangeInclusive<u32>> {
+    let (first, last) = match range.split_once('-') {
+        Some((first, last)) => (first.trim(), last.trim()),
+        None => (range.trim(), range.trim()),
+    };
+    let first = first.parse::<u32>().ok()?;
+    let last = last.parse::<u32>().ok()?;
+
+    if first > last || !PathValue::is_ok(last) {
+        return None;
+    }
+    Some(first..=last)
+}
+
+#[test]
+fn test_parse_accounts(){
+    assert_eq!(parse_accounts("3"), Some(3..=3));
+    assert_eq!(parse_accounts("0-500"), Some(0..=500));
+    assert_eq!(parse_accounts(" 2 - 4 "), Some(2..=4));
+    assert_eq!(parse_accounts("5-1"), None);
+    assert_eq!(parse_accounts("0-2147483648"), None);
+    assert_eq!(parse_accounts("a-b"), None);
+}
+
+/**
+ * Converts a comma separated list of purposes provided through the CLI
+ */
+fn parse_purposes(list: &str) -> Option<Vec<Purpose>> {
+    let mut purposes: Vec<Purpose> = Vec::new();
+    for value in list.split(',') {
+        let purpose = match value.trim() {
+            "44" => Purpose::Pubkey,
+            "49" => Purpose::ScriptHash,
+            "84" => Purpose::Witness,
+            "86" => Purpose::Custom(86),
+            _ => return None,
+        };
+        if !purposes.contains(&purpose) {
+            purposes.push(purpose);
+        }
+    }
+    Some(purposes)
+}
+
+#[test]
+fn test_parse_purposes(){
+    assert_eq!(parse_purposes("44"), Some(vec![Purpose::Pubkey]));
+    assert_eq!(parse_purposes("84, 49,84"), Some(vec![Purpose::Witness, Purpose::ScriptHash]));
+    assert_eq!(parse_purposes("44,49,84,86"), Some(vec![Purpose::Pubkey, Purpose::ScriptHash, Purpose::Witness, Purpose::Custom(86)]));
+    assert_eq!(parse_purposes("44,0"), None);
+    assert_eq!(parse_purposes(""), None);
+}
+
+/**
+ * Line of an addresses or mnemonics file that has been skipped
+ */
+pub struct SkippedLine {
+    pub line: usize,
+    pub reason: String,
+}
+
+/**
+ * Addresses read from an addresses file
+ */
+struct AddressesList {
+    valid: Vec<(String, AddressTarget)>,
+    skipped: Vec<SkippedLine>,
+}
+
+/**
+ * Parses a list of addresses, one per line. Empty lines are ignored.
+ * Returns the valid addresses along with the line number and reason
+ * of the invalid ones.
+ */
+fn parse_addresses_list(content: &str, network: Network, coin: Coin, ignore_network_mismatch: bool) -> AddressesList {
+    let mut valid: Vec<(String, AddressTarget)> = Vec::new();
+    let mut skipped: Vec<SkippedLine> = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let address = line.trim();
+        if address.is_empty() {
+            continue;
+        }
+        match self::read_target(address, coin, network) {
+            Ok((address, target)) => match self::network_mismatch(&address, &target, network, coin) {
+                Some(reason) if !ignore_network_mismatch => skipped.push(SkippedLine { line: i + 1, reason }),
+                _ => valid.push((address::canonical_target(&address, &address::address_params(coin, network)), target)),
+            },
+            Err(e) => skipped.push(SkippedLine {
+                line: i + 1,
+                reason: e.to_string(),
+            }),
+        }
+    }
+
+    AddressesList { valid, skipped }
+}
+
+#[test]
+fn test_parse_addresses_list(){
+    let content = "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK\n\n  bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y  \nnot-an-address\ntb1qv5zqqm8plkx9hzp485pnze0aljx6eg9js46dzq\n";
+    let list = parse_addresses_list(content, Network::Bitcoin, Coin::Bitcoin, false);
+
+    let valid: Vec<&str> = list.valid.iter().map(|(address, _)| address.as_str()).collect();
+    assert_eq!(valid, vec!["14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", "bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y"]);
+    assert_eq!(list.skipped.len(), 2);
+    assert_eq!(list.skipped[0].line, 4);
+    assert!(list.skipped[0].reason.contains("not-an-address"));
+    assert_eq!(list.skipped[1].line, 5);
+    assert!(list.skipped[1].reason.contains("search network is bitcoin mainnet"));
+    assert_eq!(parse_addresses_list(content, Network::Bitcoin, Coin::Bitcoin, true).valid.len(), 3);
+
+    // Bitcoin Cash targets are listed as the cashaddr the search derives
+    let list = parse_addresses_list("1mW6fDEMjKrDHvLvoEsaeLxSCzZBf3Bfg\nqqyx49mu0kkn9ftfj6hje6g2wfer34yfnq5tahq3q6\n", Network::Bitcoin, Coin::BitcoinCash, false);
+    assert!(list.valid.iter().all(|(address, _)| address == "bitcoincash:qqyx49mu0kkn9ftfj6hje6g2wfer34yfnq5tahq3q6"));
+    assert_eq!(list.valid.len(), 2);
+}
+
+/**
+ * Reads a target given either as an address or as a payment URI, whose
+ * label is kept to be reported along with the match
+ */
+fn read_target(target: &str, coin: Coin, network: Network) -> Result<(String, AddressTarget), HdiFinderError> {
+    let target = target.trim();
+    if !bip21::is_payment_uri(target) {
+        let address = address::normalize_target(target);
+        let decoded = address::decode_coin_target(&address, coin, network)?;
+        return Ok((address, decoded));
+    }
+    let uri = bip21::parse_payment_uri(target)?;
+    let decoded = address::decode_target(&uri.address)?;
+    Ok((uri.address, AddressTarget { label: uri.label, ..decoded }))
+}
+
+#[test]
+fn test_read_target(){
+    let (address, target) = read_target("bitcoin:BC1Q9XUUQJDZ920RKCS0KVNMQH0T4ANMGTK5U60H0Y?amount=0.1&label=Cold%20storage", Coin::Bitcoin, Network::Bitcoin).unwrap();
+    assert_eq!(address, "bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y");
+    assert_eq!(target.address_type, Some("p2wpkh"));
+    assert_eq!(target.label.as_deref(), Some("Cold storage"));
+    assert_eq!(read_target("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", Coin::Bitcoin, Network::Bitcoin).unwrap().1.label, None);
+    assert_eq!(read_target(" BC1Q9XUUQJDZ920RKCS0KVNMQH0T4ANMGTK5U60H0Y\u{200B}", Coin::Bitcoin, Network::Bitcoin).unwrap().0, "bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y");
+    // Invalid URIs and invalid addresses are told apart
+    assert!(matches!(read_target("bitcoin:14odE5c1eXuphR24fXMtzDfsXMLCmFTFgL", Coin::Bitcoin, Network::Bitcoin), Err(HdiFinderError::PaymentUri { .. })));
+    assert!(matches!(read_target("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgL", Coin::Bitcoin, Network::Bitcoin), Err(HdiFinderError::InvalidAddress(_))));
+}
+
+/**
+ * Reads a target given as an output descriptor into the addresses it
+ * describes. The addresses of a ranged descriptor are derived over the first
+ * indexes of its chain, the --descriptor-range, rather than over the whole
+ * scan as every one of them is held as a target.
+ */
+fn descriptor_targets(target: &str, indexes: Range<u32>, network: Network) -> Result<Vec<(String, AddressTarget)>, HdiFinderError> {
+    let secp = Secp256k1::new();
+    descriptor::parse_descriptor(target, network)?
+        .addresses(&secp, indexes, network)?
+        .into_iter()
+        .map(|address| {
+            let decoded = address::decode_target(&address)?;
+            Ok((address, decoded))
+        })
+        .collect()
+}
+
+#[test]
+fn test_descriptor_targets(){
+    let targets = descriptor_targets(
+        "wpkh([67b4602d/84'/0'/0']xpub6C2nZRpiwAoxe2a3Y1riTBRtFdEUxULcrCaDFsP1VbGRdhf7HRWPp69RsUcx79fnA5CNjHJzC39EaAgKfVuiuHGGhqHSDkv1EF9q5zDXVqE/0/*)#fllv4e44",
+        0..10,
+        Network::Bitcoin,
+    )
+    .unwrap();
+    assert_eq!(targets.len(), 10);
+    assert_eq!(targets[1].0, "bc1q4qwmrj5thpqes6naw4xe579kw4my05t0dapcht");
+    assert_eq!(targets[1].1.address_type, Some("p2wpkh"));
+    let targets = descriptor_targets("addr(14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK)", 0..10, Network::Bitcoin).unwrap();
+    assert_eq!(targets.len(), 1);
+    assert_eq!(targets[0].1.address_type, Some("p2pkh"));
+}
+
+/**
+ * Names a network the way the mismatch messages tell it
+ */
+fn network_label(network: Network, coin: Coin) -> String {
+    match (coin, network) {
+        (Coin::Bitcoin, Network::Bitcoin) => "bitcoin mainnet".to_string(),
+        (Coin::Bitcoin, network) => network.to_string(),
+        (coin, Network::Bitcoin) => format!("{} mainnet", coin),
+        (coin, network) => format!("{} {}", coin, network),
+    }
+}
+
+/**
+ * Converts a bitcoin network into the name --network takes for it
+ */
+fn network_name(network: Network) -> &'static str {
+    match network {
+        Network::Bitcoin => "mainnet",
+        Network::Testnet => "testnet",
+        Network::Signet => "signet",
+        Network::Regtest => "regtest",
+    }
+}
+
+/**
+ * Tells why a target address can't be derived on the searched network,
+ * when its encoding stands for another one, along with the --network or
+ * the --coin to pass instead. Such a target would silently never match.
+ */
+fn network_mismatch(address: &str, target: &AddressTarget, network: Network, coin: Coin) -> Option<String> {
+    if address::is_address_on_network(address, network, coin) {
+        return None;
+    }
+    let target_coin = address::address_coin(address).unwrap_or(coin);
+    if target_coin != coin {
+        let article = if target_coin.to_string().starts_with(|c: char| "aeiou".contains(c)) { "an" } else { "a" };
+        return Some(format!(
+            "{} is {} {} address but the search coin is {}; pass --coin {}",
+            address,
+            article,
+            target_coin,
+            coin,
+            self::coin_name(target_coin)
+        ));
+    }
+    Some(format!(
+        "{} is a {} address but the search network is {}; pass --network {}",
+        address,
+        self::network_label(target.network, coin),
+        self::network_label(network, coin),
+        self::network_name(target.network)
+    ))
+}
+
+#[test]
+fn test_network_mismatch(){
+    let mainnet = address::decode_target("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK").unwrap();
+    assert_eq!(network_mismatch("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", &mainnet, Network::Bitcoin, Coin::Bitcoin), None);
+    assert_eq!(
+        network_mismatch("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", &mainnet, Network::Testnet, Coin::Bitcoin).unwrap(),
+        "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK is a bitcoin mainnet address but the search network is testnet; pass --network mainnet"
+    );
+    let testnet = address::decode_target("tb1qv5zqqm8plkx9hzp485pnze0aljx6eg9js46dzq").unwrap();
+    assert_eq!(
+        network_mismatch("tb1qv5zqqm8plkx9hzp485pnze0aljx6eg9js46dzq", &testnet, Network::Bitcoin, Coin::Bitcoin).unwrap(),
+        "tb1qv5zqqm8plkx9hzp485pnze0aljx6eg9js46dzq is a testnet address but the search network is bitcoin mainnet; pass --network testnet"
+    );
+    assert!(network_mismatch("tb1qv5zqqm8plkx9hzp485pnze0aljx6eg9js46dzq", &testnet, Network::Signet, Coin::Bitcoin).is_none());
+    assert!(network_mismatch("tb1qv5zqqm8plkx9hzp485pnze0aljx6eg9js46dzq", &testnet, Network::Regtest, Coin::Bitcoin).is_some());
+    let regtest = address::decode_target("bcrt1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk554dfr7").unwrap();
+    assert!(network_mismatch("bcrt1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk554dfr7", &regtest, Network::Testnet, Coin::Bitcoin).unwrap().ends_with("pass --network regtest"));
+    // Regtest reuses the testnet base58 prefixes
+    let base58_testnet = address::decode_target("mjKaX8gzTZM5UXVgP6LGp8tCPLvudvk6EY").unwrap();
+    assert!(network_mismatch("mjKaX8gzTZM5UXVgP6LGp8tCPLvudvk6EY", &base58_testnet, Network::Regtest, Coin::Bitcoin).is_none());
+
+    let litecoin = address::decode_target("ltc1qjmxnz78nmc8nq77wuxh25n2es7rzm5c2rkk4wh").unwrap();
+    assert_eq!(network_mismatch("ltc1qjmxnz78nmc8nq77wuxh25n2es7rzm5c2rkk4wh", &litecoin, Network::Bitcoin, Coin::Litecoin), None);
+    assert_eq!(
+        network_mismatch("ltc1qjmxnz78nmc8nq77wuxh25n2es7rzm5c2rkk4wh", &litecoin, Network::Bitcoin, Coin::Bitcoin).unwrap(),
+        "ltc1qjmxnz78nmc8nq77wuxh25n2es7rzm5c2rkk4wh is a litecoin address but the search coin is bitcoin; pass --coin ltc"
+    );
+    assert_eq!(
+        network_mismatch("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", &mainnet, Network::Bitcoin, Coin::Litecoin).unwrap(),
+        "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK is a bitcoin address but the search coin is litecoin; pass --coin btc"
+    );
+    assert_eq!(
+        network_mismatch("ltc1qjmxnz78nmc8nq77wuxh25n2es7rzm5c2rkk4wh", &litecoin, Network::Testnet, Coin::Litecoin).unwrap(),
+        "ltc1qjmxnz78nmc8nq77wuxh25n2es7rzm5c2rkk4wh is a litecoin mainnet address but the search network is litecoin testnet; pass --network mainnet"
+    );
+}
+
+/**
+ * Names --coin takes for the built-in coins
+ */
+const BUILTIN_COINS: [&str; 5] = ["btc", "ltc", "doge", "bch", "eth"];
+
+/**
+ * Converts the name of a built-in coin into the coin
+ */
+fn builtin_coin(name: &str) -> Option<Coin> {
+    match name {
+        "btc" => Some(Coin::Bitcoin),
+        "ltc" => Some(Coin::Litecoin),
+        "doge" => Some(Coin::Dogecoin),
+        "bch" => Some(Coin::BitcoinCash),
+        "eth" => Some(Coin::Ethereum),
+        _ => None,
+    }
+}
+
+/**
+ * Converts a coin name provided through --coin into a coin, either built in
+ * or defined, by the --coin-config file or by the library
+ */
+fn parse_coin(name: &str, definitions: &[CoinDefinition]) -> Option<Coin> {
+    self::builtin_coin(name).or_else(|| coins::find_definition(name, definitions).map(Coin::Defined))
+}
+
+/**
+ * Lists the names --coin takes
+ */
+fn coin_names(definitions: &[CoinDefinition]) -> Vec<&'static str> {
+    let mut names: Vec<&'static str> = BUILTIN_COINS.to_vec();
+    for definition in definitions.iter().chain(coins::DEFINITIONS.iter()) {
+        if !names.contains(&definition.name) {
+            names.push(definition.name);
+        }
+    }
+    names
+}
+
+/**
+ * Parses the coin definitions of a --coin-config file, one table per coin
+ * named as --coin takes it, e.g
+ *
+ * ```toml
+ * [vtc]
+ * coin_type = 28
+ * p2pkh = 0x47
+ * p2sh = 0x05
+ * hrp = "vtc"
+ * kinds = ["p2pkh", "p2shwpkh", "p2wpkh"]
+ * ```
+ *
+ * The definitions live as long as the process, for the coins to be copied
+ * around as the built-in ones are.
+ */
+fn coin_definitions(text: &str) -> Result<Vec<CoinDefinition>, String> {
+    let mut definitions: Vec<CoinDefinition> = Vec::new();
+    for (name, table) in config::parse::<toml::Table>(text)? {
+        if self::builtin_coin(&name).is_some() {
+            return Err(format!("{} is a built-in coin, which can't be redefined", name));
+        }
+        let name: &'static str = Box::leak(name.into_boxed_str());
+        let invalid = |reason: String| format!("coin {}: {}", name, reason);
+        let table: CoinTable = table.try_into().map_err(|e| invalid(config::error_message(&e)))?;
+        let mut kinds: Vec<Purpose> = Vec::new();
+        for kind in &table.kinds {
+            let purpose = address::type_purpose(kind)
+                .ok_or_else(|| invalid(format!("unknown address kind {}, expected p2pkh, p2shwpkh, p2wpkh or p2tr", kind)))?;
+            if kinds.contains(&purpose) {
+                return Err(invalid(format!("{} is listed twice", address::address_type(&purpose))));
+            }
+            kinds.push(purpose);
+        }
+        let definition = CoinDefinition {
+            name,
+            coin_type: table.coin_type,
+            p2pkh: table.p2pkh,
+            p2sh: table.p2sh,
+            hrp: table.hrp.map(|hrp| &*Box::leak(hrp.into_boxed_str())),
+            purposes: Box::leak(kinds.into_boxed_slice()),
+        };
+        coins::validate(&definition).map_err(invalid)?;
+        definitions.push(definition);
+    }
+    Ok(definitions)
+}
+
+#[test]
+fn test_coin_definitions(){
+    let text = "[vtc]\ncoin_type = 28\np2pkh = 0x47\np2sh = 0x05\nhrp = \"vtc\"\nkinds = [\"p2pkh\", \"p2wpkh\"]\n";
+    let definitions = coin_definitions(text).unwrap();
+    assert_eq!(
+        definitions,
+        vec![CoinDefinition {
+            name: "vtc",
+            coin_type: 28,
+            p2pkh: 0x47,
+            p2sh: 0x05,
+            hrp: Some("vtc"),
+            purposes: &[Purpose::Pubkey, Purpose::Witness],
+        }]
+    );
+    // A user defined coin encodes its addresses with its own base58 prefix
+    let vtc = parse_coin("vtc", &definitions).unwrap();
+    let params = address::address_params(vtc, Network::Bitcoin);
+    let hash = [0; 20];
+    let p2pkh = address::hash160_addresses(&hash, Network::Bitcoin, vtc);
+    assert!(p2pkh[0].address.starts_with('V'));
+    assert!(p2pkh[1].address.starts_with("vtc1q"));
+    assert!(!params.supports(&Purpose::ScriptHash));
+    assert_eq!(coin_names(&definitions), ["btc", "ltc", "doge", "bch", "eth", "vtc", "dash", "dgb", "zec"]);
+    assert_eq!(parse_coin("dash", &[]).unwrap().to_string(), "dash");
+
+    let error = |text: &str| coin_definitions(text).unwrap_err();
+    assert_eq!(error("[vtc]\ncoin_type = 28\nkinds = [\"p2pkh\"]\n"), "coin vtc: missing field `p2pkh`");
+    assert_eq!(error("[btc]\ncoin_type = 0\n"), "btc is a built-in coin, which can't be redefined");
+    let valid = "coin_type = 28\np2sh = 0x05\nkinds = [\"p2pkh\"]\n";
+    assert_eq!(error(&format!("[vtc]\n{}p2pkh = 65536\n", valid)), "coin vtc: invalid value: integer `65536`, expected u16");
+    assert_eq!(error(&format!("[vtc]\n{}p2pkh = \"V\"\n", valid)), "coin vtc: invalid type: string \"V\", expected u16");
+    assert_eq!(
+        error("[vtc]\ncoin_type = 28\np2pkh = 0x47\np2sh = 0x05\nkinds = [\"p2wsh\"]\n"),
+        "coin vtc: unknown address kind p2wsh, expected p2pkh, p2shwpkh, p2wpkh or p2tr"
+    );
+    assert_eq!(error("[vtc]\nversion = 1\n"), "coin vtc: unknown key version");
+    assert_eq!(
+        error("[vtc]\ncoin_type = 28\np2pkh = 0x47\np2sh = 0x47\nkinds = [\"p2pkh\"]\n"),
+        "coin vtc: p2pkh and p2sh share the version 0x47"
+    );
+    assert_eq!(
+        error("[vtc]\ncoin_type = 28\np2pkh = 0x47\np2sh = 0x05\nkinds = [\"p2pkh\", \"p2wpkh\"]\n"),
+        "coin vtc: p2wpkh addresses need an hrp"
+    );
+    assert!(error("[vtc]\ncoin_type = 28\n[vtc]\n").starts_with("line 3: "));
+}
+
+/**
+ * Converts a coin into the name --coin takes for it
+ */
+fn coin_name(coin: Coin) -> &'static str {
+    match coin {
+        Coin::Bitcoin => "btc",
+        Coin::Litecoin => "ltc",
+        Coin::Dogecoin => "doge",
+        Coin::BitcoinCash => "bch",
+        Coin::Ethereum => "eth",
+        Coin::Defined(definition) => definition.name,
+    }
+}
+
+#[test]
+fn test_parse_coin(){
+    for coin in [Coin::Bitcoin, Coin::Litecoin, Coin::Dogecoin, Coin::BitcoinCash, Coin::Ethereum, Coin::Defined(coins::DEFINITIONS[0])] {
+        assert_eq!(parse_coin(coin_name(coin), &[]), Some(coin));
+    }
+    assert_eq!(parse_coin("bitcoin", &[]), None);
+}
+
+/**
+ * Converts a network name provided through the CLI into a bitcoin network
+ */
+fn parse_network(name: &str) -> Option<Network> {
+    match name {
+        "mainnet" | "bitcoin" => Some(Network::Bitcoin),
+        "testnet" => Some(Network::Testnet),
+        "signet" => Some(Network::Signet),
+        "regtest" => Some(Network::Regtest),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_parse_network(){
+    assert_eq!(parse_network("mainnet"), Some(Network::Bitcoin));
+    assert_eq!(parse_network("bitcoin"), Some(Network::Bitcoin));
+    assert_eq!(parse_network("testnet"), Some(Network::Testnet));
+    assert_eq!(parse_network("signet"), Some(Network::Signet));
+    assert_eq!(parse_network("regtest"), Some(Network::Regtest));
+    assert_eq!(parse_network("litecoin"), None);
+}
+
+/**
+ * Parses the name of a format of --format, the quiet ones having none
+ */
+fn parse_format(name: &str) -> OutputFormat {
+    match name {
+        "json" => OutputFormat::Json,
+        "ndjson" => OutputFormat::Ndjson,
+        "csv" => OutputFormat::Csv,
+        _ => OutputFormat::Text,
+    }
+}
+
+#[test]
+fn test_parse_format(){
+    assert_eq!(parse_format("ndjson"), OutputFormat::Ndjson);
+    assert_eq!(parse_format("text"), OutputFormat::Text);
+    let args = app().get_matches_from(vec!["hdifinder", "--format", "csv", "--language", "french", "--max-duration", "1h30m", "mnemonic", "address"]);
+    assert_eq!(args.get_one::<OutputFormat>("format"), Some(&OutputFormat::Csv));
+    let search = search_matches(&args);
+    assert_eq!(search.get_one::<Language>("language"), Some(&Language::French));
+    assert_eq!(search.get_one::<Duration>("max-duration"), Some(&Duration::from_secs(5400)));
+    let error = app().color(ColorChoice::Never).try_get_matches_from(vec!["hdifinder", "--max-duration", "soon", "mnemonic", "address"]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::ValueValidation);
+    assert!(error.to_string().starts_with("error: invalid value 'soon' for '--max-duration <max-duration>': expected a duration like 90s, 30m or 1h30m"));
+    let error = app().color(ColorChoice::Never).try_get_matches_from(vec!["hdifinder", "--network", "litecoin", "mnemonic", "address"]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidValue);
+}
+
+/**
+ * Parses a wordlist name as listed by `derivation::LANGUAGES`
+ */
+fn parse_language(name: &str) -> Option<Language> {
+    derivation::LANGUAGES
+        .iter()
+        .find(|(flag, _, _)| *flag == name)
+        .map(|(_, _, language)| *language)
+}
+
+#[test]
+fn test_parse_language(){
+    assert_eq!(parse_language("french"), Some(Language::French));
+    assert_eq!(parse_language("chinese-simplified"), Some(Language::SimplifiedChinese));
+    assert_eq!(parse_language("klingon"), None);
+}
+
+/**
+ * Arguments drawing the matches as QR codes, which builds without the qr
+ * feature don't have
+ */
+#[cfg(feature = "qr")]
+fn qr_args() -> Vec<Arg> {
+    vec![
+        Arg::new("qr")
+            .long("qr")
+            .action(ArgAction::SetTrue)
+            .help("Draws the address of a match as a QR code, to scan it into a phone wallet"),
+        Arg::new("qr-private")
+            .long("qr-private")
+            .action(ArgAction::SetTrue)
+            .requires_all(&["qr", "show-private"])
+            .help("Draws the private key of --show-private as a QR code as well"),
+        Arg::new("qr-file")
+            .long("qr-file")
+            .action(ArgAction::Set)
+            .value_name("file")
+            .value_hint(ValueHint::FilePath)
+            .requires("qr")
+            .help("Writes the QR code of the first match to a PNG image instead, the one of its private key to <file>-private.png"),
+    ]
+}
+
+#[cfg(not(feature = "qr"))]
+fn qr_args() -> Vec<Arg> {
+    Vec::new()
+}
+
+/**
+ * Options that may be given several times, which the arrays of a config file
+ * are repeated for. The other ones take the values of an array comma separated.
+ */
+const REPEATED_OPTIONS: [&str; 5] = ["slip39-share", "script", "pubkey", "hash160", "cosigner-xpub"];
+
+/**
+ * Options taking a file, named <file> in the help, whose file path hint has
+ * the completion scripts complete their values with paths
+ */
+pub const FILE_OPTIONS: [&str; 13] = [
+    "config",
+    "coin-config",
+    "mnemonic-file",
+    "mnemonics-file",
+    "slip39-file",
+    "passphrase-file",
+    "passphrase-list",
+    "addresses-file",
+    "state-file",
+    "psbt",
+    "psbt-out",
+    "qr-file",
+    "output",
+];
+
+#[test]
+fn test_file_options(){
+    let mut app = app();
+    let mut help = Vec::new();
+    app.write_help(&mut help).unwrap();
+    let help = String::from_utf8(help).unwrap();
+    for option in FILE_OPTIONS {
+        assert!(help.contains(&format!("--{} <file", option)), "{}", option);
+    }
+    for arg in app.get_arguments() {
+        let takes_file = FILE_OPTIONS.contains(&arg.get_id().as_str());
+        assert_eq!(arg.get_value_hint() == ValueHint::FilePath, takes_file, "{}", arg.get_id());
+    }
+    for named_file in help.split("--").skip(1).filter(|option| option.split('>').next().unwrap().ends_with("<file")) {
+        assert!(FILE_OPTIONS.contains(&named_file.split(' ').next().unwrap()), "{}", named_file);
+    }
+}
+
+/**
+ * Turns the keys of a config file into the command line options they mirror,
+ * leaving out the ones given on the command line, which take precedence
+ */
+fn config_file_args(text: &str, cli: &ArgMatches) -> Result<Vec<String>, String> {
+    let file: ConfigFile = config::parse(text)?;
+    if file.config.is_some() {
+        return Err("a config file can't load another one".to_string());
+    }
+    let value_arg = |value: &toml::Value| match value {
+        toml::Value::String(value) => value.clone(),
+        value => value.to_string(),
+    };
+    let mut args = Vec::new();
+    for (key, value) in file.entries() {
+        if self::is_given(cli, &key) {
+            continue;
+        }
+        match value {
+            toml::Value::Boolean(true) => args.push(format!("--{}", key)),
+            toml::Value::Boolean(false) => {}
+            toml::Value::Array(values) => {
+                let values: Vec<String> = values.iter().map(value_arg).collect();
+                if REPEATED_OPTIONS.contains(&key.as_str()) {
+                    args.extend(values.iter().map(|value| format!("--{}={}", key, value)));
+                } else {
+                    args.push(format!("--{}={}", key, values.join(",")));
+                }
+            }
+            value => args.push(format!("--{}={}", key, value_arg(&value))),
+        }
+    }
+
+    // Parsed alone, the options of the file tell their invalid values. Those
+    // requiring an option of the command line are checked along with it.
+    let file_args = std::iter::once("hdifinder".to_string()).chain(args.iter().cloned());
+    match app().color(ColorChoice::Never).try_get_matches_from(file_args) {
+        Err(e) if e.kind() != ErrorKind::MissingRequiredArgument => {
+            let message = e.to_string();
+            let message = message.lines().next().unwrap_or_default();
+            Err(message.trim_start_matches("error: ").to_string())
+        }
+        _ => Ok(args),
+    }
+}
+
+#[test]
+fn test_config_file_args(){
+    let cli = app().get_matches_from(vec!["hdifinder", "--network", "testnet", "erupt quit"]);
+    let text = "network = \"regtest\"\npurposes = [44, 84]\nscript = [\"0014aa\", \"0014bb\"]\nno-progress = true\ndry-run = false\nthreads = 4\n";
+    assert_eq!(config_file_args(text, &cli).unwrap(), ["--threads=4", "--purposes=44,84", "--script=0014aa", "--script=0014bb", "--no-progress"]);
+    assert_eq!(config_file_args("try-swaps = true\naccounts = \"0-5\"\n", &cli).unwrap(), ["--try-swaps", "--accounts=0-5"]);
+
+    let error = |text: &str| config_file_args(text, &cli).unwrap_err();
+    assert_eq!(error("threads = 4\nnetwrk = \"testnet\""), "line 2: unknown key netwrk");
+    assert_eq!(error("help = true"), "line 1: unknown key help");
+    assert_eq!(error("version = true"), "line 1: unknown key version");
+    assert_eq!(error("type = \"p2sh\""), "invalid value 'p2sh' for '--type <type>'");
+    assert!(error("purposes = true").starts_with("line 1: "));
+    assert!(error("threads = 1.5").starts_with("line 1: invalid type: floating point `1.5`"));
+    assert_eq!(error("config = \"other.toml\""), "a config file can't load another one");
+    assert_eq!(error("threads = 4\n[search]"), "line 2: unknown key search");
+    // Checked along with the command line
+    assert_eq!(config_file_args("redact = true", &cli).unwrap(), ["--redact"]);
+}
+
+/**
+ * Parses a command line layered over a config file: the options of the command
+ * line take precedence over the keys of the file, even conflicting ones such
+ * as --type over purposes, and the keys take precedence over the defaults
+ */
+fn layered_matches(args: &[OsString], cli: &ArgMatches, text: &str) -> Result<ArgMatches, String> {
+    // The options of the file go right after the search subcommand when it is
+    // given, only global options being able to come before it
+    let first = match cli.subcommand_name() {
+        Some("search") => args.iter().position(|arg| arg == "search").map_or(1, |position| position + 1),
+        _ => 1,
+    };
+    let mut layered = args.to_vec();
+    let mut kept = 0;
+    for arg in self::config_file_args(text, self::search_matches(cli))? {
+        let mut probe = args.to_vec();
+        probe.insert(first, OsString::from(&arg));
+        if matches!(app().try_get_matches_from(probe), Err(e) if e.kind() == ErrorKind::ArgumentConflict) {
+            continue;
+        }
+        // Given as --key=value, the options of the file can't take the
+        // following argument, and go first to be kept out of a -- separator
+        layered.insert(first + kept, OsString::from(&arg));
+        kept += 1;
+    }
+    Ok(self::parsed_args(layered))
+}
+
+#[test]
+fn test_layered_matches(){
+    let layered = |args: &[&str], text: &str| {
+        let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+        layered_matches(&args, &app().get_matches_from(args.clone()), text)
+    };
+    let text = "network = \"testnet\"\nthreads = 3\npurposes = \"84\"\n";
+    // The command line over the file, the file over the defaults
+    let matches = layered(&["hdifinder", "--network", "regtest", "erupt quit", "bcrt1qs7rpyl69wu6c7x9y23nkmye3rsjjthztnwhys7"], text).unwrap();
+    let config = load_config(&matches).unwrap();
+    assert_eq!(config.network, Network::Regtest);
+    assert_eq!(config.threads, 3);
+    assert_eq!(config.purposes, vec![Purpose::Witness]);
+    assert_eq!(config.start, 0);
+    assert_eq!(matches.get_one::<String>("seed-type").map(String::as_str), Some("bip39"));
+    let matches = layered(&["hdifinder", "--seed-type", "electrum", "erupt quit", "tb1q2dq975h7y93xplagze42exmzflxl4uqhgy2cxe"], "seed-type = \"bip39\"").unwrap();
+    assert_eq!(matches.get_one::<String>("seed-type").map(String::as_str), Some("electrum"));
+
+    // A key conflicting with the command line gives way to it
+    let matches = layered(&["hdifinder", "--type", "p2pkh", "erupt quit"], text).unwrap();
+    assert!(!matches.contains_id("purposes"));
+    assert_eq!(matches.get_one::<usize>("threads"), Some(&3));
+
+    // Positional arguments after a -- separator are left alone
+    let matches = layered(&["hdifinder", "--", "erupt quit", "--end"], "end = 10").unwrap();
+    assert_eq!(matches.get_one::<usize>("end"), Some(&10));
+    assert_eq!(matches.get_many::<String>("address").unwrap().map(String::as_str).collect::<Vec<_>>(), ["--end"]);
+
+    assert!(layered(&["hdifinder", "erupt quit"], "dry-run = \"yes\"").unwrap_err().starts_with("line 1: invalid type: string \"yes\""));
+
+    // Along with the search subcommand, the keys apply to it
+    let matches = layered(&["hdifinder", "-v", "search", "--threads", "2", "erupt quit"], text).unwrap();
+    let search = search_matches(&matches);
+    assert_eq!(search.get_one::<usize>("threads"), Some(&2));
+    assert_eq!(search.get_one::<Network>("network"), Some(&Network::Testnet));
+    assert_eq!(search.get_count("verbose"), 1);
+    assert!(matches.get_one::<usize>("threads").is_none());
+}
+
+/**
+ * The matches of the search, given with the search subcommand or, as it has
+ * always been, without subcommand
+ */
+pub fn search_matches(matches: &ArgMatches) -> &ArgMatches {
+    matches.subcommand_matches("search").unwrap_or(matches)
+}
+
+#[test]
+fn test_search_matches(){
+    let bare = app().get_matches_from(vec!["hdifinder", "--end", "10", "erupt quit", "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"]);
+    let subcommand = app().get_matches_from(vec!["hdifinder", "search", "--end", "10", "erupt quit", "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"]);
+    for matches in [&bare, &subcommand] {
+        let config = load_config(search_matches(matches)).unwrap();
+        assert_eq!(config.end, 10);
+        assert!(config.addresses.contains("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"));
+    }
+    // The global options are read wherever they are given
+    let matches = app().get_matches_from(vec!["hdifinder", "--network", "testnet", "search", "--format", "csv", "erupt quit", "tb1q2dq975h7y93xplagze42exmzflxl4uqhgy2cxe"]);
+    let config = load_config(search_matches(&matches)).unwrap();
+    assert_eq!(config.network, Network::Testnet);
+    assert!(config.format == OutputFormat::Csv);
+}
+
+/**
+ * Parses a command line, a usage error exiting with EXIT_USAGE like the ones
+ * found after parsing, rather than with the code clap exits with, which is
+ * EXIT_NOT_FOUND's
+ */
+fn parsed_args(args: Vec<OsString>) -> ArgMatches {
+    app().try_get_matches_from(args).unwrap_or_else(|e| {
+        let _ = e.print();
+        process::exit(if e.use_stderr() { EXIT_USAGE } else { EXIT_FOUND })
+    })
+}
+
+/**
+ * Parses the command line, layered over the --config file when one is given
+ */
+pub fn matches() -> Result<ArgMatches, HdiFinderError> {
+    let args: Vec<OsString> = env::args_os().collect();
+    let cli = self::parsed_args(args.clone());
+    let path = match self::search_matches(&cli).get_one::<String>("config").map(String::as_str) {
+        Some(path) => path,
+        None => return Ok(cli),
+    };
+    let text = fs::read_to_string(path).map_err(|source| HdiFinderError::Io {
+        path: path.to_string(),
+        source,
+    })?;
+    self::layered_matches(&args, &cli, &text).map_err(|reason| HdiFinderError::InvalidArgument(format!("Invalid config file {}: {}", path, reason)))
+}
+
+/**
+ * Arguments of the search, taken by the search subcommand as well as by a
+ * command line without subcommand
+ */
+fn search_args() -> Vec<Arg> {
+    let mut args = vec![
+        Arg::new("config")
+            .long("config")
+            .action(ArgAction::Set)
+            .value_name("file")
+            .value_hint(ValueHint::FilePath)
+            .help("Reads options from a TOML file whose keys are the long options, e.g. network = \"testnet\". The options of the command line take precedence"),
+        Arg::new("passphrase")
+            .short('p')
+            .long("passphrase")
+            .action(ArgAction::Set)
+            .help("The mnemonic passphrase")
+            .conflicts_with_all(&["ask-passphrase", "passphrase-file", "passphrase-list"]),
+        Arg::new("mnemonic-file")
+            .long("mnemonic-file")
+            .action(ArgAction::Set)
+            .value_name("file")
+            .value_hint(ValueHint::FilePath)
+            .help("Reads the mnemonic from a file instead of the command line")
+            .conflicts_with_all(self::other_sources("mnemonic-file")),
+        Arg::new("mnemonics-file")
+            .long("mnemonics-file")
+            .action(ArgAction::Set)
+            .value_name("file")
+            .value_hint(ValueHint::FilePath)
+            .help("Scans every mnemonic of a file, one per line, '#' starting a comment, to tell which one owns the address")
+            // The fixes apply to a single phrase
+            .conflicts_with_all(&["fix-checksum", "try-swaps"])
+            .conflicts_with_all(self::other_sources("mnemonics-file")),
+        Arg::new("seed")
+            .long("seed")
+            .action(ArgAction::Set)
+            .help("Derives from a BIP39 seed of 16 to 64 bytes given as hex instead of a mnemonic. The passphrase is part of the seed")
+            // There are no words to fix
+            .conflicts_with_all(&["fix-checksum", "try-swaps"])
+            .conflicts_with_all(self::other_sources("seed"))
+            .conflicts_with_all(PASSPHRASE_OPTIONS),
+        Arg::new("slip39-share")
+            .long("slip39-share")
+            .action(ArgAction::Append)
+            .help("Derives from the master secret of SLIP-39 (Shamir) shares instead of a mnemonic. Repeat it for each share, the passphrase decrypts the secret")
+            // The shares carry no BIP39 words to fix and are decrypted
+            // with a single passphrase
+            .conflicts_with_all(&["passphrase-list", "fix-checksum", "try-swaps"])
+            .conflicts_with_all(self::other_sources("slip39-share")),
+        Arg::new("slip39-file")
+            .long("slip39-file")
+            .action(ArgAction::Set)
+            .value_name("file")
+            .value_hint(ValueHint::FilePath)
+            .help("Reads SLIP-39 shares from a file, one per line, '#' starting a comment")
+            .conflicts_with_all(&["passphrase-list", "fix-checksum", "try-swaps"])
+            .conflicts_with_all(self::other_sources("slip39-file")),
+        Arg::new("seed-type")
+            .long("seed-type")
+            .action(ArgAction::Set)
+            .help("The kind of phrase given. Electrum seeds tell the paths and the address type scanned, LND aezeed seeds are scanned at m/84'/0'/0'")
+            .value_parser(["bip39", "electrum", "aezeed"])
+            .default_value("bip39"),
+        Arg::new("aezeed-passphrase")
+            .long("aezeed-passphrase")
+            .action(ArgAction::Set)
+            .value_name("passphrase")
+            .help("The passphrase an LND aezeed seed has been enciphered with, \"aezeed\" when none has been set"),
+        Arg::new("xprv")
+            .long("xprv")
+            .action(ArgAction::Set)
+            .help("Derives from a BIP32 extended private key instead of a mnemonic, either a master key or an account level key")
+            .conflicts_with_all(&["fix-checksum", "try-swaps"])
+            .conflicts_with_all(self::other_sources("xprv"))
+            .conflicts_with_all(PASSPHRASE_OPTIONS),
+        Arg::new("xpub")
+            .long("xpub")
+            .action(ArgAction::Set)
+            .help("Scans watch-only from a BIP32 extended public key, either an account level or a chain level key, without any private key")
+            .conflicts_with_all(&["fix-checksum", "try-swaps"])
+            .conflicts_with_all(self::other_sources("xpub"))
+            .conflicts_with_all(PASSPHRASE_OPTIONS),
+        Arg::new("entropy")
+            .long("entropy")
+            .action(ArgAction::Set)
+            .help("Builds the mnemonic from its BIP39 entropy, 16 to 32 bytes given as hex, instead of reading the words")
+            // The entropy always gives a valid phrase
+            .conflicts_with_all(&["fix-checksum", "try-swaps"])
+            .conflicts_with_all(self::other_sources("entropy")),
+        Arg::new("show-mnemonic")
+            .long("show-mnemonic")
+            .action(ArgAction::SetTrue)
+            .help("Prints the mnemonic built from --entropy")
+            .requires("entropy"),
+        Arg::new("bip85-children")
+            .long("bip85-children")
+            .action(ArgAction::Set)
+            .help("Scans the BIP85 child mnemonics of indexes 0 to N-1 derived from the master seed, instead of the master seed itself")
+            .value_parser(value_parser!(u32))
+            // The child mnemonics are scanned without a passphrase,
+            // the passphrase applying to the master seed
+            .conflicts_with_all(&["xpub", "mnemonics-file", "passphrase-list", "fix-checksum", "try-swaps", "state-file"]),
+        Arg::new("bip85-words")
+            .long("bip85-words")
+            .action(ArgAction::Set)
+            .help("The number of words of the BIP85 child mnemonics, 12 by default")
+            .value_parser(["12", "24"])
+            .requires("bip85-children"),
+        Arg::new("language")
+            .long("language")
+            .action(ArgAction::Set)
+            .help("The wordlist of the mnemonic. Detected from the words by default")
+            .value_parser(PossibleValuesParser::new([
+                "english",
+                "chinese-simplified",
+                "chinese-traditional",
+                "czech",
+                "french",
+                "italian",
+                "japanese",
+                "korean",
+                "spanish",
+            ])
+            .map(|language| self::parse_language(&language).expect("the possible values are wordlists"))),
+        Arg::new("fix-checksum")
+            .long("fix-checksum")
+            .action(ArgAction::SetTrue)
+            .help("Tries every last word giving a valid checksum when the mnemonic checksum doesn't match"),
+        Arg::new("try-swaps")
+            .long("try-swaps")
+            .action(ArgAction::Set)
+            .help(
+                "Also tries the phrase with two adjacent words swapped, or any two words with --try-swaps=all. \
+                 Scans up to index 1000 unless --end is given",
+            )
+            .num_args(0..=1)
+            .require_equals(true)
+            .default_missing_value("adjacent")
+            .value_parser(["adjacent", "all"]),
+        Arg::new("ask-passphrase")
+            .long("ask-passphrase")
+            .action(ArgAction::SetTrue)
+            .help("Prompts for the mnemonic passphrase, twice, with the input hidden")
+            .conflicts_with_all(&["passphrase-file", "passphrase-list"]),
+        Arg::new("passphrase-file")
+            .long("passphrase-file")
+            .action(ArgAction::Set)
+            .value_name("file")
+            .value_hint(ValueHint::FilePath)
+            .help("Reads the mnemonic passphrase from a file, as is but for the line break ending it")
+            .conflicts_with("passphrase-list"),
+        Arg::new("passphrase-keep-trailing-newline")
+            .long("passphrase-keep-trailing-newline")
+            .action(ArgAction::SetTrue)
+            .help("Keeps the line break ending the --passphrase-file as part of the passphrase")
+            .requires("passphrase-file"),
+        Arg::new("passphrase-list")
+            .long("passphrase-list")
+            .action(ArgAction::Set)
+            .value_name("file")
+            .value_hint(ValueHint::FilePath)
+            .help("Tries every passphrase of a file, one per line, an empty line standing for no passphrase"),
+        Arg::new("redact")
+            .long("redact")
+            .action(ArgAction::SetTrue)
+            .help("Reports the line of the --passphrase-list a match is found with rather than the passphrase")
+            .requires("passphrase-list"),
+        Arg::new("start")
+            .short('s')
+            .long("start")
+            .action(ArgAction::Set)
+            .help("The start index for key index search")
+            .value_parser(value_parser!(usize)),
+        Arg::new("end")
+            .short('e')
+            .long("end")
+            .action(ArgAction::Set)
+            .help("The end index for key index search")
+            .value_parser(value_parser!(usize)),
+        Arg::new("chunksize")
+            .short('c')
+            .long("chunksize")
+            .action(ArgAction::Set)
+            .help("The number of indexes scanned per task (defaults to a few chunks per thread)")
+            .value_parser(value_parser!(usize)),
+        Arg::new("threads")
+            .long("threads")
+            .action(ArgAction::Set)
+            .help("The number of worker threads (defaults to one per core)")
+            .value_parser(value_parser!(usize)),
+        Arg::new("descriptor-range")
+            .long("descriptor-range")
+            .action(ArgAction::Set)
+            .help("The number of addresses a ranged descriptor target is derived over, from index 0 (20 by default, 100000 at most)")
+            .value_parser(value_parser!(u32)),
+        Arg::new("coin")
+            .long("coin")
+            .action(ArgAction::Set)
+            .help("The coin of the wallet, deriving under its coin type and encoding the addresses for it. Litecoin has no p2tr addresses, Dogecoin and Bitcoin Cash only have p2pkh ones, Ethereum derives its accounts under BIP44. Also takes dash, dgb, zec for the transparent Zcash addresses and the coins of --coin-config")
+            .value_name("coin"),
+        Arg::new("coin-config")
+            .long("coin-config")
+            .action(ArgAction::Set)
+            .value_name("file")
+            .value_hint(ValueHint::FilePath)
+            .help("A TOML file defining coins for --coin, one [name] table per coin with its coin_type, its p2pkh and p2sh versions, one or two bytes, its bech32 hrp if any and its address kinds"),
+        Arg::new("purposes")
+            .long("purposes")
+            .action(ArgAction::Set)
+            .help("Comma separated list of derivation purposes to scan (44, 49, 84, 86)"),
+        Arg::new("type")
+            .long("type")
+            .action(ArgAction::Set)
+            .help("Computes a single address type, scanning only the purpose it is standard for")
+            .value_parser(["p2pkh", "p2shwpkh", "p2wpkh", "p2tr"])
+            .conflicts_with("purposes"),
+        Arg::new("accounts")
+            .long("accounts")
+            .action(ArgAction::Set)
+            .help("The account index or range of account indexes to scan (e.g 0-500)"),
+        Arg::new("change")
+            .long("change")
+            .action(ArgAction::SetTrue)
+            .help("Scan the internal (change) chain instead of the external one")
+            .conflicts_with("chains"),
+        Arg::new("chains")
+            .long("chains")
+            .action(ArgAction::Set)
+            .help("Comma separated list of chains to scan (0 for external, 1 for internal)"),
+        Arg::new("preset")
+            .long("preset")
+            .action(ArgAction::Set)
+            .help("A wallet software derivation scheme to scan (see --list-presets)"),
+        Arg::new("list-presets")
+            .long("list-presets")
+            .action(ArgAction::SetTrue)
+            .help("Lists the supported wallet software presets and the paths they imply"),
+        Arg::new("addresses-file")
+            .long("addresses-file")
+            .action(ArgAction::Set)
+            .value_name("file")
+            .value_hint(ValueHint::FilePath)
+            .help("A file containing the addresses to be found, one per line"),
+        Arg::new("script")
+            .long("script")
+            .action(ArgAction::Append)
+            .help("An output script (scriptPubKey) to be found, given as hex instead of an address. Repeat it for several scripts"),
+        Arg::new("pubkey")
+            .long("pubkey")
+            .action(ArgAction::Append)
+            .help("A public key to be found, given as hex, compressed or uncompressed, instead of an address. Repeat it for several keys"),
+        Arg::new("hash160")
+            .long("hash160")
+            .action(ArgAction::Append)
+            .help("A hash160 of a public key to be found, given as hex, matching both its p2pkh and p2wpkh addresses. Repeat it for several hashes"),
+        Arg::new("ignore-network-mismatch")
+            .long("ignore-network-mismatch")
+            .action(ArgAction::SetTrue)
+            .help("Scans even for addresses encoded for another network than --network, which can't match otherwise"),
+        Arg::new("hardened-index")
+            .long("hardened-index")
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(&["pubkey-only", "emit-descriptor"])
+            .help("Derives the address indexes hardened, e.g m/44'/0'/0'/0/5', as a few non-standard wallets do. Requires the private keys"),
+        Arg::new("include-uncompressed")
+            .long("include-uncompressed")
+            .action(ArgAction::SetTrue)
+            .help("Scans the p2pkh addresses of the uncompressed public keys as well, as very old wallets and paper wallets computed them"),
+        Arg::new("cosigner-xpub")
+            .long("cosigner-xpub")
+            .action(ArgAction::Append)
+            .value_name("xpub")
+            .help("The BIP48 account key of another cosigner of a multisig wallet (xpub or Zpub), scanning its p2wsh addresses. Repeat it for each cosigner")
+            .requires("threshold")
+            .conflicts_with_all(&[
+                "preset",
+                "purposes",
+                "type",
+                "coin",
+                "xpub",
+                "emit-descriptor",
+                "sign-message",
+                "sign-message-bip322",
+                "psbt",
+                "include-uncompressed",
+                "hardened-index",
+            ]),
+        Arg::new("threshold")
+            .long("threshold")
+            .action(ArgAction::Set)
+            .help("The number of signatures the multisig script of --cosigner-xpub requires")
+            .requires("cosigner-xpub")
+            .value_parser(value_parser!(usize)),
+        Arg::new("no-sort-keys")
+            .long("no-sort-keys")
+            .action(ArgAction::SetTrue)
+            .requires("cosigner-xpub")
+            .help("Keeps the keys of the multisig script in order, the key of the seed first, instead of sorting them (multi() rather than sortedmulti())"),
+        Arg::new("key-position")
+            .long("key-position")
+            .action(ArgAction::Set)
+            .value_name("position")
+            .requires("no-sort-keys")
+            .help("The position of the key of the seed among the keys of the unsorted multisig script, from 1, the cosigners following the order given")
+            .value_parser(value_parser!(usize).range(1..)),
+        Arg::new("p2sh-wrapped")
+            .long("p2sh-wrapped")
+            .action(ArgAction::SetTrue)
+            .requires("cosigner-xpub")
+            .help("Scans the p2sh-p2wsh addresses of the multisig wallet, under the 1' script type path of BIP48"),
+        Arg::new("emit-descriptor")
+            .long("emit-descriptor")
+            .action(ArgAction::SetTrue)
+            .help("Prints the Bitcoin Core importdescriptors request of the account a match belongs to"),
+        Arg::new("show-private")
+            .long("show-private")
+            .action(ArgAction::SetTrue)
+            .conflicts_with("pubkey-only")
+            .help("Prints the private key of a match in WIF, to sweep its funds"),
+        Arg::new("show-xpub")
+            .long("show-xpub")
+            .action(ArgAction::SetTrue)
+            .help("Prints the extended public key of the account of a match, to set up a watch-only wallet"),
+        Arg::new("sign-message")
+            .long("sign-message")
+            .action(ArgAction::Set)
+            .value_name("text")
+            .conflicts_with("pubkey-only")
+            .help("Signs a message with the key of a match, in the signmessage format, to prove its ownership"),
+        Arg::new("sign-message-bip322")
+            .long("sign-message-bip322")
+            .action(ArgAction::Set)
+            .value_name("text")
+            .conflicts_with("pubkey-only")
+            .help("Signs a message with the key of a match with BIP322, for p2wpkh and p2pkh addresses"),
+        Arg::new("psbt")
+            .long("psbt")
+            .action(ArgAction::Set)
+            .value_name("file-or-base64")
+            .value_hint(ValueHint::FilePath)
+            .requires("psbt-out")
+            .conflicts_with("pubkey-only")
+            .help("Signs the inputs of a PSBT spending the address of a match with its key"),
+        Arg::new("psbt-out")
+            .long("psbt-out")
+            .action(ArgAction::Set)
+            .value_name("file")
+            .value_hint(ValueHint::FilePath)
+            .requires("psbt")
+            .help("Writes the PSBT signed with --psbt to a file, in base64"),
+        Arg::new("sign-all-known")
+            .long("sign-all-known")
+            .action(ArgAction::SetTrue)
+            .requires("psbt")
+            .help("Signs the inputs of the PSBT with any key of the scanned range, not only the ones of the matches"),
+    ];
+    args.extend(self::qr_args());
+    args.extend([
+        Arg::new("with-private")
+            .long("with-private")
+            .action(ArgAction::SetTrue)
+            .requires("emit-descriptor")
+            .help("Gives the account xprv instead of the xpub in the descriptor printed for a match"),
+        Arg::new("all")
+            .long("all")
+            .action(ArgAction::SetTrue)
+            .help("Keep scanning after a match and report every address found"),
+        Arg::new("json")
+            .long("json")
+            .action(ArgAction::SetTrue)
+            .help("Prints the result as JSON. Diagnostic messages are printed on stderr")
+            .conflicts_with("format"),
+        Arg::new("quiet")
+            .short('q')
+            .long("quiet")
+            .action(ArgAction::SetTrue)
+            .help("Prints only the index of the match, or nothing when not found. Diagnostic messages are printed on stderr"),
+        Arg::new("print")
+            .long("print")
+            .action(ArgAction::Set)
+            .help("What to print for a match in quiet mode")
+            .value_parser(["index", "path"])
+            .requires("quiet"),
+        Arg::new("output")
+            .long("output")
+            .action(ArgAction::Set)
+            .value_name("file")
+            .value_hint(ValueHint::FilePath)
+            .help("Writes the results to a file, in the format of --format, as soon as they are found. The text output stays on stdout"),
+        Arg::new("force")
+            .long("force")
+            .action(ArgAction::SetTrue)
+            .help("Overwrites the file of --output when it exists")
+            .requires("output"),
+        Arg::new("pubkey-only")
+            .long("pubkey-only")
+            .action(ArgAction::SetTrue)
+            .help("Derives the addresses from the chain extended public keys, without deriving any index private key"),
+        Arg::new("dry-run")
+            .long("dry-run")
+            .action(ArgAction::SetTrue)
+            .help("Prints the scan plan and an estimate of its duration without scanning"),
+        Arg::new("trace-derivations")
+            .long("trace-derivations")
+            .action(ArgAction::Set)
+            .value_name("N")
+            .help("Logs the path and address of one in N of the indexes checked on stderr, at most 100 lines a second. Every one is logged with -vvv")
+            .value_parser(value_parser!(usize)),
+        Arg::new("max-duration")
+            .long("max-duration")
+            .action(ArgAction::Set)
+            .help("Stops the scan after a duration (e.g 90s, 30m or 1h30m)")
+            .value_parser(|value: &str| self::parse_duration(value).ok_or("expected a duration like 90s, 30m or 1h30m")),
+        Arg::new("state-file")
+            .long("state-file")
+            .action(ArgAction::Set)
+            .value_name("file")
+            .value_hint(ValueHint::FilePath)
+            .help("Saves the progress of the scan to a file, and resumes from it when the file exists")
+            // A state file identifies a single wallet
+            .conflicts_with_all(&["passphrase-list", "fix-checksum", "try-swaps", "mnemonics-file"]),
+        Arg::new("no-progress")
+            .long("no-progress")
+            .action(ArgAction::SetTrue)
+            .help("Disables the progress bar, which is only shown for text output on a terminal"),
+        Arg::new("mnemonic")
+            .index(1)
+            .help(
+                "A BIP39 mnemonic of 12, 15, 18, 21 or 24 words (without passphrase), quoted or as separate words. \
+                 Prompted for, or read from stdin when piped, if left out",
+            ),
+        Arg::new("address")
+            .index(2)
+            .num_args(1..)
+            .help("The address to be found, or an output descriptor of the addresses. Several candidate addresses can be provided"),
+    ]);
+    args
+}
+
+pub fn app() -> Command {
+    Command::new("hdifinder")
+            .after_help(
+                "EXIT CODES:\n    \
+                 0    The address has been found\n    \
+                 1    Usage error or unexpected failure\n    \
+                 2    The whole range has been scanned without finding the address\n    \
+                 3    The mnemonic is invalid\n    \
+                 4    The address is invalid\n\n\
+                 ENVIRONMENT:\n    \
+                 HDIFINDER_MNEMONIC      The mnemonic, when neither given as an argument nor with --mnemonic-file\n    \
+                 HDIFINDER_PASSPHRASE    The mnemonic passphrase, when --passphrase isn't given",
+            )
+            .version("1.0")
+            .author("Nelson Herbin <nelson@herbin.info>")
+            .about("A small utility to find if a key is part of an HD scheme")
+            .arg(
+                Arg::new("network")
+                    .short('n')
+                    .long("network")
+                    .action(ArgAction::Set)
+                    .global(true)
+                    .help("The network the addresses belong to")
+                    .value_parser(
+                        PossibleValuesParser::new(["bitcoin", "mainnet", "testnet", "signet", "regtest"])
+                            .map(|network| self::parse_network(&network).expect("the possible values are networks")),
+                    ),
+            )
+            .arg(
+                Arg::new("format")
+                    .long("format")
+                    .action(ArgAction::Set)
+                    .global(true)
+                    .help("The output format of the results. Diagnostic messages are printed on stderr for json, ndjson and csv")
+                    .value_parser(PossibleValuesParser::new(["text", "json", "ndjson", "csv"]).map(|format| self::parse_format(&format))),
+            )
+            .arg(
+                Arg::new("verbose")
+                    .short('v')
+                    .long("verbose")
+                    .action(ArgAction::Count)
+                    .global(true)
+                    .help("Prints more details, such as every chunk boundary of the dry run plan. Repeat it to log on stderr: -v info, -vv debug, -vvv trace"),
+            )
+            .arg(
+                Arg::new("no-color")
+                    .long("no-color")
+                    .action(ArgAction::SetTrue)
+                    .global(true)
+                    .help("Disables the colors of the matches, warnings and errors, as the NO_COLOR environment variable does"),
+            )
+            .args(self::search_args())
+            .subcommand(
+                Command::new("search")
+                    .about("Searches the addresses in the wallet, as a command line without subcommand does")
+                    .args(self::search_args()),
+            )
+            .subcommand(
+                Command::new("bench")
+                    .about("Measures the derivation throughput of this machine on a built-in test mnemonic")
+                    .arg(
+                        Arg::new("keys")
+                            .long("keys")
+                            .action(ArgAction::Set)
+                            .default_value("100000")
+                            .value_parser(value_parser!(usize))
+                            .help("Number of keys to derive, spread over the address types"),
+                    )
+                    .arg(
+                        Arg::new("json")
+                            .long("json")
+                            .action(ArgAction::SetTrue)
+                            .help("Prints the results as JSON"),
+                    ),
+            )
+            .subcommand(
+                Command::new("generate")
+                    .about("Generates a new mnemonic from the randomness of the operating system and lists its first addresses")
+                    .arg(
+                        Arg::new("words")
+                            .long("words")
+                            .action(ArgAction::Set)
+                            .value_parser(["12", "15", "18", "21", "24"])
+                            .default_value("12")
+                            .help("The number of words of the mnemonic"),
+                    )
+                    .arg(
+                        Arg::new("purposes")
+                            .long("purposes")
+                            .action(ArgAction::Set)
+                            .default_value("44,49,84,86")
+                            .help("Comma separated list of the purposes the addresses are listed for"),
+                    )
+                    .arg(
+                        Arg::new("count")
+                            .long("count")
+                            .action(ArgAction::Set)
+                            .default_value("5")
+                            .value_parser(value_parser!(u32))
+                            .help("Number of addresses listed for each purpose"),
+                    )
+                    .arg(
+                        Arg::new("json")
+                            .long("json")
+                            .action(ArgAction::SetTrue)
+                            .help("Prints the mnemonic and the addresses as JSON"),
+                    )
+                    .arg(
+                        Arg::new("i-know-what-im-doing")
+                            .long("i-know-what-im-doing")
+                            .action(ArgAction::SetTrue)
+                            .help("Prints the mnemonic even when the output isn't a terminal"),
+                    ),
+            )
+            .subcommand(
+                Command::new("derive")
+                    .about("Lists the addresses of a range of indexes of a wallet, without searching")
+                    .arg(
+                        Arg::new("mnemonic")
+                            .index(1)
+                            .num_args(1..)
+                            .help("A BIP39 mnemonic, quoted or as separate words. Prompted for, or read from stdin when piped, if left out"),
+                    )
+                    .arg(
+                        Arg::new("passphrase")
+                            .short('p')
+                            .long("passphrase")
+                            .action(ArgAction::Set)
+                            .help("The mnemonic passphrase"),
+                    )
+                    .arg(
+                        Arg::new("start")
+                            .short('s')
+                            .long("start")
+                            .action(ArgAction::Set)
+                            .default_value("0")
+                            .value_parser(value_parser!(usize))
+                            .help("The first index listed"),
+                    )
+                    .arg(
+                        Arg::new("end")
+                            .short('e')
+                            .long("end")
+                            .action(ArgAction::Set)
+                            .value_parser(value_parser!(usize))
+                            .help("The index the list stops before, 20 by default"),
+                    )
+                    .arg(
+                        Arg::new("purposes")
+                            .long("purposes")
+                            .action(ArgAction::Set)
+                            .help("Comma separated list of derivation purposes to list (44, 49, 84, 86)"),
+                    )
+                    .arg(
+                        Arg::new("type")
+                            .long("type")
+                            .action(ArgAction::Set)
+                            .value_parser(["p2pkh", "p2shwpkh", "p2wpkh", "p2tr", "all"])
+                            .conflicts_with("purposes")
+                            .help("Lists a single address type, or all of them"),
+                    )
+                    .arg(
+                        Arg::new("accounts")
+                            .long("accounts")
+                            .action(ArgAction::Set)
+                            .help("The account index or range of account indexes to list (e.g 0-5)"),
+                    )
+                    .arg(
+                        Arg::new("change")
+                            .long("change")
+                            .action(ArgAction::SetTrue)
+                            .conflicts_with("chains")
+                            .help("Lists the internal (change) chain instead of the external one"),
+                    )
+                    .arg(
+                        Arg::new("chains")
+                            .long("chains")
+                            .action(ArgAction::Set)
+                            .help("Comma separated list of chains to list (0 for external, 1 for internal)"),
+                    )
+                    .arg(
+                        Arg::new("preset")
+                            .long("preset")
+                            .action(ArgAction::Set)
+                            .help("A wallet software derivation scheme to list (see --list-presets)"),
+                    )
+                    .arg(
+                        Arg::new("show-xpub")
+                            .long("show-xpub")
+                            .action(ArgAction::SetTrue)
+                            .help("Prints the extended public key of each account listed"),
+                    ),
+            )
+            .subcommand(
+                Command::new("verify")
+                    .about("Checks that an index of a wallet derives an address, before sweeping it")
+                    .arg(
+                        Arg::new("mnemonic")
+                            .index(1)
+                            .num_args(1..)
+                            .help("A BIP39 mnemonic, quoted or as separate words. Prompted for, or read from stdin when piped, if left out"),
+                    )
+                    .arg(
+                        Arg::new("passphrase")
+                            .short('p')
+                            .long("passphrase")
+                            .action(ArgAction::Set)
+                            .help("The mnemonic passphrase"),
+                    )
+                    .arg(
+                        Arg::new("index")
+                            .short('i')
+                            .long("index")
+                            .action(ArgAction::Set)
+                            .required(true)
+                            .value_parser(value_parser!(u32))
+                            .help("The index the address is expected at"),
+                    )
+                    .arg(
+                        Arg::new("address")
+                            .short('a')
+                            .long("address")
+                            .action(ArgAction::Set)
+                            .required(true)
+                            .help("The address expected"),
+                    )
+                    .arg(
+                        Arg::new("purposes")
+                            .long("purposes")
+                            .action(ArgAction::Set)
+                            .help("Comma separated list of derivation purposes to check (44, 49, 84, 86)"),
+                    )
+                    .arg(
+                        Arg::new("type")
+                            .long("type")
+                            .action(ArgAction::Set)
+                            .value_parser(["p2pkh", "p2shwpkh", "p2wpkh", "p2tr", "all"])
+                            .conflicts_with("purposes")
+                            .help("Checks a single address type, or all of them"),
+                    )
+                    .arg(
+                        Arg::new("accounts")
+                            .long("accounts")
+                            .action(ArgAction::Set)
+                            .help("The account index or range of account indexes to check (e.g 0-5)"),
+                    )
+                    .arg(
+                        Arg::new("change")
+                            .long("change")
+                            .action(ArgAction::SetTrue)
+                            .conflicts_with("chains")
+                            .help("Checks the internal (change) chain instead of the external one"),
+                    )
+                    .arg(
+                        Arg::new("chains")
+                            .long("chains")
+                            .action(ArgAction::Set)
+                            .help("Comma separated list of chains to check (0 for external, 1 for internal)"),
+                    )
+                    .arg(
+                        Arg::new("preset")
+                            .long("preset")
+                            .action(ArgAction::Set)
+                            .help("A wallet software derivation scheme to check (see --list-presets)"),
+                    ),
+            )
+            .subcommand(
+                Command::new("completions")
+                    .about("Prints the completion script of a shell, to source or to install")
+                    .arg(
+                        Arg::new("shell")
+                            .index(1)
+                            .required(true)
+                            .value_parser(["bash", "zsh", "fish", "powershell"])
+                            .help("The shell the script is for"),
+                    ),
+            )
+}
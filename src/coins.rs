@@ -0,0 +1,128 @@
+use crate::{address, models::CoinDefinition};
+use bitcoin::util::base58;
+use hdpath::Purpose;
+
+/**
+ * The coins described by their data rather than built in, which --coin
+ * finds by their name along with the ones of a --coin-config file
+ */
+pub const DEFINITIONS: [CoinDefinition; 3] = [
+    CoinDefinition {
+        name: "dash",
+        coin_type: 5,
+        p2pkh: 0x4c,
+        p2sh: 0x10,
+        hrp: None,
+        purposes: &[Purpose::Pubkey],
+    },
+    CoinDefinition {
+        name: "dgb",
+        coin_type: 20,
+        p2pkh: 0x1e,
+        p2sh: 0x3f,
+        hrp: Some("dgb"),
+        purposes: &[Purpose::Pubkey, Purpose::ScriptHash, Purpose::Witness],
+    },
+    // The transparent addresses of Zcash, t1 and t3, have two bytes versions
+    CoinDefinition {
+        name: "zec",
+        coin_type: 133,
+        p2pkh: 0x1cb8,
+        p2sh: 0x1cbd,
+        hrp: None,
+        purposes: &[Purpose::Pubkey],
+    },
+];
+
+/**
+ * Retrieves a coin definition from its name, the given definitions taking
+ * precedence over the built-in ones
+ */
+pub fn find_definition(name: &str, definitions: &[CoinDefinition]) -> Option<CoinDefinition> {
+    definitions.iter().chain(DEFINITIONS.iter()).find(|definition| definition.name == name).copied()
+}
+
+/**
+ * Tells whether an address is a shielded Zcash one, which no public key
+ * derived by BIP32 pays to: a Sapling or Unified address in bech32, or a
+ * Sprout one in base58
+ */
+pub fn is_zcash_shielded(address: &str) -> bool {
+    let lowercase = address.to_lowercase();
+    if ["zs1", "ztestsapling1", "zregtestsapling1"].iter().any(|prefix| lowercase.starts_with(prefix)) {
+        return true;
+    }
+    // Unified addresses are bech32m strings above the 90 characters of bech32
+    if ["u1", "utest1", "uregtest1"].iter().any(|prefix| lowercase.starts_with(prefix)) && address.len() > 90 {
+        return true;
+    }
+    match base58::from_check(address) {
+        Ok(bytes) => bytes.len() == 66 && (bytes.starts_with(&[0x16, 0x9a]) || bytes.starts_with(&[0x16, 0xb6])),
+        Err(_) => false,
+    }
+}
+
+/**
+ * Checks a coin definition can encode the addresses it lists: both base58
+ * versions tell the types apart, and the segwit types have a human readable
+ * part to be encoded with
+ */
+pub fn validate(definition: &CoinDefinition) -> Result<(), String> {
+    if definition.name.is_empty() || !definition.name.bytes().all(|byte| byte.is_ascii_lowercase() || byte.is_ascii_digit() || byte == b'-') {
+        return Err(format!("invalid name {:?}, expected lowercase letters, digits and dashes", definition.name));
+    }
+    if definition.coin_type >= 1 << 31 {
+        return Err(format!("coin_type {} isn't below 2^31, the coin type level being hardened", definition.coin_type));
+    }
+    if definition.p2pkh == definition.p2sh {
+        return Err(format!("p2pkh and p2sh share the version {:#04x}", definition.p2pkh));
+    }
+    if definition.purposes.is_empty() {
+        return Err("no address kinds".to_string());
+    }
+    if let Some(hrp) = definition.hrp {
+        if hrp.is_empty() || !hrp.bytes().all(|byte| byte.is_ascii_lowercase() || byte.is_ascii_digit()) {
+            return Err(format!("invalid hrp {:?}, expected lowercase letters and digits", hrp));
+        }
+    }
+    let segwit: Vec<&str> = definition.purposes.iter().filter(|purpose| **purpose != Purpose::Pubkey).map(address::address_type).collect();
+    if definition.hrp.is_none() && !segwit.is_empty() {
+        return Err(format!("{} addresses need an hrp", segwit.join(" and ")));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_definitions(){
+    for definition in DEFINITIONS.iter() {
+        assert_eq!(validate(definition), Ok(()), "{}", definition.name);
+    }
+    assert_eq!(find_definition("dash", &[]).unwrap().p2pkh, 0x4c);
+    assert!(find_definition("btc", &[]).is_none());
+
+    let custom = CoinDefinition { p2pkh: 0x4d, ..DEFINITIONS[0] };
+    assert_eq!(find_definition("dash", &[custom]).unwrap().p2pkh, 0x4d);
+}
+
+#[test]
+fn test_validate(){
+    let dgb = DEFINITIONS[1];
+    assert_eq!(validate(&CoinDefinition { p2sh: 0x1e, ..dgb }).unwrap_err(), "p2pkh and p2sh share the version 0x1e");
+    assert_eq!(validate(&CoinDefinition { hrp: None, ..dgb }).unwrap_err(), "p2shwpkh and p2wpkh addresses need an hrp");
+    assert_eq!(validate(&CoinDefinition { hrp: Some("DGB"), ..dgb }).unwrap_err(), "invalid hrp \"DGB\", expected lowercase letters and digits");
+    assert_eq!(validate(&CoinDefinition { purposes: &[], ..dgb }).unwrap_err(), "no address kinds");
+    assert_eq!(validate(&CoinDefinition { name: "Dgb", ..dgb }).unwrap_err(), "invalid name \"Dgb\", expected lowercase letters, digits and dashes");
+    assert!(validate(&CoinDefinition { coin_type: 1 << 31, ..dgb }).is_err());
+}
+
+#[test]
+fn test_is_zcash_shielded(){
+    assert!(is_zcash_shielded("zs1zr3lvrvcsvyyekegkza2znprnx808vyufrgf9nfa9ukksa2ss3tczx9efycg75wedv5s774jxwv"));
+    assert!(is_zcash_shielded("ZS1ZR3LVRVCSVYYEKEGKZA2ZNPRNX808VYUFRGF9NFA9UKKSA2SS3TCZX9EFYCG75WEDV5S774JXWV"));
+    assert!(is_zcash_shielded("zcVmcdWpjG7CDM9axT4F3ZSVb4BKxKcQ15WeWsPotVVWynjLrdpRyfKrqd9vWK3dV6LnNgEykyEnXxaDQWJEDdgqDkcZo5E"));
+    assert!(is_zcash_shielded(&format!("u1{}", "q".repeat(104))));
+    // transparent addresses
+    assert!(!is_zcash_shielded("t1XVXWCvpMgBvUaed4XDqWtgQgJSu1Ghz7F"));
+    assert!(!is_zcash_shielded("t3YBYRkRFuaWJZkM4BctRwX3LppjcVcRAoJ"));
+    assert!(!is_zcash_shielded("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"));
+}
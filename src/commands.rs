@@ -0,0 +1,143 @@
+use crate::{
+    bench,
+    cli::{self, EXIT_FOUND, EXIT_NOT_FOUND},
+    completions, derive, generate, output, prompt,
+};
+use bitcoin::{network::constants::Network, util::bip32::ExtendedPrivKey};
+use clap::ArgMatches;
+use clap_complete::Shell;
+use hdifinder::{address, derivation, models::OutputFormat, presets, HdiFinderError};
+use secp256k1::Secp256k1;
+
+/**
+ * Runs the subcommand given, or --list-presets, returns the exit code to
+ * report. None is returned for the arguments of a search.
+ */
+pub fn run(matches: &ArgMatches) -> Result<Option<i32>, HdiFinderError> {
+    if cli::search_matches(matches).get_flag("list-presets") {
+        self::list_presets();
+        return Ok(Some(0));
+    }
+
+    if let Some(completions_matches) = matches.subcommand_matches("completions") {
+        let shell = completions_matches.get_one::<String>("shell").expect("the shell is required").parse::<Shell>().map_err(HdiFinderError::InvalidArgument)?;
+        output::result(&completions::script(shell));
+        return Ok(Some(0));
+    }
+
+    if let Some(bench_matches) = matches.subcommand_matches("bench") {
+        let options = cli::bench_options(bench_matches)?;
+        let secp = Secp256k1::new();
+        let results = bench::run(&secp, options.keys)?;
+        let hook = bench::hook(&secp, options.keys)?;
+        let master_key = bench::master_key(&secp, options.keys)?;
+        if options.json {
+            result!("{}", bench::json_report(&results, &hook, &master_key));
+        } else {
+            output::result(&bench::text_report(&results, &hook, &master_key));
+        }
+        return Ok(Some(0));
+    }
+
+    if let Some(generate_matches) = matches.subcommand_matches("generate") {
+        let options = cli::generate_options(generate_matches)?;
+        let secp = Secp256k1::new();
+        let mnemonic = generate::generate_mnemonic(options.words)?;
+        let addresses = generate::first_addresses(&secp, &mnemonic, options.network, &options.purposes, options.count)?;
+        if options.json {
+            result!("{}", generate::json_report(&mnemonic, options.network, &addresses));
+        } else {
+            output::result(&generate::text_report(&mnemonic, &addresses));
+        }
+        return Ok(Some(0));
+    }
+
+    if let Some(derive_matches) = matches.subcommand_matches("derive") {
+        let options = cli::derive_options(derive_matches)?;
+        let (master, _) = self::subcommand_master_key(derive_matches, options.paths.network)?;
+        let secp = Secp256k1::new();
+        let rows = derive::derive_rows(&secp, &master, &options.paths, options.start..options.end)?;
+        let accounts = if options.show_xpub {
+            derive::account_xpubs(&secp, &master, &options.paths)?
+        } else {
+            Vec::new()
+        };
+        if options.format != OutputFormat::Json {
+            for account in &accounts {
+                notice!(options.format == OutputFormat::Csv, "account xpub: {}", output::text_account_xpub(account));
+            }
+        }
+        match options.format {
+            OutputFormat::Json => result!("{}", derive::json_rows(&rows, &accounts)),
+            OutputFormat::Csv => output::result(&derive::csv_rows(&rows)),
+            _ => output::result(&derive::text_rows(&rows)),
+        }
+        return Ok(Some(0));
+    }
+
+    if let Some(verify_matches) = matches.subcommand_matches("verify") {
+        let options = cli::verify_options(verify_matches)?;
+        let (master, passphrase) = self::subcommand_master_key(verify_matches, options.paths.network)?;
+        let secp = Secp256k1::new();
+        let rows = derive::derive_rows(&secp, &master, &options.paths, options.index..options.index + 1)?;
+        let passphrase_note = if passphrase.is_empty() { "without passphrase" } else { "with the passphrase given" };
+        return match rows.iter().find(|row| row.derived.address == options.address) {
+            Some(row) => {
+                result!(
+                    "Verified: {} is the {} address at {} on {}, {}",
+                    options.address, row.derived.address_type, row.derivation_path, options.paths.network, passphrase_note
+                );
+                Ok(Some(EXIT_FOUND))
+            }
+            None => {
+                result!(
+                    "{} isn't derived at index {} on {}, {}. The addresses derived there are:",
+                    options.address, options.index, options.paths.network, passphrase_note
+                );
+                output::result(&derive::text_rows(&rows));
+                Ok(Some(EXIT_NOT_FOUND))
+            }
+        };
+    }
+
+    Ok(None)
+}
+
+/**
+ * Prints the supported presets along with the paths and address types they imply
+ */
+fn list_presets() {
+    for preset in presets::PRESETS.iter() {
+        result!("{}", preset.name);
+        let paths = presets::preset_paths(preset, derivation::coin_type(Network::Bitcoin));
+        for (path, purpose) in paths.iter().zip(preset.purposes.iter()) {
+            if preset.layout.is_legacy() {
+                result!("    {} ({}, {})", path, address::address_type(purpose), address::P2PKH_UNCOMPRESSED);
+            } else {
+                result!("    {} ({})", path, address::address_type(purpose));
+            }
+        }
+        for (path, named) in paths[preset.purposes.len()..].iter().zip(preset.named_accounts.iter()) {
+            result!("    {} ({}, {})", path, address::address_type(&named.purpose), named.name);
+        }
+    }
+}
+
+/**
+ * Reads the mnemonic and the passphrase of the derive and verify
+ * subcommands, returning the master key along with the passphrase
+ */
+fn subcommand_master_key(args: &ArgMatches, network: Network) -> Result<(ExtendedPrivKey, String), HdiFinderError> {
+    let phrase = match args.get_many::<String>("mnemonic") {
+        Some(words) => words.map(String::as_str).collect::<Vec<&str>>().join(" "),
+        None => prompt::read_secret("Enter mnemonic")?,
+    };
+    let mnemonic = derivation::get_mnemonic(&phrase, None)?;
+    let passphrase = args
+        .get_one::<String>("passphrase")
+        .cloned()
+        .or_else(|| cli::env_secret(cli::PASSPHRASE_VAR))
+        .unwrap_or_default();
+    let master = derivation::get_master_key(mnemonic.to_seed(&passphrase), network)?;
+    Ok((master, passphrase))
+}
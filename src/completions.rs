@@ -0,0 +1,37 @@
+use crate::cli;
+use clap_complete::Shell;
+
+/**
+ * Generates the completion script of a shell with clap_complete. The options
+ * taking a file carry a file path hint, which the scripts complete with paths.
+ */
+pub fn script(shell: Shell) -> String {
+    let mut generated = Vec::new();
+    clap_complete::generate(shell, &mut cli::app(), "hdifinder", &mut generated);
+    String::from_utf8(generated).expect("the completion scripts are generated as UTF-8")
+}
+
+#[test]
+fn test_script(){
+    for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell] {
+        let script = script(shell);
+        assert!(!script.is_empty());
+        for option in ["network", "end", "config", "output", "dry-run"] {
+            let written = if matches!(shell, Shell::Fish) { format!("-l {} ", option) } else { format!("--{}", option) };
+            assert!(script.contains(&written), "{} {}", shell, option);
+        }
+    }
+
+    let bash = script(Shell::Bash);
+    assert!(bash.contains("                --config)\n                    local oldifs\n"));
+    assert!(bash.contains("                    compopt -o filenames\n"));
+    assert!(bash.contains("                --network)\n                    COMPREPLY=($(compgen -W \"bitcoin mainnet testnet signet regtest\" -- \"${cur}\"))\n"));
+
+    let zsh = script(Shell::Zsh);
+    assert!(zsh.contains("--state-file=[Saves the progress of the scan to a file, and resumes from it when the file exists]:file:_files' \\\n"));
+    assert!(zsh.contains("'--dry-run[Prints the scan plan and an estimate of its duration without scanning]' \\\n"));
+
+    let fish = script(Shell::Fish);
+    assert!(fish.contains(" -l output -d 'Writes the results to a file, in the format of --format, as soon as they are found. The text output stays on stdout' -r -F\n"));
+    assert!(fish.contains(" -l dry-run -d 'Prints the scan plan and an estimate of its duration without scanning'\n"));
+}
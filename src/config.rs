@@ -0,0 +1,187 @@
+/**
+ * The TOML files of --config and --coin-config, read into typed structures:
+ * an unknown key or a value of the wrong type is refused along with the line
+ * it is on, rather than ignored.
+ */
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/**
+ * A value of the comma separated lists and of the repeatable options, given
+ * alone or as an array
+ */
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Values {
+    One(Item),
+    Many(Vec<Item>),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Item {
+    Integer(u64),
+    String(String),
+}
+
+/**
+ * --try-swaps, a flag taking an optional value
+ */
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum TrySwaps {
+    Flag(bool),
+    Kind(String),
+}
+
+/**
+ * The keys of a --config file, named after the long options they mirror: the
+ * flags are booleans, the numbers integers and the other options strings.
+ * The positional arguments, --help and --version have no key.
+ */
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ConfigFile {
+    pub network: Option<String>,
+    pub format: Option<String>,
+    pub verbose: Option<bool>,
+    pub no_color: Option<bool>,
+    pub config: Option<String>,
+    pub passphrase: Option<String>,
+    pub mnemonic_file: Option<String>,
+    pub mnemonics_file: Option<String>,
+    pub seed: Option<String>,
+    pub slip39_share: Option<Values>,
+    pub slip39_file: Option<String>,
+    pub seed_type: Option<String>,
+    pub aezeed_passphrase: Option<String>,
+    pub xprv: Option<String>,
+    pub xpub: Option<String>,
+    pub entropy: Option<String>,
+    pub show_mnemonic: Option<bool>,
+    pub bip85_children: Option<u64>,
+    pub bip85_words: Option<u64>,
+    pub language: Option<String>,
+    pub fix_checksum: Option<bool>,
+    pub try_swaps: Option<TrySwaps>,
+    pub ask_passphrase: Option<bool>,
+    pub passphrase_file: Option<String>,
+    pub passphrase_keep_trailing_newline: Option<bool>,
+    pub passphrase_list: Option<String>,
+    pub redact: Option<bool>,
+    pub start: Option<u64>,
+    pub end: Option<u64>,
+    pub chunksize: Option<u64>,
+    pub threads: Option<u64>,
+    pub coin: Option<String>,
+    pub coin_config: Option<String>,
+    pub purposes: Option<Values>,
+    #[serde(rename = "type")]
+    pub address_type: Option<String>,
+    pub accounts: Option<Values>,
+    pub change: Option<bool>,
+    pub chains: Option<Values>,
+    pub preset: Option<String>,
+    pub list_presets: Option<bool>,
+    pub addresses_file: Option<String>,
+    pub script: Option<Values>,
+    pub pubkey: Option<Values>,
+    pub hash160: Option<Values>,
+    pub ignore_network_mismatch: Option<bool>,
+    pub hardened_index: Option<bool>,
+    pub include_uncompressed: Option<bool>,
+    pub cosigner_xpub: Option<Values>,
+    pub threshold: Option<u64>,
+    pub no_sort_keys: Option<bool>,
+    pub p2sh_wrapped: Option<bool>,
+    pub emit_descriptor: Option<bool>,
+    pub show_private: Option<bool>,
+    pub i_know_what_im_doing: Option<bool>,
+    pub show_xpub: Option<bool>,
+    pub sign_message: Option<String>,
+    pub sign_message_bip322: Option<String>,
+    pub psbt: Option<String>,
+    pub psbt_out: Option<String>,
+    pub sign_all_known: Option<bool>,
+    #[cfg(feature = "qr")]
+    pub qr: Option<bool>,
+    #[cfg(feature = "qr")]
+    pub qr_private: Option<bool>,
+    #[cfg(feature = "qr")]
+    pub qr_file: Option<String>,
+    pub with_private: Option<bool>,
+    pub all: Option<bool>,
+    pub json: Option<bool>,
+    pub quiet: Option<bool>,
+    pub print: Option<String>,
+    pub output: Option<String>,
+    pub force: Option<bool>,
+    pub pubkey_only: Option<bool>,
+    pub dry_run: Option<bool>,
+    pub trace_derivations: Option<u64>,
+    pub max_duration: Option<String>,
+    pub state_file: Option<String>,
+    pub no_progress: Option<bool>,
+}
+
+impl ConfigFile {
+    /**
+     * The keys set in the file, in the order of the options, with their values
+     */
+    pub fn entries(&self) -> toml::Table {
+        toml::Table::try_from(self).expect("the keys of a config file are strings, integers, booleans or arrays")
+    }
+}
+
+/**
+ * A coin of a --coin-config file, whose table is named as --coin takes it
+ */
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CoinTable {
+    pub coin_type: u32,
+    pub p2pkh: u16,
+    pub p2sh: u16,
+    pub hrp: Option<String>,
+    pub kinds: Vec<String>,
+}
+
+/**
+ * Message of a TOML error, an unknown key being named alone rather than
+ * along with every key it could have been
+ */
+pub fn error_message(e: &toml::de::Error) -> String {
+    let message = e.message().trim_end();
+    match message.strip_prefix("unknown field `") {
+        Some(rest) => format!("unknown key {}", rest.split('`').next().unwrap_or_default()),
+        None => message.to_string(),
+    }
+}
+
+/**
+ * Reads a TOML file into its structure, the error telling the line it's on
+ */
+pub fn parse<T: DeserializeOwned>(text: &str) -> Result<T, String> {
+    toml::from_str(text).map_err(|e| {
+        let message = self::error_message(&e);
+        match e.span() {
+            Some(span) => format!("line {}: {}", text[..span.start].matches('\n').count() + 1, message),
+            None => message,
+        }
+    })
+}
+
+#[test]
+fn test_parse(){
+    let file: ConfigFile = parse("# job\nnetwork = \"testnet\"\npurposes = [44, 84]\ntype = \"p2wpkh\"\ntry-swaps = \"all\"\n").unwrap();
+    assert_eq!(file.network.as_deref(), Some("testnet"));
+    assert_eq!(file.address_type.as_deref(), Some("p2wpkh"));
+    assert!(matches!(file.purposes, Some(Values::Many(ref purposes)) if purposes.len() == 2));
+    assert!(matches!(file.try_swaps, Some(TrySwaps::Kind(ref kind)) if kind == "all"));
+    let entries = file.entries();
+    assert_eq!(entries.keys().collect::<Vec<_>>(), ["network", "try-swaps", "purposes", "type"]);
+
+    assert_eq!(parse::<ConfigFile>("threads = 4\nnetwrk = \"testnet\"\n").unwrap_err(), "line 2: unknown key netwrk");
+    assert!(parse::<ConfigFile>("threads = \"4\"\n").unwrap_err().starts_with("line 1: invalid type: string \"4\""));
+    assert!(parse::<ConfigFile>("threads = 4\nthreads = 5\n").unwrap_err().starts_with("line 2: "));
+    assert!(parse::<ConfigFile>("dry-run = \"yes\"\n").unwrap_err().starts_with("line 1: invalid type: string \"yes\""));
+}
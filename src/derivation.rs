@@ -0,0 +1,1059 @@
+use crate::{
+    error::HdiFinderError,
+    models::{AccountXpub, Coin, Seed},
+    slip132,
+};
+use bip39::{Language, Mnemonic};
+use bitcoin::{
+    hashes::{hex::FromHex, hmac, sha512, Hash, HashEngine},
+    network::constants::Network,
+    util::bip32::{ChildNumber, DerivationPath, ExtendedPrivKey, ExtendedPubKey, Fingerprint},
+};
+use hdpath::{AccountHDPath, Purpose, StandardHDPath};
+use secp256k1::{All, Secp256k1};
+use std::str::FromStr;
+use unicode_normalization::UnicodeNormalization;
+#[cfg(test)]
+use bitcoin::Address;
+
+/**
+ * Highest non-hardened BIP32 child index
+ */
+pub const MAX_INDEX: u32 = 0x7FFFFFFF;
+
+/**
+ * Characters invisible once pasted, which notes apps and web pages
+ * sometimes slip between the letters of a word
+ */
+pub const ZERO_WIDTH_CHARS: [char; 5] = ['\u{200B}', '\u{200C}', '\u{200D}', '\u{2060}', '\u{FEFF}'];
+
+/**
+ * Brings a mnemonic phrase as typed or pasted by a user to the form BIP39 expects:
+ * NFKD decomposed, without zero-width characters, lowercase and single-spaced
+ */
+pub fn normalize_mnemonic(mnemonic: &str) -> String {
+    let decomposed: String = mnemonic
+        .nfkd()
+        .filter(|c| !ZERO_WIDTH_CHARS.contains(c))
+        .collect::<String>()
+        .to_ascii_lowercase();
+    decomposed.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+#[test]
+fn test_normalize_mnemonic(){
+    let clean = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let seed = get_mnemonic(clean, None).unwrap().to_seed("");
+    assert_eq!(normalize_mnemonic(clean), clean);
+
+    let messy = [
+        format!("{}\n", clean),
+        format!("  {}  ", clean.replacen("erupt quit", "Erupt   quit", 1)),
+        clean.replace(' ', "\t"),
+        clean.to_uppercase(),
+        clean.replacen("sphere", "sph\u{200B}ere", 1).replacen("verb", "verb\u{FEFF}", 1),
+    ];
+    for phrase in &messy {
+        assert_eq!(normalize_mnemonic(phrase), clean);
+        assert_eq!(get_mnemonic(phrase, None).unwrap().to_seed(""), seed);
+    }
+
+    // composed characters are decomposed, as the wordlists of other languages require
+    assert_eq!(normalize_mnemonic("\u{00E9}l\u{00E8}ve"), "e\u{0301}le\u{0300}ve");
+}
+
+/**
+ * Number of words of the standard BIP39 mnemonics, from 128 to 256 bits of entropy
+ */
+pub const WORD_COUNTS: [usize; 5] = [12, 15, 18, 21, 24];
+
+/**
+ * BIP39 wordlists, by the name `--language` takes and the name shown to users
+ */
+pub const LANGUAGES: [(&str, &str, Language); 9] = [
+    ("english", "English", Language::English),
+    ("chinese-simplified", "Chinese (simplified)", Language::SimplifiedChinese),
+    ("chinese-traditional", "Chinese (traditional)", Language::TraditionalChinese),
+    ("czech", "Czech", Language::Czech),
+    ("french", "French", Language::French),
+    ("italian", "Italian", Language::Italian),
+    ("japanese", "Japanese", Language::Japanese),
+    ("korean", "Korean", Language::Korean),
+    ("spanish", "Spanish", Language::Spanish),
+];
+
+/**
+ * Retrieves the name of a wordlist as shown to users
+ */
+pub fn language_name(language: Language) -> &'static str {
+    LANGUAGES
+        .iter()
+        .find(|(_, _, candidate)| *candidate == language)
+        .map_or("unknown", |(_, name, _)| name)
+}
+
+fn in_wordlist(language: Language, word: &str) -> bool {
+    language.words_by_prefix(word).contains(&word)
+}
+
+/**
+ * Lists the wordlists a phrase may be written in: the ones holding every word or,
+ * when none does, the one holding the most words so that errors refer to it
+ */
+fn candidate_languages(words: &[&str]) -> Vec<Language> {
+    let known_words = |language: Language| words.iter().filter(|word| self::in_wordlist(language, word)).count();
+    let complete: Vec<Language> = Language::all()
+        .iter()
+        .copied()
+        .filter(|language| known_words(*language) == words.len())
+        .collect();
+    if !complete.is_empty() {
+        return complete;
+    }
+    // on a tie the first language wins, English being listed first
+    Language::all()
+        .iter()
+        .rev()
+        .copied()
+        .max_by_key(|language| known_words(*language))
+        .into_iter()
+        .collect()
+}
+
+/**
+ * Builds a mnemonic object based on a mnemonic phrase, normalized first.
+ * Without a language, the wordlist is detected from the words, the checksum
+ * settling the phrases whose words belong to several wordlists.
+ */
+pub fn get_mnemonic(mnemonic: &str, language: Option<Language>) -> Result<Mnemonic, HdiFinderError> {
+    let mnemonic = self::normalize_mnemonic(mnemonic);
+    let words: Vec<&str> = mnemonic.split_whitespace().collect();
+    let languages = match language {
+        Some(language) => vec![language],
+        None => self::candidate_languages(&words),
+    };
+
+    let mut parsed: Vec<Mnemonic> = Vec::new();
+    let mut errors: Vec<bip39::Error> = Vec::new();
+    for language in &languages {
+        match self::parse_words(*language, &words) {
+            Ok(candidate) => parsed.push(candidate),
+            Err(error) => errors.push(error),
+        }
+    }
+    match (parsed.pop(), errors.into_iter().next()) {
+        (Some(candidate), _) if parsed.is_empty() => Ok(candidate),
+        (Some(_), _) => match Mnemonic::language_of(&mnemonic) {
+            Ok(language) => self::parse_words(language, &words).map_err(|error| self::mnemonic_error(&mnemonic, language, error)),
+            Err(error) => Err(self::mnemonic_error(&mnemonic, languages[0], error)),
+        },
+        (None, Some(error)) => Err(self::mnemonic_error(&mnemonic, languages[0], error)),
+        (None, None) => Err(self::mnemonic_error(&mnemonic, Language::English, bip39::Error::BadWordCount(words.len()))),
+    }
+}
+
+/**
+ * Builds a mnemonic back from its entropy given as hex, as some wallet
+ * backups hold it in place of the words. English is used without a language.
+ */
+pub fn mnemonic_from_entropy(hex: &str, language: Option<Language>) -> Result<Mnemonic, HdiFinderError> {
+    let hex = hex.trim();
+    let language = language.unwrap_or(Language::English);
+    let entropy = Vec::<u8>::from_hex(hex)
+        .map_err(|_| HdiFinderError::InvalidArgument(format!("Invalid entropy: {} isn't a hexadecimal string", hex)))?;
+    Mnemonic::from_entropy_in(language, &entropy).map_err(|error| self::mnemonic_error("", language, error))
+}
+
+#[test]
+fn test_mnemonic_from_entropy(){
+    let test_mnemonic_phrase: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mnemonic = mnemonic_from_entropy("4cf5fb45ef3056713d7c718148fe1ae104916044a398350ccf2e184c067077c7", None).unwrap();
+    assert_eq!(mnemonic.to_string(), test_mnemonic_phrase);
+
+    let secp = Secp256k1::new();
+    let master = get_master_key(mnemonic.to_seed(""), Network::Bitcoin).unwrap();
+    let chain_key = chain_key(&secp, &master, &AccountHDPath::new(Purpose::Pubkey, 0, 0), 0).unwrap();
+    let public_key = get_public_key(&secp, index_key(&secp, &chain_key, 5).unwrap());
+    assert_eq!(Address::p2pkh(&public_key.public_key, Network::Bitcoin).to_string(), "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+
+    assert_eq!(
+        mnemonic_from_entropy("4cf5fb45ef3056713d7c718148fe1ae104916044a398350ccf2e184c067077", None).unwrap_err().to_string(),
+        "Invalid mnemonic: the entropy is 248 bits long, a mnemonic encodes 128 to 256 bits in steps of 32"
+    );
+    assert!(matches!(mnemonic_from_entropy("entropy", None), Err(HdiFinderError::InvalidArgument(_))));
+}
+
+/**
+ * Purpose and application of the BIP85 child mnemonics,
+ * m/83696968'/39'/language'/words'/index'
+ */
+const BIP85_PURPOSE: u32 = 83696968;
+const BIP85_BIP39_APPLICATION: u32 = 39;
+
+/**
+ * Derives the BIP85 child mnemonic of a master key at a child index: the
+ * entropy is the HMAC-SHA512 of the private key derived on the BIP85 path,
+ * truncated to the length of the mnemonic. Only English mnemonics are built.
+ */
+pub fn bip85_mnemonic(secp: &Secp256k1<All>, master: &ExtendedPrivKey, words: usize, index: u32) -> Result<Mnemonic, HdiFinderError> {
+    let path = DerivationPath::from(
+        [BIP85_PURPOSE, BIP85_BIP39_APPLICATION, 0, words as u32, index]
+            .iter()
+            .map(|index| ChildNumber::Hardened { index: *index })
+            .collect::<Vec<ChildNumber>>(),
+    );
+    let key = master.derive_priv(secp, &path).map_err(|source| HdiFinderError::Derivation {
+        target: path.to_string(),
+        source,
+    })?;
+    let mut engine = hmac::HmacEngine::<sha512::Hash>::new(b"bip-entropy-from-k");
+    engine.input(&key.private_key.key[..]);
+    let entropy = hmac::Hmac::<sha512::Hash>::from_engine(engine).into_inner();
+    Mnemonic::from_entropy_in(Language::English, &entropy[..words * 4 / 3])
+        .map_err(|error| self::mnemonic_error("", Language::English, error))
+}
+
+#[test]
+fn test_bip85_mnemonic(){
+    // BIP85 reference vectors
+    let master = ExtendedPrivKey::from_str("xprv9s21ZrQH143K2LBWUUQRFXhucrQqBpKdRRxNVq2zBqsx8HVqFk2uYo8kmbaLLHRdqtQpUm98uKfu3vca1LqdGhUtyoFnCNkfmXRyPXLjbKb").unwrap();
+    let secp = Secp256k1::new();
+    assert_eq!(
+        bip85_mnemonic(&secp, &master, 12, 0).unwrap().to_string(),
+        "girl mad pet galaxy egg matter matrix prison refuse sense ordinary nose"
+    );
+    assert_eq!(
+        bip85_mnemonic(&secp, &master, 18, 0).unwrap().to_string(),
+        "near account window bike charge season chef number sketch tomorrow excuse sniff circle vital hockey outdoor supply token"
+    );
+    assert_eq!(
+        bip85_mnemonic(&secp, &master, 24, 0).unwrap().to_string(),
+        "puppy ocean match cereal symbol another shed magic wrap hammer bulb intact gadget divorce twin tonight reason outdoor destroy simple truth cigar social volcano"
+    );
+}
+
+/**
+ * Decodes the words of a mnemonic into its entropy and checks the checksum
+ * by encoding the entropy back. Unlike `Mnemonic::parse_normalized`, which
+ * only takes multiples of 6 words, every standard word count is accepted.
+ */
+fn parse_words(language: Language, words: &[&str]) -> Result<Mnemonic, bip39::Error> {
+    if !WORD_COUNTS.contains(&words.len()) {
+        return Err(bip39::Error::BadWordCount(words.len()));
+    }
+    let bits = self::word_bits(language, words)?;
+
+    // Every 3 words hold 32 bits of entropy and 1 bit of checksum
+    let mnemonic = Mnemonic::from_entropy_in(language, &self::to_bytes(&bits[..words.len() / 3 * 32]))?;
+    if !mnemonic.word_iter().eq(words.iter().copied()) {
+        return Err(bip39::Error::InvalidChecksum);
+    }
+    Ok(mnemonic)
+}
+
+/**
+ * Concatenates the 11 bits wordlist indexes of the words
+ */
+fn word_bits(language: Language, words: &[&str]) -> Result<Vec<bool>, bip39::Error> {
+    let wordlist = language.words_by_prefix("");
+    let mut bits: Vec<bool> = Vec::with_capacity(words.len() * 11);
+    for (position, word) in words.iter().enumerate() {
+        let index = wordlist
+            .iter()
+            .position(|candidate| candidate == word)
+            .ok_or(bip39::Error::UnknownWord(position))?;
+        bits.extend((0..11).rev().map(|bit| index >> bit & 1 == 1));
+    }
+    Ok(bits)
+}
+
+fn to_bytes(bits: &[bool]) -> Vec<u8> {
+    bits.chunks(8)
+        .map(|byte| byte.iter().fold(0, |value, bit| value << 1 | *bit as u8))
+        .collect()
+}
+
+#[test]
+fn test_get_mnemonic_languages(){
+    // BIP39 Japanese reference vector, words separated by ideographic spaces
+    let phrase = "あいこくしん\u{3000}あいこくしん\u{3000}あいこくしん\u{3000}あいこくしん\u{3000}あいこくしん\u{3000}あいこくしん\u{3000}あいこくしん\u{3000}あいこくしん\u{3000}あいこくしん\u{3000}あいこくしん\u{3000}あいこくしん\u{3000}あおぞら";
+    let mnemonic = get_mnemonic(phrase, None).unwrap();
+    assert_eq!(mnemonic.language(), Language::Japanese);
+    let master = get_master_key(mnemonic.to_seed("㍍ガバヴァぱばぐゞちぢ十人十色"), Network::Bitcoin).unwrap();
+    assert_eq!(
+        master.to_string(),
+        "xprv9s21ZrQH143K258jAiWPAM6JYT9hLA91MV3AZUKfxmLZJCjCHeSjBvMbDy8C1mJ2FL5ytExyS97FAe6pQ6SD5Jt9SwHaLorA8i5Eojokfo1"
+    );
+    let secp = Secp256k1::new();
+    let account = AccountHDPath::new(Purpose::Witness, 0, 0);
+    let key = index_key(&secp, &chain_key(&secp, &master, &account, 0).unwrap(), 0).unwrap();
+    let address = crate::address::build_address(&secp, get_public_key(&secp, key), &Purpose::Witness, Network::Bitcoin).unwrap();
+    assert_eq!(address.to_string(), "bc1q4arcqm0tra7q8uuyxs769yz9ts2q3fzjevr8k0");
+
+    let french = "abaisser abaisser abaisser abaisser abaisser abaisser abaisser abaisser abaisser abaisser abaisser abeille";
+    assert_eq!(get_mnemonic(french, None).unwrap().language(), Language::French);
+    assert_eq!(get_mnemonic(french, Some(Language::French)).unwrap().language(), Language::French);
+    let error = get_mnemonic(french, Some(Language::English)).unwrap_err();
+    assert_eq!(error.to_string(), "Invalid mnemonic: word 1 'abaisser' is not in the BIP39 English wordlist");
+
+    // errors refer to the wordlist holding most of the words
+    let error = get_mnemonic(&french.replacen("abeille", "abeile", 1), None).unwrap_err();
+    assert_eq!(error.to_string(), "Invalid mnemonic: word 12 'abeile' is not in the BIP39 French wordlist");
+
+    // both Chinese wordlists start with the same characters
+    let chinese = "的 的 的 的 的 的 的 的 的 的 的 在";
+    let error = get_mnemonic(chinese, None).unwrap_err();
+    assert!(error.to_string().ends_with("valid in several wordlists (Chinese (simplified), Chinese (traditional)). Select one with --language"));
+    assert_eq!(get_mnemonic(chinese, Some(Language::TraditionalChinese)).unwrap().language(), Language::TraditionalChinese);
+}
+
+#[test]
+fn test_get_mnemonic_word_counts(){
+    // BIP39 test vector
+    let mnemonic = get_mnemonic("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about", None).unwrap();
+    assert_eq!(mnemonic.word_count(), 12);
+    let master = get_master_key(mnemonic.to_seed(""), Network::Bitcoin).unwrap();
+    let secp = Secp256k1::new();
+    let expected = [
+        (Purpose::Pubkey, "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA"),
+        (Purpose::ScriptHash, "37VucYSaXLCAsxYyAPfbSi9eh4iEcbShgf"),
+        (Purpose::Witness, "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"),
+    ];
+    for (purpose, address) in expected.iter() {
+        let account = AccountHDPath::new(purpose.clone(), 0, 0);
+        let key = index_key(&secp, &chain_key(&secp, &master, &account, 0).unwrap(), 0).unwrap();
+        let public_key = get_public_key(&secp, key);
+        let computed = crate::address::build_address(&secp, public_key, purpose, Network::Bitcoin).unwrap();
+        assert_eq!(computed.to_string(), *address);
+    }
+
+    // 15 and 21 words round trip through their entropy
+    for bytes in [20, 28] {
+        let phrase = Mnemonic::from_entropy(&vec![0x5a; bytes]).unwrap().to_string();
+        let mnemonic = get_mnemonic(&phrase, None).unwrap();
+        assert_eq!(mnemonic.word_count(), bytes * 3 / 4);
+        assert_eq!(mnemonic.to_entropy(), vec![0x5a; bytes]);
+    }
+
+    assert!(matches!(
+        get_mnemonic("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon", None),
+        Err(HdiFinderError::Mnemonic { error: bip39::Error::InvalidChecksum, .. })
+    ));
+}
+
+/**
+ * Lists the mnemonics completing the phrase with a valid checksum, for when
+ * the last word is mistyped. The last word holds the end of the entropy followed
+ * by the checksum, so every value of its entropy bits gives one candidate
+ * whatever the last word read: 128 of them for 12 words, 8 for 24 words.
+ * The candidates come in the wordlist order of their last word.
+ */
+pub fn last_word_candidates(mnemonic: &str, language: Option<Language>) -> Vec<Mnemonic> {
+    let mnemonic = self::normalize_mnemonic(mnemonic);
+    let words: Vec<&str> = mnemonic.split_whitespace().collect();
+    if !WORD_COUNTS.contains(&words.len()) {
+        return Vec::new();
+    }
+    let first_words = &words[..words.len() - 1];
+    // the last word, even mistyped, may tell apart wordlists sharing the first words
+    let languages = match language {
+        Some(language) => vec![language],
+        None => self::candidate_languages(&words),
+    };
+    let entropy_bits = 11 - words.len() / 3;
+
+    let mut candidates: Vec<Mnemonic> = Vec::new();
+    for language in languages {
+        let mut bits = match self::word_bits(language, first_words) {
+            Ok(bits) => bits,
+            Err(_) => continue,
+        };
+        for value in 0..1usize << entropy_bits {
+            bits.truncate(first_words.len() * 11);
+            bits.extend((0..entropy_bits).rev().map(|bit| value >> bit & 1 == 1));
+            if let Ok(candidate) = Mnemonic::from_entropy_in(language, &self::to_bytes(&bits)) {
+                candidates.push(candidate);
+            }
+        }
+    }
+    candidates
+}
+
+#[test]
+fn test_last_word_candidates(){
+    let phrase = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant";
+    let candidates = last_word_candidates(&format!("{} zoo", phrase), None);
+    assert_eq!(candidates.len(), 8);
+    assert!(candidates.iter().any(|candidate| candidate.word_iter().last() == Some("verb")));
+    for candidate in &candidates {
+        assert!(candidate.to_string().starts_with(phrase));
+        assert!(get_mnemonic(&candidate.to_string(), None).is_ok());
+    }
+
+    let candidates = last_word_candidates("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon zoo", None);
+    assert_eq!(candidates.len(), 128);
+    assert_eq!(candidates[0].word_iter().last(), Some("about"));
+
+    // 15 words hold 5 bits of checksum
+    let phrase = Mnemonic::from_entropy(&[0x5a; 20]).unwrap().to_string();
+    assert_eq!(last_word_candidates(&phrase, Some(Language::English)).len(), 64);
+    assert!(last_word_candidates("erupt quit sphere", None).is_empty());
+}
+
+/**
+ * Lists the mnemonics obtained by swapping two words of the phrase, along with
+ * the positions swapped, keeping those with a valid checksum. Only adjacent words
+ * are swapped unless `all_pairs` is set, which tries n × (n - 1) / 2 swaps.
+ */
+pub fn swap_candidates(mnemonic: &str, language: Option<Language>, all_pairs: bool) -> Vec<(usize, usize, Mnemonic)> {
+    let mnemonic = self::normalize_mnemonic(mnemonic);
+    let words: Vec<&str> = mnemonic.split_whitespace().collect();
+    let languages = match language {
+        Some(language) => vec![language],
+        None => self::candidate_languages(&words),
+    };
+
+    let mut candidates: Vec<(usize, usize, Mnemonic)> = Vec::new();
+    for first in 0..words.len() {
+        let last = if all_pairs { words.len() } else { words.len().min(first + 2) };
+        for second in first + 1..last {
+            if words[first] == words[second] {
+                continue;
+            }
+            let mut swapped = words.clone();
+            swapped.swap(first, second);
+            for language in &languages {
+                if let Ok(candidate) = self::parse_words(*language, &swapped) {
+                    candidates.push((first, second, candidate));
+                }
+            }
+        }
+    }
+    candidates
+}
+
+#[test]
+fn test_swap_candidates(){
+    let phrase = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let words: Vec<&str> = phrase.split(' ').collect();
+
+    // "grid" and "slide" written in the wrong order
+    let mut swapped = words.clone();
+    swapped.swap(17, 18);
+    let candidates = swap_candidates(&swapped.join(" "), None, false);
+    assert!(candidates.iter().any(|(first, second, candidate)| (*first, *second) == (17, 18) && candidate.to_string() == phrase));
+    assert!(candidates.iter().all(|(first, second, _)| *second == first + 1));
+
+    // "quit" and "tenant" are too far apart for adjacent swaps
+    let mut swapped = words.clone();
+    swapped.swap(1, 22);
+    let swapped = swapped.join(" ");
+    assert!(!swap_candidates(&swapped, None, false).iter().any(|(_, _, candidate)| candidate.to_string() == phrase));
+    let candidates = swap_candidates(&swapped, None, true);
+    assert!(candidates.iter().any(|(first, second, candidate)| (*first, *second) == (1, 22) && candidate.to_string() == phrase));
+}
+
+/**
+ * Explains why a mnemonic phrase has been rejected, in terms of what the user
+ * should look at in the phrase rather than of the BIP39 encoding
+ */
+pub fn mnemonic_error(mnemonic: &str, language: Language, error: bip39::Error) -> HdiFinderError {
+    let reason = match &error {
+        bip39::Error::UnknownWord(position) => format!(
+            "word {} '{}' is not in the BIP39 {} wordlist",
+            position + 1,
+            mnemonic.split_whitespace().nth(*position).unwrap_or_default(),
+            self::language_name(language)
+        ),
+        bip39::Error::BadWordCount(1) => "a single word has been received, a mnemonic has 12, 15, 18, 21 or 24 words".to_string(),
+        bip39::Error::BadWordCount(count) => format!("the phrase has {} words, a mnemonic has 12, 15, 18, 21 or 24 words", count),
+        bip39::Error::InvalidChecksum => {
+            "the checksum doesn't match. Every word is in the wordlist, so the last word, which carries the checksum, may be wrong".to_string()
+        }
+        bip39::Error::BadEntropyBitCount(bits) => format!(
+            "the entropy is {} bits long, a mnemonic encodes 128 to 256 bits in steps of 32",
+            bits
+        ),
+        bip39::Error::AmbiguousLanguages(languages) => format!(
+            "the phrase is valid in several wordlists ({}). Select one with --language",
+            languages.iter().map(self::language_name).collect::<Vec<&str>>().join(", ")
+        ),
+    };
+    HdiFinderError::Mnemonic { reason, error }
+}
+
+#[test]
+fn test_mnemonic_error(){
+    // "rabbit" misspelled
+    let error = get_mnemonic("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabit barely indoor crush grid slide correct scatter deal tenant verb", None).unwrap_err();
+    assert_eq!(error.to_string(), "Invalid mnemonic: word 14 'rabit' is not in the BIP39 English wordlist");
+
+    let error = get_mnemonic("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower", None).unwrap_err();
+    assert_eq!(error.to_string(), "Invalid mnemonic: the phrase has 13 words, a mnemonic has 12, 15, 18, 21 or 24 words");
+    assert!(get_mnemonic("erupt", None).unwrap_err().to_string().contains("a single word has been received"));
+
+    // last word replaced
+    let error = get_mnemonic("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant zoo", None).unwrap_err();
+    assert!(matches!(error, HdiFinderError::Mnemonic { error: bip39::Error::InvalidChecksum, .. }));
+    assert!(error.to_string().contains("the last word, which carries the checksum, may be wrong"));
+
+    let error = Mnemonic::from_entropy(&[0; 15]).unwrap_err();
+    assert_eq!(
+        mnemonic_error("", Language::English, error).to_string(),
+        "Invalid mnemonic: the entropy is 120 bits long, a mnemonic encodes 128 to 256 bits in steps of 32"
+    );
+}
+
+/**
+ * Parses a seed given as hex, as some wallets export it in place of the words
+ */
+pub fn parse_seed(hex: &str) -> Result<Seed, HdiFinderError> {
+    let hex = hex.trim();
+    let bytes = Vec::<u8>::from_hex(hex)
+        .map_err(|_| HdiFinderError::InvalidArgument(format!("Invalid seed: {} isn't a hexadecimal string", hex)))?;
+    Seed::from_slice(&bytes).ok_or_else(|| {
+        HdiFinderError::InvalidArgument(format!(
+            "Invalid seed: it is {} bytes long, a seed has {} to {} bytes ({} to {} hexadecimal characters)",
+            bytes.len(),
+            Seed::MIN_LEN,
+            Seed::MAX_LEN,
+            Seed::MIN_LEN * 2,
+            Seed::MAX_LEN * 2
+        ))
+    })
+}
+
+#[test]
+fn test_parse_seed(){
+    let test_mnemonic_phrase: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let test_seed = get_mnemonic(test_mnemonic_phrase, None).unwrap().to_seed("");
+    let hex: String = test_seed.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let seed = parse_seed(&hex.to_uppercase()).unwrap();
+    assert_eq!(seed, Seed::from(test_seed));
+    assert_eq!(seed.len(), 64);
+
+    // BIP32 test vector 1
+    let seed = parse_seed("000102030405060708090a0b0c0d0e0f").unwrap();
+    assert_eq!(seed.len(), 16);
+    assert_eq!(
+        get_master_key(seed, Network::Bitcoin).unwrap().to_string(),
+        "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi"
+    );
+
+    assert_eq!(
+        parse_seed("000102030405060708090a0b0c0d0e").unwrap_err().to_string(),
+        "Invalid seed: it is 15 bytes long, a seed has 16 to 64 bytes (32 to 128 hexadecimal characters)"
+    );
+    assert_eq!(
+        parse_seed("000102030405060708090a0b0c0d0e0g").unwrap_err().to_string(),
+        "Invalid seed: 000102030405060708090a0b0c0d0e0g isn't a hexadecimal string"
+    );
+}
+
+/**
+ * Retrieves the master key of a seed
+ */
+pub fn get_master_key(seed: impl Into<Seed>, network: Network) -> Result<ExtendedPrivKey, HdiFinderError> {
+    ExtendedPrivKey::new_master(network, seed.into().as_bytes()).map_err(|source| HdiFinderError::Derivation {
+        target: "the master key".to_string(),
+        source,
+    })
+}
+
+/**
+ * Retrieves a private key derived from a seed.
+ * The scan derives from chain keys computed once instead (see `chain_key`).
+ */
+pub fn get_private_key(seed: [u8; 64], hd_path: &StandardHDPath, network: Network) -> Result<ExtendedPrivKey, HdiFinderError> {
+    let secp = Secp256k1::new();
+    // we convert HD Path to bitcoin lib format (DerivationPath)
+    self::get_master_key(seed, network)?
+        .derive_priv(&secp, &DerivationPath::from(hd_path))
+        .map_err(|source| HdiFinderError::Derivation {
+            target: hd_path.to_string(),
+            source,
+        })
+}
+
+#[test]
+fn test_get_private_key(){
+    let test_mnemonic_phrase: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let test_seed = get_mnemonic(test_mnemonic_phrase, None).unwrap().to_seed("");
+
+    let hd_path= AccountHDPath::new(Purpose::Pubkey, 0, 0).address_at(0,5).unwrap();
+    let private_key = get_private_key(test_seed, &hd_path, Network::Bitcoin).unwrap();
+
+    assert_eq!(private_key.private_key.to_string(),"L1TmQPcEkfoxHh6pJdbVASwiq18BpF3waAKf9LaannZWvLr4p2DF")
+}
+
+/**
+ * Retrieves a public key derived from a private key
+ */
+pub fn get_public_key(secp: &Secp256k1<All>, private_key: ExtendedPrivKey) -> ExtendedPubKey {
+    ExtendedPubKey::from_private(secp, &private_key)
+}
+
+#[test]
+fn test_get_public_key(){
+    let secp = Secp256k1::new();
+    let test_mnemonic_phrase: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let test_seed = get_mnemonic(test_mnemonic_phrase, None).unwrap().to_seed("");
+
+    let hd_path= AccountHDPath::new(Purpose::Pubkey, 0, 0).address_at(0,5).unwrap();
+    let private_key = get_private_key(test_seed, &hd_path, Network::Bitcoin).unwrap();
+
+    let public_key = get_public_key(&secp, private_key);
+    assert_eq!("02016653fa405f3ecedb3dc88a378dabf7cd4c1c1acf1430515e854a630254cbbe",public_key.public_key.to_string());
+
+}
+
+/**
+ * Retrieves the BIP44 coin type to be used for a network
+ */
+pub fn coin_type(network: Network) -> u32 {
+    self::coin_type_for(Coin::Bitcoin, network)
+}
+
+/**
+ * Retrieves the SLIP-44 coin type of a coin on a network, the test networks
+ * of every coin sharing 1
+ */
+pub fn coin_type_for(coin: Coin, network: Network) -> u32 {
+    match (coin, network) {
+        (Coin::Bitcoin, Network::Bitcoin) => 0,
+        (Coin::Litecoin, Network::Bitcoin) => 2,
+        (Coin::Dogecoin, Network::Bitcoin) => 3,
+        (Coin::BitcoinCash, Network::Bitcoin) => 145,
+        // Ethereum test networks derive under the mainnet coin type
+        (Coin::Ethereum, _) => 60,
+        (Coin::Defined(definition), _) => definition.coin_type,
+        _ => 1,
+    }
+}
+
+#[test]
+fn test_coin_type_for(){
+    assert_eq!(coin_type_for(Coin::Bitcoin, Network::Bitcoin), 0);
+    assert_eq!(coin_type_for(Coin::Litecoin, Network::Bitcoin), 2);
+    assert_eq!(coin_type_for(Coin::Litecoin, Network::Testnet), 1);
+    assert_eq!(coin_type_for(Coin::Dogecoin, Network::Bitcoin), 3);
+    assert_eq!(coin_type_for(Coin::BitcoinCash, Network::Bitcoin), 145);
+    assert_eq!(coin_type_for(Coin::Ethereum, Network::Testnet), 60);
+}
+
+/**
+ * Derives the chain level key (m/purpose'/coin'/account'/change) of an account.
+ * Computed once per wallet config so that scanning an index only takes
+ * a single derivation step.
+ */
+pub fn chain_key(secp: &Secp256k1<All>, master: &ExtendedPrivKey, account: &AccountHDPath, change: u32) -> Result<ExtendedPrivKey, HdiFinderError> {
+    let path = DerivationPath::from(account).child(ChildNumber::Normal { index: change });
+    master.derive_priv(secp, &path).map_err(|source| HdiFinderError::Derivation {
+        target: path.to_string(),
+        source,
+    })
+}
+
+/**
+ * Depth of the account level keys, m/purpose'/coin'/account'
+ */
+pub const ACCOUNT_DEPTH: u8 = 3;
+
+/**
+ * Checks an extended key suits the network, the version bytes only telling
+ * mainnet keys (xprv, xpub) from the others (tprv, tpub)
+ */
+pub fn check_key_network(kind: &str, key_network: Network, network: Network) -> Result<(), HdiFinderError> {
+    if (key_network == Network::Bitcoin) == (network == Network::Bitcoin) {
+        return Ok(());
+    }
+    let suffix = if kind == "private" { "prv" } else { "pub" };
+    Err(HdiFinderError::InvalidArgument(format!(
+        "The extended {} key is a {} key while the network is {}",
+        kind,
+        if key_network == Network::Bitcoin { format!("mainnet (x{})", suffix) } else { format!("testnet (t{})", suffix) },
+        network
+    )))
+}
+
+/**
+ * Parses an extended private key exported from a wallet, in place of the
+ * mnemonic. Master keys and account level keys are supported, the version
+ * bytes telling mainnet keys (xprv) from the others (tprv).
+ */
+pub fn parse_xprv(key: &str, network: Network) -> Result<ExtendedPrivKey, HdiFinderError> {
+    let key = ExtendedPrivKey::from_str(key.trim())
+        .map_err(|error| HdiFinderError::InvalidArgument(format!("Invalid extended private key: {}", error)))?;
+    self::check_key_network("private", key.network, network)?;
+    if key.depth != 0 && key.depth != ACCOUNT_DEPTH {
+        return Err(HdiFinderError::InvalidArgument(format!(
+            "The extended private key is at depth {}, only master keys (depth 0) and account level keys (depth {}) are supported",
+            key.depth, ACCOUNT_DEPTH
+        )));
+    }
+    Ok(key)
+}
+
+#[test]
+fn test_parse_xprv(){
+    let master = parse_xprv("xprv9s21ZrQH143K2zJ7ptYCbcbY6Ysr6wwtLqWMtTH1bbBSHiYs3jA4QZHkAbN6nZkTNVhGmpiDei5jgepTmeKtFAaqe4J7nTSFyKtESXAPtRT", Network::Bitcoin).unwrap();
+    assert_eq!(master, test_master_key());
+    let account = parse_xprv("xprv9yE75FA8ApWNWuTfDW2twodwMSjsWzWRMvx9pFyDUHiWm6fzkpBhB14XsKDznyTxv83q7anDLVVPbnh2mXAjswXbptQiMZRuGeCL8YWmGgM", Network::Bitcoin).unwrap();
+    assert_eq!(account.depth, ACCOUNT_DEPTH);
+    assert_eq!(child_index(account.child_number), 0);
+
+    assert_eq!(
+        parse_xprv("tprv8ZgxMBicQKsPdoXeVTPhmGDXQgJ4LTytgPRUkshU5Zfv5KHx36VovJfC5mXknw8mjwE3mvKyp4fY9WNCtrfq4DrSAhWRSpAJtRdetF8X3yp", Network::Bitcoin).unwrap_err().to_string(),
+        "The extended private key is a testnet (tprv) key while the network is bitcoin"
+    );
+    assert!(parse_xprv("tprv8ZgxMBicQKsPdoXeVTPhmGDXQgJ4LTytgPRUkshU5Zfv5KHx36VovJfC5mXknw8mjwE3mvKyp4fY9WNCtrfq4DrSAhWRSpAJtRdetF8X3yp", Network::Regtest).is_ok());
+    let secp = Secp256k1::new();
+    let chain = chain_key(&secp, &master, &AccountHDPath::new(Purpose::Pubkey, 0, 0), 0).unwrap();
+    assert_eq!(
+        parse_xprv(&chain.to_string(), Network::Bitcoin).unwrap_err().to_string(),
+        "The extended private key is at depth 4, only master keys (depth 0) and account level keys (depth 3) are supported"
+    );
+}
+
+/**
+ * Retrieves the index of a key among its siblings, i.e. the account of an
+ * account level key or the chain of a chain level key
+ */
+pub fn child_index(child_number: ChildNumber) -> u32 {
+    match child_number {
+        ChildNumber::Normal { index } | ChildNumber::Hardened { index } => index,
+    }
+}
+
+/**
+ * Depth of the chain level keys, m/purpose'/coin'/account'/change
+ */
+pub const CHAIN_DEPTH: u8 = 4;
+
+/**
+ * Parses an extended public key for a watch-only scan. The levels down to
+ * the account being hardened, only account and chain level keys can derive
+ * the addresses.
+ */
+pub fn parse_xpub(key: &str, network: Network) -> Result<ExtendedPubKey, HdiFinderError> {
+    let key = ExtendedPubKey::from_str(key.trim())
+        .map_err(|error| HdiFinderError::InvalidArgument(format!("Invalid extended public key: {}", error)))?;
+    self::check_key_network("public", key.network, network)?;
+    if key.depth < ACCOUNT_DEPTH {
+        return Err(HdiFinderError::InvalidArgument(format!(
+            "The extended public key is at depth {}: the purpose, coin and account levels are hardened and can't be derived from a public key. \
+             Give an account level key (depth {}) or a chain level key (depth {})",
+            key.depth, ACCOUNT_DEPTH, CHAIN_DEPTH
+        )));
+    }
+    if key.depth > CHAIN_DEPTH {
+        return Err(HdiFinderError::InvalidArgument(format!(
+            "The extended public key is at depth {}, below the chain level (depth {}) the addresses are derived from",
+            key.depth, CHAIN_DEPTH
+        )));
+    }
+    if key.depth == CHAIN_DEPTH && matches!(key.child_number, ChildNumber::Hardened { .. }) {
+        return Err(HdiFinderError::InvalidArgument(
+            "The extended public key is a hardened chain key, the chains of a wallet are not hardened".to_string(),
+        ));
+    }
+    Ok(key)
+}
+
+#[test]
+fn test_parse_xpub(){
+    let secp = Secp256k1::new();
+    let account_key = test_master_key().derive_priv(&secp, &DerivationPath::from(&AccountHDPath::new(Purpose::Pubkey, 0, 0))).unwrap();
+    let account = ExtendedPubKey::from_private(&secp, &account_key);
+    assert_eq!(parse_xpub(&account.to_string(), Network::Bitcoin).unwrap(), account);
+    let chain = chain_pubkey(&secp, &chain_key(&secp, &test_master_key(), &AccountHDPath::new(Purpose::Pubkey, 0, 0), 1).unwrap());
+    assert_eq!(parse_xpub(&chain.to_string(), Network::Bitcoin).unwrap(), chain);
+
+    let master = ExtendedPubKey::from_private(&secp, &test_master_key());
+    assert!(parse_xpub(&master.to_string(), Network::Bitcoin).unwrap_err().to_string().starts_with(
+        "The extended public key is at depth 0: the purpose, coin and account levels are hardened and can't be derived from a public key."
+    ));
+    assert_eq!(
+        parse_xpub(&account.to_string(), Network::Testnet).unwrap_err().to_string(),
+        "The extended public key is a mainnet (xpub) key while the network is testnet"
+    );
+}
+
+/**
+ * Derives the public chain level key of a watch-only wallet: a child of an
+ * account level key, or a chain level key itself
+ */
+pub fn xpub_chain_key(secp: &Secp256k1<All>, key: &ExtendedPubKey, change: u32) -> Result<ExtendedPubKey, HdiFinderError> {
+    if key.depth == CHAIN_DEPTH {
+        return Ok(*key);
+    }
+    key.ckd_pub(secp, ChildNumber::Normal { index: change })
+        .map_err(|source| HdiFinderError::Derivation {
+            target: format!("the chain {} of the account key", change),
+            source,
+        })
+}
+
+#[test]
+fn test_xpub_chain_key(){
+    let secp = Secp256k1::new();
+    let master = test_master_key();
+    let account = AccountHDPath::new(Purpose::Pubkey, 0, 0);
+    let account_key = ExtendedPubKey::from_private(&secp, &master.derive_priv(&secp, &DerivationPath::from(&account)).unwrap());
+    let expected = chain_pubkey(&secp, &chain_key(&secp, &master, &account, 1).unwrap());
+    assert_eq!(xpub_chain_key(&secp, &account_key, 1).unwrap(), expected);
+    assert_eq!(xpub_chain_key(&secp, &expected, 1).unwrap(), expected);
+}
+
+/**
+ * Derives the chain level key of an account from the key the wallet is
+ * derived from: the master key, or an account level key holding the
+ * purpose, coin and account levels already
+ */
+pub fn root_chain_key(secp: &Secp256k1<All>, root: &ExtendedPrivKey, account: &AccountHDPath, change: u32) -> Result<ExtendedPrivKey, HdiFinderError> {
+    if root.depth != ACCOUNT_DEPTH {
+        return self::chain_key(secp, root, account, change);
+    }
+    root.ckd_priv(secp, ChildNumber::Normal { index: change })
+        .map_err(|source| HdiFinderError::Derivation {
+            target: format!("the chain {} of the account key", change),
+            source,
+        })
+}
+
+#[test]
+fn test_root_chain_key(){
+    let secp = Secp256k1::new();
+    let master = test_master_key();
+    let account = AccountHDPath::new(Purpose::Pubkey, 0, 0);
+    let account_key = master.derive_priv(&secp, &DerivationPath::from(&account)).unwrap();
+    assert_eq!(
+        root_chain_key(&secp, &account_key, &account, 1).unwrap(),
+        chain_key(&secp, &master, &account, 1).unwrap()
+    );
+    assert_eq!(
+        root_chain_key(&secp, &master, &account, 1).unwrap(),
+        chain_key(&secp, &master, &account, 1).unwrap()
+    );
+}
+
+/**
+ * Derives the account key at `account_path` from a root key, along with the
+ * fingerprint of the master key when the root is one. An account level root
+ * is the account key itself, the fingerprint of its master being unknown.
+ */
+pub fn account_key(
+    secp: &Secp256k1<All>,
+    root: &ExtendedPrivKey,
+    account_path: &DerivationPath,
+) -> Result<(ExtendedPrivKey, Option<Fingerprint>), HdiFinderError> {
+    if root.depth == ACCOUNT_DEPTH {
+        return Ok((*root, None));
+    }
+    let account_key = root.derive_priv(secp, account_path).map_err(|source| HdiFinderError::Derivation {
+        target: account_path.to_string(),
+        source,
+    })?;
+    Ok((account_key, self::master_fingerprint(secp, root)))
+}
+
+/**
+ * Gives the fingerprint of the master key a root key is, None for an
+ * account level root, the master of which is unknown
+ */
+pub fn master_fingerprint(secp: &Secp256k1<All>, root: &ExtendedPrivKey) -> Option<Fingerprint> {
+    if root.depth == ACCOUNT_DEPTH {
+        None
+    } else {
+        Some(root.fingerprint(secp))
+    }
+}
+
+/**
+ * Formats the origin of a key derived at `path` from the master key with
+ * the given fingerprint, e.g [d34db33f/84'/0'/0'/0/5]
+ */
+pub fn key_origin(fingerprint: &Fingerprint, path: &DerivationPath) -> String {
+    format!("[{}{}]", fingerprint, path.to_string().trim_start_matches('m'))
+}
+
+#[test]
+fn test_key_origin(){
+    let secp = Secp256k1::new();
+    let seed = get_mnemonic("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb", None).unwrap().to_seed("");
+    let master = get_master_key(seed, Network::Bitcoin).unwrap();
+    let fingerprint = master_fingerprint(&secp, &master).unwrap();
+    assert_eq!(fingerprint.to_string(), "67b4602d");
+    let path = DerivationPath::from_str("m/84'/0'/0'/0/5").unwrap();
+    assert_eq!(key_origin(&fingerprint, &path), "[67b4602d/84'/0'/0'/0/5]");
+
+    let account = DerivationPath::from(&AccountHDPath::new(Purpose::Witness, 0, 0));
+    let (account_key, _) = account_key(&secp, &master, &account).unwrap();
+    assert_eq!(master_fingerprint(&secp, &account_key), None);
+}
+
+/**
+ * Derives the extended public key of an account, encoded for the address
+ * type of its purpose as well
+ */
+pub fn account_xpub(
+    secp: &Secp256k1<All>,
+    root: &ExtendedPrivKey,
+    account_path: &DerivationPath,
+    purpose: &Purpose,
+) -> Result<AccountXpub, HdiFinderError> {
+    let (account_key, fingerprint) = self::account_key(secp, root, account_path)?;
+    let xpub = ExtendedPubKey::from_private(secp, &account_key);
+    Ok(AccountXpub {
+        path: account_path.to_string(),
+        fingerprint: fingerprint.map(|fingerprint| fingerprint.to_string()),
+        xpub: xpub.to_string(),
+        slip132: slip132::from_bip32(&xpub, purpose),
+    })
+}
+
+#[test]
+fn test_account_xpub(){
+    let secp = Secp256k1::new();
+    // The BIP84 test vector
+    let seed = get_mnemonic("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about", None).unwrap().to_seed("");
+    let master = get_master_key(seed, Network::Bitcoin).unwrap();
+    let path = DerivationPath::from(&AccountHDPath::new(Purpose::Witness, 0, 0));
+    let account = account_xpub(&secp, &master, &path, &Purpose::Witness).unwrap();
+    assert_eq!(account.path, "m/84'/0'/0'");
+    assert_eq!(account.fingerprint.as_deref(), Some("73c5da0a"));
+    assert_eq!(
+        account.slip132.as_deref(),
+        Some("zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs")
+    );
+    assert!(account.xpub.starts_with("xpub"));
+
+    let (account_key, _) = account_key(&secp, &master, &path).unwrap();
+    let from_account = account_xpub(&secp, &account_key, &path, &Purpose::Witness).unwrap();
+    assert_eq!(from_account.fingerprint, None);
+    assert_eq!(from_account.xpub, account.xpub);
+}
+
+/**
+ * Derives the key of an address index from its chain level key
+ */
+pub fn index_key(secp: &Secp256k1<All>, chain_key: &ExtendedPrivKey, index: u32) -> Result<ExtendedPrivKey, HdiFinderError> {
+    ChildNumber::from_normal_idx(index)
+        .and_then(|child| chain_key.ckd_priv(secp, child))
+        .map_err(|source| HdiFinderError::Derivation {
+            target: format!("index {}", index),
+            source,
+        })
+}
+
+/**
+ * Derives the key of a hardened address index, e.g m/44'/0'/0'/0/5', as a
+ * few non-standard wallets do. Only possible from a private chain key.
+ */
+pub fn hardened_index_key(secp: &Secp256k1<All>, chain_key: &ExtendedPrivKey, index: u32) -> Result<ExtendedPrivKey, HdiFinderError> {
+    ChildNumber::from_hardened_idx(index)
+        .and_then(|child| chain_key.ckd_priv(secp, child))
+        .map_err(|source| HdiFinderError::Derivation {
+            target: format!("index {}'", index),
+            source,
+        })
+}
+
+/**
+ * Retrieves the public counterpart of a chain level key
+ */
+pub fn chain_pubkey(secp: &Secp256k1<All>, chain_key: &ExtendedPrivKey) -> ExtendedPubKey {
+    ExtendedPubKey::from_private(secp, chain_key)
+}
+
+/**
+ * Derives the public key of an address index from its public chain level key.
+ * Only possible because address indexes are never hardened.
+ */
+pub fn index_pubkey(secp: &Secp256k1<All>, chain_pubkey: &ExtendedPubKey, index: u32) -> Result<ExtendedPubKey, HdiFinderError> {
+    ChildNumber::from_normal_idx(index)
+        .and_then(|child| chain_pubkey.ckd_pub(secp, child))
+        .map_err(|source| HdiFinderError::Derivation {
+            target: format!("index {}", index),
+            source,
+        })
+}
+
+#[cfg(test)]
+fn test_master_key() -> ExtendedPrivKey {
+    let test_mnemonic_phrase: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let test_seed = get_mnemonic(test_mnemonic_phrase, None).unwrap().to_seed("");
+    get_master_key(test_seed, Network::Bitcoin).unwrap()
+}
+
+#[test]
+fn test_index_key(){
+    let secp = Secp256k1::new();
+    let account = AccountHDPath::new(Purpose::Pubkey, 0, 0);
+    let chain = chain_key(&secp, &test_master_key(), &account, 0).unwrap();
+    let private_key = index_key(&secp, &chain, 5).unwrap();
+
+    assert_eq!(private_key.private_key.to_string(), "L1TmQPcEkfoxHh6pJdbVASwiq18BpF3waAKf9LaannZWvLr4p2DF");
+    let public_key = private_key.private_key.public_key(&secp);
+    assert_eq!(Address::p2pkh(&public_key, Network::Bitcoin).to_string(), "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+}
+
+#[test]
+fn test_hardened_index_key(){
+    let secp = Secp256k1::new();
+    let account = AccountHDPath::new(Purpose::Pubkey, 0, 0);
+    let master = test_master_key();
+    let chain = chain_key(&secp, &master, &account, 0).unwrap();
+    let key = hardened_index_key(&secp, &chain, 5).unwrap();
+    assert_eq!(key, master.derive_priv(&secp, &DerivationPath::from_str("m/44'/0'/0'/0/5'").unwrap()).unwrap());
+
+    // Another key than the one of the normal index 5, 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK
+    let public_key = key.private_key.public_key(&secp);
+    assert_eq!(Address::p2pkh(&public_key, Network::Bitcoin).to_string(), "1F9CpR8vnCnN7om7t1VGtzNevr9WDNpeRi");
+    assert_ne!(key, index_key(&secp, &chain, 5).unwrap());
+}
+
+#[test]
+fn test_index_key_matches_full_derivation(){
+    let secp = Secp256k1::new();
+    let master = test_master_key();
+    for (purpose, change) in [(Purpose::Pubkey, 1), (Purpose::Witness, 0), (Purpose::Custom(86), 1)] {
+        let account = AccountHDPath::new(purpose, 0, 3);
+        let chain = chain_key(&secp, &master, &account, change).unwrap();
+        for index in [0, 1, 15, 0x7FFFFFFF] {
+            let path = DerivationPath::from(&account.address_at(change, index).unwrap());
+            assert_eq!(index_key(&secp, &chain, index).unwrap(), master.derive_priv(&secp, &path).unwrap());
+        }
+    }
+}
+
+#[test]
+fn test_index_pubkey_matches_index_key(){
+    let secp = Secp256k1::new();
+    let account = AccountHDPath::new(Purpose::Witness, 0, 0);
+    let chain = chain_key(&secp, &test_master_key(), &account, 0).unwrap();
+    let chain_public = chain_pubkey(&secp, &chain);
+    for index in 0..20 {
+        assert_eq!(
+            index_pubkey(&secp, &chain_public, index).unwrap(),
+            ExtendedPubKey::from_private(&secp, &index_key(&secp, &chain, index).unwrap())
+        );
+    }
+}
+
+#[test]
+fn test_derivation_errors(){
+    let secp = Secp256k1::new();
+    let account = AccountHDPath::new(Purpose::Pubkey, 0, 0);
+    let chain = chain_key(&secp, &test_master_key(), &account, 0).unwrap();
+
+    // a hardened index can't be reached through a normal child number
+    match index_key(&secp, &chain, 0x80000000) {
+        Err(HdiFinderError::Derivation { target, .. }) => assert_eq!(target, "index 2147483648"),
+        _ => panic!("expected a derivation error"),
+    }
+    let error = index_pubkey(&secp, &chain_pubkey(&secp, &chain), 0x80000000).unwrap_err();
+    assert!(crate::error::cause_chain(&error).starts_with("Unable to derive index 2147483648: child number 2147483648 is invalid"));
+
+    assert!(matches!(get_mnemonic("bananaa", None), Err(HdiFinderError::Mnemonic { .. })));
+}
@@ -0,0 +1,197 @@
+use crate::{
+    cli::PathOptions,
+    output::{csv_escape, json_account_xpub, json_escape},
+};
+use bitcoin::util::bip32::ExtendedPrivKey;
+use bitcoin::util::bip32::{ChildNumber, DerivationPath};
+use hdifinder::{
+    address, derivation,
+    models::{AccountXpub, DerivedAddress},
+    HdiFinderError,
+};
+use hdpath::AccountHDPath;
+use secp256k1::{All, Secp256k1};
+use std::ops::Range;
+
+/**
+ * An address derived at an index, along with its derivation path
+ */
+pub struct AddressRow {
+    pub index: u32,
+    pub derivation_path: String,
+    pub derived: DerivedAddress,
+}
+
+/**
+ * Computes the addresses of a range of indexes for each account, purpose
+ * and chain of the path options, the named accounts of the preset after the
+ * range, in the order the search scans them. The legacy layouts have the addresses of the uncompressed keys as well.
+ */
+pub fn derive_rows(
+    secp: &Secp256k1<All>,
+    master: &ExtendedPrivKey,
+    paths: &PathOptions,
+    indexes: Range<u32>,
+) -> Result<Vec<AddressRow>, HdiFinderError> {
+    let mut rows: Vec<AddressRow> = Vec::new();
+    let key_formats: &[bool] = if paths.layout.is_legacy() { &[false, true] } else { &[false] };
+    for (account, purpose) in paths.account_purposes() {
+        let account_path = paths.layout.account_path(account);
+        let account = AccountHDPath::new(purpose.clone(), derivation::coin_type(paths.network), account);
+        for change in &paths.chains {
+            let chain_path = match &account_path {
+                Some(account_path) => account_path.child(ChildNumber::Normal { index: *change }),
+                None => DerivationPath::from(&account).child(ChildNumber::Normal { index: *change }),
+            };
+            let chain_key = match &account_path {
+                Some(_) => master.derive_priv(secp, &chain_path).map_err(|source| HdiFinderError::Derivation {
+                    target: chain_path.to_string(),
+                    source,
+                })?,
+                None => derivation::chain_key(secp, master, &account, *change)?,
+            };
+            for uncompressed in key_formats {
+                for index in indexes.clone() {
+                    let mut key = derivation::index_key(secp, &chain_key, index)?;
+                    key.private_key.compressed = !uncompressed;
+                    let mut derived = address::address_compute(secp, derivation::get_public_key(secp, key), &purpose, paths.network)?;
+                    if *uncompressed {
+                        derived.address_type = address::P2PKH_UNCOMPRESSED;
+                    }
+                    rows.push(AddressRow {
+                        index,
+                        derivation_path: chain_path.child(ChildNumber::Normal { index }).to_string(),
+                        derived,
+                    });
+                }
+            }
+        }
+    }
+    Ok(rows)
+}
+
+/**
+ * Derives the extended public key of each account and purpose of the path
+ * options
+ */
+pub fn account_xpubs(secp: &Secp256k1<All>, master: &ExtendedPrivKey, paths: &PathOptions) -> Result<Vec<AccountXpub>, HdiFinderError> {
+    let mut accounts: Vec<AccountXpub> = Vec::new();
+    for (account, purpose) in paths.account_purposes() {
+        let account_path = paths
+            .layout
+            .account_path(account)
+            .unwrap_or_else(|| DerivationPath::from(&AccountHDPath::new(purpose.clone(), derivation::coin_type(paths.network), account)));
+        accounts.push(derivation::account_xpub(secp, master, &account_path, &purpose)?);
+    }
+    Ok(accounts)
+}
+
+#[cfg(test)]
+fn test_rows() -> Vec<AddressRow> {
+    use bitcoin::network::constants::Network;
+    use hdifinder::presets::Layout;
+    use hdpath::Purpose;
+    let mnemonic = derivation::get_mnemonic("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb", None).unwrap();
+    let secp = Secp256k1::new();
+    let master = derivation::get_master_key(mnemonic.to_seed(""), Network::Bitcoin).unwrap();
+    let paths = PathOptions {
+        network: Network::Bitcoin,
+        purposes: vec![Purpose::Pubkey, Purpose::Witness],
+        accounts: 0..=0,
+        chains: vec![0, 1],
+        preset_end: None,
+        layout: Layout::Bip44,
+        named_accounts: Vec::new(),
+    };
+    derive_rows(&secp, &master, &paths, 3..6).unwrap()
+}
+
+#[test]
+fn test_derive_rows(){
+    let rows = test_rows();
+    assert_eq!(rows.len(), 12);
+    assert_eq!(rows[2].index, 5);
+    assert_eq!(rows[2].derivation_path, "m/44'/0'/0'/0/5");
+    assert_eq!(rows[2].derived.address, "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    assert_eq!(rows[3].derivation_path, "m/44'/0'/0'/1/3");
+    assert_eq!(rows[3].derived.address, "1EukGhGZ3ai3zoG8mH86irCX18zTbthDZ5");
+    assert_eq!(rows[6].derived.address_type, "p2wpkh");
+}
+
+/**
+ * Formats the rows as aligned columns: index, derivation path, address type
+ * and address. The type column widens for p2pkh-uncompressed.
+ */
+pub fn text_rows(rows: &[AddressRow]) -> String {
+    let type_width = rows.iter().map(|row| row.derived.address_type.len() + 1).max().unwrap_or(0).max(10);
+    rows.iter()
+        .map(|row| {
+            format!(
+                "{:<8}{:<22}{:<width$}{}\n",
+                row.index,
+                row.derivation_path,
+                row.derived.address_type,
+                row.derived.address,
+                width = type_width
+            )
+        })
+        .collect()
+}
+
+/**
+ * Serializes a row as a JSON object
+ */
+pub fn json_row(row: &AddressRow) -> String {
+    format!(
+        "{{\"index\":{},\"derivation_path\":{},\"address_type\":{},\"address\":{}}}",
+        row.index,
+        json_escape(&row.derivation_path),
+        json_escape(row.derived.address_type),
+        json_escape(&row.derived.address)
+    )
+}
+
+/**
+ * Serializes the rows as a JSON object listing the addresses, preceded by
+ * the extended public keys of the accounts when they are given
+ */
+pub fn json_rows(rows: &[AddressRow], accounts: &[AccountXpub]) -> String {
+    let addresses = rows.iter().map(self::json_row).collect::<Vec<String>>().join(",");
+    if accounts.is_empty() {
+        return format!("{{\"addresses\":[{}]}}", addresses);
+    }
+    let accounts = accounts.iter().map(json_account_xpub).collect::<Vec<String>>().join(",");
+    format!("{{\"accounts\":[{}],\"addresses\":[{}]}}", accounts, addresses)
+}
+
+pub const CSV_HEADER: &str = "index,derivation_path,type,address";
+
+/**
+ * Formats the rows as CSV, header included
+ */
+pub fn csv_rows(rows: &[AddressRow]) -> String {
+    let mut csv = format!("{}\n", CSV_HEADER);
+    for row in rows {
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            row.index,
+            csv_escape(&row.derivation_path),
+            row.derived.address_type,
+            row.derived.address
+        ));
+    }
+    csv
+}
+
+#[test]
+fn test_format_rows(){
+    let rows = test_rows();
+    assert!(text_rows(&rows).contains("5       m/44'/0'/0'/0/5       p2pkh     14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK\n"));
+    assert!(json_rows(&rows[2..3], &[]).starts_with(
+        "{\"addresses\":[{\"index\":5,\"derivation_path\":\"m/44'/0'/0'/0/5\",\"address_type\":\"p2pkh\",\"address\":\"14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK\"}]}"
+    ));
+    assert_eq!(
+        csv_rows(&rows[2..3]),
+        "index,derivation_path,type,address\n5,m/44'/0'/0'/0/5,p2pkh,14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK\n"
+    );
+}
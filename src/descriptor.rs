@@ -0,0 +1,437 @@
+use crate::{address, derivation, error::HdiFinderError};
+use bitcoin::{
+    network::constants::Network,
+    util::bip32::{ChildNumber, DerivationPath, ExtendedPrivKey, ExtendedPubKey},
+};
+use hdpath::Purpose;
+#[cfg(test)]
+use hdpath::AccountHDPath;
+use secp256k1::{All, Secp256k1};
+use std::{ops::Range, str::FromStr};
+
+/**
+ * Output descriptors (BIP380) wallets export to describe their addresses,
+ * e.g `wpkh([d34db33f/84'/0'/0']xpub...)#checksum`, ranged by a trailing
+ * wildcard step on the key. The single key
+ * descriptors a seed derives are supported along with `addr()`, scripts
+ * of several keys can't be matched by a single key search.
+ */
+const INPUT_CHARSET: &str = "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const CHECKSUM_LEN: usize = 8;
+
+/**
+ * The script expressions of a single key, along with the derivation purpose
+ * whose address type they produce
+ */
+const SCRIPT_EXPRESSIONS: [(&str, &str, Purpose); 4] = [
+    ("sh(wpkh(", "))", Purpose::ScriptHash),
+    ("wpkh(", ")", Purpose::Witness),
+    ("pkh(", ")", Purpose::Pubkey),
+    ("tr(", ")", Purpose::Custom(86)),
+];
+
+/**
+ * What a descriptor describes: a single address for `addr()`, otherwise an
+ * extended public key along with the path of the addresses below it
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Descriptor {
+    Address(String),
+    Key {
+        purpose: Purpose,
+        key: ExtendedPubKey,
+        // The non-hardened steps from the key down to the address, or down to
+        // the chain when the descriptor is ranged
+        path: DerivationPath,
+        ranged: bool,
+    },
+}
+
+/**
+ * Tells whether a target is given as a descriptor rather than an address.
+ * Addresses and payment URIs hold no parenthesis.
+ */
+pub fn is_descriptor(target: &str) -> bool {
+    target.contains('(')
+}
+
+fn polymod(c: u64, value: u64) -> u64 {
+    let c0 = c >> 35;
+    let mut c = ((c & 0x7ffffffff) << 5) ^ value;
+    for (bit, generator) in [0xf5dee51989, 0xa9fdca3312, 0x1bab10e32d, 0x3706b1677a, 0x644d626ffd].iter().enumerate() {
+        if (c0 >> bit) & 1 == 1 {
+            c ^= generator;
+        }
+    }
+    c
+}
+
+/**
+ * Computes the checksum of a descriptor as described in BIP380. Characters
+ * a descriptor can't hold are reported along with their position, counted
+ * from 1.
+ */
+pub fn checksum(descriptor: &str) -> Result<String, String> {
+    let mut c: u64 = 1;
+    let mut classes: u64 = 0;
+    let mut count = 0;
+    for (position, character) in descriptor.chars().enumerate() {
+        let value = INPUT_CHARSET
+            .find(character)
+            .ok_or_else(|| format!("invalid character '{}' at position {}", character, position + 1))? as u64;
+        c = self::polymod(c, value & 31);
+        classes = classes * 3 + (value >> 5);
+        count += 1;
+        if count == 3 {
+            c = self::polymod(c, classes);
+            classes = 0;
+            count = 0;
+        }
+    }
+    if count > 0 {
+        c = self::polymod(c, classes);
+    }
+    for _ in 0..CHECKSUM_LEN {
+        c = self::polymod(c, 0);
+    }
+    c ^= 1;
+    Ok((0..CHECKSUM_LEN)
+        .map(|i| CHECKSUM_CHARSET[((c >> (5 * (7 - i))) & 31) as usize] as char)
+        .collect())
+}
+
+#[test]
+fn test_checksum(){
+    // The test vector of BIP380
+    assert_eq!(checksum("raw(deadbeef)").unwrap(), "89f8spxm");
+    assert_eq!(checksum("addr(14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK)").unwrap(), "x6fj89sh");
+    assert_eq!(checksum("addr(14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK\u{e9})").unwrap_err(), "invalid character '\u{e9}' at position 40");
+}
+
+/**
+ * Parses a descriptor given as the target. The checksum is optional, as
+ * descriptors are often written down without it, but is checked when given.
+ */
+pub fn parse_descriptor(descriptor: &str, network: Network) -> Result<Descriptor, HdiFinderError> {
+    let descriptor = descriptor.trim();
+    let invalid = |reason: String| HdiFinderError::Descriptor {
+        descriptor: descriptor.to_string(),
+        reason,
+    };
+    let (body, given_checksum) = match descriptor.split_once('#') {
+        Some((body, given_checksum)) => (body, Some(given_checksum)),
+        None => (descriptor, None),
+    };
+    let expected = self::checksum(body).map_err(invalid)?;
+    if let Some(given_checksum) = given_checksum {
+        if given_checksum.chars().count() != CHECKSUM_LEN {
+            return Err(invalid(format!(
+                "the checksum at position {} is {} characters long instead of {}",
+                body.chars().count() + 2,
+                given_checksum.chars().count(),
+                CHECKSUM_LEN
+            )));
+        }
+        if given_checksum != expected {
+            return Err(invalid(format!(
+                "the checksum at position {} is {} while the descriptor gives {}",
+                body.chars().count() + 2,
+                given_checksum,
+                expected
+            )));
+        }
+    }
+
+    if let Some(address) = body.strip_prefix("addr(").and_then(|rest| rest.strip_suffix(')')) {
+        let address = address::normalize_target(address);
+        address::decode_target(&address).map_err(|e| invalid(e.to_string()))?;
+        return Ok(Descriptor::Address(address));
+    }
+    for (prefix, suffix, purpose) in SCRIPT_EXPRESSIONS.iter() {
+        if let Some(expression) = body.strip_prefix(prefix).and_then(|rest| rest.strip_suffix(suffix)) {
+            if expression.contains(',') || expression.contains('(') {
+                break;
+            }
+            let (key, path, ranged) = self::parse_key(expression, prefix.len() + 1, network).map_err(invalid)?;
+            return Ok(Descriptor::Key {
+                purpose: purpose.clone(),
+                key,
+                path,
+                ranged,
+            });
+        }
+    }
+    Err(invalid(
+        "only addr(), pkh(), sh(wpkh()), wpkh() and tr() descriptors of a single key can be searched".to_string(),
+    ))
+}
+
+/**
+ * Parses the key expression of a descriptor, `position` being where it
+ * starts in the descriptor. The key origin is checked but not used, the
+ * addresses being derived from the extended public key itself.
+ */
+fn parse_key(expression: &str, position: usize, network: Network) -> Result<(ExtendedPubKey, DerivationPath, bool), String> {
+    let mut position = position;
+    let mut expression = expression;
+    if let Some(origin) = expression.strip_prefix('[') {
+        let (origin, rest) = origin
+            .split_once(']')
+            .ok_or_else(|| format!("the key origin at position {} isn't closed", position))?;
+        let (fingerprint, path) = origin.split_once('/').unwrap_or((origin, ""));
+        if fingerprint.len() != 8 || !fingerprint.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return Err(format!("the key origin at position {} doesn't start with a fingerprint of 8 hex digits", position));
+        }
+        if !path.is_empty() {
+            DerivationPath::from_str(&format!("m/{}", path.replace('h', "'")))
+                .map_err(|e| format!("the key origin at position {} has an invalid path: {}", position, e))?;
+        }
+        position += origin.len() + 2;
+        expression = rest;
+    }
+
+    let mut steps = expression.split('/');
+    let encoded = steps.next().unwrap_or_default();
+    let key = ExtendedPubKey::from_str(encoded).map_err(|e| {
+        format!("the key at position {} isn't an extended public key ({}), only those can be searched", position, e)
+    })?;
+    if (key.network == Network::Bitcoin) != (network == Network::Bitcoin) {
+        return Err(format!(
+            "the key at position {} is a {} key while the network is {}",
+            position,
+            if key.network == Network::Bitcoin { "mainnet (xpub)" } else { "testnet (tpub)" },
+            network
+        ));
+    }
+    position += encoded.len() + 1;
+
+    let mut path: Vec<ChildNumber> = Vec::new();
+    let mut ranged = false;
+    for step in steps {
+        if ranged {
+            return Err(format!("the wildcard isn't the last step of the key at position {}", position - 2));
+        }
+        if step == "*" {
+            ranged = true;
+        } else if step.ends_with('\'') || step.ends_with('h') {
+            return Err(format!("the hardened step {} at position {} can't be derived from an extended public key", step, position));
+        } else {
+            let index = step
+                .parse::<u32>()
+                .ok()
+                .and_then(|index| ChildNumber::from_normal_idx(index).ok())
+                .ok_or_else(|| format!("the step {} at position {} isn't a derivation index", step, position))?;
+            path.push(index);
+        }
+        position += step.len() + 1;
+    }
+    Ok((key, DerivationPath::from(path), ranged))
+}
+
+#[cfg(test)]
+const TEST_DESCRIPTOR: &str = "wpkh([67b4602d/84'/0'/0']xpub6C2nZRpiwAoxe2a3Y1riTBRtFdEUxULcrCaDFsP1VbGRdhf7HRWPp69RsUcx79fnA5CNjHJzC39EaAgKfVuiuHGGhqHSDkv1EF9q5zDXVqE/0/*)";
+
+#[test]
+fn test_parse_descriptor(){
+    assert_eq!(
+        parse_descriptor("addr(14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK)#x6fj89sh", Network::Bitcoin).unwrap(),
+        Descriptor::Address("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK".to_string())
+    );
+    match parse_descriptor(&format!("{}#fllv4e44", TEST_DESCRIPTOR), Network::Bitcoin).unwrap() {
+        Descriptor::Key { purpose, path, ranged, .. } => {
+            assert_eq!(purpose, Purpose::Witness);
+            assert_eq!(path, DerivationPath::from_str("m/0").unwrap());
+            assert!(ranged);
+        }
+        descriptor => panic!("unexpected descriptor {:?}", descriptor),
+    }
+    // The checksum is optional
+    assert!(parse_descriptor(TEST_DESCRIPTOR, Network::Bitcoin).is_ok());
+
+    assert_eq!(
+        parse_descriptor("addr(14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK)#x6fj89sj", Network::Bitcoin).unwrap_err().to_string(),
+        "Invalid descriptor addr(14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK)#x6fj89sj: the checksum at position 42 is x6fj89sj while the descriptor gives x6fj89sh"
+    );
+    assert_eq!(
+        parse_descriptor("addr(14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK)#x6fj", Network::Bitcoin).unwrap_err().to_string(),
+        "Invalid descriptor addr(14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK)#x6fj: the checksum at position 42 is 4 characters long instead of 8"
+    );
+    assert!(parse_descriptor("addr(14odE5c1eXuphR24fXMtzDfsXMLCmFTFgL)", Network::Bitcoin)
+        .unwrap_err()
+        .to_string()
+        .contains(": Invalid address 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgL: base58"));
+    assert_eq!(
+        parse_descriptor(&TEST_DESCRIPTOR.replace("/0/*", "/0'/*"), Network::Bitcoin).unwrap_err().to_string(),
+        format!("Invalid descriptor {}: the hardened step 0' at position 138 can't be derived from an extended public key", TEST_DESCRIPTOR.replace("/0/*", "/0'/*"))
+    );
+    assert!(parse_descriptor(TEST_DESCRIPTOR, Network::Testnet).unwrap_err().to_string().ends_with("is a mainnet (xpub) key while the network is testnet"));
+    assert!(parse_descriptor("wsh(multi(1,xpub1,xpub2))", Network::Bitcoin)
+        .unwrap_err()
+        .to_string()
+        .ends_with("only addr(), pkh(), sh(wpkh()), wpkh() and tr() descriptors of a single key can be searched"));
+}
+
+#[test]
+fn test_parse_descriptor_spec(){
+    let secp = Secp256k1::new();
+    let seed = derivation::get_mnemonic("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about", None).unwrap().to_seed("");
+    let master = derivation::get_master_key(seed, Network::Bitcoin).unwrap();
+
+    // The nested segwit descriptor of BIP49 gives the p2sh-p2wpkh addresses
+    let account = DerivationPath::from(&AccountHDPath::new(Purpose::ScriptHash, 0, 0));
+    let nested = chain_descriptor(&Purpose::ScriptHash, &chain_key_expression(&secp, &master, &account, 0, false).unwrap());
+    assert!(nested.starts_with("sh(wpkh([73c5da0a/49'/0'/0']xpub"));
+    match parse_descriptor(&nested, Network::Bitcoin).unwrap() {
+        Descriptor::Key { purpose, ranged, .. } => {
+            assert_eq!(purpose, Purpose::ScriptHash);
+            assert!(ranged);
+        }
+        descriptor => panic!("unexpected descriptor {:?}", descriptor),
+    }
+    let addresses = parse_descriptor(&nested, Network::Bitcoin).unwrap().addresses(&secp, 0..2, Network::Bitcoin).unwrap();
+    assert_eq!(addresses[0], "37VucYSaXLCAsxYyAPfbSi9eh4iEcbShgf");
+
+    // The origin path may be hardened with h as well
+    let account = DerivationPath::from(&AccountHDPath::new(Purpose::Witness, 0, 0));
+    let witness = chain_key_expression(&secp, &master, &account, 0, false).unwrap().replace('\'', "h");
+    let descriptor = parse_descriptor(&format!("wpkh({}/*)", witness), Network::Bitcoin).unwrap();
+    assert_eq!(descriptor.addresses(&secp, 0..1, Network::Bitcoin).unwrap(), vec!["bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"]);
+
+    // Scripts of several keys are refused, nested or not
+    let key = TEST_DESCRIPTOR.trim_start_matches("wpkh(").trim_end_matches(')');
+    for script in [
+        format!("sh(multi(1,{}))", key),
+        format!("wsh(multi(1,{},{}))", key, key),
+        format!("wsh(sortedmulti(2,{},{}))", key, key),
+        format!("sh(wsh(sortedmulti(1,{})))", key),
+        format!("wpkh({},{})", key, key),
+    ] {
+        assert!(parse_descriptor(&script, Network::Bitcoin)
+            .unwrap_err()
+            .to_string()
+            .ends_with("only addr(), pkh(), sh(wpkh()), wpkh() and tr() descriptors of a single key can be searched"), "{}", script);
+    }
+
+    // A hardened wildcard needs the private key
+    for wildcard in ["*'", "*h"] {
+        let error = parse_descriptor(&TEST_DESCRIPTOR.replace("/0/*", &format!("/0/{}", wildcard)), Network::Bitcoin).unwrap_err();
+        assert!(error.to_string().contains(&format!("the hardened step {} at position 140 can't be derived", wildcard)), "{}", error);
+    }
+    assert!(parse_descriptor(&TEST_DESCRIPTOR.replace("/0/*", "/*/0"), Network::Bitcoin)
+        .unwrap_err()
+        .to_string()
+        .contains("the wildcard isn't the last step of the key"));
+}
+
+impl Descriptor {
+    /**
+     * Derives the addresses a descriptor describes, over the indexes of the
+     * range for ranged descriptors
+     */
+    pub fn addresses(&self, secp: &Secp256k1<All>, indexes: Range<u32>, network: Network) -> Result<Vec<String>, HdiFinderError> {
+        let (purpose, key, path, ranged) = match self {
+            Descriptor::Address(address) => return Ok(vec![address.clone()]),
+            Descriptor::Key { purpose, key, path, ranged } => (purpose, key, path, ranged),
+        };
+        let base = key.derive_pub(secp, path).map_err(|source| HdiFinderError::Derivation {
+            target: format!("the descriptor key at {}", path),
+            source,
+        })?;
+        if !ranged {
+            return Ok(vec![address::address_compute(secp, base, purpose, network)?.address]);
+        }
+        indexes
+            .map(|index| {
+                let pubkey = derivation::index_pubkey(secp, &base, index)?;
+                Ok(address::address_compute(secp, pubkey, purpose, network)?.address)
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_descriptor_addresses(){
+    let secp = Secp256k1::new();
+    let descriptor = parse_descriptor(TEST_DESCRIPTOR, Network::Bitcoin).unwrap();
+    let addresses = descriptor.addresses(&secp, 0..5, Network::Bitcoin).unwrap();
+    assert_eq!(addresses.len(), 5);
+    assert_eq!(addresses[1], "bc1q4qwmrj5thpqes6naw4xe579kw4my05t0dapcht");
+
+    let descriptor = parse_descriptor(&TEST_DESCRIPTOR.replace("/0/*", "/0/1"), Network::Bitcoin).unwrap();
+    assert_eq!(descriptor.addresses(&secp, 0..5, Network::Bitcoin).unwrap(), vec!["bc1q4qwmrj5thpqes6naw4xe579kw4my05t0dapcht"]);
+
+    let descriptor = parse_descriptor("pkh([67b4602d/44h/0h/0h]xpub6CDTUkh21C4fjPY8KXZuJwafuUaMvTEGj9skceNq2dFVdu19JMVwioP1iZK5urL11xzypzwt9yAKjZkm7CWjxcKUHLGpCbq2YohwPij3nbm/1/*)#vgwrys8s", Network::Bitcoin).unwrap();
+    assert_eq!(descriptor.addresses(&secp, 0..5, Network::Bitcoin).unwrap()[3], "1EukGhGZ3ai3zoG8mH86irCX18zTbthDZ5");
+}
+
+/**
+ * Appends its checksum to a descriptor built from derived keys, whose
+ * characters are all valid
+ */
+pub fn with_checksum(descriptor: &str) -> String {
+    let checksum = self::checksum(descriptor).expect("derived descriptors hold valid characters only");
+    format!("{}#{}", descriptor, checksum)
+}
+
+/**
+ * Builds the ranged descriptor of a chain, with its checksum, from the key
+ * expression of the chain and the purpose of its account
+ */
+pub fn chain_descriptor(purpose: &Purpose, chain_key: &str) -> String {
+    let (prefix, suffix) = SCRIPT_EXPRESSIONS
+        .iter()
+        .find(|(_, _, script_purpose)| script_purpose == purpose)
+        .map(|(prefix, suffix, _)| (*prefix, *suffix))
+        .unwrap_or(("pkh(", ")"));
+    self::with_checksum(&format!("{}{}/*{}", prefix, chain_key, suffix))
+}
+
+/**
+ * Builds the key expression of a chain derived from a root key, i.e the
+ * key at `account_path` followed by the chain step. The origin of the key is
+ * given when the root is the master key, the fingerprint of an account level
+ * root's master being unknown. The account key is given as an xprv when
+ * `private` is set, as an xpub otherwise.
+ */
+pub fn chain_key_expression(
+    secp: &Secp256k1<All>,
+    root: &ExtendedPrivKey,
+    account_path: &DerivationPath,
+    change: u32,
+    private: bool,
+) -> Result<String, HdiFinderError> {
+    let (account_key, fingerprint) = derivation::account_key(secp, root, account_path)?;
+    let origin = match fingerprint {
+        Some(fingerprint) => derivation::key_origin(&fingerprint, account_path),
+        None => String::new(),
+    };
+    let key = if private {
+        account_key.to_string()
+    } else {
+        ExtendedPubKey::from_private(secp, &account_key).to_string()
+    };
+    Ok(format!("{}{}/{}", origin, key, change))
+}
+
+#[test]
+fn test_chain_descriptor(){
+    let secp = Secp256k1::new();
+    let seed = derivation::get_mnemonic("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb", None).unwrap().to_seed("");
+    let master = derivation::get_master_key(seed, Network::Bitcoin).unwrap();
+    let account = DerivationPath::from(&AccountHDPath::new(Purpose::Witness, 0, 0));
+    let chain_key = chain_key_expression(&secp, &master, &account, 0, false).unwrap();
+    // The descriptor Bitcoin Core gives for the account, checksum included
+    assert_eq!(chain_descriptor(&Purpose::Witness, &chain_key), format!("{}#fllv4e44", TEST_DESCRIPTOR));
+
+    // An account level root gives no origin
+    let account_key = master.derive_priv(&secp, &account).unwrap();
+    assert_eq!(
+        chain_key_expression(&secp, &account_key, &account, 1, false).unwrap(),
+        "xpub6C2nZRpiwAoxe2a3Y1riTBRtFdEUxULcrCaDFsP1VbGRdhf7HRWPp69RsUcx79fnA5CNjHJzC39EaAgKfVuiuHGGhqHSDkv1EF9q5zDXVqE/1"
+    );
+    let private = chain_key_expression(&secp, &master, &account, 0, true).unwrap();
+    assert!(private.starts_with("[67b4602d/84'/0'/0']xprv"));
+    assert!(chain_descriptor(&Purpose::ScriptHash, &private).starts_with("sh(wpkh([67b4602d/84'/0'/0']xprv"));
+    assert!(chain_descriptor(&Purpose::Custom(86), &chain_key).starts_with("tr([67b4602d/84'/0'/0']xpub"));
+}
@@ -0,0 +1,179 @@
+use bitcoin::{
+    hashes::{hmac, sha512, Hash, HashEngine},
+    util::bip32::{ChildNumber, DerivationPath},
+};
+use hdpath::Purpose;
+use unicode_normalization::{char::canonical_combining_class, UnicodeNormalization};
+
+/**
+ * Electrum seeds aren't BIP39 mnemonics: the words carry no checksum, the
+ * version of the seed is told by the hash of the phrase instead, and the
+ * root key is computed from the phrase itself rather than from the entropy
+ * the words encode.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SeedVersion {
+    Standard,
+    Segwit,
+    TwoFactor,
+    TwoFactorSegwit,
+}
+
+/**
+ * Prefixes of the hex encoded HMAC of the phrase telling the seed version
+ */
+const VERSION_PREFIXES: [(&str, SeedVersion); 4] = [
+    ("01", SeedVersion::Standard),
+    ("100", SeedVersion::Segwit),
+    ("101", SeedVersion::TwoFactor),
+    ("102", SeedVersion::TwoFactorSegwit),
+];
+
+const PBKDF2_ROUNDS: u32 = 2048;
+
+/**
+ * Unicode blocks of the scripts written without spaces between the words,
+ * where Electrum drops the whitespace between two characters
+ */
+const CJK_INTERVALS: [(u32, u32); 30] = [
+    (0x1100, 0x11FF),
+    (0x2E80, 0x2EFF),
+    (0x2F00, 0x2FDF),
+    (0x2FF0, 0x2FFF),
+    (0x3000, 0x303F),
+    (0x3040, 0x309F),
+    (0x30A0, 0x30FF),
+    (0x3100, 0x312F),
+    (0x3130, 0x318F),
+    (0x3190, 0x319F),
+    (0x31A0, 0x31BF),
+    (0x31C0, 0x31EF),
+    (0x31F0, 0x31FF),
+    (0x3200, 0x32FF),
+    (0x3300, 0x33FF),
+    (0x3400, 0x4DBF),
+    (0x4E00, 0x9FFF),
+    (0xA000, 0xA48F),
+    (0xA490, 0xA4CF),
+    (0xA960, 0xA97F),
+    (0xAC00, 0xD7AF),
+    (0xD7B0, 0xD7FF),
+    (0xF900, 0xFAFF),
+    (0xFE30, 0xFE4F),
+    (0xFF00, 0xFFEF),
+    (0x1B000, 0x1B0FF),
+    (0x20000, 0x2A6DF),
+    (0x2A700, 0x2B81F),
+    (0x2B820, 0x2CEAF),
+    (0x2F800, 0x2FA1F),
+];
+
+fn is_cjk(c: char) -> bool {
+    CJK_INTERVALS.iter().any(|(start, end)| (*start..=*end).contains(&(c as u32)))
+}
+
+/**
+ * Normalizes a phrase or a passphrase the way Electrum does before hashing
+ * it: NFKD, lowercase, accents removed, whitespace collapsed and dropped
+ * between CJK characters
+ */
+pub fn normalize(text: &str) -> String {
+    let lowercase: String = text.nfkd().collect::<String>().to_lowercase();
+    let stripped: String = lowercase.chars().filter(|c| canonical_combining_class(*c) == 0).collect();
+    let collapsed: Vec<char> = stripped.split_whitespace().collect::<Vec<&str>>().join(" ").chars().collect();
+    collapsed
+        .iter()
+        .enumerate()
+        .filter(|(i, c)| {
+            !(c.is_whitespace() && *i > 0 && *i + 1 < collapsed.len() && is_cjk(collapsed[i - 1]) && is_cjk(collapsed[i + 1]))
+        })
+        .map(|(_, c)| *c)
+        .collect()
+}
+
+#[test]
+fn test_normalize(){
+    assert_eq!(normalize("  Cycle  rocket\tWEST\n"), "cycle rocket west");
+    assert_eq!(normalize("café"), "cafe");
+    assert_eq!(normalize("あいう えお abc def"), "あいうえお abc def");
+}
+
+fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; 64] {
+    let mut engine = hmac::HmacEngine::<sha512::Hash>::new(key);
+    engine.input(data);
+    hmac::Hmac::<sha512::Hash>::from_engine(engine).into_inner()
+}
+
+/**
+ * Retrieves the version of an Electrum seed, None when the phrase isn't one
+ */
+pub fn seed_version(phrase: &str) -> Option<SeedVersion> {
+    let hash = hmac_sha512(b"Seed version", self::normalize(phrase).as_bytes());
+    let hex: String = hash[..2].iter().map(|byte| format!("{:02x}", byte)).collect();
+    VERSION_PREFIXES
+        .iter()
+        .find(|(prefix, _)| hex.starts_with(prefix))
+        .map(|(_, version)| *version)
+}
+
+/**
+ * Stretches an Electrum seed phrase and its passphrase into the seed the
+ * root key is computed from, as PBKDF2-HMAC-SHA512 salted with "electrum"
+ */
+pub fn to_seed(phrase: &str, passphrase: &str) -> [u8; 64] {
+    let password = self::normalize(phrase);
+    let mut salt = b"electrum".to_vec();
+    salt.extend(self::normalize(passphrase).as_bytes());
+    // A single block, the seed being as long as the hash
+    salt.extend(1u32.to_be_bytes());
+    let mut block = hmac_sha512(password.as_bytes(), &salt);
+    let mut seed = block;
+    for _ in 1..PBKDF2_ROUNDS {
+        block = hmac_sha512(password.as_bytes(), &block);
+        for (byte, round_byte) in seed.iter_mut().zip(block.iter()) {
+            *byte ^= round_byte;
+        }
+    }
+    seed
+}
+
+#[test]
+fn test_seed_version(){
+    assert_eq!(seed_version("cycle rocket west magnet parrot shuffle foot correct salt library feed song"), Some(SeedVersion::Standard));
+    assert_eq!(seed_version("bitter grass shiver impose acquire brush forget axis eager alone wine silver"), Some(SeedVersion::Segwit));
+    assert_eq!(seed_version("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb"), None);
+
+    let seed = to_seed("cycle rocket west magnet parrot shuffle foot correct salt library feed song", "");
+    assert_eq!(seed[..8], [0x00, 0x30, 0x2d, 0x7d, 0xb1, 0x62, 0xde, 0x47]);
+}
+
+/**
+ * Retrieves the purpose standing for the address type of a seed version,
+ * the two-factor seeds being multisig wallets
+ */
+pub fn purpose(version: SeedVersion) -> Option<Purpose> {
+    match version {
+        SeedVersion::Standard => Some(Purpose::Pubkey),
+        SeedVersion::Segwit => Some(Purpose::Witness),
+        SeedVersion::TwoFactor | SeedVersion::TwoFactorSegwit => None,
+    }
+}
+
+/**
+ * Retrieves the path of a chain of an Electrum wallet: m/change for the
+ * standard seeds and m/0'/change for the segwit ones
+ */
+pub fn chain_path(version: SeedVersion, change: u32) -> DerivationPath {
+    let mut path: Vec<ChildNumber> = Vec::new();
+    if version == SeedVersion::Segwit {
+        path.push(ChildNumber::Hardened { index: 0 });
+    }
+    path.push(ChildNumber::Normal { index: change });
+    DerivationPath::from(path)
+}
+
+#[test]
+fn test_chain_path(){
+    assert_eq!(chain_path(SeedVersion::Standard, 1).to_string(), "m/1");
+    assert_eq!(chain_path(SeedVersion::Segwit, 0).to_string(), "m/0'/0");
+}
@@ -0,0 +1,100 @@
+use bitcoin::util::bip32;
+use std::{error, io};
+use thiserror::Error;
+
+/**
+ * Errors raised while searching an address.
+ * Every variant carries what is needed to explain the failure to a user.
+ */
+#[derive(Debug, Error)]
+pub enum HdiFinderError {
+    /**
+     * The mnemonic phrase isn't a valid BIP39 mnemonic, `reason` tells
+     * the user what to fix in the phrase
+     */
+    #[error("Invalid mnemonic: {reason}")]
+    Mnemonic { reason: String, error: bip39::Error },
+    /**
+     * SLIP-39 shares can't be combined, the reason telling which share
+     * or how many are missing
+     */
+    #[error("Invalid SLIP-39 shares: {0}")]
+    Shares(String),
+    /**
+     * A key can't be derived, `target` tells which one
+     */
+    #[error("Unable to derive {target}")]
+    Derivation { target: String, source: bip32::Error },
+    /**
+     * A derivation path holds an out of range component
+     */
+    #[error("Invalid derivation path: {0}")]
+    InvalidPath(String),
+    /**
+     * An address can't be built or doesn't suit the search
+     */
+    #[error("{0}")]
+    InvalidAddress(String),
+    /**
+     * A payment URI (BIP21) given as the target can't be parsed
+     */
+    #[error("Invalid BIP21 URI {uri}: {reason}")]
+    PaymentUri { uri: String, reason: String },
+    /**
+     * An output descriptor given as the target can't be parsed
+     */
+    #[error("Invalid descriptor {descriptor}: {reason}")]
+    Descriptor { descriptor: String, reason: String },
+    /**
+     * The indexes to scan are out of bounds
+     */
+    #[error("{0}")]
+    InvalidRange(String),
+    /**
+     * A command line argument can't be used
+     */
+    #[error("{0}")]
+    InvalidArgument(String),
+    /**
+     * A file can't be read or written
+     */
+    #[error("Unable to access {path}")]
+    Io { path: String, source: io::Error },
+    /**
+     * A worker failed unexpectedly, or an invariant of the search doesn't hold
+     */
+    #[error("Internal error: {0}")]
+    Internal(String),
+}
+
+/**
+ * Formats an error followed by its sources on a single line,
+ * e.g. "Unable to access state.txt: permission denied"
+ */
+pub fn cause_chain(error: &dyn error::Error) -> String {
+    let mut chain = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        chain.push_str(": ");
+        chain.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    chain
+}
+
+#[test]
+fn test_display(){
+    let error = HdiFinderError::Derivation {
+        target: "index 5".to_string(),
+        source: bip32::Error::InvalidChildNumber(0x80000000),
+    };
+    assert_eq!(error.to_string(), "Unable to derive index 5");
+    assert_eq!(cause_chain(&error), "Unable to derive index 5: child number 2147483648 is invalid (not within [0, 2^31 - 1])");
+    assert!(error::Error::source(&error).is_some());
+
+    let error = HdiFinderError::Io {
+        path: "state.txt".to_string(),
+        source: io::Error::new(io::ErrorKind::PermissionDenied, "denied"),
+    };
+    assert_eq!(cause_chain(&error), "Unable to access state.txt: denied");
+}
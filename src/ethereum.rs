@@ -0,0 +1,109 @@
+use secp256k1::PublicKey;
+use tiny_keccak::{Hasher, Keccak};
+
+/**
+ * Ethereum addresses, e.g `0x9858EfFD232B4033E47d90003D41EC34EcaEda94`: the
+ * last 20 bytes of the keccak256 of the uncompressed public key, in hex.
+ * The case of the letters is the EIP-55 checksum, an address being valid
+ * as well all lowercase or all uppercase.
+ */
+fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    hasher.update(data);
+    let mut hash = [0; 32];
+    hasher.finalize(&mut hash);
+    hash
+}
+
+/**
+ * Cases the letters of a lowercase hex address by its EIP-55 checksum, the
+ * nibbles of the keccak256 of the address telling them
+ */
+fn checksum_case(lowercase: &str) -> String {
+    let hash = self::keccak256(lowercase.as_bytes());
+    let cased: String = lowercase
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect();
+    format!("0x{}", cased)
+}
+
+/**
+ * Computes the checksummed address of a public key
+ */
+pub fn address(pubkey: &PublicKey) -> String {
+    let hash = self::keccak256(&pubkey.serialize_uncompressed()[1..]);
+    let hex: String = hash[12..].iter().map(|byte| format!("{:02x}", byte)).collect();
+    self::checksum_case(&hex)
+}
+
+/**
+ * Tells whether a target is an Ethereum address, whatever its checksum
+ */
+pub fn is_address(target: &str) -> bool {
+    target.len() == 42 && (target.starts_with("0x") || target.starts_with("0X")) && target[2..].bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+/**
+ * Checks the EIP-55 checksum of an address whose letters are mixed case,
+ * and gives it back checksummed for the search to compare it with the
+ * derived ones
+ */
+pub fn checksummed(address: &str) -> Result<String, String> {
+    if !self::is_address(address) {
+        return Err("expected 0x followed by 40 hex characters".to_string());
+    }
+    let hex = &address[2..];
+    let checksummed = self::checksum_case(&hex.to_lowercase());
+    let mixed_case = hex.chars().any(|c| c.is_ascii_lowercase()) && hex.chars().any(|c| c.is_ascii_uppercase());
+    if mixed_case && checksummed[2..] != *hex {
+        return Err(format!("EIP-55 checksum mismatch, expected {}", checksummed));
+    }
+    Ok(checksummed)
+}
+
+#[test]
+fn test_checksummed(){
+    // Vectors of EIP-55
+    for address in [
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ] {
+        assert_eq!(checksummed(address).unwrap(), address);
+        assert_eq!(checksummed(&address.to_lowercase()).unwrap(), address);
+        assert_eq!(checksummed(&format!("0x{}", address[2..].to_uppercase())).unwrap(), address);
+    }
+    assert_eq!(
+        checksummed("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD").unwrap_err(),
+        "EIP-55 checksum mismatch, expected 0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+    );
+    assert!(checksummed("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA").is_err());
+    assert!(!is_address("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"));
+}
+
+#[test]
+fn test_address(){
+    use crate::derivation::{get_mnemonic, get_private_key, get_public_key};
+    use bitcoin::network::constants::Network;
+    use hdpath::{AccountHDPath, Purpose};
+    use secp256k1::Secp256k1;
+
+    let secp = Secp256k1::new();
+    let seed = get_mnemonic("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about", None).unwrap().to_seed("");
+    let expected = ["0x9858EfFD232B4033E47d90003D41EC34EcaEda94", "0x6Fac4D18c912343BF86fa7049364Dd4E424Ab9C0"];
+    for (index, expected) in expected.iter().enumerate() {
+        let hd_path = AccountHDPath::new(Purpose::Pubkey, 60, 0).address_at(0, index as u32).unwrap();
+        let public_key = get_public_key(&secp, get_private_key(seed, &hd_path, Network::Bitcoin).unwrap());
+        assert_eq!(address(&public_key.public_key.key), *expected);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{
+    cli::PathOptions,
+    derive::{self, AddressRow},
+    output::json_escape,
+};
+use bip39::{Language, Mnemonic};
+use bitcoin::network::constants::Network;
+use hdifinder::{derivation, presets::Layout, HdiFinderError};
+use hdpath::Purpose;
+use secp256k1::{All, Secp256k1};
+
+/**
+ * Generates a mnemonic of `words` words from the entropy of the random
+ * generator of the operating system, a CSPRNG
+ */
+pub fn generate_mnemonic(words: usize) -> Result<Mnemonic, HdiFinderError> {
+    let mut entropy = vec![0u8; words * 4 / 3];
+    getrandom::getrandom(&mut entropy).map_err(|error| HdiFinderError::Io {
+        path: "the random generator of the operating system".to_string(),
+        source: error.into(),
+    })?;
+    Mnemonic::from_entropy(&entropy).map_err(|error| derivation::mnemonic_error("", Language::English, error))
+}
+
+#[test]
+fn test_generate_mnemonic(){
+    let first = generate_mnemonic(24).unwrap();
+    assert_eq!(first.word_count(), 24);
+    assert_eq!(generate_mnemonic(12).unwrap().word_count(), 12);
+    assert_ne!(first, generate_mnemonic(24).unwrap());
+}
+
+/**
+ * Computes the first `count` addresses of the external chain of account 0
+ * for each purpose
+ */
+pub fn first_addresses(
+    secp: &Secp256k1<All>,
+    mnemonic: &Mnemonic,
+    network: Network,
+    purposes: &[Purpose],
+    count: u32,
+) -> Result<Vec<AddressRow>, HdiFinderError> {
+    let master = derivation::get_master_key(mnemonic.to_seed(""), network)?;
+    let paths = PathOptions {
+        network,
+        purposes: purposes.to_vec(),
+        accounts: 0..=0,
+        chains: vec![0],
+        preset_end: None,
+        layout: Layout::Bip44,
+        named_accounts: Vec::new(),
+    };
+    derive::derive_rows(secp, &master, &paths, 0..count)
+}
+
+#[test]
+fn test_first_addresses(){
+    let mnemonic = derivation::get_mnemonic("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb", None).unwrap();
+    let secp = Secp256k1::new();
+    let addresses = first_addresses(&secp, &mnemonic, Network::Bitcoin, &[Purpose::Pubkey, Purpose::Witness], 6).unwrap();
+    assert_eq!(addresses.len(), 12);
+    assert_eq!(addresses[5].derivation_path, "m/44'/0'/0'/0/5");
+    assert_eq!(addresses[5].derived.address, "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    assert_eq!(addresses[6].derivation_path, "m/84'/0'/0'/0/0");
+    assert_eq!(addresses[6].derived.address_type, "p2wpkh");
+}
+
+/**
+ * Formats a generated wallet as human readable lines, the mnemonic first
+ */
+pub fn text_report(mnemonic: &Mnemonic, addresses: &[AddressRow]) -> String {
+    format!("mnemonic: {}\n{}", mnemonic, derive::text_rows(addresses))
+}
+
+/**
+ * Formats a generated wallet as a JSON object
+ */
+pub fn json_report(mnemonic: &Mnemonic, network: Network, addresses: &[AddressRow]) -> String {
+    format!(
+        "{{\"mnemonic\":{},\"network\":{},\"addresses\":[{}]}}",
+        json_escape(&mnemonic.to_string()),
+        json_escape(&network.to_string()),
+        addresses.iter().map(derive::json_row).collect::<Vec<String>>().join(",")
+    )
+}
+
+#[test]
+fn test_json_report(){
+    let mnemonic = derivation::get_mnemonic("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb", None).unwrap();
+    let secp = Secp256k1::new();
+    let addresses = first_addresses(&secp, &mnemonic, Network::Bitcoin, &[Purpose::Pubkey], 1).unwrap();
+    assert_eq!(
+        json_report(&mnemonic, Network::Bitcoin, &addresses),
+        format!(
+            "{{\"mnemonic\":\"{}\",\"network\":\"bitcoin\",\"addresses\":[{{\"index\":0,\"derivation_path\":\"m/44'/0'/0'/0/0\",\"address_type\":\"p2pkh\",\"address\":\"{}\"}}]}}",
+            mnemonic, addresses[0].derived.address
+        )
+    );
+    assert!(text_report(&mnemonic, &addresses).starts_with("mnemonic: erupt quit"));
+}
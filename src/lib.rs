@@ -0,0 +1,54 @@
+/*!
+ * Finds the derivation path of an address in a BIP32 hierarchical deterministic wallet.
+ *
+ * Seeds and keys are handled by `derivation`, public keys are encoded by `address`,
+ * the wallets of a mnemonic or a key are built by `wallet` and index ranges are
+ * scanned by `search`.
+ * The binary is a CLI over this library, which can be reused by other recovery tools:
+ *
+ * ```
+ * use bitcoin::network::constants::Network;
+ * use hdifinder::{derivation, models::{ChainKey, WalletConf}};
+ * use hdpath::{AccountHDPath, Purpose};
+ * use secp256k1::Secp256k1;
+ *
+ * let mnemonic = derivation::get_mnemonic("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb", None).unwrap();
+ * let master = derivation::get_master_key(mnemonic.to_seed(""), Network::Bitcoin).unwrap();
+ * let account = AccountHDPath::new(Purpose::Pubkey, 0, 0);
+ * let chain_key = ChainKey::Private(derivation::chain_key(&Secp256k1::new(), &master, &account, 0).unwrap());
+ * let wallet = WalletConf {
+ *     fingerprint: Some(master.fingerprint(&Secp256k1::new())),
+ *     ..WalletConf::new(chain_key, account, 0, Network::Bitcoin)
+ * };
+ *
+ * let found = hdifinder::find_address(&wallet, "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", 0..20).unwrap().unwrap();
+ * assert_eq!(found.derivation_path, "m/44'/0'/0'/0/5");
+ * assert_eq!(found.origin.as_deref(), Some("[67b4602d/44'/0'/0'/0/5]"));
+ * ```
+ */
+
+pub mod address;
+pub mod aezeed;
+pub mod bip21;
+pub mod bip322;
+pub mod cashaddr;
+pub mod coins;
+pub mod derivation;
+pub mod descriptor;
+pub mod electrum;
+pub mod error;
+pub mod ethereum;
+pub mod message;
+pub mod models;
+pub mod multisig;
+pub mod presets;
+pub mod progress;
+pub mod psbt;
+pub mod search;
+pub mod slip132;
+pub mod slip39;
+pub mod state;
+pub mod wallet;
+
+pub use error::HdiFinderError;
+pub use search::{find_address, scan_range, scan_range_with, Observer};
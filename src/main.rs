@@ -1,374 +1,2649 @@
+// Declared first for its printing macros to be seen by the other modules
+#[macro_use]
+mod output;
 
-mod models;
+mod bench;
+mod cli;
+mod commands;
+mod completions;
+mod config;
+mod derive;
+mod generate;
+mod prompt;
+mod scan;
+mod style;
 
+#[cfg(test)]
 use assert_cmd::Command;
-use bip39::{Error, Mnemonic};
-use bitcoin::{
-    network::constants::Network,
-    util::bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey},
-    Address,
+#[cfg(test)]
+use cli::{EXIT_FOUND, EXIT_NOT_FOUND};
+use hdifinder::{error, HdiFinderError};
+use std::{panic, process::exit, sync::Mutex};
+#[cfg(test)]
+use std::{fs, str::from_utf8};
+#[cfg(test)]
+use {
+    bitcoin::{
+        network::constants::Network,
+        util::{
+            misc::{signed_msg_hash, MessageSignature},
+            psbt::PartiallySignedTransaction,
+        },
+        Address, OutPoint, Transaction, TxIn, TxOut,
+    },
+    hdifinder::{address, bip322, derivation, descriptor, message, presets, psbt, state},
+    hdpath::AccountHDPath,
+    secp256k1::Secp256k1,
+    serde_json::Value,
+    std::{
+        str::FromStr,
+        thread,
+        time::{Duration, Instant},
+    },
 };
-use clap::{App, Arg, ArgMatches};
-use hdpath::{AccountHDPath, Purpose, StandardHDPath};
-use models::{SearchConfig, ExecutionConf, WalletConf };
-use rayon::prelude::*;
-use secp256k1::Secp256k1;
-use std::{process::exit, str::from_utf8};
-use std::{convert::TryInto, usize};
 
 /**
- * Retrieves a private key derived from a seed
+ * Runs the command, returns the exit code to report
  */
-fn get_private_key(seed: [u8; 64], hd_path: &StandardHDPath) -> ExtendedPrivKey {
+fn run() -> Result<i32, HdiFinderError> {
+    let matches = cli::matches()?;
+    if matches.get_flag("no-color") {
+        style::disable();
+    }
+    // -v is global, its count is kept by the subcommand when given after it
+    output::init_logging(
+        u64::from(matches.subcommand().map_or(&matches, |(_, subcommand)| subcommand).get_count("verbose")),
+        cli::search_matches(&matches).contains_id("trace-derivations"),
+    );
+
+    if let Some(code) = commands::run(&matches)? {
+        return Ok(code);
+    }
+    let matches = cli::search_matches(&matches);
+    let config = cli::load_config(matches)?;
+    scan::run(matches, config)
+}
+
+/**
+ * Message and location of the last panic, recorded by the panic hook
+ * so that it can be reported once the panic has been caught
+ */
+static LAST_PANIC: Mutex<Option<String>> = Mutex::new(None);
+
+fn main() {
+    // A panic, in a worker thread or not, is reported like any other error
+    // rather than with the default message and backtrace
+    panic::set_hook(Box::new(|info| {
+        let message = match info.payload().downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => match info.payload().downcast_ref::<String>() {
+                Some(message) => message.clone(),
+                None => "unknown panic".to_string(),
+            },
+        };
+        let location = info.location().map(|location| format!(" at {}", location)).unwrap_or_default();
+        if let Ok(mut last_panic) = LAST_PANIC.lock() {
+            *last_panic = Some(format!("{}{}", message, location));
+        }
+    }));
+
+    let code = match panic::catch_unwind(self::run) {
+        Ok(Ok(code)) => code,
+        Ok(Err(e)) => self::report(&e),
+        Err(_) => {
+            let reason = LAST_PANIC
+                .lock()
+                .ok()
+                .and_then(|last_panic| last_panic.clone())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            self::report(&HdiFinderError::Internal(reason))
+        }
+    };
+    exit(code);
+}
+
+/**
+ * Prints an error with its causes on a single line of stderr,
+ * returns the exit code matching the error
+ */
+fn report(error: &HdiFinderError) -> i32 {
+    output::error(&format!("{}. Exiting", error::cause_chain(error)));
+    cli::exit_code(error)
+}
+
+#[test]
+fn test_main() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let address_test: &str = "15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg(mnemonic_test);
+    cmd.arg(address_test);
+    let result = cmd.assert().success();
+    let stdout = &result.get_output().stdout;
+    let output = from_utf8(stdout).unwrap();
+    assert!(output.contains("15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3 "));
+    assert!(output.contains("15"));
+    assert!(output.contains("p2pkh"));
+    assert!(output.contains("m/44'/0'/0'/0/15"));
+    Ok(())
+}
+
+#[test]
+fn test_main_search_subcommand() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--format", "csv", "search", "--purposes", "44", "--end", "20"]);
+    cmd.arg(mnemonic_test).arg("15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3");
+    let result = cmd.assert().success();
+    let output = from_utf8(&result.get_output().stdout)?;
+    assert!(output.contains("15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3,"));
+    assert!(output.contains("m/44'/0'/0'/0/15"));
+    Ok(())
+}
+
+#[test]
+fn test_main_purposes() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("--purposes").arg("84");
+    cmd.arg(mnemonic_test);
+    cmd.arg("bc1q885ce0gv2zmuhke20jtvjrtvsr5dmtut6pxwtt");
+    let result = cmd.assert().success();
+    let stdout = &result.get_output().stdout;
+    let output = from_utf8(stdout).unwrap();
+    assert!(output.contains("p2wpkh"));
+    assert!(output.contains("m/84'/0'/0'/0/3"));
+    Ok(())
+}
+
+#[test]
+fn test_main_accounts() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("--accounts").arg("0-3");
+    cmd.arg("--end").arg("10");
+    cmd.arg("--chunksize").arg("5");
+    cmd.arg(mnemonic_test);
+    cmd.arg("1BMJBmhLLdPyVr3NzstjopExv4BtGJbLXe");
+    let result = cmd.assert().success();
+    let stdout = &result.get_output().stdout;
+    let output = from_utf8(stdout).unwrap();
+    assert!(output.contains("account 2 index 7"));
+    assert!(output.contains("m/44'/0'/2'/0/7"));
+    Ok(())
+}
+
+#[test]
+fn test_main_accounts_grid() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--json", "--purposes", "44", "--accounts", "0-3", "--end", "10", "--chunksize", "5"]);
+    cmd.arg(mnemonic_test);
+    cmd.arg("1BMJBmhLLdPyVr3NzstjopExv4BtGJbLXe");
+    let result = cmd.assert().success();
+    let output = from_utf8(&result.get_output().stdout).unwrap();
+    assert!(output.contains("\"index\":7,\"account\":2,\"derivation_path\":\"m/44'/0'/2'/0/7\""));
+
+    // The index is beyond the range in every account
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--json", "--purposes", "44", "--accounts", "0-3", "--end", "5"]);
+    cmd.arg(mnemonic_test);
+    cmd.arg("1BMJBmhLLdPyVr3NzstjopExv4BtGJbLXe");
+    let result = cmd.assert().code(2);
+    let output = from_utf8(&result.get_output().stdout).unwrap();
+    assert!(output.starts_with("{\"found\":false,\"scanned\":20,\"start\":0,\"end\":5,\"address_types\":[\"p2pkh\"],\"accounts\":4,"));
+    Ok(())
+}
+
+#[test]
+fn test_main_chains() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("--chains").arg("0,1");
+    cmd.arg("--end").arg("10");
+    cmd.arg("--chunksize").arg("5");
+    cmd.arg(mnemonic_test);
+    cmd.arg("1EukGhGZ3ai3zoG8mH86irCX18zTbthDZ5");
+    let result = cmd.assert().success();
+    let stdout = &result.get_output().stdout;
+    let output = from_utf8(stdout).unwrap();
+    assert!(output.contains("index 3 on the internal chain"));
+    assert!(output.contains("m/44'/0'/0'/1/3"));
+    Ok(())
+}
+
+#[test]
+fn test_main_ledger_live_preset() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("--preset").arg("ledger-live");
+    cmd.arg("--accounts").arg("0-2");
+    cmd.arg("--end").arg("10");
+    cmd.arg("--chunksize").arg("10");
+    cmd.arg(mnemonic_test);
+    cmd.arg("1BMJBmhLLdPyVr3NzstjopExv4BtGJbLXe");
+    let result = cmd.assert().success();
+    let stdout = &result.get_output().stdout;
+    let output = from_utf8(stdout).unwrap();
+    assert!(output.contains("derivation path: m/44'/0'/2'/0/7"));
+    Ok(())
+}
+
+#[test]
+fn test_main_unknown_preset() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("--preset").arg("unknown-wallet");
+    cmd.arg(mnemonic_test);
+    cmd.arg("15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3");
+    let result = cmd.assert().failure();
+    let stderr = &result.get_output().stderr;
+    let output = from_utf8(stderr).unwrap();
+    assert!(output.contains("Unknown preset unknown-wallet"));
+    assert!(output.contains("blockchain-info, bluewallet, electrum, exodus, ledger-eth, ledger-live, legacy-bip32, mycelium, samourai, trezor-suite"));
+    Ok(())
+}
+
+#[test]
+fn test_main_list_presets() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("--list-presets");
+    let result = cmd.assert().success();
+    let stdout = &result.get_output().stdout;
+    let output = from_utf8(stdout).unwrap();
+    for name in presets::preset_names() {
+        assert!(output.contains(name));
+    }
+    assert!(output.contains("m/84'/0'/{0-9}'/0/i (p2wpkh)"));
+    assert!(output.contains("m/0'/{0,1}/i (p2pkh, p2pkh-uncompressed)"));
+    assert!(output.contains("m/84'/0'/2147483646'/{0,1}/i (p2wpkh, postmix)"));
+    Ok(())
+}
+
+#[test]
+fn test_main_samourai_preset() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--preset", "samourai", "--end", "10", "--format", "json"]);
+    cmd.arg(mnemonic_test).arg("bc1qaraknwaeejyhjdg327sltp0yvl5t9hhlq4ws9y");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.contains(
+        "\"derivation_path\":\"m/84'/0'/2147483646'/0/3\",\"fingerprint\":\"67b4602d\",\"origin\":\"[67b4602d/84'/0'/2147483646'/0/3]\",\"address_type\":\"p2wpkh\",\"network\":\"bitcoin\",\"account_label\":\"postmix\""
+    ));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--preset", "samourai", "--end", "10"]);
+    cmd.arg(mnemonic_test).arg("bc1qj5dx6h34y87fl56fc6ydpr4h8jejgttxdshl54");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.contains(
+        "found at account 2147483644 index 2 on the internal chain. address type: p2wpkh. derivation path: m/84'/0'/2147483644'/1/2. key origin: [67b4602d/84'/0'/2147483644'/1/2]. account label: bad bank"
+    ));
+
+    // The deposit account is scanned along with them, unlabelled
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--preset", "samourai", "--end", "10", "--format", "csv"]);
+    cmd.arg(mnemonic_test).arg("bc1q885ce0gv2zmuhke20jtvjrtvsr5dmtut6pxwtt");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.starts_with(
+        "index,derivation_path,address,type,target,fingerprint,origin,account_label\n\
+         3,m/84'/0'/0'/0/3,bc1q885ce0gv2zmuhke20jtvjrtvsr5dmtut6pxwtt,p2wpkh,bc1q885ce0gv2zmuhke20jtvjrtvsr5dmtut6pxwtt,67b4602d,[67b4602d/84'/0'/0'/0/3],\n"
+    ));
+
+    // The accounts given replace the ones of the preset, named ones included
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--preset", "samourai", "--end", "10", "--accounts", "0"]);
+    cmd.arg(mnemonic_test).arg("bc1qaraknwaeejyhjdg327sltp0yvl5t9hhlq4ws9y");
+    cmd.assert().code(cli::EXIT_NOT_FOUND);
+    Ok(())
+}
+
+#[test]
+fn test_main_legacy_presets() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    // The uncompressed key of m/0/5
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--preset", "legacy-bip32", "--end", "10"]);
+    cmd.arg(mnemonic_test).arg("1D3zMEyEViSJmEynX6XMtFUZSHZNogv9xy");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.contains("derivation path: m/0/5. key origin: [67b4602d/0/5]"));
+
+    // The change chain of the account m/0'
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--preset", "blockchain-info", "--end", "10", "--format", "csv"]);
+    cmd.arg(mnemonic_test).arg("1DwLWk1VZwxN5A8b1pWaDqVYS7fEVd2niA");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.contains("\n5,m/0'/1/5,1DwLWk1VZwxN5A8b1pWaDqVYS7fEVd2niA,p2pkh,"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--preset", "blockchain-info", "--end", "10", "--dry-run"]);
+    cmd.arg(mnemonic_test).arg("1DwLWk1VZwxN5A8b1pWaDqVYS7fEVd2niA");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.contains("    m/0'/0/i (p2pkh)\n    m/0'/0/i (p2pkh-uncompressed)\n    m/0'/1/i (p2pkh)\n"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--preset", "legacy-bip32", "--type", "p2wpkh"]);
+    cmd.arg(mnemonic_test).arg("1D3zMEyEViSJmEynX6XMtFUZSHZNogv9xy");
+    cmd.assert().code(1).stderr("--preset legacy-bip32 only derives p2pkh addresses, its paths having no purpose level. Exiting\n");
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--preset", "legacy-bip32", "--xpub", "xpub6CDTUkh21C4fjPY8KXZuJwafuUaMvTEGj9skceNq2dFVdu19JMVwioP1iZK5urL11xzypzwt9yAKjZkm7CWjxcKUHLGpCbq2YohwPij3nbm"]);
+    cmd.arg("1D3zMEyEViSJmEynX6XMtFUZSHZNogv9xy");
+    cmd.assert().code(1).stderr("The legacy presets derive their chains from a BIP32 master private key. Exiting\n");
+    Ok(())
+}
+
+#[test]
+fn test_main_multiple_addresses() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("--purposes").arg("44");
+    cmd.arg("--end").arg("10");
+    cmd.arg("--chunksize").arg("10");
+    cmd.arg(mnemonic_test);
+    cmd.arg("1BMJBmhLLdPyVr3NzstjopExv4BtGJbLXe");
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    let stdout = &result.get_output().stdout;
+    let output = from_utf8(stdout).unwrap();
+    assert!(output.contains("address 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK found at account 0 index 5"));
+    Ok(())
+}
+
+#[test]
+fn test_main_addresses_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let addresses_file = std::env::temp_dir().join("hdifinder_test_addresses_file.txt");
+    fs::write(
+        &addresses_file,
+        "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK\nnot-an-address\n15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3\n",
+    )?;
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("--purposes").arg("44");
+    cmd.arg("--end").arg("20");
+    cmd.arg("--chunksize").arg("10");
+    cmd.arg("--addresses-file").arg(&addresses_file);
+    cmd.arg(mnemonic_test);
+    let result = cmd.assert().success();
+    let stdout = &result.get_output().stdout;
+    let output = from_utf8(stdout).unwrap();
+    assert!(output.contains("line 2"));
+    assert!(output.contains("address 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK found at account 0 index 5"));
+    assert!(output.contains("address 15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3 found at account 0 index 15"));
+
+    fs::remove_file(&addresses_file)?;
+    Ok(())
+}
+
+#[test]
+fn test_main_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("--json");
+    cmd.arg("--purposes").arg("84");
+    cmd.arg("--end").arg("10");
+    cmd.arg("--chunksize").arg("10");
+    cmd.arg(mnemonic_test);
+    cmd.arg("bc1q885ce0gv2zmuhke20jtvjrtvsr5dmtut6pxwtt");
+    let result = cmd.assert().success();
+    let output: Value = serde_json::from_slice(&result.get_output().stdout)?;
+    assert_eq!(output["found"], true);
+    assert_eq!(output["address"], "bc1q885ce0gv2zmuhke20jtvjrtvsr5dmtut6pxwtt");
+    assert_eq!(output["index"], 3);
+    assert_eq!(output["account"], 0);
+    assert_eq!(output["derivation_path"], "m/84'/0'/0'/0/3");
+    assert_eq!(output["fingerprint"], "67b4602d");
+    assert_eq!(output["origin"], "[67b4602d/84'/0'/0'/0/3]");
+    assert_eq!(output["address_type"], "p2wpkh");
+    assert_eq!(output["network"], "bitcoin");
+    assert!(output["stats"]["keys"].is_u64());
+    assert_eq!(output["stats"]["chunks"], 1);
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("--json");
+    cmd.arg("--purposes").arg("44,84");
+    cmd.arg("--end").arg("10");
+    cmd.arg("--chunksize").arg("5");
+    cmd.arg(mnemonic_test);
+    cmd.arg("15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3");
+    let result = cmd.assert().code(2);
+    let output: Value = serde_json::from_slice(&result.get_output().stdout)?;
+    assert_eq!(output["found"], false);
+    assert_eq!(output["scanned"], 20);
+    assert_eq!(output["start"], 0);
+    assert_eq!(output["end"], 10);
+    assert_eq!(output["address_types"], serde_json::json!(["p2pkh", "p2wpkh"]));
+    assert!(output["elapsed_ms"].is_u64());
+    let stderr = from_utf8(&result.get_output().stderr).unwrap();
+    assert!(stderr.contains("address not found after scanning 20 keys (indexes 0 to 9, p2pkh, p2wpkh addresses)"));
+    Ok(())
+}
+
+#[test]
+fn test_main_json_diagnostics() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("--json");
+    cmd.arg("--network").arg("testnet");
+    cmd.arg(mnemonic_test);
+    cmd.arg("15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3");
+    let result = cmd.assert().failure();
+    assert!(result.get_output().stdout.is_empty());
+    let stderr = from_utf8(&result.get_output().stderr).unwrap();
+    assert!(stderr.contains("is a bitcoin mainnet address but the search network is testnet"));
+    Ok(())
+}
+
+#[test]
+fn test_main_csv() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("--format").arg("csv");
+    cmd.arg("--all");
+    cmd.arg("--purposes").arg("44");
+    cmd.arg("--end").arg("20");
+    cmd.arg("--chunksize").arg("20");
+    cmd.arg(mnemonic_test);
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    cmd.arg("15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3");
+    let result = cmd.assert().success();
+    let stdout = &result.get_output().stdout;
+    let output = from_utf8(stdout).unwrap();
+    assert_eq!(
+        output,
+        "index,derivation_path,address,type,target,fingerprint,origin\n\
+         5,m/44'/0'/0'/0/5,14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK,p2pkh,14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK,67b4602d,[67b4602d/44'/0'/0'/0/5]\n\
+         15,m/44'/0'/0'/0/15,15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3,p2pkh,15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3,67b4602d,[67b4602d/44'/0'/0'/0/15]\n"
+    );
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("--format").arg("csv");
+    cmd.arg("--purposes").arg("44");
+    cmd.arg("--end").arg("5");
+    cmd.arg("--chunksize").arg("5");
+    cmd.arg(mnemonic_test);
+    cmd.arg("15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3");
+    let result = cmd.assert().code(2);
+    let stdout = &result.get_output().stdout;
+    let output = from_utf8(stdout).unwrap();
+    assert_eq!(output, "index,derivation_path,address,type,target,fingerprint,origin\n");
+    Ok(())
+}
+
+#[test]
+fn test_main_ndjson() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("--format").arg("ndjson");
+    cmd.arg("--all");
+    cmd.arg("--purposes").arg("44,84");
+    cmd.arg("--end").arg("20");
+    cmd.arg("--chunksize").arg("5");
+    cmd.arg(mnemonic_test);
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    cmd.arg("15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3");
+    cmd.arg("bc1q885ce0gv2zmuhke20jtvjrtvsr5dmtut6pxwtt");
+    let result = cmd.assert().success();
+    let stdout = &result.get_output().stdout;
+    let output = from_utf8(stdout).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+
+    assert_eq!(lines.len(), 4);
+    // Every line is a JSON document of its own
+    let documents = lines.iter().map(|line| serde_json::from_str::<Value>(line)).collect::<Result<Vec<Value>, _>>()?;
+    for address in ["14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", "15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3", "bc1q885ce0gv2zmuhke20jtvjrtvsr5dmtut6pxwtt"] {
+        assert_eq!(documents[..3].iter().filter(|document| document["address"] == address).count(), 1);
+    }
+    assert_eq!(documents[3]["summary"], true);
+    assert_eq!(documents[3]["found"], 3);
+    assert_eq!(documents[3]["scanned"], 40);
+    assert!(documents[3]["elapsed_ms"].is_u64());
+    Ok(())
+}
+
+#[test]
+fn test_main_closed_stdout() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    // The reader of the matches goes away before the first one, as `| head -1` does
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("hdifinder"))
+        .args(["--format", "ndjson", "--all", "--purposes", "44,84", "--end", "20"])
+        .arg(mnemonic_test)
+        .args(["14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", "15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3", "bc1q885ce0gv2zmuhke20jtvjrtvsr5dmtut6pxwtt"])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()?;
+    drop(child.stdout.take());
+    let output = child.wait_with_output()?;
+    let stderr = from_utf8(&output.stderr)?;
+    assert!(!stderr.contains("Internal error"), "{}", stderr);
+    assert_eq!(output.status.code(), Some(cli::EXIT_FOUND));
+    Ok(())
+}
+
+#[test]
+fn test_main_quiet() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("-q");
+    cmd.arg("--purposes").arg("44");
+    cmd.arg("--end").arg("10");
+    cmd.arg("--chunksize").arg("10");
+    cmd.arg(mnemonic_test);
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    assert_eq!(result.get_output().stdout, b"5\n");
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("--quiet").arg("--print").arg("path");
+    cmd.arg("--purposes").arg("44");
+    cmd.arg("--end").arg("10");
+    cmd.arg("--chunksize").arg("10");
+    cmd.arg(mnemonic_test);
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    assert_eq!(result.get_output().stdout, b"m/44'/0'/0'/0/5\n");
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("-q");
+    cmd.arg("--purposes").arg("44");
+    cmd.arg("--end").arg("5");
+    cmd.arg("--chunksize").arg("5");
+    cmd.arg(mnemonic_test);
+    cmd.arg("15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3");
+    let result = cmd.assert().code(2);
+    assert!(result.get_output().stdout.is_empty());
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("-q");
+    cmd.arg("--network").arg("testnet");
+    cmd.arg(mnemonic_test);
+    cmd.arg("15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3");
+    let result = cmd.assert().failure();
+    assert!(result.get_output().stdout.is_empty());
+    assert!(!result.get_output().stderr.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_main_exit_codes() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+
+    // found
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--purposes", "44", "--end", "10", "--chunksize", "10"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    cmd.assert().code(0);
+
+    // usage error
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--purposes", "12"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    cmd.assert().code(1);
+
+    // not found
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--purposes", "44", "--end", "5", "--chunksize", "5"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    cmd.assert().code(2);
+
+    // invalid mnemonic
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("erupt quit sphere").arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(3);
+    let stderr = from_utf8(&result.get_output().stderr).unwrap();
+    assert!(stderr.contains("Invalid mnemonic"));
+
+    // invalid address
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--network", "testnet"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    cmd.assert().code(4);
+    Ok(())
+}
+
+#[test]
+fn test_main_start() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+
+    // the reported index is the absolute derivation index
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["-q", "--purposes", "44", "--start", "4", "--end", "8", "--chunksize", "3"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    assert_eq!(from_utf8(&result.get_output().stdout).unwrap(), "5\n");
+
+    // indexes before the start are not scanned
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["-q", "--purposes", "44", "--start", "6", "--end", "10", "--chunksize", "3"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(2);
+    let stderr = from_utf8(&result.get_output().stderr).unwrap();
+    assert!(stderr.contains("after scanning 4 keys (indexes 6 to 9, p2pkh addresses)"));
+    Ok(())
+}
+
+#[test]
+fn test_main_invalid_range() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--start", "100", "--end", "50"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(1);
+    assert!(result.get_output().stdout.is_empty());
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "Invalid --start 100. The start index must be lower than the end index (--end 50). Exiting\n"
+    );
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "3000000000"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(1);
+    assert!(result.get_output().stdout.is_empty());
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "Invalid --end 3000000000. Non-hardened BIP32 indexes can't exceed 2147483647. Exiting\n"
+    );
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--json", "--chunksize", "0"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(1);
+    assert!(result.get_output().stdout.is_empty());
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "Invalid --chunksize 0. The chunksize must be at least 1. Exiting\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_main_error_messages() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+
+    // errors are reported on a single line of stderr along with their cause
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower bananaa barely indoor crush grid slide correct scatter deal tenant verb");
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(3);
+    assert!(result.get_output().stdout.is_empty());
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "Invalid mnemonic: word 14 'bananaa' is not in the BIP39 English wordlist. Exiting\n"
+    );
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--network", "testnet"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(4);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "Target 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK is a bitcoin mainnet address but the search network is testnet; pass --network mainnet. Exiting\n"
+    );
+
+    // A mistyped address is rejected before scanning
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgL");
+    let result = cmd.assert().code(4);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "Invalid address 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgL: base58: base58ck checksum 0x911bd685 does not match expected 0x901bd685. Exiting\n"
+    );
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg(mnemonic_test).arg("bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtK5u60h0y");
+    let result = cmd.assert().code(4);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "Invalid address bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtK5u60h0y: bech32: mixed-case strings not allowed. Exiting\n"
+    );
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--start", "ten"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(1);
+    assert!(from_utf8(&result.get_output().stderr)?.starts_with("error: invalid value 'ten' for '--start <start>': invalid digit found in string\n"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--addresses-file", "/nonexistent/targets.txt"]);
+    cmd.arg(mnemonic_test);
+    let result = cmd.assert().code(1);
+    let stderr = from_utf8(&result.get_output().stderr).unwrap();
+    assert!(stderr.starts_with("Unable to access /nonexistent/targets.txt: "));
+    assert_eq!(stderr.lines().count(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_main_normalized_mnemonic() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--json", "--purposes", "44", "--end", "10", "--chunksize", "10"]);
+    cmd.arg(format!("  {}\n", mnemonic_test.replacen("erupt", "Erupt", 1))).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout).unwrap().contains("\"index\":5"));
+    assert!(from_utf8(&result.get_output().stderr).unwrap().starts_with("The mnemonic has been normalized"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--json", "--purposes", "44", "--end", "10", "--chunksize", "10"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    assert!(result.get_output().stderr.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_main_word_counts() -> Result<(), Box<dyn std::error::Error>> {
+    // BIP39 test vector, first BIP84 address
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["-v", "--purposes", "84", "--end", "10", "--chunksize", "10"]);
+    cmd.arg("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about");
+    cmd.arg("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+    let result = cmd.assert().success();
+    let output = from_utf8(&result.get_output().stdout).unwrap();
+    assert!(output.starts_with("Mnemonic read from the command line\nUsing a 12 words English mnemonic\n"));
+    assert!(output.contains("m/84'/0'/0'/0/0"));
+
+    // unquoted, the words are gathered into the phrase
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--purposes", "84", "--end", "10", "--chunksize", "10"]);
+    cmd.args(["abandon"; 11]).arg("about");
+    cmd.arg("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.contains("m/84'/0'/0'/0/0"));
+
+    // a word short, on either side of the address
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu").args(["abandon"; 10]).arg("about");
+    cmd.assert().code(3).stderr(
+        "Invalid mnemonic: the phrase has 11 words, a mnemonic has 12, 15, 18, 21 or 24 words. Exiting\n",
+    );
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("abandon").arg("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+    let result = cmd.assert().code(3);
+    let stderr = from_utf8(&result.get_output().stderr).unwrap();
+    assert!(stderr.starts_with("Invalid mnemonic: a single word has been received"));
+    Ok(())
+}
+
+#[test]
+fn test_main_language() -> Result<(), Box<dyn std::error::Error>> {
+    let french = "abaisser abaisser abaisser abaisser abaisser abaisser abaisser abaisser abaisser abaisser abaisser abeille";
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--dry-run", "--end", "10"]);
+    cmd.arg(french).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout).unwrap().starts_with("mnemonic: 12 words (French)\n"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--language", "english"]);
+    cmd.arg(french).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(3);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "Invalid mnemonic: word 1 'abaisser' is not in the BIP39 English wordlist. Exiting\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_main_mnemonic_stdin() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+
+    // the mnemonic is left out of the command line and piped instead
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["-q", "--purposes", "44", "--end", "10", "--chunksize", "10"]);
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    cmd.write_stdin(format!("{}\n", mnemonic_test));
+    let result = cmd.assert().success();
+    assert_eq!(from_utf8(&result.get_output().stdout).unwrap(), "5\n");
+    // the line break ending the input doesn't count as a normalization
+    assert!(result.get_output().stderr.is_empty());
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    cmd.write_stdin("");
+    let result = cmd.assert().code(1);
+    assert_eq!(from_utf8(&result.get_output().stderr).unwrap(), "No mnemonic found. Exiting\n");
+    Ok(())
+}
+
+#[test]
+fn test_main_mnemonic_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mnemonic_file = std::env::temp_dir().join(format!("hdifinder-test-mnemonic-{}", std::process::id()));
+    let mnemonic_file = mnemonic_file.to_str().unwrap();
+    fs::write(mnemonic_file, format!("  {}\n", mnemonic_test))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(mnemonic_file, fs::Permissions::from_mode(0o600))?;
+    }
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["-q", "--purposes", "44", "--end", "10", "--chunksize", "10", "--mnemonic-file", mnemonic_file]);
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    assert_eq!(from_utf8(&result.get_output().stdout).unwrap(), "5\n");
+    assert!(result.get_output().stderr.is_empty());
+
+    // a mnemonic can't be given twice
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--mnemonic-file", mnemonic_file]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(1);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "--mnemonic-file can't be used along with a mnemonic argument. Exiting\n"
+    );
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(mnemonic_file, fs::Permissions::from_mode(0o644))?;
+        let mut cmd = Command::cargo_bin("hdifinder")?;
+        cmd.args(["-q", "--purposes", "44", "--end", "10", "--chunksize", "10", "--mnemonic-file", mnemonic_file]);
+        cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+        let result = cmd.assert().success();
+        assert!(from_utf8(&result.get_output().stderr).unwrap().contains("can be read by other users (mode 644)"));
+    }
+
+    fs::remove_file(mnemonic_file)?;
+    Ok(())
+}
+
+#[test]
+fn test_main_environment() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["-v", "--json", "--purposes", "44", "--end", "10", "--chunksize", "10"]);
+    cmd.env("HDIFINDER_MNEMONIC", mnemonic_test);
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    let stderr = from_utf8(&result.get_output().stderr).unwrap();
+    assert!(stderr.contains("Mnemonic read from the HDIFINDER_MNEMONIC environment variable\n"));
+    assert!(!stderr.contains(mnemonic_test));
+
+    // the passphrase changes the wallet, the address isn't part of it anymore
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["-v", "--json", "--purposes", "44", "--end", "10", "--chunksize", "10"]);
+    cmd.env("HDIFINDER_MNEMONIC", mnemonic_test).env("HDIFINDER_PASSPHRASE", "secret");
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(2);
+    let stderr = from_utf8(&result.get_output().stderr).unwrap();
+    assert!(stderr.contains("Passphrase read from the HDIFINDER_PASSPHRASE environment variable\n"));
+    assert!(!stderr.contains("secret"));
+
+    // the command line takes precedence over the environment
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["-v", "--json", "--purposes", "44", "--end", "10", "--chunksize", "10", "--passphrase", ""]);
+    cmd.env("HDIFINDER_MNEMONIC", "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about");
+    cmd.env("HDIFINDER_PASSPHRASE", "secret");
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    let stderr = from_utf8(&result.get_output().stderr).unwrap();
+    assert!(stderr.contains("Mnemonic read from the command line\n"));
+    assert!(!stderr.contains("Passphrase read from"));
+    Ok(())
+}
+
+#[test]
+fn test_main_ask_passphrase() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let secp = Secp256k1::new();
+    let seed = derivation::get_mnemonic(mnemonic_test, None)?.to_seed("secret");
+    let master = derivation::get_master_key(seed, Network::Bitcoin)?;
+    let account = AccountHDPath::new(hdpath::Purpose::Pubkey, 0, 0);
+    let key = derivation::index_key(&secp, &derivation::chain_key(&secp, &master, &account, 0)?, 2)?;
+    let address = address::build_address(&secp, derivation::get_public_key(&secp, key), &hdpath::Purpose::Pubkey, Network::Bitcoin)?;
+
+    // the entries differ once, then match
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["-q", "--ask-passphrase", "--purposes", "44", "--end", "10", "--chunksize", "10"]);
+    cmd.arg(mnemonic_test).arg(address.to_string());
+    cmd.write_stdin("secret\nsecrte\nsecret\nsecret\n");
+    let result = cmd.assert().success();
+    assert_eq!(from_utf8(&result.get_output().stdout).unwrap(), "2\n");
+    assert_eq!(from_utf8(&result.get_output().stderr).unwrap(), "The passphrases don't match\n");
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--ask-passphrase"]);
+    cmd.arg(mnemonic_test).arg(address.to_string());
+    cmd.write_stdin("a\nb\na\nb\na\nb\n");
+    let result = cmd.assert().code(1);
+    assert!(from_utf8(&result.get_output().stderr).unwrap().ends_with("The passphrases didn't match after 3 attempts. Exiting\n"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--ask-passphrase", "-p", "secret"]);
+    cmd.arg(mnemonic_test).arg(address.to_string());
+    let result = cmd.assert().code(1);
+    let error = from_utf8(&result.get_output().stderr)?.lines().next().unwrap_or_default();
+    assert!(error.contains("cannot be used with") && error.contains("'--passphrase") && error.contains("'--ask-passphrase"));
+    Ok(())
+}
+
+#[test]
+fn test_main_passphrase_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let passphrase_file = std::env::temp_dir().join(format!("hdifinder-test-passphrase-{}", std::process::id()));
+    let passphrase_file = passphrase_file.to_str().unwrap();
+    let secp = Secp256k1::new();
+    let expected_address = |passphrase: &str| -> Result<String, HdiFinderError> {
+        let seed = derivation::get_mnemonic(mnemonic_test, None)?.to_seed(passphrase);
+        let master = derivation::get_master_key(seed, Network::Bitcoin)?;
+        let account = AccountHDPath::new(hdpath::Purpose::Pubkey, 0, 0);
+        let key = derivation::index_key(&secp, &derivation::chain_key(&secp, &master, &account, 0)?, 3)?;
+        let public_key = derivation::get_public_key(&secp, key);
+        Ok(address::build_address(&secp, public_key, &hdpath::Purpose::Pubkey, Network::Bitcoin)?.to_string())
+    };
+
+    // the spaces are part of the passphrase, the line break ending the file isn't
+    fs::write(passphrase_file, "  my  passphrase \n")?;
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["-q", "--purposes", "44", "--end", "10", "--chunksize", "10", "--passphrase-file", passphrase_file]);
+    cmd.arg(mnemonic_test).arg(expected_address("  my  passphrase ")?);
+    let result = cmd.assert().success();
+    assert_eq!(from_utf8(&result.get_output().stdout).unwrap(), "3\n");
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["-q", "--purposes", "44", "--end", "10", "--chunksize", "10", "--passphrase-file", passphrase_file]);
+    cmd.arg("--passphrase-keep-trailing-newline");
+    cmd.arg(mnemonic_test).arg(expected_address("  my  passphrase \n")?);
+    let result = cmd.assert().success();
+    assert_eq!(from_utf8(&result.get_output().stdout).unwrap(), "3\n");
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["-p", "secret", "--passphrase-file", passphrase_file]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(1);
+    let error = from_utf8(&result.get_output().stderr)?.lines().next().unwrap_or_default();
+    assert!(error.contains("cannot be used with") && error.contains("'--passphrase") && error.contains("'--passphrase-file"));
+
+    fs::remove_file(passphrase_file)?;
+    Ok(())
+}
+
+#[test]
+fn test_main_passphrase_list() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let passphrase_list = std::env::temp_dir().join(format!("hdifinder-test-passphrase-list-{}", std::process::id()));
+    let passphrase_list = passphrase_list.to_str().unwrap();
     let secp = Secp256k1::new();
-    ExtendedPrivKey::new_master(Network::Bitcoin, &seed)
-        // we convert HD Path to bitcoin lib format (DerivationPath)
-        .and_then(|k| k.derive_priv(&secp, &DerivationPath::from(hd_path)))
+    let seed = derivation::get_mnemonic(mnemonic_test, None)?.to_seed("Summer 2017");
+    let master = derivation::get_master_key(seed, Network::Bitcoin)?;
+    let account = AccountHDPath::new(hdpath::Purpose::Pubkey, 0, 0);
+    let key = derivation::index_key(&secp, &derivation::chain_key(&secp, &master, &account, 0)?, 2)?;
+    let public_key = derivation::get_public_key(&secp, key);
+    let expected_address = address::build_address(&secp, public_key, &hdpath::Purpose::Pubkey, Network::Bitcoin)?.to_string();
+
+    fs::write(passphrase_list, "summer2017\nSummer2017\n\nSummer 2017\nsummer 2017\n")?;
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--purposes", "44", "--end", "5", "--passphrase-list", passphrase_list]);
+    cmd.arg(mnemonic_test).arg(&expected_address);
+    let result = cmd.assert().success();
+    assert_eq!(
+        from_utf8(&result.get_output().stdout).unwrap().lines().next().unwrap(),
+        format!(
+            "address {} found at account 0 index 2 on the external chain. address type: p2pkh. derivation path: m/44'/0'/0'/0/2. \
+             key origin: [{}/44'/0'/0'/0/2]. passphrase: \"Summer 2017\" (line 4)",
+            expected_address,
+            master.fingerprint(&secp)
+        )
+    );
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--json", "--redact", "--purposes", "44", "--end", "5", "--passphrase-list", passphrase_list]);
+    cmd.arg(mnemonic_test).arg(&expected_address);
+    let result = cmd.assert().success();
+    let output = from_utf8(&result.get_output().stdout).unwrap();
+    assert!(output.contains(&format!(
+        "\"index\":2,\"account\":0,\"derivation_path\":\"m/44'/0'/0'/0/2\",\"fingerprint\":\"{0}\",\"origin\":\"[{0}/44'/0'/0'/0/2]\",\"address_type\":\"p2pkh\",\"network\":\"bitcoin\",\"passphrase_line\":4,",
+        master.fingerprint(&secp)
+    )));
+    assert!(!output.contains("Summer"));
+
+    // every candidate is tried on every index
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--json", "--purposes", "44", "--end", "5", "--passphrase-list", passphrase_list]);
+    cmd.arg(mnemonic_test).arg("1BMJBmhLLdPyVr3NzstjopExv4BtGJbLXe");
+    let result = cmd.assert().code(2);
+    assert!(from_utf8(&result.get_output().stdout).unwrap().starts_with(
+        "{\"found\":false,\"scanned\":25,\"start\":0,\"end\":5,\"address_types\":[\"p2pkh\"],\"candidates\":5,"
+    ));
+    assert!(from_utf8(&result.get_output().stderr).unwrap().contains(
+        "address not found after scanning 25 keys (5 passphrase candidates × indexes 0 to 4, p2pkh addresses)"
+    ));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["-p", "secret", "--passphrase-list", passphrase_list]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(1);
+    let error = from_utf8(&result.get_output().stderr)?.lines().next().unwrap_or_default();
+    assert!(error.contains("cannot be used with") && error.contains("'--passphrase") && error.contains("'--passphrase-list"));
+
+    fs::remove_file(passphrase_list)?;
+    Ok(())
+}
+
+#[test]
+fn test_main_fix_checksum() -> Result<(), Box<dyn std::error::Error>> {
+    // the last word "verb" mistyped as "very"
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant very";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(3);
+    let stderr = from_utf8(&result.get_output().stderr).unwrap();
+    let words = stderr.lines().next().unwrap();
+    assert!(words.starts_with("Last words giving a valid checksum: "));
+    assert!(words.split(' ').any(|word| word.trim_end_matches('.') == "verb"));
+    assert!(words.ends_with(". Try them all with --fix-checksum"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--fix-checksum", "--purposes", "44", "--end", "10"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    let stdout = from_utf8(&result.get_output().stdout).unwrap();
+    assert!(stdout.starts_with(
+        "The checksum doesn't match, trying the 8 last words giving a valid one\n\
+         address 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK found at account 0 index 5 on the external chain. \
+         address type: p2pkh. derivation path: m/44'/0'/0'/0/5. key origin: [67b4602d/44'/0'/0'/0/5]. mnemonic last word: verb\n"
+    ));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--json", "--fix-checksum", "--purposes", "44", "--end", "10"]);
+    cmd.arg(mnemonic_test).arg("1BMJBmhLLdPyVr3NzstjopExv4BtGJbLXe");
+    let result = cmd.assert().code(2);
+    assert!(from_utf8(&result.get_output().stdout).unwrap().starts_with(
+        "{\"found\":false,\"scanned\":80,\"start\":0,\"end\":10,\"address_types\":[\"p2pkh\"],\"mnemonics\":8,"
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_main_try_swaps() -> Result<(), Box<dyn std::error::Error>> {
+    // "grid" and "slide" written in the wrong order
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush slide grid correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--purposes", "44", "--end", "10", "--try-swaps"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    let stdout = from_utf8(&result.get_output().stdout).unwrap();
+    assert!(stdout.contains(
+        "address 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK found at account 0 index 5 on the external chain. \
+         address type: p2pkh. derivation path: m/44'/0'/0'/0/5. key origin: [67b4602d/44'/0'/0'/0/5]. mnemonic words 18 and 19 swapped\n"
+    ));
+
+    // "quit" and "tenant" are only found swapped when trying every pair
+    let mnemonic_test: &str = "erupt tenant sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal quit verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--json", "--purposes", "44", "--end", "10", "--try-swaps=all"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout).unwrap().contains("\"derivation_path\":\"m/44'/0'/0'/0/5\",\"fingerprint\":\"67b4602d\",\"origin\":\"[67b4602d/44'/0'/0'/0/5]\",\"address_type\":\"p2pkh\",\"network\":\"bitcoin\",\"swapped_words\":[2,23],"));
+    assert!(from_utf8(&result.get_output().stderr).unwrap().starts_with("Warning: --try-swaps=all swaps every pair of words, 276 of them for 24 words."));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--try-swaps", "--state-file", "state.txt"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(1);
+    let error = from_utf8(&result.get_output().stderr)?.lines().next().unwrap_or_default();
+    assert!(error.contains("cannot be used with") && error.contains("'--state-file") && error.contains("'--try-swaps"));
+    Ok(())
+}
+
+#[test]
+fn test_main_mnemonics_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonics_file = std::env::temp_dir().join(format!("hdifinder-test-mnemonics-file-{}", std::process::id()));
+    let mnemonics_file = mnemonics_file.to_str().unwrap();
+    fs::write(
+        mnemonics_file,
+        "# old wallets\n\
+         abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about\n\
+         erupt quit sphere taxi\n\
+         erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb # paper backup\n",
+    )?;
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--purposes", "44", "--end", "10", "--mnemonics-file", mnemonics_file]);
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    let stdout = from_utf8(&result.get_output().stdout).unwrap();
+    assert!(stdout.contains(
+        "address 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK found at account 0 index 5 on the external chain. \
+         address type: p2pkh. derivation path: m/44'/0'/0'/0/5. key origin: [67b4602d/44'/0'/0'/0/5]. mnemonic from line 4\n"
+    ));
+    assert!(stdout.contains("Skipping line 3 of "));
+    assert!(stdout.contains("    line 2: fully scanned\n    line 3: skipped, invalid mnemonic"));
+    assert!(stdout.contains("    line 4: holds a match\n"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--mnemonics-file", mnemonics_file, "--fix-checksum"]);
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(1);
+    let error = from_utf8(&result.get_output().stderr)?.lines().next().unwrap_or_default();
+    assert!(error.contains("cannot be used with") && error.contains("'--mnemonics-file") && error.contains("'--fix-checksum"));
+    fs::remove_file(mnemonics_file)?;
+    Ok(())
+}
+
+#[test]
+fn test_main_seed() -> Result<(), Box<dyn std::error::Error>> {
+    // seed of the test mnemonic, without a passphrase
+    let seed_test: &str = "5c81d856a4694c06338b3286b83e603d7a3c11a0720abfe6ad3d6e58ab9ff25e3649400e7cb39f1ddfdde422453fe5c4d48f58322dca0b6fe4ef5c2f4685a576";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--purposes", "44", "--end", "10", "--seed", seed_test]);
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout).unwrap().starts_with(
+        "address 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK found at account 0 index 5 on the external chain. \
+         address type: p2pkh. derivation path: m/44'/0'/0'/0/5. key origin: [67b4602d/44'/0'/0'/0/5]\n"
+    ));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--seed", seed_test, "--passphrase", "Summer 2017"]);
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(1);
+    let error = from_utf8(&result.get_output().stderr)?.lines().next().unwrap_or_default();
+    assert!(error.contains("cannot be used with") && error.contains("'--seed") && error.contains("'--passphrase"));
+
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--seed", seed_test]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(1);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "--seed can't be used along with a mnemonic argument. Exiting\n"
+    );
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--seed", &seed_test[..30]]);
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(1);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "Invalid seed: it is 15 bytes long, a seed has 16 to 64 bytes (32 to 128 hexadecimal characters). Exiting\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_main_entropy() -> Result<(), Box<dyn std::error::Error>> {
+    // entropy of the test mnemonic
+    let entropy_test: &str = "4cf5fb45ef3056713d7c718148fe1ae104916044a398350ccf2e184c067077c7";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--purposes", "44", "--end", "10", "--entropy", entropy_test]);
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout).unwrap().starts_with(
+        "address 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK found at account 0 index 5 on the external chain. \
+         address type: p2pkh. derivation path: m/44'/0'/0'/0/5. key origin: [67b4602d/44'/0'/0'/0/5]\n"
+    ));
+
+    // the passphrase is applied to the mnemonic built
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--json", "--show-mnemonic", "--purposes", "44", "--end", "10", "--passphrase", "Summer 2017", "--entropy", entropy_test]);
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(2);
+    assert!(from_utf8(&result.get_output().stderr).unwrap().starts_with(
+        "Mnemonic: erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb\n"
+    ));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--entropy", &entropy_test[..62]]);
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(3);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "Invalid mnemonic: the entropy is 248 bits long, a mnemonic encodes 128 to 256 bits in steps of 32. Exiting\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_main_xprv() -> Result<(), Box<dyn std::error::Error>> {
+    // master key of the test mnemonic, and its m/44'/0'/0' account key
+    let master_test: &str = "xprv9s21ZrQH143K2zJ7ptYCbcbY6Ysr6wwtLqWMtTH1bbBSHiYs3jA4QZHkAbN6nZkTNVhGmpiDei5jgepTmeKtFAaqe4J7nTSFyKtESXAPtRT";
+    let account_test: &str = "xprv9yE75FA8ApWNWuTfDW2twodwMSjsWzWRMvx9pFyDUHiWm6fzkpBhB14XsKDznyTxv83q7anDLVVPbnh2mXAjswXbptQiMZRuGeCL8YWmGgM";
+    // The master of an account key being unknown, so is the origin of its keys
+    for (key, fingerprint) in [(master_test, ". key origin: [67b4602d"), (account_test, "")] {
+        let mut cmd = Command::cargo_bin("hdifinder")?;
+        cmd.args(["--all", "--purposes", "44", "--end", "10", "--chains", "0,1", "--xprv", key]);
+        cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK").arg("1EukGhGZ3ai3zoG8mH86irCX18zTbthDZ5");
+        let result = cmd.assert().success();
+        let stdout = from_utf8(&result.get_output().stdout).unwrap();
+        let origin = |path: &str| if fingerprint.is_empty() { String::new() } else { format!("{}/{}]", fingerprint, path) };
+        assert!(stdout.contains(&format!("derivation path: m/44'/0'/0'/0/5{}\n", origin("44'/0'/0'/0/5"))));
+        assert!(stdout.contains(&format!("derivation path: m/44'/0'/0'/1/3{}\n", origin("44'/0'/0'/1/3"))));
+    }
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--accounts", "0-5", "--xprv", account_test]);
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(1);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "--accounts can't be used along with an account level key, which holds a single account. Exiting\n"
+    );
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--network", "testnet", "--xprv", master_test]);
+    cmd.arg("mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn");
+    let result = cmd.assert().code(1);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "The extended private key is a mainnet (xprv) key while the network is testnet. Exiting\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_main_xpub() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    // m/44'/0'/0' and m/44'/0'/0'/1 public keys of the test mnemonic
+    let account_test: &str = "xpub6CDTUkh21C4fjPY8KXZuJwafuUaMvTEGj9skceNq2dFVdu19JMVwioP1iZK5urL11xzypzwt9yAKjZkm7CWjxcKUHLGpCbq2YohwPij3nbm";
+    let chain_test: &str = "xpub6EvDkEuN24cFHe32AuXTcaArjZREQv2zWfKWHFrPAkdQp5YFA12jJoaZo2fM5a1MDg1hrfuheshK7EGhruVUcLSTZVHPXeu4tAFToxi4Jdt";
+    let addresses = ["14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", "1EukGhGZ3ai3zoG8mH86irCX18zTbthDZ5"];
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--all", "--purposes", "44", "--chains", "0,1", "--end", "10"]);
+    cmd.arg(mnemonic_test).args(addresses);
+    let result = cmd.assert().success();
+        // The origin of the keys is unknown to a watch-only scan
+    let expected: Vec<String> = from_utf8(&result.get_output().stdout)?
+        .lines()
+        .take(2)
+        .map(|line| format!("{}. watch-only", line.split(". key origin: ").next().unwrap()))
+        .collect();
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--all", "--purposes", "44", "--chains", "0,1", "--end", "10", "--xpub", account_test]);
+    cmd.args(addresses);
+    let result = cmd.assert().success();
+    let stdout = from_utf8(&result.get_output().stdout)?;
+    assert!(stdout.starts_with("Watch-only mode: the addresses are derived from the account level public key of account 0"));
+    assert_eq!(stdout.lines().skip(1).take(2).map(String::from).collect::<Vec<String>>(), expected);
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--json", "--purposes", "44", "--end", "10", "--xpub", chain_test]);
+    cmd.arg(addresses[1]);
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.contains("\"derivation_path\":\"m/44'/0'/0'/1/3\",\"address_type\":\"p2pkh\",\"network\":\"bitcoin\",\"watch_only\":true,"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--chains", "0", "--xpub", chain_test]);
+    cmd.arg(addresses[1]);
+    let result = cmd.assert().code(1);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr)?,
+        "The extended public key is the key of chain 1, --chains and --change can't select other chains. Exiting\n"
+    );
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--xpub", account_test, "--passphrase", "Summer 2017"]);
+    cmd.arg(addresses[0]);
+    let result = cmd.assert().code(1);
+    let error = from_utf8(&result.get_output().stderr)?.lines().next().unwrap_or_default();
+    assert!(error.contains("cannot be used with") && error.contains("'--xpub") && error.contains("'--passphrase"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--xpub", account_test, "--seed", "00"]);
+    cmd.arg(addresses[0]);
+    let result = cmd.assert().code(1);
+    let error = from_utf8(&result.get_output().stderr)?.lines().next().unwrap_or_default();
+    assert!(error.contains("cannot be used with") && error.contains("'--xpub") && error.contains("'--seed"));
+    Ok(())
+}
+
+#[test]
+fn test_main_slip132() -> Result<(), Box<dyn std::error::Error>> {
+    // BIP84 test vector: account key of "abandon abandon ... about" and its first addresses
+    let zpub_test: &str = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--all", "--chains", "0,1", "--end", "5", "--xpub", zpub_test]);
+    cmd.args(["bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu", "bc1qnjg0jd8228aq7egyzacy8cys3knf9xvrerkf9g", "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el"]);
+    let result = cmd.assert().success();
+    let stdout = from_utf8(&result.get_output().stdout)?;
+    assert!(stdout.contains("address bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu found at account 0 index 0 on the external chain. address type: p2wpkh. derivation path: m/84'/0'/0'/0/0. watch-only\n"));
+    assert!(stdout.contains("address bc1qnjg0jd8228aq7egyzacy8cys3knf9xvrerkf9g found at account 0 index 1 on the external chain. address type: p2wpkh. derivation path: m/84'/0'/0'/0/1. watch-only\n"));
+    assert!(stdout.contains("address bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el found at account 0 index 0 on the internal chain. address type: p2wpkh. derivation path: m/84'/0'/0'/1/0. watch-only\n"));
+    // only the purpose of the prefix is scanned
+    assert!(from_utf8(&result.get_output().stderr)?.contains("keys derived:    10\n"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--xprv", zpub_test]);
+    cmd.arg("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+    let result = cmd.assert().code(1);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr)?,
+        "zpub is a public key, an extended private key is expected. Exiting\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_main_electrum() -> Result<(), Box<dyn std::error::Error>> {
+    // Electrum test vectors, with the first receiving and change addresses
+    let standard_test: &str = "cycle rocket west magnet parrot shuffle foot correct salt library feed song";
+    let segwit_test: &str = "bitter grass shiver impose acquire brush forget axis eager alone wine silver";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--seed-type", "electrum", "--all", "--chains", "0,1", "--end", "5"]);
+    cmd.arg(standard_test).args(["1NNkttn1YvVGdqBW4PR6zvc3Zx3H5owKRf", "1KSezYMhAJMWqFbVFB2JshYg69UpmEXR4D"]);
+    let result = cmd.assert().success();
+    let stdout = from_utf8(&result.get_output().stdout)?;
+    assert!(stdout.contains("address 1NNkttn1YvVGdqBW4PR6zvc3Zx3H5owKRf found at account 0 index 0 on the external chain. address type: p2pkh. derivation path: m/0/0. key origin: [48adc7a0/0/0]\n"));
+    assert!(stdout.contains("address 1KSezYMhAJMWqFbVFB2JshYg69UpmEXR4D found at account 0 index 0 on the internal chain. address type: p2pkh. derivation path: m/1/0. key origin: [48adc7a0/1/0]\n"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--seed-type", "electrum", "--all", "--chains", "0,1", "--end", "5"]);
+    cmd.arg(segwit_test).args(["bc1q3g5tmkmlvxryhh843v4dz026avatc0zzr6h3af", "bc1qdy94n2q5qcp0kg7v9yzwe6wvfkhnvyzje7nx2p"]);
+    let result = cmd.assert().success();
+    let stdout = from_utf8(&result.get_output().stdout)?;
+    assert!(stdout.contains("address bc1q3g5tmkmlvxryhh843v4dz026avatc0zzr6h3af found at account 0 index 0 on the external chain. address type: p2wpkh. derivation path: m/0'/0/0. key origin: [b2e35a7d/0'/0/0]\n"));
+    assert!(stdout.contains("address bc1qdy94n2q5qcp0kg7v9yzwe6wvfkhnvyzje7nx2p found at account 0 index 0 on the internal chain. address type: p2wpkh. derivation path: m/0'/1/0. key origin: [b2e35a7d/0'/1/0]\n"));
+
+    // an Electrum seed given as a BIP39 mnemonic, and the other way around
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg(standard_test).arg("1NNkttn1YvVGdqBW4PR6zvc3Zx3H5owKRf");
+    let result = cmd.assert().code(3);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr)?,
+        "Invalid mnemonic: the phrase is an Electrum seed, not a BIP39 mnemonic. Give --seed-type electrum. Exiting\n"
+    );
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--seed-type", "electrum"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(3);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr)?,
+        "Invalid mnemonic: the phrase is a BIP39 mnemonic, not an Electrum seed. Leave --seed-type electrum out. Exiting\n"
+    );
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--seed-type", "electrum", "--purposes", "84"]);
+    cmd.arg(segwit_test).arg("bc1q3g5tmkmlvxryhh843v4dz026avatc0zzr6h3af");
+    let result = cmd.assert().code(1);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr)?,
+        "--seed-type electrum can't be used along with --purposes. Exiting\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_main_slip39() -> Result<(), Box<dyn std::error::Error>> {
+    // SLIP-39 test vector, 2 of 3 shares of a secret encrypted with "TREZOR"
+    let first: &str = "shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed";
+    let second: &str = "shadow pistol academic acid actress prayer class unknown daughter sweater depict flip twice unkind craft early superior advocate guest smoking";
+    let single: &str = "duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision keyboard";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--purposes", "44", "--end", "10", "--slip39-share", single, "--passphrase", "TREZOR"]);
+    cmd.arg("1Frdhpgb993ufnRfByp7jAov3kNYFTNjjF");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout).unwrap().starts_with(
+        "address 1Frdhpgb993ufnRfByp7jAov3kNYFTNjjF found at account 0 index 3 on the external chain. \
+         address type: p2pkh. derivation path: m/44'/0'/0'/0/3. key origin: [828ecab2/44'/0'/0'/0/3]\n"
+    ));
+
+    let shares_file = std::env::temp_dir().join(format!("hdifinder-test-slip39-file-{}", std::process::id()));
+    let shares_file = shares_file.to_str().unwrap();
+    fs::write(shares_file, format!("# two of three\n{}\n\n{} # second share\n", first, second))?;
+    // The shares combine, the address belonging to another secret
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--purposes", "44", "--end", "10", "--slip39-file", shares_file, "--passphrase", "TREZOR"]);
+    cmd.arg("1Frdhpgb993ufnRfByp7jAov3kNYFTNjjF");
+    cmd.assert().code(2);
+    fs::remove_file(shares_file)?;
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--slip39-share", first]);
+    cmd.arg("1Frdhpgb993ufnRfByp7jAov3kNYFTNjjF");
+    let result = cmd.assert().code(3);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "Invalid SLIP-39 shares: not enough shares, 1 of the 2 shares required are given. Exiting\n"
+    );
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--slip39-share", first, "--slip39-share", single]);
+    cmd.arg("1Frdhpgb993ufnRfByp7jAov3kNYFTNjjF");
+    let result = cmd.assert().code(3);
+    assert!(from_utf8(&result.get_output().stderr)
         .unwrap()
+        .starts_with("Invalid SLIP-39 shares: share 2 doesn't belong to the same secret as share 1"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--slip39-share", &first.replace("armed", "axis")]);
+    cmd.arg("1Frdhpgb993ufnRfByp7jAov3kNYFTNjjF");
+    let result = cmd.assert().code(3);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "Invalid SLIP-39 shares: share 1: the share checksum is invalid, a word may be wrong. Exiting\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_main_aezeed() -> Result<(), Box<dyn std::error::Error>> {
+    // 24 words encoding a version 0 aezeed payload and its CRC32C checksum
+    let aezeed: &str = "ability toe have renew affair run sauce lobster excess build deputy dwarf juice genius suffer hire noise fitness snack spring unfold place planet joke";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg(aezeed).arg("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+    let result = cmd.assert().code(3);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "Invalid mnemonic: the phrase is an LND aezeed cipher seed, not a BIP39 mnemonic. Give --seed-type aezeed. Exiting\n"
+    );
+
+    // The payload isn't enciphered, it can't be deciphered with any passphrase
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--seed-type", "aezeed"]).arg(aezeed).arg("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+    let result = cmd.assert().code(1);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "Unable to decipher the aezeed cipher seed, the passphrase is wrong. Give the one set when creating the LND wallet with --aezeed-passphrase. Exiting\n"
+    );
+
+    // The entropy of the LND test vector, enciphered with the default passphrase
+    let default_passphrase: &str = "absorb original enlist once climb erode kid thrive kitchen giant define tube orange leader harbor comfort olive fatal success suggest drink penalty chimney ritual";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--seed-type", "aezeed", "--end", "5"]).arg(default_passphrase).arg("bc1qsehwg2x2jlju43arlygd7l74vn6pkj9z3ucdlm");
+    let result = cmd.assert().code(EXIT_FOUND);
+    let output = from_utf8(&result.get_output().stdout).unwrap();
+    assert!(output.contains("m/84'/0'/0'/0/3"));
+    assert!(output.contains("p2wpkh"));
+    assert!(from_utf8(&result.get_output().stderr).unwrap().contains("aezeed wallet birthday: 2018-03-22, 3365 days after the genesis block"));
+
+    // and with a passphrase of its own
+    let passphrase: &str = "absent roof siege awesome milk book wish basket bullet donor cable strategy explain prepare mystery chest stamp bean success suggest drip burden identify unit";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--seed-type", "aezeed", "--aezeed-passphrase", "!very_safe_55345_password*", "--chains", "0,1", "--end", "5"]);
+    cmd.arg(passphrase).arg("bc1q94fj7j8p4emwllc50h7wtue3u6k75hu6v3hpat");
+    let result = cmd.assert().code(EXIT_FOUND);
+    assert!(from_utf8(&result.get_output().stdout).unwrap().contains("m/84'/0'/0'/1/2"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--seed-type", "aezeed"]).arg(passphrase.replace("milk", "million")).arg("bc1q94fj7j8p4emwllc50h7wtue3u6k75hu6v3hpat");
+    let result = cmd.assert().code(3);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "Invalid mnemonic: the CRC32C checksum of the aezeed cipher seed doesn't match. A word may be wrong. Exiting\n"
+    );
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--aezeed-passphrase", "secret"]).arg(default_passphrase).arg("bc1qsehwg2x2jlju43arlygd7l74vn6pkj9z3ucdlm");
+    let result = cmd.assert().code(1);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "--aezeed-passphrase is only used along with --seed-type aezeed. Exiting\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_main_bip85() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    // m/44'/0'/0'/0/4 of the 12 words child mnemonic of index 2,
+    // "gather demise snap home hip siege surface turn nothing magic ancient witness"
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--purposes", "44", "--end", "10", "--bip85-children", "4"]);
+    cmd.arg(mnemonic_test).arg("1B3DetQs1V2R4QphpZ3bT4G8rkEYKGD4rt");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout).unwrap().starts_with(
+        "address 1B3DetQs1V2R4QphpZ3bT4G8rkEYKGD4rt found at account 0 index 4 on the external chain. \
+         address type: p2pkh. derivation path: m/44'/0'/0'/0/4. key origin: [162069c3/44'/0'/0'/0/4]. BIP85 child mnemonic 2\n"
+    ));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--purposes", "44", "--end", "10", "--bip85-children", "4", "--json"]);
+    cmd.arg(mnemonic_test).arg("1B3DetQs1V2R4QphpZ3bT4G8rkEYKGD4rt");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.contains("\"derivation_path\":\"m/44'/0'/0'/0/4\",\"fingerprint\":\"162069c3\",\"origin\":\"[162069c3/44'/0'/0'/0/4]\",\"address_type\":\"p2pkh\",\"network\":\"bitcoin\",\"bip85_child\":2,"));
+
+    // The child mnemonics are 24 words long, none of them owning the address
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--purposes", "44", "--end", "10", "--bip85-children", "4", "--bip85-words", "24"]);
+    cmd.arg(mnemonic_test).arg("1B3DetQs1V2R4QphpZ3bT4G8rkEYKGD4rt");
+    cmd.assert().code(2);
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--bip85-children", "0"]);
+    cmd.arg(mnemonic_test).arg("1B3DetQs1V2R4QphpZ3bT4G8rkEYKGD4rt");
+    let result = cmd.assert().code(1);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "Invalid BIP85 children count 0. Expected a number of child mnemonics from 1 to 2147483648. Exiting\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_main_generate() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["generate", "--words", "24", "--network", "testnet", "--purposes", "84", "--count", "3", "--json"]);
+    let result = cmd.assert().code(1);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr).unwrap(),
+        "Refusing to print a new mnemonic to a pipe or a file, where it may be logged. Run it in a terminal or give --i-know-what-im-doing. Exiting\n"
+    );
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["generate", "--words", "24", "--network", "testnet", "--purposes", "84", "--count", "3", "--json", "--i-know-what-im-doing"]);
+    let result = cmd.assert().success();
+    let stdout = from_utf8(&result.get_output().stdout)?;
+    assert!(stdout.starts_with("{\"mnemonic\":\""));
+    assert!(stdout.contains("\"network\":\"testnet\",\"addresses\":[{\"index\":0,\"derivation_path\":\"m/84'/1'/0'/0/0\",\"address_type\":\"p2wpkh\",\"address\":\"tb1q"));
+    let mnemonic = stdout.split('"').nth(3).unwrap();
+    assert_eq!(mnemonic.split(' ').count(), 24);
+    let last_address = stdout.split('"').rev().nth(1).unwrap();
+
+    // The generated wallet is found back by the search
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--network", "testnet", "--purposes", "84", "--end", "10"]);
+    cmd.arg(mnemonic).arg(last_address);
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.contains("derivation path: m/84'/1'/0'/0/2"));
+    Ok(())
+}
+
+#[test]
+fn test_main_derive() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["derive", mnemonic_test, "--start", "0", "--end", "50", "--type", "all"]);
+    let result = cmd.assert().success();
+    let stdout = from_utf8(&result.get_output().stdout)?;
+    assert_eq!(stdout.lines().count(), 200);
+    assert!(stdout.contains("5       m/44'/0'/0'/0/5       p2pkh     14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK\n"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["derive", mnemonic_test, "--start", "3", "--end", "4", "--purposes", "44", "--change", "--format", "csv"]);
+    cmd.assert()
+        .success()
+        .stdout("index,derivation_path,type,address\n3,m/44'/0'/0'/1/3,p2pkh,1EukGhGZ3ai3zoG8mH86irCX18zTbthDZ5\n");
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["derive", mnemonic_test, "--start", "5", "--end", "6", "--type", "p2pkh", "--format", "json"]);
+    cmd.assert().success().stdout(
+        "{\"addresses\":[{\"index\":5,\"derivation_path\":\"m/44'/0'/0'/0/5\",\"address_type\":\"p2pkh\",\"address\":\"14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK\"}]}\n",
+    );
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["derive", mnemonic_test, "--start", "10", "--end", "5"]);
+    cmd.assert().code(1);
+    Ok(())
+}
+
+#[test]
+fn test_main_verify() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["verify", mnemonic_test, "--index", "5", "--address", "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"]);
+    cmd.assert()
+        .code(EXIT_FOUND)
+        .stdout("Verified: 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK is the p2pkh address at m/44'/0'/0'/0/5 on bitcoin, without passphrase\n");
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["verify", mnemonic_test, "--index", "3", "--address", "1EukGhGZ3ai3zoG8mH86irCX18zTbthDZ5", "--change", "--type", "p2pkh"]);
+    cmd.assert().code(EXIT_FOUND);
+
+    // The address isn't at that index: the addresses derived there are listed
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["verify", mnemonic_test, "--index", "4", "--address", "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"]);
+    let result = cmd.assert().code(EXIT_NOT_FOUND);
+    let stdout = from_utf8(&result.get_output().stdout)?;
+    assert!(stdout.starts_with("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK isn't derived at index 4 on bitcoin, without passphrase. The addresses derived there are:\n"));
+    assert_eq!(stdout.lines().count(), 5);
+    assert!(stdout.contains("4       m/84'/0'/0'/0/4       p2wpkh    bc1q"));
+
+    // A passphrase changes the wallet
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["verify", mnemonic_test, "--index", "5", "--address", "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", "-p", "secret"]);
+    let result = cmd.assert().code(EXIT_NOT_FOUND);
+    assert!(from_utf8(&result.get_output().stdout)?.contains("on bitcoin, with the passphrase given."));
+
+    // As does the network
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["verify", mnemonic_test, "--index", "5", "--address", "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", "--network", "testnet"]);
+    cmd.assert()
+        .code(cli::EXIT_INVALID_ADDRESS)
+        .stderr("Target 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK is a bitcoin mainnet address but the search network is testnet; pass --network mainnet. Exiting\n");
+    Ok(())
+}
+
+#[test]
+fn test_main_bip21() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--json"]);
+    cmd.arg(mnemonic_test).arg("bitcoin:14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK?amount=0.1&label=Cold%20storage");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.contains("\"derivation_path\":\"m/44'/0'/0'/0/5\",\"fingerprint\":\"67b4602d\",\"origin\":\"[67b4602d/44'/0'/0'/0/5]\",\"address_type\":\"p2pkh\",\"network\":\"bitcoin\",\"label\":\"Cold storage\","));
+
+    // Uppercase URIs of QR codes
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--purposes", "84"]);
+    cmd.arg(mnemonic_test).arg("BITCOIN:BC1Q4QWMRJ5THPQES6NAW4XE579KW4MY05T0DAPCHT");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.contains("address bc1q4qwmrj5thpqes6naw4xe579kw4my05t0dapcht found at account 0 index 1"));
+
+    // An invalid URI isn't reported as an invalid address
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg(mnemonic_test).arg("bitcoin:14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK?req-fee=1");
+    cmd.assert()
+        .code(4)
+        .stderr("Invalid BIP21 URI bitcoin:14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK?req-fee=1: the required parameter req-fee isn't supported. Exiting\n");
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg(mnemonic_test).arg("bitcoin:14odE5c1eXuphR24fXMtzDfsXMLCmFTFgL");
+    let result = cmd.assert().code(4);
+    assert!(from_utf8(&result.get_output().stderr)?.starts_with("Invalid BIP21 URI bitcoin:14odE5c1eXuphR24fXMtzDfsXMLCmFTFgL: invalid address"));
+    Ok(())
+}
+
+#[test]
+fn test_main_uppercase_bech32() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--purposes", "84"]);
+    cmd.arg(mnemonic_test).arg(" BC1Q4QWMRJ5THPQES6NAW4XE579KW4MY05T0DAPCHT\u{200B}");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?
+        .contains("address bc1q4qwmrj5thpqes6naw4xe579kw4my05t0dapcht found at account 0 index 1 on the external chain. address type: p2wpkh. derivation path: m/84'/0'/0'/0/1"));
+    Ok(())
+}
+
+#[test]
+fn test_main_script() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--script", "76a91429b9c049a22a9e3b620fb327b05debaf67b42ed488ac"]);
+    cmd.arg(mnemonic_test);
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.contains(
+        "address 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK found at account 0 index 5 on the external chain. address type: p2pkh. derivation path: m/44'/0'/0'/0/5. key origin: [67b4602d/44'/0'/0'/0/5]. script: 76a91429b9c049a22a9e3b620fb327b05debaf67b42ed488ac"
+    ));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--format", "csv", "--script", "76a91429b9c049a22a9e3b620fb327b05debaf67b42ed488ac"]);
+    cmd.arg(mnemonic_test);
+    cmd.assert().success().stdout(
+        "index,derivation_path,address,type,target,fingerprint,origin,script\n\
+         5,m/44'/0'/0'/0/5,14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK,p2pkh,14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK,67b4602d,[67b4602d/44'/0'/0'/0/5],76a91429b9c049a22a9e3b620fb327b05debaf67b42ed488ac\n",
+    );
+
+    // Scripts no derived address type produces are rejected before scanning
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--script", "0020701a8d401c84fb13e6baf169d59684e17abd9fa216c8cc5b9fc63d622ff8c58d"]);
+    cmd.arg(mnemonic_test);
+    cmd.assert().code(4).stderr(
+        "Unsupported script 0020701a8d401c84fb13e6baf169d59684e17abd9fa216c8cc5b9fc63d622ff8c58d. Only p2pkh, p2sh, p2wpkh and p2tr output scripts can be searched. Exiting\n",
+    );
+    Ok(())
+}
+
+#[test]
+fn test_main_pubkey() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    // The uncompressed encoding is found as the compressed key BIP32 derives
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--pubkey", "04016653fa405f3ecedb3dc88a378dabf7cd4c1c1acf1430515e854a630254cbbeada7e09891c8b3a36234d45dffcff8e4b49b6f68c69396ad39fe3ea8cb9356fa"]);
+    cmd.arg(mnemonic_test);
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.contains(
+        "address 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK found at account 0 index 5 on the external chain. address type: p2pkh. derivation path: m/44'/0'/0'/0/5. \
+         key origin: [67b4602d/44'/0'/0'/0/5]. public key: 02016653fa405f3ecedb3dc88a378dabf7cd4c1c1acf1430515e854a630254cbbe (p2pkh 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK, \
+         p2shwpkh 39gFyg2s6bp5AwwqtCrH7iNqRBh664LnZg, p2wpkh bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y, \
+         p2tr bc1pqw4gsp6kj0f2rumlcxnkmt56syvhdpc334f80fqjh6zup9lgp92sq2jmud)"
+    ));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--pubkey", "02016653fa"]);
+    cmd.arg(mnemonic_test);
+    cmd.assert().code(4).stderr("Invalid public key 02016653fa: expected 33 or 65 bytes, got 5. Exiting\n");
+    Ok(())
+}
+
+#[test]
+fn test_main_hash160() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--purposes", "44", "--hash160", "29B9C049A22A9E3B620FB327B05DEBAF67B42ED4"]);
+    cmd.arg(mnemonic_test);
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.contains(
+        "address 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK found at account 0 index 5 on the external chain. address type: p2pkh. derivation path: m/44'/0'/0'/0/5. \
+         key origin: [67b4602d/44'/0'/0'/0/5]. hash160: 29b9c049a22a9e3b620fb327b05debaf67b42ed4 (p2pkh 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK, p2wpkh bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y)"
+    ));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--hash160", "29b9c049a22a9e3b620fb327b05debaf67b42e"]);
+    cmd.arg(mnemonic_test);
+    cmd.assert().code(4).stderr("Invalid hash160 29b9c049a22a9e3b620fb327b05debaf67b42e: expected 20 bytes, got 19. Exiting\n");
+    Ok(())
+}
+
+#[test]
+fn test_main_descriptor() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let descriptor = "wpkh([67b4602d/84'/0'/0']xpub6C2nZRpiwAoxe2a3Y1riTBRtFdEUxULcrCaDFsP1VbGRdhf7HRWPp69RsUcx79fnA5CNjHJzC39EaAgKfVuiuHGGhqHSDkv1EF9q5zDXVqE/0/*)";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10"]);
+    cmd.arg(mnemonic_test).arg(format!("{}#fllv4e44", descriptor));
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.contains(
+        "address bc1q2dq975h7y93xplagze42exmzflxl4uqhl435qd found at account 0 index 0 on the external chain. address type: p2wpkh"
+    ));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg(mnemonic_test).arg("addr(14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK)");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg(mnemonic_test).arg(format!("{}#fllv4e45", descriptor));
+    cmd.assert().code(4).stderr(format!(
+        "Invalid descriptor {}#fllv4e45: the checksum at position 143 is fllv4e45 while the descriptor gives fllv4e44. Exiting\n",
+        descriptor
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_main_emit_descriptor() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--emit-descriptor"]);
+    cmd.arg(mnemonic_test).arg("bc1q4qwmrj5thpqes6naw4xe579kw4my05t0dapcht");
+    let result = cmd.assert().success();
+    let output = from_utf8(&result.get_output().stdout)?;
+    let line = output.lines().find(|line| line.starts_with("importdescriptors ")).unwrap();
+    assert_eq!(
+        line,
+        "importdescriptors '[{\"desc\":\"wpkh([67b4602d/84'/0'/0']xpub6C2nZRpiwAoxe2a3Y1riTBRtFdEUxULcrCaDFsP1VbGRdhf7HRWPp69RsUcx79fnA5CNjHJzC39EaAgKfVuiuHGGhqHSDkv1EF9q5zDXVqE/0/*)#fllv4e44\",\
+         \"range\":[0,1001],\"timestamp\":0,\"internal\":false}]'"
+    );
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--emit-descriptor", "--with-private", "--format", "json", "--purposes", "44", "--chains", "1"]);
+    cmd.arg(mnemonic_test).arg("1EukGhGZ3ai3zoG8mH86irCX18zTbthDZ5");
+    let result = cmd.assert().success();
+    let output = from_utf8(&result.get_output().stdout)?;
+    let descriptor = output.split("\"importdescriptors\":[{\"desc\":\"").nth(1).unwrap().split('"').next().unwrap();
+    assert!(descriptor.starts_with("pkh([67b4602d/44'/0'/0']xprv"));
+    let (body, checksum) = descriptor.split_once('#').unwrap();
+    assert!(body.ends_with("/1/*)"));
+    assert_eq!(descriptor::checksum(body)?, checksum);
+    assert!(output.contains("\"range\":[0,1003],\"timestamp\":0,\"internal\":true}]"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--with-private", "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"]);
+    cmd.arg(mnemonic_test);
+    cmd.assert().code(1);
+    Ok(())
+}
+
+#[test]
+fn test_main_hardened_index() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--purposes", "44", "--end", "10", "--hardened-index"]);
+    cmd.arg(mnemonic_test).arg("1F9CpR8vnCnN7om7t1VGtzNevr9WDNpeRi");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.contains("derivation path: m/44'/0'/0'/0/5'. key origin: [67b4602d/44'/0'/0'/0/5']"));
+
+    // The normal index 5 isn't scanned along with it
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--purposes", "44", "--end", "10", "--hardened-index"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    cmd.assert().code(cli::EXIT_NOT_FOUND);
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--hardened-index", "--xpub", "xpub6CDTUkh21C4fjPY8KXZuJwafuUaMvTEGj9skceNq2dFVdu19JMVwioP1iZK5urL11xzypzwt9yAKjZkm7CWjxcKUHLGpCbq2YohwPij3nbm"]);
+    cmd.arg("1F9CpR8vnCnN7om7t1VGtzNevr9WDNpeRi");
+    cmd.assert().code(1).stderr("--hardened-index can't be used along with --xpub, which holds no private key. Exiting\n");
+    Ok(())
+}
+
+#[test]
+fn test_main_include_uncompressed() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    // The uncompressed key of m/44'/0'/0'/0/5
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--include-uncompressed"]);
+    cmd.arg(mnemonic_test).arg("1J4Lx1AMWEhp1jMFNLZ8jNgmxHW2ibwDxJ");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.contains("index 5 on the external chain. address type: p2pkh-uncompressed. derivation path: m/44'/0'/0'/0/5"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10"]);
+    cmd.arg(mnemonic_test).arg("1J4Lx1AMWEhp1jMFNLZ8jNgmxHW2ibwDxJ");
+    cmd.assert().code(cli::EXIT_NOT_FOUND);
+
+    // A public key is hashed uncompressed as well
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--purposes", "44", "--end", "10", "--include-uncompressed", "--xpub", "xpub6CDTUkh21C4fjPY8KXZuJwafuUaMvTEGj9skceNq2dFVdu19JMVwioP1iZK5urL11xzypzwt9yAKjZkm7CWjxcKUHLGpCbq2YohwPij3nbm"]);
+    cmd.arg("1J4Lx1AMWEhp1jMFNLZ8jNgmxHW2ibwDxJ");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--purposes", "84", "--include-uncompressed"]);
+    cmd.arg(mnemonic_test).arg("bc1q885ce0gv2zmuhke20jtvjrtvsr5dmtut6pxwtt");
+    cmd.assert().code(1).stderr("--include-uncompressed only applies to p2pkh addresses, which the scanned purposes don't have. Exiting\n");
+    Ok(())
+}
+
+#[test]
+fn test_main_multisig() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    // A 2-of-3 wallet along with the m/48'/0'/0'/2' keys of two other seeds
+    let cosigners = [
+        "xpub6DkFAXWQ2dHxq2vatrt9qyA3bXYU4ToWQwCHbf5XB2mSTexcHZCeKS1VZYcPoBd5X8yVcbXFHJR9R8UCVpt82VX1VhR28mCyxUFL4r6KFrf",
+        "xpub6FQya7zGhR92kacYsNnjreouvnHJMpXYsUXnW6NJJAJRCKsa26TzDy4LdnGhEurr3d6y1J8PJ7EEMKQp74XTqYvmGJNogYXSKDszYHtF8mX",
+    ];
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--threshold", "2", "--cosigner-xpub", cosigners[0], "--cosigner-xpub", cosigners[1]]);
+    cmd.arg(mnemonic_test).arg("bc1qcz9tjvwf307awl3ax77u4r7r27t0dva4m30428ac5c2fvmlfy0nskhqsjz");
+    let result = cmd.assert().success();
+    let stdout = from_utf8(&result.get_output().stdout)?;
+    assert!(stdout.contains("index 3 on the external chain. address type: p2wsh. derivation path: m/48'/0'/0'/2'/0/3"));
+    assert!(stdout.contains(
+        "multisig 2 of 3 keys: 0225c9c0f7a023b9db62a40185ce6f74ea8683adb2192587b58aac26f47f7f1eb7, 0348f8ac66cd7d63649e9e98f53538e37c84d5dd0e3822d62ae143e9baa64862c3, 0349031328a3aebf7c953f793a4fe5becb94487d72d3eb4c9f3681ade7c57cb585"
+    ));
+
+    // multi(), the key of the seed first
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--threshold", "2", "--no-sort-keys", "--cosigner-xpub", cosigners[0], "--cosigner-xpub", cosigners[1]]);
+    cmd.arg(mnemonic_test).arg("bc1q0wg7xakk8n4ndf3rynfcrw20jw8f7f6mcqxdktgjj5gu3jlxqg3qjpdgqe");
+    cmd.assert().success();
+
+    // multi(), the key of the seed second, between the cosigners
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--threshold", "2", "--no-sort-keys", "--key-position", "2"]);
+    cmd.args(["--cosigner-xpub", cosigners[0], "--cosigner-xpub", cosigners[1]]);
+    cmd.arg(mnemonic_test).arg("bc1qvjtaz9qzxz9p5mvdjelgtvx9yx2rrwk7c822sspnswka068vnnhs5fkxyk");
+    cmd.assert().success();
+
+    // p2sh-p2wsh, under the m/48'/0'/0'/1' keys
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args([
+        "--end",
+        "10",
+        "--threshold",
+        "2",
+        "--p2sh-wrapped",
+        "--cosigner-xpub",
+        "xpub6DkFAXWQ2dHxnMKoSBogHrw1rgNJKR4umdbnNVNTYeCGcduxWnNUHgGptqEQWPKRmeW4Zn4FHSbLMBKEWYaMDYu47Ytg6DdFnPNt8hwn5mE",
+        "--cosigner-xpub",
+        "xpub6FQya7zGhR92giSkXpPgPHpq85nUnqabbbNuJiae1zndR3B6Nq2QCoSWBkdLF7bkifSYSNvyTfhg4KBvKyJ94HXuEaeWZsabMnTyJiPz21N",
+    ]);
+    cmd.arg(mnemonic_test).arg("3MoJYHQoH271ouUXUjbttupHNwzBk18hWT");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.contains("address type: p2shwsh. derivation path: m/48'/0'/0'/1'/0/3"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--threshold", "4", "--cosigner-xpub", cosigners[0], "--cosigner-xpub", cosigners[1]]);
+    cmd.arg(mnemonic_test).arg("bc1qcz9tjvwf307awl3ax77u4r7r27t0dva4m30428ac5c2fvmlfy0nskhqsjz");
+    cmd.assert().code(1).stderr("Invalid --threshold: threshold 4 out of the 1 to 3 keys of the script. Exiting\n");
+    Ok(())
 }
 
 #[test]
-fn test_get_private_key(){
-    let test_mnemonic_phrase: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
-    let test_seed = self::get_mnemonic(test_mnemonic_phrase).unwrap().to_seed("");
+fn test_main_show_private() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--show-private", "-vvv", "--trace-derivations", "1"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?
+        .contains("derivation path: m/44'/0'/0'/0/5. key origin: [67b4602d/44'/0'/0'/0/5]. private key: L1TmQPcEkfoxHh6pJdbVASwiq18BpF3waAKf9LaannZWvLr4p2DF\n"));
+    // The key is printed along with the match only, never logged
+    assert!(!from_utf8(&result.get_output().stderr)?.contains("L1TmQPcEkfoxHh6pJdbVASwiq18BpF3waAKf9LaannZWvLr4p2DF"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--show-private", "--format", "csv"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.starts_with(
+        "index,derivation_path,address,type,target,fingerprint,origin,wif\n\
+         5,m/44'/0'/0'/0/5,14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK,p2pkh,14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK,67b4602d,[67b4602d/44'/0'/0'/0/5],L1TmQPcEkfoxHh6pJdbVASwiq18BpF3waAKf9LaannZWvLr4p2DF\n"
+    ));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--show-private", "--xpub", "xpub6CDTUkh21C4fjPY8KXZuJwafuUaMvTEGj9skceNq2dFVdu19JMVwioP1iZK5urL11xzypzwt9yAKjZkm7CWjxcKUHLGpCbq2YohwPij3nbm"]);
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    cmd.assert().code(1).stderr("--show-private can't be used along with --xpub, which holds no private key. Exiting\n");
+    Ok(())
+}
 
-    let hd_path= AccountHDPath::new(Purpose::Pubkey, 0, 0).address_at(0,5).unwrap();
-    let private_key = get_private_key(test_seed, &hd_path);
+#[test]
+fn test_main_show_xpub() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--show-xpub"]);
+    cmd.arg(mnemonic_test).arg("bc1q4qwmrj5thpqes6naw4xe579kw4my05t0dapcht");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.contains(
+        "derivation path: m/84'/0'/0'/0/1. key origin: [67b4602d/84'/0'/0'/0/1]. account xpub: xpub6C2nZRpiwAoxe2a3Y1riTBRtFdEUxULcrCaDFsP1VbGRdhf7HRWPp69RsUcx79fnA5CNjHJzC39EaAgKfVuiuHGGhqHSDkv1EF9q5zDXVqE (zpub6qhKAmAZEXtvLcxHCjRxsMctbZXNqiKcgRcepfAnFc2BjuHZnjqX4DThutY86xycyMRzEEW77MrLLjuT6tjkVkdUSWgHPaYymhH7s5Q6tay), path m/84'/0'/0', master fingerprint 67b4602d\n"
+    ));
 
-    assert_eq!(private_key.private_key.to_string(),"L1TmQPcEkfoxHh6pJdbVASwiq18BpF3waAKf9LaannZWvLr4p2DF")
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["derive", mnemonic_test, "--end", "1", "--purposes", "44", "--show-xpub", "--format", "json"]);
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.starts_with(
+        "{\"accounts\":[{\"path\":\"m/44'/0'/0'\",\"fingerprint\":\"67b4602d\",\"xpub\":\"xpub6CDTUkh21C4fjPY8KXZuJwafuUaMvTEGj9skceNq2dFVdu19JMVwioP1iZK5urL11xzypzwt9yAKjZkm7CWjxcKUHLGpCbq2YohwPij3nbm\"}],\"addresses\":["
+    ));
+    Ok(())
 }
 
-/**
- * Retrieves a public key derived from a private key
- */
-fn get_public_key(private_key: ExtendedPrivKey) -> ExtendedPubKey {
+#[test]
+fn test_main_sign_message() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let secp = Secp256k1::new();
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--sign-message", "I own this address"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    let stdout = from_utf8(&result.get_output().stdout)?;
+    let armored: Vec<&str> = stdout.lines().skip_while(|line| !line.starts_with("-----BEGIN BITCOIN SIGNED MESSAGE")).take(6).collect();
+    assert_eq!(armored[1], "I own this address");
+    assert_eq!(armored[3], "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    assert_eq!(armored[5], "-----END BITCOIN SIGNATURE-----");
+    // As Bitcoin Core's verifymessage does
+    let signature = MessageSignature::from_slice(&message::from_base64(armored[4]).unwrap())?;
+    let address = Address::from_str("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK")?;
+    assert!(signature.is_signed_by_address(&secp, &address, signed_msg_hash("I own this address"))?);
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--json", "--end", "10", "--purposes", "84", "--sign-message", "I own this address"]);
+    cmd.arg(mnemonic_test).arg("bc1q2dq975h7y93xplagze42exmzflxl4uqhl435qd");
+    let result = cmd.assert().success();
+    let stdout = from_utf8(&result.get_output().stdout)?;
+    assert!(stdout.contains(",\"signed_message\":{\"address\":\"bc1q2dq975h7y93xplagze42exmzflxl4uqhl435qd\",\"message\":\"I own this address\",\"signature\":\"K"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--sign-message", "I own this address", "--xpub", "xpub6CDTUkh21C4fjPY8KXZuJwafuUaMvTEGj9skceNq2dFVdu19JMVwioP1iZK5urL11xzypzwt9yAKjZkm7CWjxcKUHLGpCbq2YohwPij3nbm"]);
+    cmd.arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    cmd.assert().code(1).stderr("--sign-message can't be used along with --xpub, which holds no private key. Exiting\n");
+    Ok(())
+}
+
+#[test]
+fn test_main_sign_message_bip322() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
     let secp = Secp256k1::new();
-    ExtendedPubKey::from_private(&secp, &private_key)
+    let address = Address::from_str("bc1q4qwmrj5thpqes6naw4xe579kw4my05t0dapcht")?;
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--sign-message-bip322", "Hello World"]);
+    cmd.arg(mnemonic_test).arg(address.to_string());
+    let result = cmd.assert().success();
+    let stdout = from_utf8(&result.get_output().stdout)?;
+    let proof = stdout.lines().find_map(|line| line.split(". BIP322 signature of \"Hello World\": ").nth(1)).unwrap();
+    assert!(bip322::verify(&secp, &address, "Hello World", proof));
+    assert!(!bip322::verify(&secp, &address, "Hello", proof));
+
+    // The default purposes are narrowed to the supported address types
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--dry-run", "--sign-message-bip322", "Hello World"]);
+    cmd.arg(mnemonic_test).arg(address.to_string());
+    let result = cmd.assert().success();
+    let stdout = from_utf8(&result.get_output().stdout)?;
+    assert!(stdout.contains("p2pkh") && stdout.contains("p2wpkh") && !stdout.contains("p2tr") && !stdout.contains("p2shwpkh"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--purposes", "49,84", "--sign-message-bip322", "Hello World"]);
+    cmd.arg(mnemonic_test).arg(address.to_string());
+    cmd.assert().code(1).stderr("--sign-message-bip322 only signs p2wpkh and p2pkh addresses, not p2shwpkh. Exiting\n");
+    Ok(())
 }
 
 #[test]
-fn test_get_public_key(){
-    let test_mnemonic_phrase: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
-    let test_seed = self::get_mnemonic(test_mnemonic_phrase).unwrap().to_seed("");
+fn test_main_psbt() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    // A regtest PSBT spending the outputs paid to indexes 0 and 3 of the
+    // p2wpkh chain of the mnemonic and to a foreign address
+    let scripts: Vec<_> = ["bcrt1qs7rpyl69wu6c7x9y23nkmye3rsjjthztnwhys7", "bcrt1q07kezj8cs4vkxsmltvsuydzuttv28u5p3xzghd", "bcrt1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk554dfr7"]
+        .iter()
+        .map(|address| Address::from_str(address).unwrap().script_pubkey())
+        .collect();
+    let spending = Transaction {
+        version: 2,
+        lock_time: 0,
+        input: (0..3).map(|vout| TxIn { previous_output: OutPoint { vout, ..OutPoint::default() }, ..TxIn::default() }).collect(),
+        output: vec![TxOut { value: 290_000, script_pubkey: scripts[2].clone() }],
+    };
+    let mut unsigned = PartiallySignedTransaction::from_unsigned_tx(spending)?;
+    for (input, script_pubkey) in unsigned.inputs.iter_mut().zip(scripts) {
+        input.witness_utxo = Some(TxOut { value: 100_000, script_pubkey });
+    }
+    let psbt_file = std::env::temp_dir().join(format!("hdifinder-test-psbt-{}", std::process::id()));
+    let psbt_out = std::env::temp_dir().join(format!("hdifinder-test-psbt-out-{}", std::process::id()));
+    fs::write(&psbt_file, psbt::to_base64(&unsigned))?;
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--network", "regtest", "--purposes", "84", "--end", "10"]);
+    cmd.arg("--psbt").arg(&psbt_file).arg("--psbt-out").arg(&psbt_out);
+    cmd.arg(mnemonic_test).arg("bcrt1qs7rpyl69wu6c7x9y23nkmye3rsjjthztnwhys7");
+    let result = cmd.assert().success();
+    let stdout = from_utf8(&result.get_output().stdout)?;
+    assert!(stdout.contains("Input 1 of the PSBT left unsigned: no key of the scan matches its script\n"));
+    assert!(stdout.contains("Input 2 of the PSBT left unsigned: no key of the scan matches its script\n"));
+    assert!(stdout.ends_with(&format!("Signed input 0 of the PSBT, written to {}\n", psbt_out.display())));
+    let signed = psbt::read_psbt(psbt_out.to_str().unwrap())?;
+    assert_eq!(signed.inputs.iter().map(|input| input.partial_sigs.len()).collect::<Vec<_>>(), vec![1, 0, 0]);
+    assert_eq!(signed.global, unsigned.global);
 
-    let hd_path= AccountHDPath::new(Purpose::Pubkey, 0, 0).address_at(0,5).unwrap();
-    let private_key = get_private_key(test_seed, &hd_path);
+    // Every key of the range signs along with the one of the match, the
+    // PSBT being given as base64 this time
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--network", "regtest", "--purposes", "84", "--end", "10", "--json", "--sign-all-known"]);
+    cmd.args(["--psbt", &psbt::to_base64(&unsigned)]).arg("--psbt-out").arg(&psbt_out);
+    cmd.arg(mnemonic_test).arg("bcrt1qs7rpyl69wu6c7x9y23nkmye3rsjjthztnwhys7");
+    let result = cmd.assert().success();
+    let stderr = from_utf8(&result.get_output().stderr)?;
+    assert!(stderr.ends_with(&format!("Signed inputs 0, 1 of the PSBT, written to {}\n", psbt_out.display())));
+    let signed = psbt::read_psbt(psbt_out.to_str().unwrap())?;
+    assert_eq!(signed.inputs.iter().map(|input| input.partial_sigs.len()).collect::<Vec<_>>(), vec![1, 1, 0]);
+    fs::remove_file(&psbt_out)?;
 
-    let public_key = get_public_key(private_key);
-    assert_eq!("02016653fa405f3ecedb3dc88a378dabf7cd4c1c1acf1430515e854a630254cbbe",public_key.public_key.to_string());
+    // Nothing is written when no input has been signed
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--network", "regtest", "--purposes", "84", "--end", "10"]);
+    cmd.arg("--psbt").arg(&psbt_file).arg("--psbt-out").arg(&psbt_out);
+    cmd.arg(mnemonic_test).arg("bcrt1qyd86yr7xyhjfcgc4mgjw8wwm6e0vjhek8xdrqw");
+    let result = cmd.assert().success();
+    let stdout = from_utf8(&result.get_output().stdout)?;
+    assert!(stdout.ends_with(&format!("No input of the PSBT has been signed, {} has not been written\n", psbt_out.display())));
+    assert!(!psbt_out.exists());
+    fs::remove_file(&psbt_file)?;
 
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--network", "regtest", "--psbt", "not a psbt", "--psbt-out", "signed.psbt"]);
+    cmd.arg(mnemonic_test).arg("bcrt1qs7rpyl69wu6c7x9y23nkmye3rsjjthztnwhys7");
+    cmd.assert().code(1).stderr("Invalid PSBT: neither a file nor base64. Exiting\n");
+    Ok(())
 }
 
-/**
- * Computes the different addresses types of a public key
- */
-fn address_compute(pubkey: ExtendedPubKey) -> [(&'static str, String); 3] {
-    let p2pkh: String = Address::p2pkh(&pubkey.public_key, Network::Bitcoin).to_string();
+#[cfg(feature = "qr")]
+#[test]
+fn test_main_qr() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--qr"]);
+    cmd.arg(mnemonic_test).arg("bc1q2dq975h7y93xplagze42exmzflxl4uqhl435qd");
+    let result = cmd.assert().success();
+    let stdout = from_utf8(&result.get_output().stdout)?;
+    // The uppercased address takes version 3, 29 modules and the quiet zone
+    let drawing: Vec<&str> = stdout.split("QR code of the address:\n").nth(1).unwrap().lines().collect();
+    assert_eq!(drawing.len(), 19);
+    assert!(drawing.iter().all(|line| line.chars().count() == 37));
+    assert!(!stdout.contains("QR code of the private key"));
 
-    let p2wpkh: String = Address::p2wpkh(&pubkey.public_key, Network::Bitcoin)
-        .unwrap()
-        .to_string();
+    // Kept off the JSON document
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--qr", "--json"]);
+    cmd.arg(mnemonic_test).arg("bc1q2dq975h7y93xplagze42exmzflxl4uqhl435qd");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout)?.starts_with("{\"found\":true"));
+    assert!(from_utf8(&result.get_output().stderr)?.starts_with("QR code of the address:\n"));
 
-    let p2shwpkh: String = Address::p2shwpkh(&pubkey.public_key, Network::Bitcoin)
-        .unwrap()
-        .to_string();
+    let qr_file = std::env::temp_dir().join(format!("hdifinder-test-qr-{}.png", std::process::id()));
+    let qr_private_file = std::env::temp_dir().join(format!("hdifinder-test-qr-{}-private.png", std::process::id()));
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--qr", "--show-private", "--qr-private", "--qr-file"]).arg(&qr_file);
+    cmd.arg(mnemonic_test).arg("bc1q2dq975h7y93xplagze42exmzflxl4uqhl435qd");
+    let result = cmd.assert().success();
+    let stdout = from_utf8(&result.get_output().stdout)?;
+    assert!(stdout.contains(&format!("QR code of the address written to {}\n", qr_file.display())));
+    assert!(stdout.contains(&format!("QR code of the private key written to {}\n", qr_private_file.display())));
+    assert!(fs::read(&qr_file)?.starts_with(b"\x89PNG\r\n\x1a\n"));
+    assert!(fs::read(&qr_private_file)?.starts_with(b"\x89PNG\r\n\x1a\n"));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(fs::metadata(&qr_private_file)?.permissions().mode() & 0o777, 0o600);
+    }
+    fs::remove_file(&qr_file)?;
+    fs::remove_file(&qr_private_file)?;
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--qr", "--qr-private"]);
+    cmd.arg(mnemonic_test).arg("bc1q2dq975h7y93xplagze42exmzflxl4uqhl435qd");
+    cmd.assert().code(1);
+    Ok(())
+}
 
-    [("p2pkh", p2pkh), ("p2wpkh", p2wpkh), ("p2shwpkh", p2shwpkh)]
+#[test]
+fn test_main_no_color() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    // Pipes get no colors, the JSON and CSV documents neither
+    for format in ["text", "json", "csv"] {
+        let mut cmd = Command::cargo_bin("hdifinder")?;
+        cmd.args(["--end", "10", "--format", format]);
+        cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+        let result = cmd.assert().success();
+        assert!(!from_utf8(&result.get_output().stdout)?.contains('\x1b'));
+        assert!(!from_utf8(&result.get_output().stderr)?.contains('\x1b'));
+    }
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.env("NO_COLOR", "1").args(["--end", "10", "--no-color"]);
+    cmd.arg(mnemonic_test).arg("bananas");
+    let result = cmd.assert().failure();
+    assert!(from_utf8(&result.get_output().stderr)?.starts_with("Invalid address bananas"));
+    Ok(())
 }
 
 #[test]
-fn test_address_compute(){
-let test_mnemonic_phrase: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
-    let test_seed = self::get_mnemonic(test_mnemonic_phrase).unwrap().to_seed("");
+fn test_main_output() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let output = std::env::temp_dir().join(format!("hdifinder-test-output-{}.csv", std::process::id()));
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--format", "csv", "--output"]).arg(&output);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    // stdout keeps the text output
+    assert!(from_utf8(&result.get_output().stdout)?.starts_with("address 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK found at account 0 index 5"));
+    let written = fs::read_to_string(&output)?;
+    assert!(written.starts_with("index,derivation_path,address,type,target,"));
+    assert!(written.contains(",14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK,"));
+    // The file may hold private keys
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(fs::metadata(&output)?.permissions().mode() & 0o777, 0o600);
+    }
 
-    let hd_path= AccountHDPath::new(Purpose::Pubkey, 0, 0).address_at(0,5).unwrap();
-    let private_key = get_private_key(test_seed, &hd_path);
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "--output"]).arg(&output);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().failure();
+    assert!(from_utf8(&result.get_output().stderr)?.contains("already exists, give --force to overwrite it"));
+    assert_eq!(fs::read_to_string(&output)?, written);
 
-    let public_key = get_public_key(private_key);
-    
-    let expected_results = [
-        [
-            "p2pkh",
-            "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"
-        ],
-        [
-            "p2wpkh",
-            "bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y"
-        ],
-        [
-            "p2shwpkh",
-            "39gFyg2s6bp5AwwqtCrH7iNqRBh664LnZg"
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--end", "10", "-q", "--format", "ndjson", "--force", "--output"]).arg(&output);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    assert_eq!(from_utf8(&result.get_output().stdout)?, "5\n");
+    let written = fs::read_to_string(&output)?;
+    assert!(written.starts_with("{\"found\":true,\"address\":\"14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK\""));
+    assert!(written.lines().last().unwrap().starts_with("{\"summary\":true,\"found\":1,"));
 
-        ]
-    ];
-    let addresses = address_compute(public_key);
-    for (i,address) in IntoIterator::into_iter(addresses).enumerate(){
-        assert_eq!(expected_results[i][0],address.0);
-        assert_eq!(expected_results[i][1],address.1);
-         
+    // A run killed long before the end of its range keeps the match found
+    // at its start, in the temporary file the results go to until the end
+    fs::remove_file(&output)?;
+    let partial = format!("{}.partial", output.display());
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("hdifinder"))
+        .args(["--end", "100000000", "--chunksize", "100", "--purposes", "44", "--all", "--no-progress", "--format", "ndjson", "--output"])
+        .arg(&output)
+        .arg(mnemonic_test)
+        .arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK")
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+    let started = Instant::now();
+    while !fs::read_to_string(&partial).unwrap_or_default().contains("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK") && started.elapsed() < Duration::from_secs(30) {
+        thread::sleep(Duration::from_millis(50));
     }
+    child.kill()?;
+    child.wait()?;
+    let written = fs::read_to_string(&partial)?;
+    assert_eq!(written.lines().count(), 1);
+    assert!(written.starts_with("{\"found\":true,\"address\":\"14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK\""));
+    // The file reserved by the run is left empty
+    assert_eq!(fs::read_to_string(&output)?, "");
+    fs::remove_file(&partial)?;
+    fs::remove_file(&output)?;
+    Ok(())
 }
 
-/**
- * Executor for parallelized computing in order to find an address.
- */
-fn executor(
-    address: &str,
-    wallet_config: &WalletConf,
-    execution_config: ExecutionConf,
-) -> Option<(usize, String, String)> {
-    let start = execution_config.start;
-    let end = execution_config.end;
-
-    '_outer: for i in start..end {
-        let hd_path = wallet_config
-            .account
-            .address_at(0, i.try_into().unwrap())
-            .unwrap();
-        let private_key = self::get_private_key(wallet_config.seed, &hd_path);
-        let public_key = self::get_public_key(private_key);
-        let addresses = self::address_compute(public_key);
-        '_inner: for addr in &addresses {
-            if addr.1.as_str() == address {
-                return Some((i, addr.1.to_string(), addr.0.to_string()));
-            }
-        }
+#[test]
+fn test_main_config() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let config = std::env::temp_dir().join(format!("hdifinder-test-config-{}.toml", std::process::id()));
+    fs::write(&config, "# regtest jobs\nnetwork = \"regtest\"\npurposes = [84]\nend = 2\nno-progress = true\n")?;
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("--config").arg(&config).args(["-q"]);
+    cmd.arg(mnemonic_test).arg("bcrt1qx45ql4q0ht2qt4vgwy7u5jac0t8uk3xu046v54");
+    cmd.assert().success().stdout("1\n");
+
+    // --end of the command line scans further than the file
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("--config").arg(&config).args(["-q", "--end", "4"]);
+    cmd.arg(mnemonic_test).arg("bcrt1q07kezj8cs4vkxsmltvsuydzuttv28u5p3xzghd");
+    cmd.assert().success().stdout("3\n");
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("--config").arg(&config).args(["-q"]);
+    cmd.arg(mnemonic_test).arg("bcrt1q07kezj8cs4vkxsmltvsuydzuttv28u5p3xzghd");
+    cmd.assert().code(EXIT_NOT_FOUND);
+
+    fs::write(&config, "network = \"regtest\"\nchunk-size = 100\n")?;
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("--config").arg(&config);
+    cmd.arg(mnemonic_test).arg("bcrt1qx45ql4q0ht2qt4vgwy7u5jac0t8uk3xu046v54");
+    let result = cmd.assert().code(1);
+    assert_eq!(
+        from_utf8(&result.get_output().stderr)?,
+        format!("Invalid config file {}: line 2: unknown key chunk-size. Exiting\n", config.display())
+    );
+    fs::remove_file(&config)?;
+    Ok(())
+}
+
+#[test]
+fn test_main_completions() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["completions", "bash"]);
+    let result = cmd.assert().success();
+    let stdout = from_utf8(&result.get_output().stdout)?;
+    assert!(stdout.starts_with("_hdifinder() {"));
+    assert!(stdout.ends_with("    complete -F _hdifinder -o bashdefault -o default hdifinder\nfi\n"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["completions", "tcsh"]);
+    cmd.assert().failure();
+    Ok(())
+}
+
+#[test]
+fn test_main_type() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--json", "--type", "p2wpkh", "--end", "10", "--chunksize", "10"]);
+    cmd.arg(mnemonic_test).arg("bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y");
+    let result = cmd.assert().code(2);
+    let output = from_utf8(&result.get_output().stdout).unwrap();
+    // a single address is computed per index
+    assert!(output.starts_with("{\"found\":false,\"scanned\":10,\"start\":0,\"end\":10,\"address_types\":[\"p2wpkh\"],"));
+    assert!(output.contains("\"stats\":{\"keys\":10,"));
+    Ok(())
+}
+
+#[test]
+fn test_main_state_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let state_file = std::env::temp_dir().join(format!("hdifinder-test-state-{}", std::process::id()));
+    let state_file = state_file.to_str().unwrap();
+
+    // the first run is interrupted before reaching the address
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["-q", "--purposes", "44", "--end", "4", "--chunksize", "1", "--state-file", state_file]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    cmd.assert().code(2);
+    assert!(fs::read_to_string(state_file)?.ends_with("next=4\n"));
+
+    // the next one resumes where it stopped
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["-q", "--purposes", "44", "--end", "10", "--chunksize", "1", "--state-file", state_file]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    assert_eq!(from_utf8(&result.get_output().stdout).unwrap(), "5\n");
+    assert!(from_utf8(&result.get_output().stderr).unwrap().starts_with("Resuming from index 4\n"));
+
+    // another scan refuses to resume from it
+    for args in [["--start", "2"], ["--passphrase", "secret"], ["--purposes", "84"]] {
+        let mut cmd = Command::cargo_bin("hdifinder")?;
+        cmd.args(["--end", "10", "--state-file", state_file]);
+        cmd.args(args);
+        cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+        let result = cmd.assert().code(1);
+        assert!(from_utf8(&result.get_output().stderr).unwrap().starts_with("State file"));
     }
-    return None;
+
+    fs::remove_file(state_file)?;
+    Ok(())
 }
 
 #[test]
-fn test_executor(){
+fn test_main_max_duration() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let state_file = std::env::temp_dir().join(format!("hdifinder-test-duration-{}", std::process::id()));
+    let state_file = state_file.to_str().unwrap();
 
-    let test_address = "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK";
-    let test_mnemonic_phrase = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
-    let test_seed = self::get_mnemonic(test_mnemonic_phrase).unwrap().to_seed("");
+    let started = Instant::now();
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--json", "--max-duration", "1s", "--purposes", "44", "--end", "100000000", "--chunksize", "100"]);
+    cmd.args(["--state-file", state_file]);
+    cmd.arg(mnemonic_test).arg("1BMJBmhLLdPyVr3NzstjopExv4BtGJbLXe");
+    let result = cmd.assert().code(2);
+    assert!(started.elapsed() < Duration::from_secs(30));
+    let stderr = from_utf8(&result.get_output().stderr).unwrap();
+    assert!(stderr.contains("Time limit reached"));
+    let stdout = from_utf8(&result.get_output().stdout).unwrap();
+    assert!(stdout.starts_with("{\"found\":false,"));
 
-    let test_wallet_config = WalletConf{
-        seed: test_seed,
-        account: AccountHDPath::new(Purpose::Pubkey, 0, 0)
-    };
+    // a follow-up run resumes from the scanned range
+    let state = state::load(state_file)?.unwrap();
+    assert!(state.next > 0);
+    fs::remove_file(state_file)?;
 
-    let execution_config =  ExecutionConf{
-        start: 10,
-        end: 25
-    };
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--max-duration", "soon"]);
+    cmd.arg(mnemonic_test).arg("1BMJBmhLLdPyVr3NzstjopExv4BtGJbLXe");
+    cmd.assert().code(1);
+    Ok(())
+}
+
+#[test]
+fn test_main_dry_run() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--dry-run", "-v", "--purposes", "44,84", "--threads", "2", "--end", "10", "--chunksize", "4"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    let output = from_utf8(&result.get_output().stdout).unwrap();
+    assert!(output.starts_with(
+        "Mnemonic read from the command line\n\
+         mnemonic: 24 words (English)\n\
+         network: bitcoin\n\
+         paths:\n    m/44'/0'/0'/0/i (p2pkh)\n    m/84'/0'/0'/0/i (p2wpkh)\n\
+         range: 0 to 9 (10 indexes)\n\
+         chunks: 3 of up to 4 indexes\n    0..4\n    4..8\n    8..10\n\
+         threads: 2\n\
+         keys to derive: 20\n\
+         calibration: 20 keys in "
+    ));
+    assert!(output.contains("estimated duration: 0:00:00\n"));
+    // the address isn't searched
+    assert!(!output.contains("found"));
 
-    assert!(executor(test_address,&test_wallet_config,execution_config).is_none());
+    // without --chunksize the chunks are sized from the range and the threads
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--dry-run", "-v", "--purposes", "44", "--threads", "2", "--end", "10000"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    let output = from_utf8(&result.get_output().stdout).unwrap();
+    assert!(output.starts_with("Using a chunksize of 625 indexes for 2 threads\n"));
+    assert!(output.contains("chunks: 16 of up to 625 indexes\n"));
+    Ok(())
+}
 
-    let execution_config =  ExecutionConf{
-        start: 0,
-        end: 10
-    };
-    
-    let result = executor(test_address,&test_wallet_config,execution_config.clone());
+#[test]
+fn test_main_log_levels() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["-vv", "--purposes", "44", "--end", "10", "--chunksize", "5", "--passphrase", "secret"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(2);
+    let stderr = from_utf8(&result.get_output().stderr).unwrap();
+    assert!(stderr.contains("range 0..10 in 2 chunks of up to 5 indexes, 1 wallets, 1 candidates"));
+    assert!(stderr.contains("chunk 0..5 of m/44'/0'/0'/0 started"));
+    assert!(stderr.contains("chunk 5..10 of m/44'/0'/0'/0 finished in "));
+    assert!(!stderr.contains("TRACE"));
+    assert!(!stderr.contains("secret"));
+    assert!(!stderr.contains("erupt"));
 
-    assert!(&result.is_some());
-    assert_eq!(result.clone().unwrap().0,5);
-    assert_eq!(result.clone().unwrap().1,test_address);
+    // one index in 4 is traced, without the debug lines
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--trace-derivations", "4", "--purposes", "44", "--end", "10", "--chunksize", "10"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    let stderr = from_utf8(&result.get_output().stderr).unwrap();
+    assert_eq!(stderr.lines().count(), 3);
+    assert!(stderr.contains("m/44'/0'/0'/0/4 p2pkh "));
+    assert!(!stderr.contains("chunk "));
 
+    // without -v, nothing is logged
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--purposes", "44", "--end", "10", "--chunksize", "5"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    assert!(result.get_output().stderr.is_empty());
+    Ok(())
 }
 
-/**
- * Configuration builder
- */
-fn load_config(args: &ArgMatches) -> SearchConfig {
-    let mut passphrase = String::new();
-    if args.is_present("passphrase") {
-        match args.value_of("passphrase") {
-            Some(r) => {
-                passphrase = r.to_string();
-            }
-            None => {
-                passphrase = "".to_string();
-            }
-        }
-    }
+#[test]
+fn test_main_litecoin() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--coin", "ltc", "--end", "10"]);
+    cmd.arg(mnemonic_test).arg("ltc1qjmxnz78nmc8nq77wuxh25n2es7rzm5c2rkk4wh").arg("LUWPbpM43E2p7ZSh8cyTBEkvpHmr3cB8Ez");
+    let result = cmd.assert().success();
+    let output = from_utf8(&result.get_output().stdout).unwrap();
+    assert!(output.contains("m/84'/2'/0'/0/0"));
+    assert!(output.contains("m/44'/2'/0'/0/0"));
 
-    let mut start: usize = 0;
-    if args.is_present("start") {
-        match args.value_of("start") {
-            Some(r) => {
-                start = r.parse::<usize>().unwrap_or_default();
-            }
-            None => {
-                start = 0;
-            }
-        }
-    }
+    // The coin of the targets is checked as their network is
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--coin", "ltc"]);
+    cmd.arg(mnemonic_test).arg("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+    cmd.assert()
+        .code(cli::EXIT_INVALID_ADDRESS)
+        .stderr("Target bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu is a bitcoin address but the search coin is litecoin; pass --coin btc. Exiting\n");
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg(mnemonic_test).arg("ltc1qjmxnz78nmc8nq77wuxh25n2es7rzm5c2rkk4wh");
+    cmd.assert()
+        .code(cli::EXIT_INVALID_ADDRESS)
+        .stderr("Target ltc1qjmxnz78nmc8nq77wuxh25n2es7rzm5c2rkk4wh is a litecoin address but the search coin is bitcoin; pass --coin ltc. Exiting\n");
 
-    let mut end: usize = 10000000;
-    if args.is_present("end") {
-        match args.value_of("end") {
-            Some(r) => {
-                end = r.parse::<usize>().unwrap_or_else(|_| 10000000);
-            }
-            None => {
-                end = 10000000;
-            }
-        }
-    }
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--coin", "ltc", "--type", "p2tr"]);
+    cmd.arg(mnemonic_test).arg("ltc1qjmxnz78nmc8nq77wuxh25n2es7rzm5c2rkk4wh");
+    cmd.assert()
+        .code(cli::EXIT_USAGE)
+        .stderr("--coin ltc can't scan p2tr addresses, which litecoin doesn't have. Exiting\n");
+    Ok(())
+}
 
-    let mut chunksize: usize = 2500;
-    if args.is_present("chunksize") {
-        match args.value_of("chunksize") {
-            Some(r) => {
-                chunksize = r.parse::<usize>().unwrap_or_else(|_| 2500);
-            }
-            None => {
-                chunksize = 2500;
-            }
-        }
-    }
+#[test]
+fn test_main_dogecoin() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--coin", "doge", "--end", "10"]);
+    cmd.arg(mnemonic_test).arg("DAcDAtJRztxBHyA6D6h8du1HguyTR43Mas");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout).unwrap().contains("m/44'/3'/0'/0/1"));
 
-    let address: String;
-    match args.value_of("address") {
-        Some(r) => address = r.to_string(),
-        None => {
-            println!("No address provided. Exiting");
-            exit(1);
-        }
+    // Dogecoin has no segwit, neither to scan nor to search for
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--coin", "doge", "--type", "p2wpkh"]);
+    cmd.arg(mnemonic_test).arg("DAcDAtJRztxBHyA6D6h8du1HguyTR43Mas");
+    cmd.assert()
+        .code(cli::EXIT_USAGE)
+        .stderr("--coin doge can't scan p2wpkh addresses, which dogecoin doesn't have. Exiting\n");
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--coin", "doge"]);
+    cmd.arg(mnemonic_test).arg("9yD3AjCTjHyHvs4chBsGMz3DNPshJXyL3i");
+    cmd.assert()
+        .code(cli::EXIT_INVALID_ADDRESS)
+        .stderr("Target 9yD3AjCTjHyHvs4chBsGMz3DNPshJXyL3i is a p2sh address, which can't be derived: it pays to a script rather than to a key of the wallet. Exiting\n");
+    Ok(())
+}
+
+#[test]
+fn test_main_bitcoin_cash() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    // The legacy form of a Bitcoin Cash address matches the cashaddr derived
+    for target in ["bitcoincash:qp8sfdhgjlq68hlzka9lcsxtcnvuvnd0xqxugfzzc5", "qp8sfdhgjlq68hlzka9lcsxtcnvuvnd0xqxugfzzc5", "18Cp2ivkLHyJwHMm9NzDRBh6Gi7m4MC2we"] {
+        let mut cmd = Command::cargo_bin("hdifinder")?;
+        cmd.args(["--coin", "bch", "--end", "10"]);
+        cmd.arg(mnemonic_test).arg(target);
+        let result = cmd.assert().success();
+        let output = from_utf8(&result.get_output().stdout).unwrap();
+        assert!(output.contains("m/44'/145'/0'/0/1"));
+        assert!(output.contains("bitcoincash:qp8sfdhgjlq68hlzka9lcsxtcnvuvnd0xqxugfzzc5"));
     }
 
-    return SearchConfig {
-        passphrase,
-        start,
-        end,
-        chunksize,
-        address,
-    };
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg(mnemonic_test).arg("bitcoincash:qp8sfdhgjlq68hlzka9lcsxtcnvuvnd0xqxugfzzc5");
+    cmd.assert()
+        .code(cli::EXIT_INVALID_ADDRESS)
+        .stderr("Target bitcoincash:qp8sfdhgjlq68hlzka9lcsxtcnvuvnd0xqxugfzzc5 is a bitcoin cash address but the search coin is bitcoin; pass --coin bch. Exiting\n");
+    Ok(())
 }
 
-/**
- * Builds a mnemonic object based on a mnemonic phrase
- */
-fn get_mnemonic(mnemonic: &str) -> Result<Mnemonic, Error> {
-    return Mnemonic::parse_normalized(&mnemonic);
+#[test]
+fn test_main_ethereum() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    // Targets are compared whatever their case
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--coin", "eth", "--end", "10"]);
+    cmd.arg(mnemonic_test).arg("0x6fac4d18c912343bf86fa7049364dd4e424ab9c0");
+    let result = cmd.assert().success();
+    let output = from_utf8(&result.get_output().stdout).unwrap();
+    assert!(output.contains("m/44'/60'/0'/0/1"));
+    assert!(output.contains("0x6Fac4D18c912343BF86fa7049364Dd4E424Ab9C0"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--coin", "eth", "--preset", "ledger-eth"]);
+    cmd.arg(mnemonic_test).arg("0x78839F6054d7ed13918bAe0473BA31b1Ca9D7265");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout).unwrap().contains("m/44'/60'/1'/0/0"));
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--coin", "eth"]);
+    cmd.arg(mnemonic_test).arg("0x6Fac4D18c912343BF86fa7049364Dd4E424Ab9c0");
+    cmd.assert()
+        .code(cli::EXIT_INVALID_ADDRESS)
+        .stderr("Invalid address 0x6Fac4D18c912343BF86fa7049364Dd4E424Ab9c0: EIP-55 checksum mismatch, expected 0x6Fac4D18c912343BF86fa7049364Dd4E424Ab9C0. Exiting\n");
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg(mnemonic_test).arg("0x6Fac4D18c912343BF86fa7049364Dd4E424Ab9C0");
+    cmd.assert()
+        .code(cli::EXIT_INVALID_ADDRESS)
+        .stderr("Target 0x6Fac4D18c912343BF86fa7049364Dd4E424Ab9C0 is an ethereum address but the search coin is bitcoin; pass --coin eth. Exiting\n");
+    Ok(())
 }
 
-/**
- * Executor configuration builder. Will be called before each
- * thread iteration.
- */
-fn get_executor_config(config: &SearchConfig, iteration: usize) -> ExecutionConf {
-    let mut conf = ExecutionConf {
-        start: iteration * config.chunksize,
-        end: 0,
-    };
+#[test]
+fn test_main_zcash() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--coin", "zec", "--end", "10"]);
+    cmd.arg(mnemonic_test).arg("t1aQ2b1XszNVo15BguYLbQGqETBL9QZA8Jq");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout).unwrap().contains("m/44'/133'/0'/0/1"));
+
+    let shielded = "zs1zr3lvrvcsvyyekegkza2znprnx808vyufrgf9nfa9ukksa2ss3tczx9efycg75wedv5s774jxwv";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--coin", "zec"]);
+    cmd.arg(mnemonic_test).arg(shielded);
+    cmd.assert().code(cli::EXIT_INVALID_ADDRESS).stderr(format!(
+        "Invalid address {}: shielded Zcash addresses aren't supported, only the transparent t1 ones are searched. Exiting\n",
+        shielded
+    ));
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--coin", "zec"]);
+    cmd.arg(mnemonic_test).arg("t3YBYRkRFuaWJZkM4BctRwX3LppjcVcRAoJ");
+    cmd.assert().code(cli::EXIT_INVALID_ADDRESS).stderr(
+        "Target t3YBYRkRFuaWJZkM4BctRwX3LppjcVcRAoJ is a p2sh address, which can't be derived: it pays to a script rather than to a key of the wallet. Exiting\n",
+    );
+    Ok(())
+}
 
-    let remaining = config.end - (iteration * config.chunksize);
+#[test]
+fn test_main_coin_config() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    let coin_config = std::env::temp_dir().join(format!("hdifinder-test-coins-{}.toml", std::process::id()));
+    let coin_config = coin_config.to_str().unwrap();
+    fs::write(coin_config, "[vtc]\ncoin_type = 28\np2pkh = 0x47\np2sh = 0x05\nhrp = \"vtc\"\nkinds = [\"p2pkh\", \"p2wpkh\"]\n")?;
 
-    if remaining < config.chunksize {
-        conf.end = conf.start + remaining;
-    } else {
-        conf.end = conf.start + config.chunksize
-    }
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--coin-config", coin_config, "--coin", "vtc", "--end", "10"]);
+    cmd.arg(mnemonic_test).arg("vtc1qfe8v6c4r39fq8xnjgcpunt5spdfcxw63zzfwru");
+    let result = cmd.assert().success();
+    assert!(from_utf8(&result.get_output().stdout).unwrap().contains("m/84'/28'/0'/0/0"));
 
-    return conf;
-}
-
-fn app() -> App<'static, 'static>{
-    App::new("hdifinder")
-            .version("1.0")
-            .author("Nelson Herbin <nelson@herbin.info>")
-            .about("A small utility to find if a key is part of an HD scheme")
-            .arg(
-                Arg::with_name("passphrase")
-                    .short("p")
-                    .long("passphrase")
-                    .help("The mnemonic passphrase")
-                    .takes_value(true),
-            )
-            .arg(
-                Arg::with_name("start")
-                    .short("s")
-                    .long("start")
-                    .help("The start index for key index search")
-                    .takes_value(true),
-            )
-            .arg(
-                Arg::with_name("end")
-                    .short("e")
-                    .long("end")
-                    .help("The end index for key index search")
-                    .takes_value(true),
-            )
-            .arg(
-                Arg::with_name("chunksize")
-                    .short("c")
-                    .long("chunksize")
-                    .help("The chuncksize for index search threads")
-                    .takes_value(true),
-            )
-            .arg(
-                Arg::with_name("mnemonic")
-                    .index(1)
-                    .help("A 24 words seed (without passphrase)"),
-            )
-            .arg(
-                Arg::with_name("address")
-                    .index(2)
-                    .help("The address to be found"),
-            )
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--coin", "vtc"]);
+    cmd.arg(mnemonic_test).arg("vtc1qfe8v6c4r39fq8xnjgcpunt5spdfcxw63zzfwru");
+    cmd.assert()
+        .code(cli::EXIT_USAGE)
+        .stderr("Unknown coin vtc. Supported coins are: btc, ltc, doge, bch, eth, dash, dgb, zec. Exiting\n");
+
+    fs::write(coin_config, "[btc]\ncoin_type = 0\np2pkh = 0x00\np2sh = 0x05\nkinds = [\"p2pkh\"]\n")?;
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--coin-config", coin_config, "--coin", "btc"]);
+    cmd.arg(mnemonic_test).arg("1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA");
+    cmd.assert()
+        .code(cli::EXIT_USAGE)
+        .stderr(format!("Invalid coin config {}: btc is a built-in coin, which can't be redefined. Exiting\n", coin_config));
+    fs::remove_file(coin_config)?;
+    Ok(())
 }
-fn main() {
-    let matches = self::app().get_matches();
 
-    let config: SearchConfig = self::load_config(&matches);
+#[test]
+fn test_main_bench() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["bench", "--keys", "40", "--json"]);
+    let result = cmd.assert().success();
+    let output = from_utf8(&result.get_output().stdout).unwrap();
+    assert!(output.starts_with("{\"keys\":40,"));
+    assert!(output.contains("{\"type\":\"p2tr\",\"keys\":10,"));
+    assert!(output.contains(",\"master_key_speedup\":"));
 
-    let slices = (config.end - config.start) / config.chunksize;
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["bench", "--keys", "8"]);
+    let result = cmd.assert().success();
+    let output = from_utf8(&result.get_output().stdout).unwrap();
+    assert!(output.starts_with("8 keys in "));
+    assert_eq!(output.lines().count(), 7);
+    assert!(output.contains("derivation hook disabled: "));
+    assert!(output.contains("cached master key: "));
 
-    let mnemonic;
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["bench", "--keys", "0"]);
+    cmd.assert().code(cli::EXIT_USAGE);
+    Ok(())
+}
 
-    match matches.value_of("mnemonic") {
-        Some(r) => mnemonic = r,
-        None => {
-            println!("No mnemonic found. Exiting");
-            exit(1);
-        }
-    }
+#[test]
+fn test_main_threads() -> Result<(), Box<dyn std::error::Error>> {
+    let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--json", "--threads", "3", "--purposes", "44", "--end", "10", "--chunksize", "5"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().success();
+    let output = from_utf8(&result.get_output().stdout).unwrap();
+    assert!(output.contains("\"threads\":3,"));
 
-    match self::get_mnemonic(mnemonic) {
-        Ok(mnemonic) => {
-            let seed = mnemonic.to_seed(&config.passphrase);
-            let wallet_config: WalletConf = WalletConf {
-                seed,
-                account: AccountHDPath::new(Purpose::Pubkey, 0, 0),
-            };
-
-            (0..slices).into_par_iter().for_each(|slice| {
-                let execution_config = self::get_executor_config(&config, slice);
-
-                match self::executor(&config.address, &wallet_config, execution_config) {
-                    Some(result) => {
-                        println!(
-                            "address {} found at index {}. address type: {}",
-                            result.1, result.0, result.2
-                        );
-                        exit(0);
-                    }
-                    None => {}
-                }
-            });
-        }
-        Err(_) => {
-            exit(1);
-        }
-    }
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--threads", "many"]);
+    cmd.arg(mnemonic_test).arg("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    let result = cmd.assert().code(1);
+    let output = from_utf8(&result.get_output().stderr).unwrap();
+    assert!(output.starts_with("error: invalid value 'many' for '--threads <threads>': invalid digit found in string\n"));
+    Ok(())
 }
 
 #[test]
-fn test_main() -> Result<(), Box<dyn std::error::Error>> {
+fn test_main_network_mismatch() -> Result<(), Box<dyn std::error::Error>> {
     let mnemonic_test: &str = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
-    let address_test: &str = "15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3";
     let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg("--network").arg("testnet");
     cmd.arg(mnemonic_test);
-    cmd.arg(address_test);
-    let result = cmd.assert().success();
-    let stdout = &result.get_output().stdout;
-    let output = from_utf8(&stdout).unwrap();
-    assert!(output.contains("15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3 "));
-    assert!(output.contains("15"));
-    assert!(output.contains("p2pkh"));
+    cmd.arg("15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3");
+    let result = cmd.assert().failure();
+    let stderr = &result.get_output().stderr;
+    let output = from_utf8(stderr).unwrap();
+    assert_eq!(
+        output,
+        "Target 15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3 is a bitcoin mainnet address but the search network is testnet; pass --network mainnet. Exiting\n"
+    );
+
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.arg(mnemonic_test).arg("tb1qv5zqqm8plkx9hzp485pnze0aljx6eg9js46dzq");
+    cmd.assert().code(4).stderr(
+        "Target tb1qv5zqqm8plkx9hzp485pnze0aljx6eg9js46dzq is a testnet address but the search network is bitcoin mainnet; pass --network testnet. Exiting\n",
+    );
+
+    // Regtest has its own bech32 prefix, testnet ones can't match
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--network", "regtest"]);
+    cmd.arg(mnemonic_test).arg("tb1qv5zqqm8plkx9hzp485pnze0aljx6eg9js46dzq");
+    cmd.assert().code(4).stderr(
+        "Target tb1qv5zqqm8plkx9hzp485pnze0aljx6eg9js46dzq is a testnet address but the search network is regtest; pass --network testnet. Exiting\n",
+    );
+
+    // The scan still runs when told to
+    let mut cmd = Command::cargo_bin("hdifinder")?;
+    cmd.args(["--network", "regtest", "--ignore-network-mismatch", "--end", "10"]);
+    cmd.arg(mnemonic_test).arg("tb1qv5zqqm8plkx9hzp485pnze0aljx6eg9js46dzq");
+    let result = cmd.assert().code(EXIT_NOT_FOUND);
+    assert!(from_utf8(&result.get_output().stdout)?.starts_with(
+        "Warning: target tb1qv5zqqm8plkx9hzp485pnze0aljx6eg9js46dzq is a testnet address but the search network is regtest; pass --network testnet. Scanning anyway as --ignore-network-mismatch is given\n"
+    ));
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,157 @@
+use bitcoin::{
+    util::{
+        ecdsa::{PrivateKey, PublicKey},
+        misc::signed_msg_hash,
+    },
+    Address,
+};
+use secp256k1::{
+    recovery::{RecoverableSignature, RecoveryId},
+    All, Message, Secp256k1,
+};
+#[cfg(test)]
+use {
+    crate::{address, derivation},
+    bitcoin::{network::constants::Network, util::misc::MessageSignature},
+    hdpath::{AccountHDPath, Purpose},
+};
+
+const BASE64_CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/**
+ * Encodes bytes in standard base64, padded, as signed messages are given
+ */
+pub fn to_base64(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| group | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_CHARSET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/**
+ * Decodes standard base64, None when a character isn't one of its digits
+ */
+pub fn from_base64(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut group = 0u32;
+    let mut bits = 0;
+    for c in encoded.trim_end_matches('=').bytes() {
+        group = group << 6 | BASE64_CHARSET.iter().position(|&digit| digit == c)? as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((group >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+#[test]
+fn test_base64(){
+    // RFC 4648 test vectors
+    for (bytes, encoded) in [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"), ("foob", "Zm9vYg=="), ("fooba", "Zm9vYmE="), ("foobar", "Zm9vYmFy")] {
+        assert_eq!(to_base64(bytes.as_bytes()), encoded);
+        assert_eq!(from_base64(encoded).unwrap(), bytes.as_bytes());
+    }
+}
+
+/**
+ * First byte of a signature for an address type, to which the recovery id is
+ * added. Bitcoin Core only signs with p2pkh addresses, the segwit ones using the
+ * values of BIP137 that Electrum and hardware wallets verify. The keys of
+ * uncompressed p2pkh addresses have their own range below the compressed one.
+ * Taproot addresses have none, their keys being signed with BIP322.
+ */
+fn header_base(address_type: &str) -> Option<u8> {
+    match address_type {
+        "p2pkh-uncompressed" => Some(27),
+        "p2pkh" => Some(31),
+        "p2shwpkh" => Some(35),
+        "p2wpkh" => Some(39),
+        _ => None,
+    }
+}
+
+/**
+ * Signs a message with a key, in the format of Bitcoin Core's `signmessage`:
+ * the recoverable signature of the message hash, with its header byte, in
+ * base64. None when the address type has no such format, i.e for p2tr.
+ */
+pub fn sign_message(secp: &Secp256k1<All>, key: &PrivateKey, address_type: &str, message: &str) -> Option<String> {
+    let header_base = self::header_base(address_type)?;
+    let hash = Message::from_slice(&signed_msg_hash(message)[..]).expect("a message hash is 32 bytes long");
+    let (recovery_id, compact) = secp.sign_recoverable(&hash, &key.key).serialize_compact();
+    let mut signature = [0u8; 65];
+    signature[0] = header_base + recovery_id.to_i32() as u8;
+    signature[1..].copy_from_slice(&compact);
+    Some(to_base64(&signature))
+}
+
+/**
+ * Verifies a signed message the way wallets do: the key is recovered from
+ * the signature and the address of the type its header tells is compared
+ * against the signing address
+ */
+pub fn verify_message(secp: &Secp256k1<All>, address: &Address, message: &str, signature: &str) -> bool {
+    let signature = match from_base64(signature) {
+        Some(signature) if signature.len() == 65 && (27..=42).contains(&signature[0]) => signature,
+        _ => return false,
+    };
+    let recovery_id = RecoveryId::from_i32(((signature[0] - 27) % 4) as i32).expect("the recovery id is within 0..4");
+    let recoverable = match RecoverableSignature::from_compact(&signature[1..], recovery_id) {
+        Ok(recoverable) => recoverable,
+        Err(_) => return false,
+    };
+    let hash = Message::from_slice(&signed_msg_hash(message)[..]).expect("a double SHA256 is 32 bytes");
+    let key = match secp.recover(&hash, &recoverable) {
+        Ok(key) => PublicKey::new(key),
+        Err(_) => return false,
+    };
+    let signer = match signature[0] {
+        27..=30 => Address::p2pkh(&PublicKey { compressed: false, ..key }, address.network),
+        31..=34 => Address::p2pkh(&key, address.network),
+        35..=38 => Address::p2shwpkh(&key, address.network).expect("a recovered key is compressed"),
+        _ => Address::p2wpkh(&key, address.network).expect("a recovered key is compressed"),
+    };
+    signer == *address
+}
+
+#[test]
+fn test_sign_message(){
+    let secp = Secp256k1::new();
+    let seed = derivation::get_mnemonic("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb", None).unwrap().to_seed("");
+    let master = derivation::get_master_key(seed, Network::Bitcoin).unwrap();
+    let message = "I own this address, 2026-10-16";
+    for purpose in [Purpose::Pubkey, Purpose::ScriptHash, Purpose::Witness] {
+        let chain_key = derivation::chain_key(&secp, &master, &AccountHDPath::new(purpose.clone(), 0, 0), 0).unwrap();
+        let key = derivation::index_key(&secp, &chain_key, 5).unwrap();
+        let address = address::build_address(&secp, derivation::get_public_key(&secp, key), &purpose, Network::Bitcoin).unwrap();
+        let signature = sign_message(&secp, &key.private_key, address::address_type(&purpose), message).unwrap();
+        assert!(verify_message(&secp, &address, message, &signature));
+        assert!(!verify_message(&secp, &address, "I don't own this address", &signature));
+        // Bitcoin Core verifies p2pkh signatures only
+        let core_signature = MessageSignature::from_slice(&from_base64(&signature).unwrap()).unwrap();
+        assert_eq!(core_signature.is_signed_by_address(&secp, &address, signed_msg_hash(message)).unwrap(), purpose == Purpose::Pubkey);
+    }
+    // The uncompressed key of m/44'/0'/0'/0/5 has an address of its own
+    let chain_key = derivation::chain_key(&secp, &master, &AccountHDPath::new(Purpose::Pubkey, 0, 0), 0).unwrap();
+    let mut key = derivation::index_key(&secp, &chain_key, 5).unwrap();
+    key.private_key.compressed = false;
+    let address = address::build_address(&secp, derivation::get_public_key(&secp, key), &Purpose::Pubkey, Network::Bitcoin).unwrap();
+    let signature = sign_message(&secp, &key.private_key, address::P2PKH_UNCOMPRESSED, message).unwrap();
+    assert!(verify_message(&secp, &address, message, &signature));
+    let core_signature = MessageSignature::from_slice(&from_base64(&signature).unwrap()).unwrap();
+    assert!(core_signature.is_signed_by_address(&secp, &address, signed_msg_hash(message)).unwrap());
+
+    let chain_key = derivation::chain_key(&secp, &master, &AccountHDPath::new(Purpose::Custom(86), 0, 0), 0).unwrap();
+    let key = derivation::index_key(&secp, &chain_key, 0).unwrap();
+    assert_eq!(sign_message(&secp, &key.private_key, "p2tr", message), None);
+}
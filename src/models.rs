@@ -1,8 +1,253 @@
-use hdpath::{AccountHDPath};
+use crate::presets::{Layout, NamedAccount};
+use bip39::Language;
+use bitcoin::{
+    network::constants::Network,
+    util::{
+        bip32::{ChildNumber, DerivationPath, ExtendedPrivKey, ExtendedPubKey, Fingerprint},
+        ecdsa::PrivateKey,
+        psbt::PartiallySignedTransaction,
+    },
+};
+use hdpath::{AccountHDPath, Purpose};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    ops::RangeInclusive,
+    time::Duration,
+};
+
+/**
+ * Key of the scanned chain, i.e m/purpose'/coin'/account'/change.
+ * A public chain key is enough to derive the addresses of the indexes
+ * without handling any of their private keys.
+ */
+pub enum ChainKey {
+    Private(ExtendedPrivKey),
+    Public(ExtendedPubKey),
+}
+
+/**
+ * Seed the master key is computed from. Mnemonics give 64 bytes while BIP32
+ * allows 16 to 64, so the length is kept along with the bytes.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Seed {
+    bytes: [u8; 64],
+    len: usize,
+}
+
+impl Seed {
+    pub const MIN_LEN: usize = 16;
+    pub const MAX_LEN: usize = 64;
+
+    /**
+     * Builds a seed from 16 to 64 bytes, None otherwise
+     */
+    pub fn from_slice(slice: &[u8]) -> Option<Seed> {
+        if !(Seed::MIN_LEN..=Seed::MAX_LEN).contains(&slice.len()) {
+            return None;
+        }
+        let mut bytes = [0; 64];
+        bytes[..slice.len()].copy_from_slice(slice);
+        Some(Seed { bytes, len: slice.len() })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl From<[u8; 64]> for Seed {
+    fn from(bytes: [u8; 64]) -> Seed {
+        Seed { bytes, len: 64 }
+    }
+}
+
+/**
+ * A multisig wallet the seed is one of the cosigners of, each index paying
+ * to the script of the keys of every cosigner at that index
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Multisig {
+    // Number of signatures the script requires
+    pub threshold: usize,
+    // The extended public keys of the other cosigners, the chains of a wallet
+    // being derived below them, e.g xpub.../0/*
+    pub cosigners: Vec<ExtendedPubKey>,
+    // The keys are sorted as BIP67 tells, as sortedmulti() does, rather than
+    // kept in order
+    pub sorted: bool,
+    // Position of the key of the seed among the cosigners of an unsorted
+    // script, 0 being first
+    pub key_position: usize,
+    // The witness script is nested in p2sh rather than native p2wsh
+    pub nested: bool,
+}
 
 pub struct WalletConf {
-    pub seed: [u8; 64],
+    pub chain_key: ChainKey,
     pub account: AccountHDPath,
+    pub change: u32,
+    pub network: Network,
+    // The coin the addresses are encoded for
+    pub coin: Coin,
+    // The candidate the keys have been derived with, when trying a list of passphrases
+    pub passphrase: Option<PassphraseCandidate>,
+    // The line of the mnemonics file the keys have been derived from
+    pub mnemonic_line: Option<usize>,
+    // How the mnemonic phrase has been corrected, when trying several versions of it
+    pub mnemonic_fix: Option<MnemonicFix>,
+    // The keys come from an extended public key, the seed being unknown
+    pub watch_only: bool,
+    // The BIP85 child index of the mnemonic the keys have been derived from
+    pub bip85_child: Option<u32>,
+    // Path of the chain when it doesn't follow the purpose'/coin'/account'
+    // layout, e.g. for Electrum wallets
+    pub chain_path: Option<DerivationPath>,
+    // The name the preset gives to the account, e.g postmix
+    pub account_label: Option<&'static str>,
+    // The address indexes are hardened, e.g m/44'/0'/0'/0/5', as a few
+    // non-standard wallets derive them
+    pub hardened_index: bool,
+    // The addresses are the ones of the uncompressed public keys, as the
+    // wallets predating BIP32 compression used
+    pub uncompressed: bool,
+    // The addresses are the ones of a multisig script, the cosigners being
+    // given by their chain keys
+    pub multisig: Option<Multisig>,
+    // Key expression of the chain, e.g [fingerprint/84'/0'/0']xpub.../0, the
+    // descriptors of the matches are built from when they are emitted
+    pub descriptor_key: Option<String>,
+    // Report the private key of the matches, the chain key being private
+    pub show_private: bool,
+    // The extended public key of the account, reported along with the matches
+    pub account_xpub: Option<AccountXpub>,
+    // The fingerprint of the master key, unknown when scanning from an account
+    // or a chain key
+    pub fingerprint: Option<Fingerprint>,
+    // The message signed with the key of the matches
+    pub sign_message: Option<String>,
+    // The message signed with BIP322 instead of the legacy format
+    pub sign_message_bip322: Option<String>,
+    // Keep the private key of the matches, to sign the PSBT of --psbt with
+    pub sign_psbt: bool,
+}
+
+/**
+ * Extended public key of an account, to set up a watch-only wallet with
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountXpub {
+    pub path: String,
+    // The fingerprint of the master key, unknown when scanning from an account key
+    pub fingerprint: Option<String>,
+    pub xpub: String,
+    // The SLIP-132 encoding of the key for the address type, e.g zpub
+    pub slip132: Option<String>,
+}
+
+impl WalletConf {
+    /**
+     * Builds the configuration of a Bitcoin chain of an account, the options
+     * being left to their defaults: they are set with the struct update syntax,
+     * e.g `WalletConf { show_private: true, ..WalletConf::new(...) }`
+     */
+    pub fn new(chain_key: ChainKey, account: AccountHDPath, change: u32, network: Network) -> WalletConf {
+        WalletConf {
+            chain_key,
+            account,
+            change,
+            network,
+            coin: Coin::Bitcoin,
+            passphrase: None,
+            mnemonic_line: None,
+            mnemonic_fix: None,
+            watch_only: false,
+            bip85_child: None,
+            chain_path: None,
+            account_label: None,
+            hardened_index: false,
+            uncompressed: false,
+            multisig: None,
+            descriptor_key: None,
+            show_private: false,
+            account_xpub: None,
+            fingerprint: None,
+            sign_message: None,
+            sign_message_bip322: None,
+            sign_psbt: false,
+        }
+    }
+
+    /**
+     * Retrieves the path of the scanned chain
+     */
+    pub fn path(&self) -> DerivationPath {
+        match &self.chain_path {
+            Some(path) => path.clone(),
+            None => DerivationPath::from(&self.account).child(ChildNumber::Normal { index: self.change }),
+        }
+    }
+
+    /**
+     * Retrieves the child number of an address index of the chain
+     */
+    pub fn index_child(&self, index: u32) -> ChildNumber {
+        if self.hardened_index {
+            ChildNumber::Hardened { index }
+        } else {
+            ChildNumber::Normal { index }
+        }
+    }
+}
+
+/**
+ * The public keys of the multisig script of a match, as hex in the order of
+ * the script, along with the number of signatures it requires
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultisigKeys {
+    pub threshold: usize,
+    pub public_keys: Vec<String>,
+}
+
+/**
+ * Message signed with the key of a match, to prove the ownership of its address
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignedMessage {
+    pub message: String,
+    // The signature in the `signmessage` format, in base64
+    pub signature: String,
+}
+
+/**
+ * Correction of a transcription mistake in the mnemonic phrase
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum MnemonicFix {
+    // The last word replaced to get a valid checksum
+    LastWord(String),
+    // The words at two positions, counted from 0, swapped
+    Swap(usize, usize),
+}
+
+/**
+ * Passphrase read from a list of candidates, along with the line it
+ * has been read from so that a match can be reported without it
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct PassphraseCandidate {
+    pub line: usize,
+    pub passphrase: String,
 }
 
 #[derive(Clone)]
@@ -11,10 +256,235 @@ pub struct ExecutionConf {
     pub end: usize,
 }
 
+/**
+ * A cell of the grid a scan covers: a chunk of indexes of one of the
+ * wallets, i.e of one account, purpose and chain
+ */
+#[derive(Clone)]
+pub struct GridCell {
+    // The position of the wallet in the scanned ones
+    pub wallet: usize,
+    pub execution_config: ExecutionConf,
+}
+
 pub struct SearchConfig {
     pub start: usize,
     pub end: usize,
     pub chunksize: usize,
     pub passphrase: String,
+    // Tried in turn instead of `passphrase` when not empty
+    pub passphrase_candidates: Vec<PassphraseCandidate>,
+    pub redact: bool,
+    pub fix_checksum: bool,
+    pub try_swaps: Option<Swaps>,
+    pub language: Option<Language>,
+    pub addresses: HashSet<String>,
+    // What decoding each address tells before the scan
+    pub targets: HashMap<String, AddressTarget>,
+    pub stop_at_first: bool,
+    pub format: OutputFormat,
+    pub network: Network,
+    pub coin: Coin,
+    pub purposes: Vec<Purpose>,
+    pub accounts: RangeInclusive<u32>,
+    pub chains: Vec<u32>,
+    // Where the chains sit, below the master key for the legacy presets
+    pub layout: Layout,
+    // The accounts of the preset scanned besides the range, e.g the Whirlpool
+    // accounts of Samourai
+    pub named_accounts: Vec<NamedAccount>,
+    // Derive the address indexes hardened
+    pub hardened_index: bool,
+    // Scan the p2pkh addresses of the uncompressed public keys as well
+    pub include_uncompressed: bool,
+    // Scan the multisig wallet the seed is a cosigner of instead of its own
+    // addresses
+    pub multisig: Option<Multisig>,
+    pub progress: bool,
+    pub threads: usize,
+    pub pubkey_only: bool,
+    pub state_file: Option<String>,
+    pub max_duration: Option<Duration>,
+    pub dry_run: bool,
+    pub verbose: u64,
+    // Logs one in N of the addresses checked, along with their path
+    pub trace_derivations: Option<usize>,
+    // Number of BIP85 child mnemonics scanned instead of the mnemonic itself
+    pub bip85_children: Option<u32>,
+    pub bip85_words: usize,
+    // Print the Bitcoin Core import of the matched account, with its private
+    // keys when `with_private` is set
+    pub emit_descriptor: bool,
+    pub with_private: bool,
+    pub show_private: bool,
+    pub show_xpub: bool,
+    pub sign_message: Option<String>,
+    pub sign_message_bip322: Option<String>,
+    // The PSBT signed with the keys of the matches and the file it is written
+    // to, along with the keys of the scanned range when `sign_all_known` is set
+    pub psbt: Option<PartiallySignedTransaction>,
+    pub psbt_out: Option<String>,
+    pub sign_all_known: bool,
+    // Draw the address of the matches as QR codes, along with their private
+    // key when `qr_private` is set, or write them to `qr_file` as PNG images
+    pub qr: bool,
+    pub qr_private: bool,
+    pub qr_file: Option<String>,
+    // The file the results are written to, in `output_format`, `format` then
+    // being the one of stdout
+    pub output: Option<String>,
+    pub output_format: OutputFormat,
+    // Replace the file of `output` when it exists
+    pub force_output: bool,
+}
+
+/**
+ * Kind and network of a target address, as decoded from its encoding
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct AddressTarget {
+    // The address type the search computes for it, None when no derived type can
+    // produce it or when the target is a public key, matched by any type
+    pub address_type: Option<&'static str>,
+    pub network: Network,
+    // The label of the payment URI the address has been given with
+    pub label: Option<String>,
+    // The output script the address has been given as, in hex
+    pub script: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Swaps {
+    Adjacent,
+    All,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Ndjson,
+    Csv,
+    // Quiet modes printing only the index or the derivation path of the matches
+    Index,
+    Path,
+}
+
+/**
+ * Coin the addresses are encoded for. The keys are derived the same way
+ * for every coin, under its own BIP44 coin type. The coins whose addresses
+ * are plain base58 and bech32 ones can be described by their data instead
+ * of being built in.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Coin {
+    Bitcoin,
+    Litecoin,
+    Dogecoin,
+    BitcoinCash,
+    Ethereum,
+    Defined(CoinDefinition),
+}
+
+/**
+ * Data of a coin whose addresses are encoded as Bitcoin ones with other
+ * prefixes: its coin type, the versions of its base58 addresses, one or two
+ * bytes, the human readable part of its bech32 ones if it has segwit, and
+ * the purposes of the address types it has
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CoinDefinition {
+    pub name: &'static str,
+    pub coin_type: u32,
+    pub p2pkh: u16,
+    pub p2sh: u16,
+    pub hrp: Option<&'static str>,
+    pub purposes: &'static [Purpose],
+}
+
+impl fmt::Display for Coin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Coin::Bitcoin => write!(f, "bitcoin"),
+            Coin::Litecoin => write!(f, "litecoin"),
+            Coin::Dogecoin => write!(f, "dogecoin"),
+            Coin::BitcoinCash => write!(f, "bitcoin cash"),
+            Coin::Ethereum => write!(f, "ethereum"),
+            Coin::Defined(definition) => write!(f, "{}", definition.name),
+        }
+    }
+}
+
+/**
+ * Address computed for a derived public key, along with its type
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct DerivedAddress {
+    pub address_type: &'static str,
     pub address: String,
-}
\ No newline at end of file
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    pub index: usize,
+    pub account: u32,
+    pub chain: u32,
+    pub derivation_path: String,
+    // The fingerprint of the master key and the origin of the matched key, e.g
+    // [67b4602d/84'/0'/0'/0/5], when the master key is known
+    pub fingerprint: Option<String>,
+    pub origin: Option<String>,
+    pub address: String,
+    pub address_type: String,
+    pub target: String,
+    pub network: Network,
+    pub passphrase: Option<PassphraseCandidate>,
+    pub mnemonic_line: Option<usize>,
+    pub mnemonic_fix: Option<MnemonicFix>,
+    pub watch_only: bool,
+    pub bip85_child: Option<u32>,
+    // The name the preset gives to the account of the match
+    pub account_label: Option<String>,
+    // The keys of the multisig script of the match
+    pub multisig: Option<MultisigKeys>,
+    // The label of the payment URI the target has been given with
+    pub label: Option<String>,
+    // The output script the target has been given as, in hex
+    pub script: Option<String>,
+    // The compressed public key matched when the target is a key, along with
+    // the addresses of each standard type it has
+    pub public_key: Option<String>,
+    // The hash160 matched when the target is one, along with its p2pkh and
+    // p2wpkh addresses
+    pub hash160: Option<String>,
+    pub key_addresses: Vec<DerivedAddress>,
+    // The ranged descriptor of the matched chain, with its checksum
+    pub descriptor: Option<String>,
+    // The private key of the match in WIF, when asked for
+    pub wif: Option<String>,
+    // The extended public key of the account of the match, when asked for
+    pub account_xpub: Option<AccountXpub>,
+    // The message signed with the key of the match, when asked for and its
+    // address type has a signature format
+    pub signed_message: Option<SignedMessage>,
+    // The message signed with BIP322 and its proof, when asked for
+    pub bip322_message: Option<SignedMessage>,
+    // The private key of the match, kept to sign a PSBT with
+    pub private_key: Option<PrivateKey>,
+}
+
+/**
+ * Describes what has been scanned, reported when the address is not found
+ */
+pub struct ScanSummary {
+    pub scanned: usize,
+    pub start: usize,
+    pub end: usize,
+    pub address_types: Vec<&'static str>,
+    // Number of passphrases each index has been tried with
+    pub candidates: usize,
+    // Number of versions of the mnemonic phrase tried
+    pub mnemonics: usize,
+    // Number of accounts the index range has been scanned in
+    pub accounts: usize,
+}
@@ -0,0 +1,239 @@
+use crate::{
+    derivation,
+    error::HdiFinderError,
+    models::{DerivedAddress, Multisig},
+    slip132,
+};
+use bitcoin::{
+    blockdata::{opcodes, script::Builder},
+    network::constants::Network,
+    util::{
+        bip32::{ChildNumber, DerivationPath, ExtendedPubKey},
+        ecdsa::PublicKey,
+    },
+    Address, Script,
+};
+use secp256k1::{All, Secp256k1};
+use std::str::FromStr;
+
+/**
+ * Multisig wallets (BIP48): each cosigner derives its key under
+ * m/48'/coin'/account'/script_type', 2' for p2wsh and 1' for p2sh-p2wsh, and
+ * exports the extended public key of that path. The address of an index is
+ * the one of the script of the keys of every cosigner at that index.
+ */
+pub const P2WSH: &str = "p2wsh";
+
+/**
+ * Type of the addresses of a witness script nested in p2sh
+ */
+pub const P2SHWSH: &str = "p2shwsh";
+
+/**
+ * Retrieves the type of the addresses of a multisig wallet
+ */
+pub fn address_type(nested: bool) -> &'static str {
+    if nested {
+        P2SHWSH
+    } else {
+        P2WSH
+    }
+}
+
+/**
+ * Most keys a multisig witness script holds, as standardness allows
+ */
+pub const MAX_KEYS: usize = 20;
+
+/**
+ * Retrieves the BIP48 path of the account of a cosigner, e.g m/48'/0'/0'/2'
+ */
+pub fn account_path(coin_type: u32, account: u32, nested: bool) -> DerivationPath {
+    let script_type = if nested { 1 } else { 2 };
+    DerivationPath::from(
+        [48, coin_type, account, script_type]
+            .iter()
+            .map(|index| ChildNumber::Hardened { index: *index })
+            .collect::<Vec<ChildNumber>>(),
+    )
+}
+
+#[test]
+fn test_account_path(){
+    assert_eq!(account_path(0, 0, false).to_string(), "m/48'/0'/0'/2'");
+    assert_eq!(account_path(1, 3, true).to_string(), "m/48'/1'/3'/1'");
+}
+
+/**
+ * Checks the threshold of a script of a number of keys: at least one
+ * signature, and no more than there are keys
+ */
+pub fn check_threshold(threshold: usize, keys: usize) -> Result<(), String> {
+    if keys > MAX_KEYS {
+        return Err(format!("{} keys, a multisig script holding at most {}", keys, MAX_KEYS));
+    }
+    if threshold == 0 || threshold > keys {
+        return Err(format!("threshold {} out of the 1 to {} keys of the script", threshold, keys));
+    }
+    Ok(())
+}
+
+/**
+ * Parses the extended public key of a cosigner, given as xpub or with its
+ * SLIP-132 multisig prefix, e.g Zpub
+ */
+pub fn parse_cosigner(key: &str, network: Network) -> Result<ExtendedPubKey, HdiFinderError> {
+    let key = slip132::cosigner_to_bip32(key)?;
+    let key = ExtendedPubKey::from_str(&key).map_err(|error| HdiFinderError::InvalidArgument(format!("Invalid cosigner key: {}", error)))?;
+    derivation::check_key_network("public", key.network, network)?;
+    Ok(key)
+}
+
+/**
+ * Derives the chain keys of the cosigners, their extended keys standing for
+ * their accounts
+ */
+pub fn chain_keys(secp: &Secp256k1<All>, cosigners: &[ExtendedPubKey], change: u32) -> Result<Vec<ExtendedPubKey>, HdiFinderError> {
+    cosigners
+        .iter()
+        .map(|key| {
+            key.ckd_pub(secp, ChildNumber::Normal { index: change }).map_err(|source| HdiFinderError::Derivation {
+                target: format!("the chain {} of the cosigner key {}", change, key),
+                source,
+            })
+        })
+        .collect()
+}
+
+/**
+ * Builds the witness script of a multisig: OP_m <keys> OP_n OP_CHECKMULTISIG.
+ * The keys are sorted by their serialization when asked for, as BIP67 tells.
+ * Returns the script along with the keys in its order.
+ */
+pub fn witness_script(threshold: usize, keys: &[PublicKey], sorted: bool) -> (Script, Vec<PublicKey>) {
+    let mut keys = keys.to_vec();
+    if sorted {
+        keys.sort_by_key(|key| key.to_bytes());
+    }
+    let mut builder = Builder::new().push_int(threshold as i64);
+    for key in &keys {
+        builder = builder.push_key(key);
+    }
+    let script = builder.push_int(keys.len() as i64).push_opcode(opcodes::all::OP_CHECKMULTISIG).into_script();
+    (script, keys)
+}
+
+#[test]
+fn test_witness_script(){
+    use bitcoin::hashes::hex::ToHex;
+
+    // Vector of BIP67, the keys given unsorted
+    let keys: Vec<PublicKey> = [
+        "02ff12471208c14bd580709cb2358d98975247d8765f92bc25eab3b2763ed605f8",
+        "02fe6f0a5a297eb38c391581c4413e084773ea23954d93f7753db7dc0adc188b2f",
+    ]
+    .iter()
+    .map(|key| PublicKey::from_str(key).unwrap())
+    .collect();
+    let (script, sorted) = witness_script(2, &keys, true);
+    assert_eq!(
+        script.to_hex(),
+        "522102fe6f0a5a297eb38c391581c4413e084773ea23954d93f7753db7dc0adc188b2f2102ff12471208c14bd580709cb2358d98975247d8765f92bc25eab3b2763ed605f852ae"
+    );
+    assert_eq!(sorted, vec![keys[1], keys[0]]);
+    assert_eq!(Address::p2sh(&script, Network::Bitcoin).to_string(), "39bgKC7RFbpoCRbtD5KEdkYKtNyhpsNa3Z");
+
+    // multi() keeps the order of the keys
+    let (script, unsorted) = witness_script(2, &keys, false);
+    assert!(script.to_hex().starts_with("522102ff1247"));
+    assert_eq!(unsorted, keys);
+}
+
+/**
+ * Computes the address of an index of a multisig wallet from the key of the
+ * seed at that index, along with the keys of its script. The key of the seed
+ * takes its position among the cosigners before the keys are sorted.
+ */
+pub fn address_at(
+    secp: &Secp256k1<All>,
+    multisig: &Multisig,
+    key: PublicKey,
+    index: u32,
+    network: Network,
+) -> Result<(DerivedAddress, Vec<PublicKey>), HdiFinderError> {
+    let mut keys = multisig
+        .cosigners
+        .iter()
+        .map(|cosigner| Ok(derivation::index_pubkey(secp, cosigner, index)?.public_key))
+        .collect::<Result<Vec<PublicKey>, HdiFinderError>>()?;
+    keys.insert(multisig.key_position.min(keys.len()), key);
+    let (script, keys) = self::witness_script(multisig.threshold, &keys, multisig.sorted);
+    let address = if multisig.nested {
+        Address::p2shwsh(&script, network)
+    } else {
+        Address::p2wsh(&script, network)
+    };
+    let derived = DerivedAddress {
+        address_type: self::address_type(multisig.nested),
+        address: address.to_string(),
+    };
+    Ok((derived, keys))
+}
+
+#[test]
+fn test_address_at(){
+    use bitcoin::hashes::hex::ToHex;
+
+    // A 2-of-3 wallet, whose first descriptor is
+    // wsh(sortedmulti(2,[67b4602d/48'/0'/0'/2']xpub6Djgf4.../0/*,[73c5da0a/48'/0'/0'/2']xpub6DkFAX.../0/*,[b8688df1/48'/0'/0'/2']xpub6FQya7.../0/*))
+    let secp = Secp256k1::new();
+    let seed = derivation::get_mnemonic("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb", None).unwrap().to_seed("");
+    let master = derivation::get_master_key(seed, Network::Bitcoin).unwrap();
+    let cosigners = [
+        "xpub6DkFAXWQ2dHxq2vatrt9qyA3bXYU4ToWQwCHbf5XB2mSTexcHZCeKS1VZYcPoBd5X8yVcbXFHJR9R8UCVpt82VX1VhR28mCyxUFL4r6KFrf",
+        "xpub6FQya7zGhR92kacYsNnjreouvnHJMpXYsUXnW6NJJAJRCKsa26TzDy4LdnGhEurr3d6y1J8PJ7EEMKQp74XTqYvmGJNogYXSKDszYHtF8mX",
+    ];
+    let cosigners: Vec<ExtendedPubKey> = cosigners.iter().map(|key| parse_cosigner(key, Network::Bitcoin).unwrap()).collect();
+    let chain_path = account_path(0, 0, false).child(ChildNumber::Normal { index: 0 });
+    let chain_key = derivation::chain_pubkey(&secp, &master.derive_priv(&secp, &chain_path).unwrap());
+    let multisig = Multisig {
+        threshold: 2,
+        cosigners: chain_keys(&secp, &cosigners, 0).unwrap(),
+        sorted: true,
+        key_position: 0,
+        nested: false,
+    };
+    let key = derivation::index_pubkey(&secp, &chain_key, 3).unwrap().public_key;
+    let (derived, keys) = address_at(&secp, &multisig, key, 3, Network::Bitcoin).unwrap();
+    assert_eq!(derived.address, "bc1qcz9tjvwf307awl3ax77u4r7r27t0dva4m30428ac5c2fvmlfy0nskhqsjz");
+    assert_eq!(derived.address_type, "p2wsh");
+    let keys: Vec<String> = keys.iter().map(|key| key.to_bytes().to_hex()).collect();
+    assert_eq!(
+        keys,
+        [
+            "0225c9c0f7a023b9db62a40185ce6f74ea8683adb2192587b58aac26f47f7f1eb7",
+            "0348f8ac66cd7d63649e9e98f53538e37c84d5dd0e3822d62ae143e9baa64862c3",
+            "0349031328a3aebf7c953f793a4fe5becb94487d72d3eb4c9f3681ade7c57cb585"
+        ]
+    );
+
+    // multi(), the key of the seed first
+    let unsorted = Multisig { sorted: false, ..multisig.clone() };
+    let (derived, _) = address_at(&secp, &unsorted, key, 3, Network::Bitcoin).unwrap();
+    assert_eq!(derived.address, "bc1q0wg7xakk8n4ndf3rynfcrw20jw8f7f6mcqxdktgjj5gu3jlxqg3qjpdgqe");
+
+    // multi(), the key of the seed between the two cosigners
+    let second = Multisig { key_position: 1, ..unsorted.clone() };
+    let (derived, keys) = address_at(&secp, &second, key, 3, Network::Bitcoin).unwrap();
+    assert_eq!(derived.address, "bc1qvjtaz9qzxz9p5mvdjelgtvx9yx2rrwk7c822sspnswka068vnnhs5fkxyk");
+    assert_eq!(keys[1], key);
+    // Last, the order of the keys happens to be the sorted one
+    let last = Multisig { key_position: 2, ..unsorted };
+    let (derived, _) = address_at(&secp, &last, key, 3, Network::Bitcoin).unwrap();
+    assert_eq!(derived.address, "bc1qcz9tjvwf307awl3ax77u4r7r27t0dva4m30428ac5c2fvmlfy0nskhqsjz");
+
+    assert_eq!(check_threshold(2, 3), Ok(()));
+    assert_eq!(check_threshold(4, 3).unwrap_err(), "threshold 4 out of the 1 to 3 keys of the script");
+    assert!(check_threshold(0, 3).is_err());
+    assert!(check_threshold(2, 21).is_err());
+}
@@ -0,0 +1,1815 @@
+/**
+ * Prints a diagnostic message, on stderr when stdout is reserved for machine readable output
+ */
+macro_rules! notice {
+    ($json:expr, $($arg:tt)*) => {
+        $crate::output::notice($json, &format!($($arg)*))
+    };
+}
+
+/**
+ * Prints a warning like a diagnostic message, in yellow on a terminal
+ */
+macro_rules! warning {
+    ($json:expr, $($arg:tt)*) => {
+        $crate::output::warning($json, &format!($($arg)*))
+    };
+}
+
+/**
+ * Prints a line of what a command produces, on stdout whatever the format
+ */
+macro_rules! result {
+    ($($arg:tt)*) => {
+        $crate::output::result(&format!("{}\n", format_args!($($arg)*)))
+    };
+}
+
+use crate::style::{self, Stream, Style};
+use hdifinder::{
+    error::HdiFinderError,
+    models::{AccountXpub, AddressTarget, MnemonicFix, OutputFormat, PassphraseCandidate, ScanSummary, SearchResult, SignedMessage},
+    progress::Stats,
+    search,
+};
+#[cfg(test)]
+use {
+    bitcoin::network::constants::Network,
+    hdifinder::models::{DerivedAddress, MultisigKeys},
+};
+#[cfg(feature = "qr")]
+use {
+    qrcode::{Color, QrCode},
+    std::path::Path,
+};
+use log::LevelFilter;
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, Seek, SeekFrom, Write},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+
+/**
+ * Every message for the user is printed by the functions below, or by the
+ * writer for the results, so that they are colored the same way
+ */
+pub fn notice(machine_output: bool, message: &str) {
+    if machine_output {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
+
+pub fn warning(machine_output: bool, message: &str) {
+    if machine_output {
+        eprintln!("{}", style::paint(message, Style::Warning, Stream::Stderr));
+    } else {
+        println!("{}", style::paint(message, Style::Warning, Stream::Stdout));
+    }
+}
+
+/**
+ * Prints what a command produces, a report or a document, on stdout as is
+ */
+pub fn result(text: &str) {
+    print!("{}", text);
+}
+
+/**
+ * Diagnostics go through the log macros, on stderr, at the level of the
+ * number of -v given: info, debug then trace. `trace_derivations` enables the
+ * derivation trace alone. RUST_LOG overrides both.
+ * They never include a seed, a passphrase or a private key.
+ */
+pub fn init_logging(verbose: u64, trace_derivations: bool) {
+    let level = match verbose {
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(level);
+    if trace_derivations {
+        builder.filter_module(search::TRACE_TARGET, LevelFilter::Trace);
+    }
+    // Set once, a second call in the tests is ignored
+    let _ = builder
+        .parse_env("RUST_LOG")
+        .target(env_logger::Target::Stderr)
+        .try_init();
+}
+
+/**
+ * Prints an error the run stops on, on stderr
+ */
+pub fn error(message: &str) {
+    eprintln!("{}", style::paint(message, Style::Error, Stream::Stderr));
+}
+
+/**
+ * Escapes a string to be used as a JSON string value
+ */
+pub fn json_escape(value: &str) -> String {
+    serde_json::to_string(value).expect("a string always serializes to JSON")
+}
+
+#[test]
+fn test_json_escape(){
+    assert_eq!(json_escape("m/44'/0'/0'/0/5"), "\"m/44'/0'/0'/0/5\"");
+    assert_eq!(json_escape("a \"quoted\" \\ value\n"), "\"a \\\"quoted\\\" \\\\ value\\n\"");
+    assert_eq!(json_escape("\u{1}"), "\"\\u0001\"");
+}
+
+/**
+ * Number of indexes past a match an import covers, the keypool size Bitcoin
+ * Core looks ahead with by default
+ */
+pub const IMPORT_LOOKAHEAD: usize = 1000;
+
+/**
+ * Builds the Bitcoin Core `importdescriptors` request of the chain a match
+ * belongs to, when its descriptor has been built. The timestamp is 0 for the
+ * whole chain to be rescanned, the birth date of the wallet being unknown.
+ */
+pub fn import_descriptors(result: &SearchResult) -> Option<String> {
+    result.descriptor.as_ref().map(|descriptor| {
+        format!(
+            "[{{\"desc\":{},\"range\":[0,{}],\"timestamp\":0,\"internal\":{}}}]",
+            json_escape(descriptor),
+            result.index + IMPORT_LOOKAHEAD,
+            result.chain == 1
+        )
+    })
+}
+
+#[test]
+fn test_import_descriptors(){
+    assert_eq!(import_descriptors(&test_search_result()), None);
+    let result = SearchResult {
+        index: 1200,
+        chain: 1,
+        descriptor: Some("wpkh([67b4602d/84'/0'/0']xpub6C2n/1/*)#abcdefgh".to_string()),
+        ..test_search_result()
+    };
+    assert_eq!(
+        import_descriptors(&result).unwrap(),
+        "[{\"desc\":\"wpkh([67b4602d/84'/0'/0']xpub6C2n/1/*)#abcdefgh\",\"range\":[0,2200],\"timestamp\":0,\"internal\":true}]"
+    );
+    assert!(json_match(&result, false).ends_with(
+        ",\"importdescriptors\":[{\"desc\":\"wpkh([67b4602d/84'/0'/0']xpub6C2n/1/*)#abcdefgh\",\"range\":[0,2200],\"timestamp\":0,\"internal\":true}]}"
+    ));
+}
+
+/**
+ * Serializes a search result as a JSON object, along with the mnemonic line, the
+ * mnemonic fix and the passphrase candidate it has been found with when trying several.
+ * The passphrase is left out when redacted, only its line in the list being given then.
+ */
+pub fn json_match(result: &SearchResult, redact: bool) -> String {
+    let mut origin = String::new();
+    if let (Some(fingerprint), Some(key_origin)) = (&result.fingerprint, &result.origin) {
+        origin = format!(",\"fingerprint\":{},\"origin\":{}", json_escape(fingerprint), json_escape(key_origin));
+    }
+    let mut candidates = String::new();
+    if let Some(account_label) = &result.account_label {
+        candidates.push_str(&format!(",\"account_label\":{}", json_escape(account_label)));
+    }
+    if let Some(line) = result.mnemonic_line {
+        candidates.push_str(&format!(",\"mnemonic_line\":{}", line));
+    }
+    if let Some(child) = result.bip85_child {
+        candidates.push_str(&format!(",\"bip85_child\":{}", child));
+    }
+    match &result.mnemonic_fix {
+        Some(MnemonicFix::LastWord(word)) => candidates.push_str(&format!(",\"last_word\":{}", json_escape(word))),
+        Some(MnemonicFix::Swap(first, second)) => {
+            candidates.push_str(&format!(",\"swapped_words\":[{},{}]", first + 1, second + 1))
+        }
+        None => {}
+    }
+    match &result.passphrase {
+        Some(candidate) if redact => candidates.push_str(&format!(",\"passphrase_line\":{}", candidate.line)),
+        Some(candidate) => candidates.push_str(&format!(
+            ",\"passphrase_line\":{},\"passphrase\":{}",
+            candidate.line,
+            json_escape(&candidate.passphrase)
+        )),
+        None => {}
+    }
+    if result.watch_only {
+        candidates.push_str(",\"watch_only\":true");
+    }
+    if let Some(label) = &result.label {
+        candidates.push_str(&format!(",\"label\":{}", json_escape(label)));
+    }
+    if let Some(script) = &result.script {
+        candidates.push_str(&format!(",\"script\":{}", json_escape(script)));
+    }
+    if let Some(key) = &result.public_key {
+        let addresses: Vec<String> = result
+            .key_addresses
+            .iter()
+            .map(|derived| format!("{}:{}", json_escape(derived.address_type), json_escape(&derived.address)))
+            .collect();
+        candidates.push_str(&format!(",\"public_key\":{},\"key_addresses\":{{{}}}", json_escape(key), addresses.join(",")));
+    }
+    if let Some(multisig) = &result.multisig {
+        let keys: Vec<String> = multisig.public_keys.iter().map(|key| json_escape(key)).collect();
+        candidates.push_str(&format!(",\"multisig\":{{\"threshold\":{},\"public_keys\":[{}]}}", multisig.threshold, keys.join(",")));
+    }
+    if let Some(hash) = &result.hash160 {
+        let addresses: Vec<String> = result
+            .key_addresses
+            .iter()
+            .map(|derived| format!("{}:{}", json_escape(derived.address_type), json_escape(&derived.address)))
+            .collect();
+        candidates.push_str(&format!(",\"hash160\":{},\"key_addresses\":{{{}}}", json_escape(hash), addresses.join(",")));
+    }
+    if let Some(request) = self::import_descriptors(result) {
+        candidates.push_str(&format!(",\"importdescriptors\":{}", request));
+    }
+    if let Some(wif) = &result.wif {
+        candidates.push_str(&format!(",\"wif\":{}", json_escape(wif)));
+    }
+    if let Some(account) = &result.account_xpub {
+        candidates.push_str(&format!(",\"account_xpub\":{}", json_account_xpub(account)));
+    }
+    if let Some(signed) = &result.signed_message {
+        candidates.push_str(&format!(
+            ",\"signed_message\":{{\"address\":{},\"message\":{},\"signature\":{}}}",
+            json_escape(&result.address),
+            json_escape(&signed.message),
+            json_escape(&signed.signature)
+        ));
+    }
+    if let Some(signed) = &result.bip322_message {
+        candidates.push_str(&format!(
+            ",\"bip322\":{{\"address\":{},\"message\":{},\"signature\":{}}}",
+            json_escape(&result.address),
+            json_escape(&signed.message),
+            json_escape(&signed.signature)
+        ));
+    }
+    format!(
+        "{{\"found\":true,\"address\":{},\"index\":{},\"account\":{},\"derivation_path\":{}{},\"address_type\":{},\"network\":{}{}}}",
+        json_escape(&result.address),
+        result.index,
+        result.account,
+        json_escape(&result.derivation_path),
+        origin,
+        json_escape(&result.address_type),
+        json_escape(&result.network.to_string()),
+        candidates
+    )
+}
+
+#[test]
+fn test_json_match(){
+    assert_eq!(
+        json_match(&test_search_result(), false),
+        "{\"found\":true,\"address\":\"14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK\",\"index\":5,\"account\":0,\"derivation_path\":\"m/44'/0'/0'/0/5\",\"address_type\":\"p2pkh\",\"network\":\"bitcoin\"}"
+    );
+    let result = SearchResult {
+        fingerprint: Some("67b4602d".to_string()),
+        origin: Some("[67b4602d/44'/0'/0'/0/5]".to_string()),
+        ..test_search_result()
+    };
+    assert!(json_match(&result, false).contains(
+        ",\"derivation_path\":\"m/44'/0'/0'/0/5\",\"fingerprint\":\"67b4602d\",\"origin\":\"[67b4602d/44'/0'/0'/0/5]\",\"address_type\":"
+    ));
+    let result = test_candidate_search_result();
+    assert!(json_match(&result, false).ends_with(",\"network\":\"bitcoin\",\"passphrase_line\":12,\"passphrase\":\"Summer 2017!\"}"));
+    assert!(json_match(&result, true).ends_with(",\"network\":\"bitcoin\",\"passphrase_line\":12}"));
+    let result = SearchResult { mnemonic_fix: Some(MnemonicFix::LastWord("verb".to_string())), ..test_search_result() };
+    assert!(json_match(&result, false).ends_with(",\"network\":\"bitcoin\",\"last_word\":\"verb\"}"));
+    let result = SearchResult { mnemonic_fix: Some(MnemonicFix::Swap(17, 18)), ..test_search_result() };
+    assert!(json_match(&result, false).ends_with(",\"network\":\"bitcoin\",\"swapped_words\":[18,19]}"));
+    let result = SearchResult { mnemonic_line: Some(3), ..result };
+    assert!(json_match(&result, false).ends_with(",\"network\":\"bitcoin\",\"mnemonic_line\":3,\"swapped_words\":[18,19]}"));
+    let result = SearchResult { watch_only: true, ..test_search_result() };
+    assert!(json_match(&result, false).ends_with(",\"network\":\"bitcoin\",\"watch_only\":true}"));
+    let result = SearchResult { bip85_child: Some(7), ..test_search_result() };
+    assert!(json_match(&result, false).ends_with(",\"network\":\"bitcoin\",\"bip85_child\":7}"));
+    let result = SearchResult { label: Some("Cold \"storage\"".to_string()), ..test_search_result() };
+    assert!(json_match(&result, false).ends_with(",\"network\":\"bitcoin\",\"label\":\"Cold \\\"storage\\\"\"}"));
+    let result = SearchResult { account_label: Some("postmix".to_string()), mnemonic_line: Some(3), ..test_search_result() };
+    assert!(json_match(&result, false).ends_with(",\"network\":\"bitcoin\",\"account_label\":\"postmix\",\"mnemonic_line\":3}"));
+    assert!(text_match(&result, false).contains(". account label: postmix. mnemonic from line 3"));
+}
+
+/**
+ * Serializes the outcome of a scan reporting every match as a JSON object
+ */
+pub fn json_matches(matches: &[String], scanned: usize) -> String {
+    format!(
+        "{{\"found\":{},\"scanned\":{},\"matches\":[{}]}}",
+        !matches.is_empty(),
+        scanned,
+        matches.join(",")
+    )
+}
+
+/**
+ * Start and end of the document the matches of a scan still running are
+ * written in, every match being written over the end of the document and
+ * followed by it
+ */
+const JSON_PARTIAL_START: &str = "{\"partial\":true,\"matches\":[";
+const JSON_PARTIAL_END: &str = "]}\n";
+
+/**
+ * Serializes a scan without any match as a JSON object
+ */
+pub fn json_not_found(summary: &ScanSummary, elapsed: Duration) -> String {
+    let address_types: Vec<String> = summary.address_types.iter().map(|t| json_escape(t)).collect();
+    let mut candidates = String::new();
+    if summary.accounts > 1 {
+        candidates.push_str(&format!(",\"accounts\":{}", summary.accounts));
+    }
+    if summary.mnemonics > 1 {
+        candidates.push_str(&format!(",\"mnemonics\":{}", summary.mnemonics));
+    }
+    if summary.candidates > 1 {
+        candidates.push_str(&format!(",\"candidates\":{}", summary.candidates));
+    }
+    format!(
+        "{{\"found\":false,\"scanned\":{},\"start\":{},\"end\":{},\"address_types\":[{}]{},\"elapsed_ms\":{}}}",
+        summary.scanned,
+        summary.start,
+        summary.end,
+        address_types.join(","),
+        candidates,
+        elapsed.as_millis()
+    )
+}
+
+/**
+ * Describes a scan without any match along with the likely causes
+ */
+pub fn text_not_found(summary: &ScanSummary, elapsed: Duration) -> String {
+    let accounts = if summary.accounts > 1 {
+        format!(" of {} accounts", summary.accounts)
+    } else {
+        String::new()
+    };
+    if summary.candidates > 1 || summary.mnemonics > 1 {
+        let mut combinations = String::new();
+        if summary.mnemonics > 1 {
+            combinations.push_str(&format!("{} mnemonic candidates × ", summary.mnemonics));
+        }
+        if summary.candidates > 1 {
+            combinations.push_str(&format!("{} passphrase candidates × ", summary.candidates));
+        }
+        return format!(
+            "address not found after scanning {} keys ({}indexes {} to {}{}, {} addresses) in {:.2}s.\n\
+             Every candidate has been tried at every index. {}, the address may have been derived \
+             under another purpose (see --purposes) or at an index beyond the scanned range (see --end).",
+            summary.scanned,
+            combinations,
+            summary.start,
+            summary.end.saturating_sub(1),
+            accounts,
+            summary.address_types.join(", "),
+            elapsed.as_secs_f64(),
+            if summary.candidates > 1 {
+                "The passphrase may not be in the list"
+            } else {
+                "The passphrase may be wrong, the phrase may hold another mistake"
+            }
+        );
+    }
+    format!(
+        "address not found after scanning {} keys (indexes {} to {}{}, {} addresses) in {:.2}s.\n\
+         The passphrase may be wrong, the address may have been derived under another purpose \
+         (see --purposes) or at an index beyond the scanned range (see --end).",
+        summary.scanned,
+        summary.start,
+        summary.end.saturating_sub(1),
+        accounts,
+        summary.address_types.join(", "),
+        elapsed.as_secs_f64()
+    )
+}
+
+/**
+ * Serializes the totals of a scan as a JSON object
+ */
+pub fn json_summary(found: usize, scanned: usize, elapsed: Duration) -> String {
+    format!(
+        "{{\"summary\":true,\"found\":{},\"scanned\":{},\"elapsed_ms\":{}}}",
+        found,
+        scanned,
+        elapsed.as_millis()
+    )
+}
+
+#[test]
+fn test_json_summary(){
+    assert_eq!(
+        json_summary(3, 40, Duration::from_millis(1500)),
+        "{\"summary\":true,\"found\":3,\"scanned\":40,\"elapsed_ms\":1500}"
+    );
+}
+
+#[cfg(test)]
+fn test_scan_summary() -> ScanSummary {
+    ScanSummary {
+        scanned: 250,
+        start: 0,
+        end: 125,
+        address_types: vec!["p2pkh", "p2wpkh"],
+        candidates: 1,
+        mnemonics: 1,
+        accounts: 1,
+    }
+}
+
+#[test]
+fn test_json_not_found(){
+    assert_eq!(
+        json_not_found(&test_scan_summary(), Duration::from_millis(1500)),
+        "{\"found\":false,\"scanned\":250,\"start\":0,\"end\":125,\"address_types\":[\"p2pkh\",\"p2wpkh\"],\"elapsed_ms\":1500}"
+    );
+    assert_eq!(json_matches(&[], 250), "{\"found\":false,\"scanned\":250,\"matches\":[]}");
+
+    let summary = ScanSummary { candidates: 2, ..test_scan_summary() };
+    assert_eq!(
+        json_not_found(&summary, Duration::from_millis(1500)),
+        "{\"found\":false,\"scanned\":250,\"start\":0,\"end\":125,\"address_types\":[\"p2pkh\",\"p2wpkh\"],\"candidates\":2,\"elapsed_ms\":1500}"
+    );
+    let summary = ScanSummary { accounts: 4, ..test_scan_summary() };
+    assert!(json_not_found(&summary, Duration::from_millis(1500)).ends_with(",\"address_types\":[\"p2pkh\",\"p2wpkh\"],\"accounts\":4,\"elapsed_ms\":1500}"));
+}
+
+#[test]
+fn test_text_not_found(){
+    assert_eq!(
+        text_not_found(&test_scan_summary(), Duration::from_millis(1500)),
+        "address not found after scanning 250 keys (indexes 0 to 124, p2pkh, p2wpkh addresses) in 1.50s.\n\
+         The passphrase may be wrong, the address may have been derived under another purpose \
+         (see --purposes) or at an index beyond the scanned range (see --end)."
+    );
+    let summary = ScanSummary { candidates: 2, ..test_scan_summary() };
+    assert!(text_not_found(&summary, Duration::from_millis(1500)).starts_with(
+        "address not found after scanning 250 keys (2 passphrase candidates × indexes 0 to 124, p2pkh, p2wpkh addresses) in 1.50s.\n\
+         Every candidate has been tried at every index. The passphrase may not be in the list"
+    ));
+    let summary = ScanSummary { mnemonics: 8, ..test_scan_summary() };
+    assert!(text_not_found(&summary, Duration::from_millis(1500)).starts_with(
+        "address not found after scanning 250 keys (8 mnemonic candidates × indexes 0 to 124, p2pkh, p2wpkh addresses) in 1.50s.\n\
+         Every candidate has been tried at every index. The passphrase may be wrong, the phrase may hold another mistake"
+    ));
+    let summary = ScanSummary { accounts: 4, ..test_scan_summary() };
+    assert!(text_not_found(&summary, Duration::from_millis(1500)).starts_with(
+        "address not found after scanning 250 keys (indexes 0 to 124 of 4 accounts, p2pkh, p2wpkh addresses) in 1.50s.\n"
+    ));
+}
+
+/**
+ * Outcome of a scan for one of the lines of a mnemonics file
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum SeedStatus {
+    Found,
+    Scanned,
+    // Stopped by a match elsewhere or by the time limit
+    PartlyScanned,
+    Skipped(String),
+}
+
+/**
+ * Tells, line by line, which seeds of a mnemonics file have been fully scanned
+ */
+pub fn text_seeds_summary(path: &str, seeds: &[(usize, SeedStatus)]) -> String {
+    let mut summary = format!("seeds of {}:", path);
+    for (line, status) in seeds {
+        let status = match status {
+            SeedStatus::Found => "holds a match".to_string(),
+            SeedStatus::Scanned => "fully scanned".to_string(),
+            SeedStatus::PartlyScanned => "partly scanned, the scan stopped before the end of the range".to_string(),
+            SeedStatus::Skipped(reason) => format!("skipped, {}", reason),
+        };
+        summary.push_str(&format!("\n    line {}: {}", line, status));
+    }
+    summary
+}
+
+#[test]
+fn test_text_seeds_summary(){
+    let seeds = [
+        (1, SeedStatus::Scanned),
+        (2, SeedStatus::Skipped("invalid mnemonic: the phrase has 3 words".to_string())),
+        (4, SeedStatus::Found),
+        (5, SeedStatus::PartlyScanned),
+    ];
+    assert_eq!(
+        text_seeds_summary("seeds.txt", &seeds),
+        "seeds of seeds.txt:\n    line 1: fully scanned\n    line 2: skipped, invalid mnemonic: the phrase has 3 words\n    \
+         line 4: holds a match\n    line 5: partly scanned, the scan stopped before the end of the range"
+    );
+}
+
+/**
+ * Serializes the statistics of a run as a JSON object
+ */
+pub fn json_stats(stats: &Stats) -> String {
+    format!(
+        "{{\"keys\":{},\"elapsed_ms\":{},\"keys_per_second\":{:.0},\"threads\":{},\"chunks\":{}}}",
+        stats.keys,
+        stats.elapsed.as_millis(),
+        stats.keys_per_second(),
+        stats.threads,
+        stats.chunks
+    )
+}
+
+/**
+ * Adds the statistics of the run to a JSON object
+ */
+pub fn json_with_stats(object: &str, stats: &Stats) -> String {
+    // Only the closing brace of the object itself goes, the last field
+    // may be an object as well
+    format!("{},\"stats\":{}}}", object.strip_suffix('}').unwrap_or(object), json_stats(stats))
+}
+
+#[cfg(test)]
+fn test_stats() -> Stats {
+    Stats {
+        keys: 210,
+        elapsed: Duration::from_millis(1500),
+        threads: 4,
+        chunks: 10,
+    }
+}
+
+#[test]
+fn test_json_stats(){
+    assert_eq!(
+        json_with_stats("{\"found\":false,\"scanned\":250}", &test_stats()),
+        "{\"found\":false,\"scanned\":250,\"stats\":{\"keys\":210,\"elapsed_ms\":1500,\"keys_per_second\":140,\"threads\":4,\"chunks\":10}}"
+    );
+    assert!(json_with_stats("{\"found\":true,\"account_xpub\":{\"xpub\":\"xpub6C2n\"}}", &test_stats())
+        .starts_with("{\"found\":true,\"account_xpub\":{\"xpub\":\"xpub6C2n\"},\"stats\":{"));
+}
+
+/**
+ * Formats the statistics of a run as a human readable block
+ */
+pub fn text_stats(stats: &Stats) -> String {
+    format!(
+        "keys derived:    {}\n\
+         elapsed:         {:.2}s\n\
+         keys per second: {:.0}\n\
+         threads:         {}\n\
+         chunks:          {}",
+        stats.keys,
+        stats.elapsed.as_secs_f64(),
+        stats.keys_per_second(),
+        stats.threads,
+        stats.chunks
+    )
+}
+
+#[test]
+fn test_text_stats(){
+    assert_eq!(
+        text_stats(&test_stats()),
+        "keys derived:    210\nelapsed:         1.50s\nkeys per second: 140\nthreads:         4\nchunks:          10"
+    );
+}
+
+/**
+ * Retrieves the human readable name of a chain
+ */
+pub fn chain_name(change: u32) -> &'static str {
+    match change {
+        0 => "external",
+        _ => "internal",
+    }
+}
+
+/**
+ * Quotes a CSV field when it contains a separator, a quote or a line break
+ */
+pub fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[test]
+fn test_csv_escape(){
+    assert_eq!(csv_escape("m/44'/0'/0'/0/5"), "m/44'/0'/0'/0/5");
+    assert_eq!(csv_escape("a,b"), "\"a,b\"");
+    assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+    assert_eq!(csv_escape("two\nlines"), "\"two\nlines\"");
+}
+
+/**
+ * Describes the passphrase candidate of a match, quoted so that leading
+ * or trailing whitespace shows, or by its line only when redacted
+ */
+pub fn passphrase_text(candidate: &PassphraseCandidate, redact: bool) -> String {
+    if redact {
+        format!("line {}", candidate.line)
+    } else {
+        format!("{:?} (line {})", candidate.passphrase, candidate.line)
+    }
+}
+
+/**
+ * Describes how the mnemonic phrase has been corrected, positions counted from 1
+ */
+pub fn mnemonic_fix_text(fix: &MnemonicFix) -> String {
+    match fix {
+        MnemonicFix::LastWord(word) => format!("last word: {}", word),
+        MnemonicFix::Swap(first, second) => format!("words {} and {} swapped", first + 1, second + 1),
+    }
+}
+
+/**
+ * Formats the extended public key of an account along with its path and the
+ * fingerprint of its master key, when known
+ */
+pub fn text_account_xpub(account: &AccountXpub) -> String {
+    let mut text = account.xpub.clone();
+    if let Some(slip132) = &account.slip132 {
+        text.push_str(&format!(" ({})", slip132));
+    }
+    text.push_str(&format!(", path {}", account.path));
+    if let Some(fingerprint) = &account.fingerprint {
+        text.push_str(&format!(", master fingerprint {}", fingerprint));
+    }
+    text
+}
+
+/**
+ * Serializes the extended public key of an account as a JSON object
+ */
+pub fn json_account_xpub(account: &AccountXpub) -> String {
+    let mut json = format!("{{\"path\":{}", json_escape(&account.path));
+    if let Some(fingerprint) = &account.fingerprint {
+        json.push_str(&format!(",\"fingerprint\":{}", json_escape(fingerprint)));
+    }
+    json.push_str(&format!(",\"xpub\":{}", json_escape(&account.xpub)));
+    if let Some(slip132) = &account.slip132 {
+        json.push_str(&format!(",\"slip132\":{}", json_escape(slip132)));
+    }
+    json.push('}');
+    json
+}
+
+#[test]
+fn test_account_xpub(){
+    let account = AccountXpub {
+        path: "m/84'/0'/0'".to_string(),
+        fingerprint: Some("67b4602d".to_string()),
+        xpub: "xpub6C2n".to_string(),
+        slip132: Some("zpub6rLt".to_string()),
+    };
+    assert_eq!(text_account_xpub(&account), "xpub6C2n (zpub6rLt), path m/84'/0'/0', master fingerprint 67b4602d");
+    assert_eq!(
+        json_account_xpub(&account),
+        "{\"path\":\"m/84'/0'/0'\",\"fingerprint\":\"67b4602d\",\"xpub\":\"xpub6C2n\",\"slip132\":\"zpub6rLt\"}"
+    );
+    let account = AccountXpub { fingerprint: None, slip132: None, ..account };
+    assert_eq!(text_account_xpub(&account), "xpub6C2n, path m/84'/0'/0'");
+    assert_eq!(json_account_xpub(&account), "{\"path\":\"m/84'/0'/0'\",\"xpub\":\"xpub6C2n\"}");
+}
+
+/**
+ * Formats a signed message in the armored format wallets verify it from,
+ * the message followed by the address and the signature
+ */
+pub fn armored_message(address: &str, signed: &SignedMessage) -> String {
+    format!(
+        "-----BEGIN BITCOIN SIGNED MESSAGE-----\n{}\n-----BEGIN BITCOIN SIGNATURE-----\n{}\n{}\n-----END BITCOIN SIGNATURE-----",
+        signed.message, address, signed.signature
+    )
+}
+
+#[test]
+fn test_armored_message(){
+    let signed = SignedMessage { message: "I own this address".to_string(), signature: "H9Lc".to_string() };
+    assert_eq!(
+        armored_message("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", &signed),
+        "-----BEGIN BITCOIN SIGNED MESSAGE-----\nI own this address\n-----BEGIN BITCOIN SIGNATURE-----\n14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK\nH9Lc\n-----END BITCOIN SIGNATURE-----"
+    );
+    let result = SearchResult { signed_message: Some(signed), ..test_search_result() };
+    assert!(json_match(&result, false).ends_with(
+        ",\"signed_message\":{\"address\":\"14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK\",\"message\":\"I own this address\",\"signature\":\"H9Lc\"}}"
+    ));
+    assert!(csv_match(&result, false, CandidateColumns { signature: true, ..CandidateColumns::default() }).ends_with(",H9Lc"));
+}
+
+#[test]
+fn test_bip322_message(){
+    let signed = SignedMessage { message: "Hello World".to_string(), signature: "AkcwRAIg".to_string() };
+    let result = SearchResult { bip322_message: Some(signed), ..test_search_result() };
+    assert!(text_match(&result, false).ends_with(". BIP322 signature of \"Hello World\": AkcwRAIg"));
+    assert!(json_match(&result, false).ends_with(
+        ",\"bip322\":{\"address\":\"14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK\",\"message\":\"Hello World\",\"signature\":\"AkcwRAIg\"}}"
+    ));
+    assert!(csv_match(&result, false, CandidateColumns { bip322_signature: true, ..CandidateColumns::default() }).ends_with(",AkcwRAIg"));
+}
+
+/**
+ * Formats a search result as a line of the plain text output
+ */
+pub fn text_match(result: &SearchResult, redact: bool) -> String {
+    let mut line = format!(
+        "address {} found at account {} index {} on the {} chain. address type: {}. derivation path: {}",
+        result.address,
+        result.account,
+        result.index,
+        chain_name(result.chain),
+        result.address_type,
+        result.derivation_path
+    );
+    if let Some(origin) = &result.origin {
+        line.push_str(&format!(". key origin: {}", origin));
+    }
+    if let Some(account_label) = &result.account_label {
+        line.push_str(&format!(". account label: {}", account_label));
+    }
+    if let Some(mnemonic_line) = result.mnemonic_line {
+        line.push_str(&format!(". mnemonic from line {}", mnemonic_line));
+    }
+    if let Some(child) = result.bip85_child {
+        line.push_str(&format!(". BIP85 child mnemonic {}", child));
+    }
+    if let Some(fix) = &result.mnemonic_fix {
+        line.push_str(&format!(". mnemonic {}", mnemonic_fix_text(fix)));
+    }
+    if let Some(candidate) = &result.passphrase {
+        line.push_str(&format!(". passphrase: {}", passphrase_text(candidate, redact)));
+    }
+    if result.watch_only {
+        line.push_str(". watch-only");
+    }
+    if let Some(label) = &result.label {
+        line.push_str(&format!(". label: {}", label));
+    }
+    if let Some(script) = &result.script {
+        line.push_str(&format!(". script: {}", script));
+    }
+    if let Some(key) = &result.public_key {
+        let addresses: Vec<String> = result
+            .key_addresses
+            .iter()
+            .map(|derived| format!("{} {}", derived.address_type, derived.address))
+            .collect();
+        line.push_str(&format!(". public key: {} ({})", key, addresses.join(", ")));
+    }
+    if let Some(multisig) = &result.multisig {
+        line.push_str(&format!(
+            ". multisig {} of {} keys: {}",
+            multisig.threshold,
+            multisig.public_keys.len(),
+            multisig.public_keys.join(", ")
+        ));
+    }
+    if let Some(hash) = &result.hash160 {
+        let addresses: Vec<String> = result
+            .key_addresses
+            .iter()
+            .map(|derived| format!("{} {}", derived.address_type, derived.address))
+            .collect();
+        line.push_str(&format!(". hash160: {} ({})", hash, addresses.join(", ")));
+    }
+    if let Some(wif) = &result.wif {
+        line.push_str(&format!(". private key: {}", wif));
+    }
+    if let Some(account) = &result.account_xpub {
+        line.push_str(&format!(". account xpub: {}", text_account_xpub(account)));
+    }
+    if let Some(signed) = &result.bip322_message {
+        line.push_str(&format!(". BIP322 signature of \"{}\": {}", signed.message, signed.signature));
+    }
+    line
+}
+
+/**
+ * Columns added to the CSV output when trying several mnemonics or passphrases,
+ * when targets are given with a label, as a script, a public key or a hash160,
+ * when the private keys or the account keys are shown, when several accounts
+ * are scanned, when a preset scans named accounts or when the addresses are
+ * the ones of a multisig script
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CandidateColumns {
+    pub account: bool,
+    pub account_label: bool,
+    pub mnemonic_line: bool,
+    pub bip85_child: bool,
+    pub mnemonic_fix: bool,
+    pub passphrase: bool,
+    pub label: bool,
+    pub script: bool,
+    pub public_key: bool,
+    pub multisig: bool,
+    pub hash160: bool,
+    pub wif: bool,
+    pub account_xpub: bool,
+    pub signature: bool,
+    pub bip322_signature: bool,
+}
+
+/**
+ * Formats a search result as a CSV row, ending with the candidate columns.
+ * A column is left empty for a result without the matching candidate, e.g.
+ * the mnemonic fix for the phrase as given.
+ */
+pub fn csv_match(result: &SearchResult, redact: bool, columns: CandidateColumns) -> String {
+    let mut fields = vec![
+        result.index.to_string(),
+        result.derivation_path.clone(),
+        result.address.clone(),
+        result.address_type.clone(),
+        result.target.clone(),
+        result.fingerprint.clone().unwrap_or_default(),
+        result.origin.clone().unwrap_or_default(),
+    ];
+    if columns.account {
+        fields.push(result.account.to_string());
+    }
+    if columns.account_label {
+        fields.push(result.account_label.clone().unwrap_or_default());
+    }
+    if columns.mnemonic_line {
+        fields.push(result.mnemonic_line.map(|line| line.to_string()).unwrap_or_default());
+    }
+    if columns.bip85_child {
+        fields.push(result.bip85_child.map(|child| child.to_string()).unwrap_or_default());
+    }
+    if columns.mnemonic_fix {
+        fields.push(result.mnemonic_fix.as_ref().map(mnemonic_fix_text).unwrap_or_default());
+    }
+    if columns.passphrase {
+        let candidate = result.passphrase.as_ref();
+        fields.push(candidate.map(|candidate| candidate.line.to_string()).unwrap_or_default());
+        fields.push(candidate.filter(|_| !redact).map(|candidate| candidate.passphrase.clone()).unwrap_or_default());
+    }
+    if columns.label {
+        fields.push(result.label.clone().unwrap_or_default());
+    }
+    if columns.script {
+        fields.push(result.script.clone().unwrap_or_default());
+    }
+    if columns.public_key {
+        fields.push(result.public_key.clone().unwrap_or_default());
+    }
+    if columns.multisig {
+        // The keys of the script, space separated
+        fields.push(result.multisig.as_ref().map(|multisig| multisig.public_keys.join(" ")).unwrap_or_default());
+    }
+    if columns.hash160 {
+        fields.push(result.hash160.clone().unwrap_or_default());
+    }
+    if columns.wif {
+        fields.push(result.wif.clone().unwrap_or_default());
+    }
+    if columns.account_xpub {
+        fields.push(result.account_xpub.as_ref().map(|account| account.xpub.clone()).unwrap_or_default());
+    }
+    if columns.signature {
+        fields.push(result.signed_message.as_ref().map(|signed| signed.signature.clone()).unwrap_or_default());
+    }
+    if columns.bip322_signature {
+        fields.push(result.bip322_message.as_ref().map(|signed| signed.signature.clone()).unwrap_or_default());
+    }
+    fields
+        .iter()
+        .map(|field| csv_escape(field))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+pub const CSV_HEADER: &str = "index,derivation_path,address,type,target,fingerprint,origin";
+
+/**
+ * Builds the CSV header line, candidate columns included
+ */
+pub fn csv_header(columns: CandidateColumns) -> String {
+    let mut header = CSV_HEADER.to_string();
+    if columns.account {
+        header.push_str(",account");
+    }
+    if columns.account_label {
+        header.push_str(",account_label");
+    }
+    if columns.mnemonic_line {
+        header.push_str(",mnemonic_line");
+    }
+    if columns.bip85_child {
+        header.push_str(",bip85_child");
+    }
+    if columns.mnemonic_fix {
+        header.push_str(",mnemonic_fix");
+    }
+    if columns.passphrase {
+        header.push_str(",passphrase_line,passphrase");
+    }
+    if columns.label {
+        header.push_str(",label");
+    }
+    if columns.script {
+        header.push_str(",script");
+    }
+    if columns.public_key {
+        header.push_str(",public_key");
+    }
+    if columns.multisig {
+        header.push_str(",multisig_keys");
+    }
+    if columns.hash160 {
+        header.push_str(",hash160");
+    }
+    if columns.wif {
+        header.push_str(",wif");
+    }
+    if columns.account_xpub {
+        header.push_str(",account_xpub");
+    }
+    if columns.signature {
+        header.push_str(",signature");
+    }
+    if columns.bip322_signature {
+        header.push_str(",bip322_signature");
+    }
+    header
+}
+
+#[cfg(test)]
+fn test_search_result() -> SearchResult {
+    SearchResult {
+        index: 5,
+        account: 0,
+        chain: 0,
+        derivation_path: "m/44'/0'/0'/0/5".to_string(),
+        fingerprint: None,
+        origin: None,
+        address: "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK".to_string(),
+        address_type: "p2pkh".to_string(),
+        target: "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK".to_string(),
+        network: Network::Bitcoin,
+        passphrase: None,
+        mnemonic_line: None,
+        mnemonic_fix: None,
+        watch_only: false,
+        bip85_child: None,
+        account_label: None,
+        multisig: None,
+        label: None,
+        script: None,
+        public_key: None,
+        hash160: None,
+        key_addresses: Vec::new(),
+        descriptor: None,
+        wif: None,
+        account_xpub: None,
+        signed_message: None,
+        bip322_message: None,
+        private_key: None,
+    }
+}
+
+#[cfg(test)]
+fn test_pubkey_search_result() -> SearchResult {
+    SearchResult {
+        target: "02016653".to_string(),
+        public_key: Some("02016653".to_string()),
+        key_addresses: vec![
+            DerivedAddress {
+                address_type: "p2pkh",
+                address: "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK".to_string(),
+            },
+            DerivedAddress {
+                address_type: "p2wpkh",
+                address: "bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y".to_string(),
+            },
+        ],
+        ..test_search_result()
+    }
+}
+
+#[cfg(test)]
+fn test_candidate_search_result() -> SearchResult {
+    SearchResult {
+        passphrase: Some(PassphraseCandidate {
+            line: 12,
+            passphrase: "Summer 2017!".to_string(),
+        }),
+        ..test_search_result()
+    }
+}
+
+#[test]
+fn test_text_match(){
+    assert_eq!(
+        text_match(&test_search_result(), false),
+        "address 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK found at account 0 index 5 on the external chain. address type: p2pkh. derivation path: m/44'/0'/0'/0/5"
+    );
+    let result = SearchResult {
+        fingerprint: Some("67b4602d".to_string()),
+        origin: Some("[67b4602d/44'/0'/0'/0/5]".to_string()),
+        ..test_search_result()
+    };
+    assert!(text_match(&result, false).ends_with("derivation path: m/44'/0'/0'/0/5. key origin: [67b4602d/44'/0'/0'/0/5]"));
+    let result = SearchResult { wif: Some("L1TmQPcEkfoxHh6pJdbVASwiq18BpF3waAKf9LaannZWvLr4p2DF".to_string()), ..test_search_result() };
+    assert!(text_match(&result, false).ends_with("derivation path: m/44'/0'/0'/0/5. private key: L1TmQPcEkfoxHh6pJdbVASwiq18BpF3waAKf9LaannZWvLr4p2DF"));
+    assert!(json_match(&result, false).ends_with(",\"wif\":\"L1TmQPcEkfoxHh6pJdbVASwiq18BpF3waAKf9LaannZWvLr4p2DF\"}"));
+    assert!(csv_match(&result, false, CandidateColumns { wif: true, ..CandidateColumns::default() }).ends_with(",L1TmQPcEkfoxHh6pJdbVASwiq18BpF3waAKf9LaannZWvLr4p2DF"));
+    let result = test_candidate_search_result();
+    assert!(text_match(&result, false).ends_with("derivation path: m/44'/0'/0'/0/5. passphrase: \"Summer 2017!\" (line 12)"));
+    assert!(text_match(&result, true).ends_with("derivation path: m/44'/0'/0'/0/5. passphrase: line 12"));
+    let result = SearchResult { mnemonic_fix: Some(MnemonicFix::LastWord("verb".to_string())), ..result };
+    assert!(text_match(&result, true).ends_with("derivation path: m/44'/0'/0'/0/5. mnemonic last word: verb. passphrase: line 12"));
+    let result = SearchResult { mnemonic_fix: Some(MnemonicFix::Swap(17, 18)), ..result };
+    assert!(text_match(&result, true).ends_with("derivation path: m/44'/0'/0'/0/5. mnemonic words 18 and 19 swapped. passphrase: line 12"));
+    let result = SearchResult { mnemonic_line: Some(3), mnemonic_fix: None, passphrase: None, ..result };
+    assert!(text_match(&result, true).ends_with("derivation path: m/44'/0'/0'/0/5. mnemonic from line 3"));
+    let result = SearchResult { watch_only: true, ..test_search_result() };
+    assert!(text_match(&result, false).ends_with("derivation path: m/44'/0'/0'/0/5. watch-only"));
+    let result = SearchResult { bip85_child: Some(7), ..test_search_result() };
+    assert!(text_match(&result, false).ends_with("derivation path: m/44'/0'/0'/0/5. BIP85 child mnemonic 7"));
+    let result = SearchResult { label: Some("Cold storage".to_string()), ..test_search_result() };
+    assert!(text_match(&result, false).ends_with("derivation path: m/44'/0'/0'/0/5. label: Cold storage"));
+    let result = SearchResult { script: Some("76a914".to_string()), ..test_search_result() };
+    assert!(text_match(&result, false).ends_with("derivation path: m/44'/0'/0'/0/5. script: 76a914"));
+    let result = test_pubkey_search_result();
+    assert!(text_match(&result, false).ends_with(
+        "derivation path: m/44'/0'/0'/0/5. public key: 02016653 (p2pkh 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK, p2wpkh bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y)"
+    ));
+    assert!(json_match(&result, false).ends_with(
+        ",\"public_key\":\"02016653\",\"key_addresses\":{\"p2pkh\":\"14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK\",\"p2wpkh\":\"bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y\"}}"
+    ));
+    assert!(csv_match(&result, false, CandidateColumns { public_key: true, ..CandidateColumns::default() }).ends_with(",02016653"));
+    let result = SearchResult {
+        multisig: Some(MultisigKeys {
+            threshold: 2,
+            public_keys: vec!["0225c9c0".to_string(), "0348f8ac".to_string(), "03490313".to_string()],
+        }),
+        ..test_search_result()
+    };
+    assert!(text_match(&result, false).ends_with("derivation path: m/44'/0'/0'/0/5. multisig 2 of 3 keys: 0225c9c0, 0348f8ac, 03490313"));
+    assert!(json_match(&result, false).ends_with(",\"multisig\":{\"threshold\":2,\"public_keys\":[\"0225c9c0\",\"0348f8ac\",\"03490313\"]}}"));
+    assert!(csv_match(&result, false, CandidateColumns { multisig: true, ..CandidateColumns::default() }).ends_with(",0225c9c0 0348f8ac 03490313"));
+    let result = SearchResult {
+        target: "29b9c049".to_string(),
+        public_key: None,
+        hash160: Some("29b9c049".to_string()),
+        ..test_pubkey_search_result()
+    };
+    assert!(text_match(&result, false).ends_with(
+        "derivation path: m/44'/0'/0'/0/5. hash160: 29b9c049 (p2pkh 14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK, p2wpkh bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y)"
+    ));
+    assert!(json_match(&result, false).ends_with(
+        ",\"hash160\":\"29b9c049\",\"key_addresses\":{\"p2pkh\":\"14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK\",\"p2wpkh\":\"bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y\"}}"
+    ));
+}
+
+#[test]
+fn test_csv_match(){
+    assert_eq!(
+        csv_match(&test_search_result(), false, CandidateColumns::default()),
+        "5,m/44'/0'/0'/0/5,14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK,p2pkh,14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK,,"
+    );
+    let result = SearchResult {
+        fingerprint: Some("67b4602d".to_string()),
+        origin: Some("[67b4602d/44'/0'/0'/0/5]".to_string()),
+        ..test_search_result()
+    };
+    assert!(csv_match(&result, false, CandidateColumns::default()).ends_with(",p2pkh,14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK,67b4602d,[67b4602d/44'/0'/0'/0/5]"));
+    let result = test_candidate_search_result();
+    let passphrase = CandidateColumns { passphrase: true, ..CandidateColumns::default() };
+    assert!(csv_match(&result, false, passphrase).ends_with(",p2pkh,14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK,,,12,Summer 2017!"));
+    assert!(csv_match(&result, true, passphrase).ends_with(",p2pkh,14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK,,,12,"));
+    let all = CandidateColumns { account: true, account_label: true, mnemonic_line: true, bip85_child: true, mnemonic_fix: true, passphrase: true, label: true, script: true, public_key: true, multisig: true, hash160: true, wif: true, account_xpub: true, signature: true, bip322_signature: true };
+    assert!(csv_match(&result, true, all).ends_with(",p2pkh,14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK,,,0,,,,,12,,,,,,,,,,"));
+    assert_eq!(
+        csv_header(all),
+        "index,derivation_path,address,type,target,fingerprint,origin,account,account_label,mnemonic_line,bip85_child,mnemonic_fix,passphrase_line,passphrase,label,script,public_key,multisig_keys,hash160,wif,account_xpub,signature,bip322_signature"
+    );
+    let result = SearchResult { mnemonic_fix: Some(MnemonicFix::LastWord("verb".to_string())), ..result };
+    let fix = CandidateColumns { mnemonic_fix: true, ..passphrase };
+    assert!(csv_match(&result, true, fix).ends_with(",p2pkh,14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK,,,last word: verb,12,"));
+}
+
+/**
+ * Writes the search results in the selected format.
+ * Shared between the workers, so results buffered until the end of
+ * the scan are kept behind a mutex.
+ */
+/**
+ * The QR codes drawn for the matches, written to a PNG image for the first
+ * match when `file` is set
+ */
+#[cfg(feature = "qr")]
+pub struct QrOutput {
+    private: bool,
+    file: Option<String>,
+    written: AtomicBool,
+}
+
+#[cfg(feature = "qr")]
+impl QrOutput {
+    pub fn new(private: bool, file: Option<String>) -> QrOutput {
+        QrOutput {
+            private,
+            file,
+            written: AtomicBool::new(false),
+        }
+    }
+}
+
+/**
+ * Text of the QR code of an address, bech32 addresses being uppercased for
+ * the alphanumeric mode, which takes fewer modules
+ */
+#[cfg(feature = "qr")]
+pub fn qr_text(address: &str) -> String {
+    if ["bc1", "tb1", "bcrt1"].iter().any(|prefix| address.to_lowercase().starts_with(prefix)) {
+        address.to_uppercase()
+    } else {
+        address.to_string()
+    }
+}
+
+#[cfg(feature = "qr")]
+#[test]
+fn test_qr_text(){
+    assert_eq!(qr_text("bc1q2dq975h7y93xplagze42exmzflxl4uqhl435qd"), "BC1Q2DQ975H7Y93XPLAGZE42EXMZFLXL4UQHL435QD");
+    assert_eq!(qr_text("bcrt1qs7rpyl69wu6c7x9y23nkmye3rsjjthztnwhys7"), "BCRT1QS7RPYL69WU6C7X9Y23NKMYE3RSJJTHZTNWHYS7");
+    assert_eq!(qr_text("14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK"), "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+}
+
+/**
+ * Path of the image of the private key QR code, next to the one of the address
+ */
+#[cfg(feature = "qr")]
+fn private_qr_path(file: &str) -> String {
+    let path = Path::new(file);
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    let name = match path.extension() {
+        Some(extension) => format!("{}-private.{}", stem, extension.to_string_lossy()),
+        None => format!("{}-private", stem),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+#[cfg(feature = "qr")]
+#[test]
+fn test_private_qr_path(){
+    assert_eq!(private_qr_path("out.png"), "out-private.png");
+    assert_eq!(private_qr_path("/tmp/qr/out.png"), "/tmp/qr/out-private.png");
+    assert_eq!(private_qr_path("out"), "out-private");
+}
+
+/**
+ * Modules of light around the QR codes, so that scanners find them
+ */
+#[cfg(feature = "qr")]
+const QR_QUIET_ZONE: usize = 4;
+
+/**
+ * Pixels per module of the PNG images
+ */
+#[cfg(feature = "qr")]
+const QR_PNG_SCALE: usize = 8;
+
+/**
+ * Whether the module of a QR code at x, y is dark, the quiet zone around
+ * the code being light
+ */
+#[cfg(feature = "qr")]
+fn qr_is_dark(code: &QrCode, x: isize, y: isize) -> bool {
+    let width = code.width() as isize;
+    (0..width).contains(&x) && (0..width).contains(&y) && code[(x as usize, y as usize)] == Color::Dark
+}
+
+/**
+ * Draws a QR code with half block characters, two rows of modules per line.
+ * Light modules are the ones drawn, so that the code reads right on the dark
+ * background of terminals.
+ */
+#[cfg(feature = "qr")]
+fn qr_terminal(code: &QrCode) -> String {
+    let (start, end) = (-(QR_QUIET_ZONE as isize), (code.width() + QR_QUIET_ZONE) as isize);
+    let mut drawing = String::new();
+    for y in (start..end).step_by(2) {
+        for x in start..end {
+            let top = !self::qr_is_dark(code, x, y);
+            let bottom = y + 1 < end && !self::qr_is_dark(code, x, y + 1);
+            drawing.push(match (top, bottom) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            });
+        }
+        drawing.push('\n');
+    }
+    drawing.pop();
+    drawing
+}
+
+#[cfg(feature = "qr")]
+#[test]
+fn test_qr_terminal(){
+    let code = QrCode::new("HELLO WORLD").unwrap();
+    let drawing = qr_terminal(&code);
+    let lines: Vec<&str> = drawing.lines().collect();
+    // 21 modules and the quiet zone, two rows per line
+    assert_eq!(lines.len(), 15);
+    assert!(lines.iter().all(|line| line.chars().count() == 29));
+    assert_eq!(lines[0], "█".repeat(29));
+    // The top of the finder patterns
+    assert!(lines[2].starts_with("████ ▄▄▄▄▄ █"));
+}
+
+/**
+ * Writes a QR code to a grayscale PNG image, dark modules on a light
+ * background. The image of a private key is only readable by its owner.
+ */
+#[cfg(feature = "qr")]
+fn write_qr_png(code: &QrCode, path: &str, private: bool) -> io::Result<()> {
+    let width = (code.width() + 2 * QR_QUIET_ZONE) * QR_PNG_SCALE;
+    let mut pixels = Vec::with_capacity(width * width);
+    for row in 0..width {
+        let y = (row / QR_PNG_SCALE) as isize - QR_QUIET_ZONE as isize;
+        for column in 0..width {
+            let x = (column / QR_PNG_SCALE) as isize - QR_QUIET_ZONE as isize;
+            pixels.push(if self::qr_is_dark(code, x, y) { 0 } else { 255 });
+        }
+    }
+    let file = if private { self::create_private(path)? } else { File::create(path)? };
+    let mut encoder = png::Encoder::new(io::BufWriter::new(file), width as u32, width as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&pixels)?;
+    writer.finish()?;
+    Ok(())
+}
+
+#[cfg(feature = "qr")]
+#[test]
+fn test_write_qr_png(){
+    let path = std::env::temp_dir().join(format!("hdifinder-test-write-qr-{}.png", std::process::id()));
+    let path = path.to_string_lossy().into_owned();
+    write_qr_png(&QrCode::new("HELLO WORLD").unwrap(), &path, true).unwrap();
+    let png = fs::read(&path).unwrap();
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR"));
+    // 29 modules of 8 pixels
+    assert_eq!(&png[16..24], &[0, 0, 0, 232, 0, 0, 0, 232]);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+    fs::remove_file(&path).unwrap();
+}
+
+/**
+ * Creates a file only its owner can read, for the ones holding keys
+ */
+fn create_private(path: &str) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    options.open(path)
+}
+
+/**
+ * Creates a file only its owner can read, failing when it exists rather
+ * than taking over a file whose permissions may be wider
+ */
+fn create_new_private(path: &str) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    options.open(path)
+}
+
+/**
+ * The file the results are written to with --output, in a format of its own.
+ * The results go to a temporary file next to it, created for the run only and
+ * synced after every match: an aborted run leaves every match found so far in
+ * it. A JSON document is kept valid by writing the match over its end. Once
+ * the scan is over, the summary is added and the temporary file is renamed
+ * over the file, which is only replaced when the run completes.
+ */
+pub struct OutputFile {
+    path: String,
+    format: OutputFormat,
+    // Replace the file when it exists, otherwise it is created empty before
+    // the scan for no other run to take it
+    force: bool,
+    // The matches of a JSON document, locked before the file
+    matches: Mutex<Vec<String>>,
+    file: Mutex<Option<File>>,
+    // The empty file reserved without --force, removed by a run failing
+    reserved: AtomicBool,
+}
+
+impl OutputFile {
+    pub fn new(path: String, format: OutputFormat, force: bool) -> OutputFile {
+        OutputFile {
+            path,
+            format,
+            force,
+            matches: Mutex::new(Vec::new()),
+            file: Mutex::new(None),
+            reserved: AtomicBool::new(false),
+        }
+    }
+
+    /**
+     * The temporary file the results are written to until the run completes
+     */
+    pub fn partial_path(&self) -> String {
+        format!("{}.partial", self.path)
+    }
+
+    /**
+     * Creates the temporary file, only its owner can read, along with its
+     * header. Without --force, the file itself is created first, and an
+     * existing one refused.
+     */
+    fn create(&self, header: Vec<String>) -> Result<(), HdiFinderError> {
+        if !self.force {
+            match self::create_new_private(&self.path) {
+                Ok(_) => self.reserved.store(true, Ordering::SeqCst),
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+                    return Err(HdiFinderError::InvalidArgument(format!("{} already exists, give --force to overwrite it", self.path)))
+                }
+                Err(source) => return Err(HdiFinderError::Io { path: self.path.clone(), source }),
+            }
+        }
+        let partial = self.partial_path();
+        let mut file = match self::create_new_private(&partial) {
+            Ok(file) => file,
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+                return Err(HdiFinderError::InvalidArgument(format!(
+                    "{} holds the results of an interrupted run, move or remove it first",
+                    partial
+                )))
+            }
+            Err(source) => return Err(HdiFinderError::Io { path: partial, source }),
+        };
+        let contents = if self.format == OutputFormat::Json {
+            format!("{}{}", JSON_PARTIAL_START, JSON_PARTIAL_END)
+        } else {
+            header.iter().map(|line| format!("{}\n", line)).collect()
+        };
+        file.write_all(contents.as_bytes()).and_then(|_| file.sync_data()).map_err(|source| HdiFinderError::Io { path: partial, source })?;
+        *self.file.lock().map_err(|_| self::poisoned())? = Some(file);
+        Ok(())
+    }
+
+    /**
+     * Appends lines to the temporary file, or a match to the JSON document
+     */
+    fn push(&self, new_lines: Vec<String>) -> Result<(), HdiFinderError> {
+        if new_lines.is_empty() {
+            return Ok(());
+        }
+        let mut matches = self.matches.lock().map_err(|_| self::poisoned())?;
+        let mut file = self.file.lock().map_err(|_| self::poisoned())?;
+        let file = file.as_mut().ok_or_else(|| HdiFinderError::Internal(format!("{} is written before being created", self.path)))?;
+        let written = if self.format == OutputFormat::Json {
+            let separator = if matches.is_empty() { "" } else { "," };
+            let contents = format!("{}{}{}", separator, new_lines.join(","), JSON_PARTIAL_END);
+            matches.extend(new_lines);
+            file.seek(SeekFrom::End(-(JSON_PARTIAL_END.len() as i64))).and_then(|_| file.write_all(contents.as_bytes()))
+        } else {
+            let contents: String = new_lines.iter().map(|line| format!("{}\n", line)).collect();
+            file.write_all(contents.as_bytes())
+        };
+        written.and_then(|_| file.sync_data()).map_err(|source| HdiFinderError::Io { path: self.partial_path(), source })
+    }
+
+    /**
+     * Replaces the contents of the temporary file, with the whole JSON document
+     */
+    fn rewrite(&self, contents: &str) -> Result<(), HdiFinderError> {
+        let mut file = self.file.lock().map_err(|_| self::poisoned())?;
+        let file = file.as_mut().ok_or_else(|| HdiFinderError::Internal(format!("{} is written before being created", self.path)))?;
+        file.set_len(0)
+            .and_then(|_| file.seek(SeekFrom::Start(0)))
+            .and_then(|_| file.write_all(contents.as_bytes()))
+            .and_then(|_| file.sync_data())
+            .map_err(|source| HdiFinderError::Io { path: self.partial_path(), source })
+    }
+
+    /**
+     * Renames the temporary file over the file, once the run is complete
+     */
+    fn commit(&self) -> Result<(), HdiFinderError> {
+        // Closed before being renamed
+        drop(self.file.lock().map_err(|_| self::poisoned())?.take());
+        fs::rename(self.partial_path(), &self.path).map_err(|source| HdiFinderError::Io { path: self.path.clone(), source })?;
+        self.reserved.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+impl Drop for OutputFile {
+    /**
+     * A run failing before its end doesn't leave the empty file it reserved,
+     * its matches staying in the temporary file
+     */
+    fn drop(&mut self) {
+        if self.reserved.load(Ordering::SeqCst) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/**
+ * Error of a worker having panicked while holding the matches
+ */
+pub fn poisoned() -> HdiFinderError {
+    HdiFinderError::Internal("a worker panicked while reporting a match".to_string())
+}
+
+pub struct OutputWriter {
+    format: OutputFormat,
+    stop_at_first: bool,
+    columns: CandidateColumns,
+    redact: bool,
+    // What the targets given as payment URIs or scripts are reported with
+    targets: HashMap<String, AddressTarget>,
+    buffered: Mutex<Vec<String>>,
+    found: AtomicUsize,
+    // The reader of stdout went away, e.g. `| head -1`
+    closed: AtomicBool,
+    started: Instant,
+    #[cfg(feature = "qr")]
+    qr: Option<QrOutput>,
+    file: Option<OutputFile>,
+}
+
+impl OutputWriter {
+    pub fn new(format: OutputFormat, stop_at_first: bool, columns: CandidateColumns, redact: bool) -> OutputWriter {
+        OutputWriter {
+            format,
+            stop_at_first,
+            columns,
+            redact,
+            targets: HashMap::new(),
+            buffered: Mutex::new(Vec::new()),
+            found: AtomicUsize::new(0),
+            closed: AtomicBool::new(false),
+            started: Instant::now(),
+            #[cfg(feature = "qr")]
+            qr: None,
+            file: None,
+        }
+    }
+
+    /**
+     * Sets the targets whose label or script is reported along with their matches
+     */
+    pub fn with_targets(mut self, targets: HashMap<String, AddressTarget>) -> OutputWriter {
+        self.targets = targets;
+        self
+    }
+
+    /**
+     * Writes the results to a file as well
+     */
+    pub fn with_file(mut self, file: OutputFile) -> OutputWriter {
+        self.file = Some(file);
+        self
+    }
+
+    /**
+     * Draws the QR codes of the matches along with them
+     */
+    #[cfg(feature = "qr")]
+    pub fn with_qr(mut self, qr: QrOutput) -> OutputWriter {
+        self.qr = Some(qr);
+        self
+    }
+
+    /**
+     * Tells whether the reader of stdout went away, the scan having nowhere
+     * to report its next matches
+     */
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /**
+     * Writes to stdout and flushes it, the lock being held for the whole
+     * write so that lines written by different workers never interleave.
+     * A closed pipe, e.g. `| head -1`, closes the output instead of failing,
+     * nothing being written to it afterwards.
+     */
+    fn write_stdout<F>(&self, write: F) -> Result<(), HdiFinderError>
+    where
+        F: FnOnce(&mut dyn Write) -> io::Result<()>,
+    {
+        if self.is_closed() {
+            return Ok(());
+        }
+        let stdout = io::stdout();
+        let mut handle = stdout.lock();
+        match write(&mut handle).and_then(|_| handle.flush()) {
+            Ok(()) => Ok(()),
+            Err(error) if error.kind() == io::ErrorKind::BrokenPipe => {
+                self.closed.store(true, Ordering::SeqCst);
+                Ok(())
+            }
+            Err(source) => Err(HdiFinderError::Io {
+                path: "stdout".to_string(),
+                source,
+            }),
+        }
+    }
+
+    /**
+     * Draws the QR codes of a match, after it in text output and on stderr
+     * otherwise, or writes them to PNG images
+     */
+    #[cfg(feature = "qr")]
+    fn draw_qr(&self, result: &SearchResult, qr: &QrOutput) -> Result<(), HdiFinderError> {
+        // Only the first match gets images, the next ones would overwrite them
+        if qr.file.is_some() && qr.written.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let print = |message: String| {
+            if self.format == OutputFormat::Text {
+                self.write_stdout(|out| writeln!(out, "{}", message))
+            } else {
+                self::notice(true, &message);
+                Ok(())
+            }
+        };
+        let mut codes = vec![("address", self::qr_text(&result.address))];
+        if let Some(wif) = result.wif.as_ref().filter(|_| qr.private) {
+            codes.push(("private key", wif.clone()));
+        }
+        for (name, text) in codes {
+            let code = match QrCode::new(&text) {
+                Ok(code) => code,
+                Err(_) => {
+                    warning!(true, "The {} of the match is too long for a QR code", name);
+                    continue;
+                }
+            };
+            match &qr.file {
+                Some(file) => {
+                    let path = if name == "address" { file.clone() } else { self::private_qr_path(file) };
+                    match self::write_qr_png(&code, &path, name != "address") {
+                        Ok(()) => print(format!("QR code of the {} written to {}", name, path))?,
+                        Err(e) => warning!(true, "Unable to write the QR code of the {} to {}: {}", name, path, e),
+                    }
+                }
+                None => print(format!("QR code of the {}:\n{}", name, self::qr_terminal(&code)))?,
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * Prints what precedes the results, and creates the file of the results
+     */
+    pub fn begin(&self) -> Result<(), HdiFinderError> {
+        if self.format == OutputFormat::Csv {
+            self.write_stdout(|out| writeln!(out, "{}", csv_header(self.columns)))?;
+        }
+        if let Some(file) = &self.file {
+            let header = if file.format == OutputFormat::Csv { vec![csv_header(self.columns)] } else { Vec::new() };
+            file.create(header)?;
+        }
+        Ok(())
+    }
+
+    /**
+     * Lines a match is written with in a format, none for JSON whose matches
+     * are part of a single document
+     */
+    fn match_lines(&self, format: OutputFormat, result: &SearchResult) -> Vec<String> {
+        match format {
+            OutputFormat::Text => {
+                let mut lines = vec![text_match(result, self.redact)];
+                if let Some(request) = self::import_descriptors(result) {
+                    lines.push(format!("importdescriptors '{}'", request));
+                }
+                if let Some(signed) = &result.signed_message {
+                    lines.push(armored_message(&result.address, signed));
+                }
+                lines
+            }
+            OutputFormat::Csv => vec![csv_match(result, self.redact, self.columns)],
+            OutputFormat::Index => vec![result.index.to_string()],
+            OutputFormat::Path => vec![result.derivation_path.clone()],
+            OutputFormat::Ndjson => vec![json_match(result, self.redact)],
+            OutputFormat::Json => Vec::new(),
+        }
+    }
+
+    /**
+     * Prints or buffers a result as soon as it is found, and writes it to the
+     * file of the results
+     */
+    pub fn found(&self, result: &SearchResult) -> Result<(), HdiFinderError> {
+        self.found.fetch_add(1, Ordering::SeqCst);
+        let reported: SearchResult;
+        let result = match self.targets.get(&result.target) {
+            Some(target) if target.label.is_some() || target.script.is_some() => {
+                reported = SearchResult {
+                    label: target.label.clone(),
+                    script: target.script.clone(),
+                    ..result.clone()
+                };
+                &reported
+            }
+            _ => result,
+        };
+        if self.format == OutputFormat::Json {
+            self.buffered.lock().map_err(|_| self::poisoned())?.push(json_match(result, self.redact));
+        } else {
+            let lines = self.match_lines(self.format, result);
+            self.write_stdout(|out| {
+                for (i, line) in lines.iter().enumerate() {
+                    if i == 0 && self.format == OutputFormat::Text {
+                        writeln!(out, "{}", style::paint(line, Style::Found, Stream::Stdout))?;
+                    } else {
+                        writeln!(out, "{}", line)?;
+                    }
+                }
+                Ok(())
+            })?;
+        }
+        if let Some(file) = &self.file {
+            if file.format == OutputFormat::Json {
+                file.push(vec![json_match(result, self.redact)])?;
+            } else {
+                file.push(self.match_lines(file.format, result))?;
+            }
+        }
+        #[cfg(feature = "qr")]
+        if let Some(qr) = &self.qr {
+            self.draw_qr(result, qr)?;
+        }
+        Ok(())
+    }
+
+    /**
+     * The JSON document of a whole scan: every match of it, only the first one
+     * when the scan stops at it, or the summary of the scan when none is found
+     */
+    fn json_document(&self, matches: &[String], summary: &ScanSummary, elapsed: Duration) -> String {
+        if !self.stop_at_first {
+            json_matches(matches, summary.scanned)
+        } else if let Some(json) = matches.first() {
+            json.clone()
+        } else {
+            json_not_found(summary, elapsed)
+        }
+    }
+
+    /**
+     * Prints what follows the results once the whole range has been scanned.
+     * When nothing has been found, a summary of the scan is printed on stderr.
+     */
+    pub fn finish(&self, summary: &ScanSummary, stats: &Stats) -> Result<(), HdiFinderError> {
+        let found = self.found.load(Ordering::SeqCst);
+        let elapsed = self.started.elapsed();
+        let document = match self.format {
+            OutputFormat::Json => Some(json_with_stats(&self.json_document(&*self.buffered.lock().map_err(|_| self::poisoned())?, summary, elapsed), stats)),
+            OutputFormat::Ndjson => Some(json_summary(found, summary.scanned, elapsed)),
+            _ => None,
+        };
+        if let Some(document) = document {
+            self.write_stdout(|out| writeln!(out, "{}", document))?;
+        }
+        if let Some(file) = &self.file {
+            match file.format {
+                OutputFormat::Json => {
+                    let matches = file.matches.lock().map_err(|_| self::poisoned())?;
+                    file.rewrite(&format!("{}\n", json_with_stats(&self.json_document(&matches, summary, elapsed), stats)))?;
+                }
+                OutputFormat::Ndjson => file.push(vec![json_summary(found, summary.scanned, elapsed)])?,
+                _ => {}
+            }
+            file.commit()?;
+        }
+        // On stderr whatever the format, the text results being over
+        if found == 0 {
+            self::notice(true, &text_not_found(summary, elapsed));
+        }
+        if self.format == OutputFormat::Text {
+            self::notice(true, &text_stats(stats));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_output_file(){
+    let path = std::env::temp_dir().join(format!("hdifinder-test-output-{}.json", std::process::id()));
+    let path = path.to_str().unwrap();
+    let file = OutputFile::new(path.to_string(), OutputFormat::Json, false);
+    let partial = file.partial_path();
+    let writer = OutputWriter::new(OutputFormat::Index, false, CandidateColumns::default(), false).with_file(file);
+    writer.begin().unwrap();
+    // The file is reserved, the results going to the temporary file
+    assert_eq!(fs::read_to_string(path).unwrap(), "");
+    assert_eq!(fs::read_to_string(&partial).unwrap(), "{\"partial\":true,\"matches\":[]}\n");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(fs::metadata(&partial).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+    // Written as soon as found, in a document that stays valid
+    writer.found(&test_search_result()).unwrap();
+    let written = fs::read_to_string(&partial).unwrap();
+    assert!(written.starts_with("{\"partial\":true,\"matches\":[{\"found\":true,\"address\":\"14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK\""));
+    writer.found(&test_search_result()).unwrap();
+    let written: serde_json::Value = serde_json::from_str(&fs::read_to_string(&partial).unwrap()).unwrap();
+    assert_eq!(written["matches"].as_array().unwrap().len(), 2);
+    writer.finish(&test_scan_summary(), &test_stats()).unwrap();
+    assert!(fs::metadata(&partial).is_err());
+    let complete = fs::read_to_string(path).unwrap();
+    assert!(complete.starts_with("{\"found\":true,\"scanned\":250,\"matches\":[{\"found\":true,\"address\":\"14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK\""));
+    assert!(complete.ends_with("\"chunks\":10}}\n"));
+
+    // An existing file is only replaced with --force
+    let writer = OutputWriter::new(OutputFormat::Index, false, CandidateColumns::default(), false)
+        .with_file(OutputFile::new(path.to_string(), OutputFormat::Csv, false));
+    assert!(matches!(writer.begin(), Err(HdiFinderError::InvalidArgument(_))));
+    assert_eq!(fs::read_to_string(path).unwrap(), complete);
+    assert!(fs::metadata(&partial).is_err());
+    let writer = OutputWriter::new(OutputFormat::Index, false, CandidateColumns::default(), false)
+        .with_file(OutputFile::new(path.to_string(), OutputFormat::Csv, true));
+    writer.begin().unwrap();
+    writer.found(&test_search_result()).unwrap();
+    // Nothing replaces the previous results before the run completes
+    assert_eq!(fs::read_to_string(path).unwrap(), complete);
+    writer.finish(&test_scan_summary(), &test_stats()).unwrap();
+    let lines: Vec<String> = fs::read_to_string(path).unwrap().lines().map(|line| line.to_string()).collect();
+    assert_eq!(lines, [csv_header(CandidateColumns::default()), csv_match(&test_search_result(), false, CandidateColumns::default())]);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(fs::metadata(path).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+    fs::remove_file(path).unwrap();
+
+    // A file that can't be written fails the run rather than losing the matches
+    let missing = std::env::temp_dir().join(format!("hdifinder-test-output-missing-{}", std::process::id())).join("results.csv");
+    let writer = OutputWriter::new(OutputFormat::Index, false, CandidateColumns::default(), false)
+        .with_file(OutputFile::new(missing.to_string_lossy().into_owned(), OutputFormat::Csv, true));
+    assert!(matches!(writer.begin(), Err(HdiFinderError::Io { .. })));
+    assert!(matches!(writer.found(&test_search_result()), Err(HdiFinderError::Internal(_))));
+}
+
+#[test]
+fn test_output_file_abort(){
+    let path = std::env::temp_dir().join(format!("hdifinder-test-output-abort-{}.ndjson", std::process::id()));
+    let path = path.to_str().unwrap();
+    fs::write(path, "previous results\n").unwrap();
+    let file = OutputFile::new(path.to_string(), OutputFormat::Ndjson, true);
+    let partial = file.partial_path();
+    let writer = OutputWriter::new(OutputFormat::Index, false, CandidateColumns::default(), false).with_file(file);
+    writer.begin().unwrap();
+    writer.found(&test_search_result()).unwrap();
+    // A run aborted mid-way never gets to finish
+    drop(writer);
+    assert_eq!(fs::read_to_string(path).unwrap(), "previous results\n");
+    let written = fs::read_to_string(&partial).unwrap();
+    assert_eq!(written, format!("{}\n", json_match(&test_search_result(), false)));
+
+    // Its results aren't overwritten by the next run
+    let writer = OutputWriter::new(OutputFormat::Index, false, CandidateColumns::default(), false)
+        .with_file(OutputFile::new(path.to_string(), OutputFormat::Ndjson, true));
+    assert!(matches!(writer.begin(), Err(HdiFinderError::InvalidArgument(_))));
+    assert_eq!(fs::read_to_string(&partial).unwrap(), written);
+
+    // Without --force, the file reserved by a run failing is removed
+    fs::remove_file(path).unwrap();
+    let writer = OutputWriter::new(OutputFormat::Index, false, CandidateColumns::default(), false)
+        .with_file(OutputFile::new(path.to_string(), OutputFormat::Ndjson, false));
+    assert!(writer.begin().is_err());
+    drop(writer);
+    assert!(fs::metadata(path).is_err());
+    fs::remove_file(&partial).unwrap();
+}
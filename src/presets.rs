@@ -0,0 +1,313 @@
+use bitcoin::util::bip32::{ChildNumber, DerivationPath};
+use hdpath::Purpose;
+use std::ops::RangeInclusive;
+
+/**
+ * A wallet software derivation scheme.
+ * Address types are implied by the purposes (p2pkh for 44, p2shwpkh for 49,
+ * p2wpkh for 84 and p2tr for 86).
+ */
+pub struct Preset {
+    pub name: &'static str,
+    pub purposes: &'static [Purpose],
+    pub accounts: RangeInclusive<u32>,
+    pub chains: &'static [u32],
+    pub end: Option<usize>,
+    pub layout: Layout,
+    // Accounts scanned besides the range, the wallet giving them a name
+    pub named_accounts: &'static [NamedAccount],
+}
+
+/**
+ * An account a wallet keeps apart from the ones of its range, e.g. the
+ * Whirlpool accounts of Samourai, scanned under a single purpose and
+ * reported with its name
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct NamedAccount {
+    pub name: &'static str,
+    pub index: u32,
+    pub purpose: Purpose,
+}
+
+/**
+ * The Whirlpool accounts of Samourai, numbered from the top of the hardened
+ * account indexes, 2^31 - 1 excluded
+ */
+const WHIRLPOOL_ACCOUNTS: [NamedAccount; 3] = [
+    NamedAccount {
+        name: "bad bank",
+        index: 2_147_483_644,
+        purpose: Purpose::Witness,
+    },
+    NamedAccount {
+        name: "premix",
+        index: 2_147_483_645,
+        purpose: Purpose::Witness,
+    },
+    NamedAccount {
+        name: "postmix",
+        index: 2_147_483_646,
+        purpose: Purpose::Witness,
+    },
+];
+
+/**
+ * Where the chains of a wallet sit: under the purpose'/coin'/account'
+ * levels of BIP44, or right below the master key as the wallets predating
+ * it derived them, with p2pkh addresses only
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Layout {
+    Bip44,
+    // m/account'/chain/i, the default wallet layout of BIP32
+    Bip32,
+    // m/chain/i
+    Root,
+}
+
+impl Layout {
+    /**
+     * Tells whether the chains are below the master key without a purpose
+     * level
+     */
+    pub fn is_legacy(&self) -> bool {
+        *self != Layout::Bip44
+    }
+
+    /**
+     * Retrieves the path of the key the chains of a legacy layout are
+     * derived from, the master key itself for `Root`. None for BIP44 whose
+     * paths are the ones of the account.
+     */
+    pub fn account_path(&self, account: u32) -> Option<DerivationPath> {
+        match self {
+            Layout::Bip44 => None,
+            Layout::Bip32 => Some(DerivationPath::from(vec![ChildNumber::Hardened { index: account }])),
+            Layout::Root => Some(DerivationPath::from(Vec::new())),
+        }
+    }
+
+    /**
+     * Retrieves the path of a chain of a legacy layout, None for BIP44
+     */
+    pub fn chain_path(&self, account: u32, change: u32) -> Option<DerivationPath> {
+        self.account_path(account).map(|path| path.child(ChildNumber::Normal { index: change }))
+    }
+}
+
+pub const PRESETS: [Preset; 10] = [
+    // The wallets of blockchain.info before 2014, as well as Multibit HD,
+    // followed the default layout of BIP32
+    Preset {
+        name: "blockchain-info",
+        purposes: &[Purpose::Pubkey],
+        accounts: RangeInclusive::new(0, 0),
+        chains: &[0, 1],
+        end: None,
+        layout: Layout::Bip32,
+        named_accounts: &[],
+    },
+    Preset {
+        name: "bluewallet",
+        purposes: &[Purpose::Witness, Purpose::ScriptHash, Purpose::Pubkey],
+        accounts: RangeInclusive::new(0, 0),
+        chains: &[0, 1],
+        end: None,
+        layout: Layout::Bip44,
+        named_accounts: &[],
+    },
+    // Electrum wallets restored from a BIP39 seed
+    Preset {
+        name: "electrum",
+        purposes: &[Purpose::Pubkey, Purpose::ScriptHash, Purpose::Witness],
+        accounts: RangeInclusive::new(0, 0),
+        chains: &[0, 1],
+        end: None,
+        layout: Layout::Bip44,
+        named_accounts: &[],
+    },
+    Preset {
+        name: "exodus",
+        purposes: &[Purpose::Witness, Purpose::Pubkey],
+        accounts: RangeInclusive::new(0, 0),
+        chains: &[0, 1],
+        end: Some(100),
+        layout: Layout::Bip44,
+        named_accounts: &[],
+    },
+    // Ledger derives its Ethereum accounts at m/44'/60'/i'/0/0, the account
+    // index growing where the other wallets grow the address index
+    Preset {
+        name: "ledger-eth",
+        purposes: &[Purpose::Pubkey],
+        accounts: RangeInclusive::new(0, 19),
+        chains: &[0],
+        end: Some(1),
+        layout: Layout::Bip44,
+        named_accounts: &[],
+    },
+    // Ledger Live derives one account per path, so funds are spread across
+    // account indexes with only a few address indexes used in each of them.
+    Preset {
+        name: "ledger-live",
+        purposes: &[Purpose::Pubkey, Purpose::ScriptHash, Purpose::Witness],
+        accounts: RangeInclusive::new(0, 9),
+        chains: &[0],
+        end: Some(50),
+        layout: Layout::Bip44,
+        named_accounts: &[],
+    },
+    // Some of the first BIP32 wallets derived their chains right below the
+    // master key
+    Preset {
+        name: "legacy-bip32",
+        purposes: &[Purpose::Pubkey],
+        accounts: RangeInclusive::new(0, 0),
+        chains: &[0, 1],
+        end: None,
+        layout: Layout::Root,
+        named_accounts: &[],
+    },
+    Preset {
+        name: "mycelium",
+        purposes: &[Purpose::Pubkey, Purpose::ScriptHash, Purpose::Witness],
+        accounts: RangeInclusive::new(0, 4),
+        chains: &[0, 1],
+        end: None,
+        layout: Layout::Bip44,
+        named_accounts: &[],
+    },
+    // The deposit account of Samourai, along with the accounts its Whirlpool
+    // coinjoins move the funds through
+    Preset {
+        name: "samourai",
+        purposes: &[Purpose::Witness, Purpose::ScriptHash, Purpose::Pubkey],
+        accounts: RangeInclusive::new(0, 0),
+        chains: &[0, 1],
+        end: None,
+        layout: Layout::Bip44,
+        named_accounts: &WHIRLPOOL_ACCOUNTS,
+    },
+    Preset {
+        name: "trezor-suite",
+        purposes: &[Purpose::Witness, Purpose::ScriptHash, Purpose::Pubkey, Purpose::Custom(86)],
+        accounts: RangeInclusive::new(0, 9),
+        chains: &[0, 1],
+        end: None,
+        layout: Layout::Bip44,
+        named_accounts: &[],
+    },
+];
+
+/**
+ * Retrieves a preset from its name
+ */
+pub fn find_preset(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|preset| preset.name == name)
+}
+
+/**
+ * Retrieves the names of all the supported presets
+ */
+pub fn preset_names() -> Vec<&'static str> {
+    PRESETS.iter().map(|preset| preset.name).collect()
+}
+
+/**
+ * Describes the derivation paths a preset implies for a coin type,
+ * e.g m/84'/0'/0'/{0,1}/i, the legacy layouts having a single path
+ * without coin type, e.g m/0'/{0,1}/i. The paths of the named accounts
+ * follow the ones of the purposes.
+ */
+pub fn preset_paths(preset: &Preset, coin_type: u32) -> Vec<String> {
+    let accounts = if preset.accounts.start() == preset.accounts.end() {
+        preset.accounts.start().to_string()
+    } else {
+        format!("{{{}-{}}}", preset.accounts.start(), preset.accounts.end())
+    };
+    let chains = if preset.chains.len() == 1 {
+        preset.chains[0].to_string()
+    } else {
+        let chains: Vec<String> = preset.chains.iter().map(|c| c.to_string()).collect();
+        format!("{{{}}}", chains.join(","))
+    };
+    match preset.layout {
+        Layout::Bip32 => return vec![format!("m/{}'/{}/i", accounts, chains)],
+        Layout::Root => return vec![format!("m/{}/i", chains)],
+        Layout::Bip44 => {}
+    }
+
+    let named = preset.named_accounts.iter().map(|named| (&named.purpose, named.index.to_string()));
+    preset
+        .purposes
+        .iter()
+        .map(|purpose| (purpose, accounts.clone()))
+        .chain(named)
+        .map(|(purpose, accounts)| {
+            format!(
+                "m/{}'/{}'/{}'/{}/i",
+                purpose.as_value().as_number(),
+                coin_type,
+                accounts,
+                chains
+            )
+        })
+        .collect()
+}
+
+/**
+ * Retrieves the name a preset gives to an account, for the named accounts
+ */
+pub fn account_name(named_accounts: &[NamedAccount], purpose: &Purpose, account: u32) -> Option<&'static str> {
+    named_accounts
+        .iter()
+        .find(|named| named.index == account && named.purpose == *purpose)
+        .map(|named| named.name)
+}
+
+#[test]
+fn test_find_preset(){
+    assert_eq!(find_preset("ledger-live").unwrap().accounts, 0..=9);
+    assert!(find_preset("unknown").is_none());
+
+    // every preset must be reachable by its name
+    for name in preset_names() {
+        assert_eq!(find_preset(name).unwrap().name, name);
+    }
+}
+
+#[test]
+fn test_preset_paths(){
+    assert_eq!(
+        preset_paths(find_preset("ledger-live").unwrap(), 0),
+        vec!["m/44'/0'/{0-9}'/0/i", "m/49'/0'/{0-9}'/0/i", "m/84'/0'/{0-9}'/0/i"]
+    );
+    assert_eq!(preset_paths(find_preset("ledger-eth").unwrap(), 60), vec!["m/44'/60'/{0-19}'/0/i"]);
+    assert_eq!(preset_paths(find_preset("blockchain-info").unwrap(), 0), vec!["m/0'/{0,1}/i"]);
+    assert_eq!(preset_paths(find_preset("legacy-bip32").unwrap(), 0), vec!["m/{0,1}/i"]);
+    assert_eq!(
+        preset_paths(find_preset("samourai").unwrap(), 0)[3..],
+        ["m/84'/0'/2147483644'/{0,1}/i", "m/84'/0'/2147483645'/{0,1}/i", "m/84'/0'/2147483646'/{0,1}/i"]
+    );
+    assert_eq!(
+        preset_paths(find_preset("exodus").unwrap(), 1),
+        vec!["m/84'/1'/0'/{0,1}/i", "m/44'/1'/0'/{0,1}/i"]
+    );
+}
+
+#[test]
+fn test_account_name(){
+    let samourai = find_preset("samourai").unwrap();
+    assert_eq!(account_name(samourai.named_accounts, &Purpose::Witness, 2_147_483_646), Some("postmix"));
+    assert_eq!(account_name(samourai.named_accounts, &Purpose::Pubkey, 2_147_483_646), None);
+    assert_eq!(account_name(samourai.named_accounts, &Purpose::Witness, 0), None);
+}
+
+#[test]
+fn test_layout_chain_path(){
+    assert_eq!(Layout::Bip44.chain_path(0, 1), None);
+    assert_eq!(Layout::Bip32.chain_path(2, 1).unwrap().to_string(), "m/2'/1");
+    assert_eq!(Layout::Root.chain_path(2, 1).unwrap().to_string(), "m/1");
+    assert_eq!(Layout::Root.account_path(2).unwrap().to_string(), "m");
+}
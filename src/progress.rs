@@ -0,0 +1,208 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use std::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+const REFRESH_INTERVAL: Duration = Duration::from_millis(200);
+
+/**
+ * Layout of the progress bar: the keys scanned against the total, the
+ * throughput and the estimated time left, followed by the grid and the
+ * candidates
+ */
+const BAR_TEMPLATE: &str = "[{bar:30}] {percent:>3}% {pos}/{len} keys {per_sec} ETA {eta_precise}{msg}";
+
+/**
+ * Counts the keys scanned by the workers against the total to scan.
+ * Shared between the workers, which increment it as they go.
+ */
+pub struct Progress {
+    scanned: AtomicUsize,
+    total: usize,
+    candidates: usize,
+    grid: Option<(usize, usize)>,
+    started: Instant,
+}
+
+impl Progress {
+    pub fn new(total: usize) -> Progress {
+        Progress {
+            scanned: AtomicUsize::new(0),
+            total,
+            candidates: 1,
+            grid: None,
+            started: Instant::now(),
+        }
+    }
+
+    /**
+     * Splits the total evenly between several passphrase candidates,
+     * so that the number of candidates tried is shown along the keys
+     */
+    pub fn with_candidates(self, candidates: usize) -> Progress {
+        Progress { candidates, ..self }
+    }
+
+    /**
+     * Tells the keys are a grid of accounts by indexes, which the line
+     * then reports so that the coverage reads as the whole grid
+     */
+    pub fn with_grid(self, accounts: usize, indexes: usize) -> Progress {
+        Progress { grid: Some((accounts, indexes)), ..self }
+    }
+
+    /**
+     * Number of candidates worth of keys scanned so far
+     */
+    pub fn candidates_tried(&self) -> usize {
+        if self.total == 0 {
+            return self.candidates;
+        }
+        self.scanned().min(self.total) * self.candidates / self.total
+    }
+
+    /**
+     * Records that `count` more keys have been scanned
+     */
+    pub fn inc(&self, count: usize) {
+        self.scanned.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn scanned(&self) -> usize {
+        self.scanned.load(Ordering::Relaxed)
+    }
+
+    /**
+     * Builds the statistics of the run so far
+     */
+    pub fn stats(&self, threads: usize, chunks: usize) -> Stats {
+        Stats {
+            keys: self.scanned(),
+            elapsed: self.started.elapsed(),
+            threads,
+            chunks,
+        }
+    }
+
+    /**
+     * Renders what follows the bar: the candidates tried and the grid
+     * scanned when there are several
+     */
+    fn suffix(&self) -> String {
+        let mut line = String::new();
+        if let Some((accounts, indexes)) = self.grid {
+            line.push_str(&format!(" of {} accounts x {} indexes", accounts, indexes));
+        }
+        if self.candidates > 1 {
+            line.push_str(&format!(" {}/{} candidates", self.candidates_tried(), self.candidates));
+        }
+        line
+    }
+
+    /**
+     * Draws the progress bar on stderr, following the counter of the workers
+     * until `done` is set, then clears it
+     */
+    pub fn draw_until(&self, done: &AtomicBool) {
+        let bar = ProgressBar::new(self.total as u64);
+        bar.set_style(
+            ProgressStyle::with_template(BAR_TEMPLATE)
+                .expect("the progress bar template is valid")
+                .progress_chars("#-"),
+        );
+        while !done.load(Ordering::Relaxed) {
+            bar.set_position(self.scanned().min(self.total) as u64);
+            bar.set_message(self.suffix());
+            thread::sleep(REFRESH_INTERVAL);
+        }
+        bar.finish_and_clear();
+    }
+}
+
+#[test]
+fn test_progress_inc(){
+    let progress = Progress::new(100);
+    progress.inc(1);
+    progress.inc(10);
+    assert_eq!(progress.scanned(), 11);
+}
+
+#[test]
+fn test_candidates_tried(){
+    // 4 candidates of 25 keys each
+    let progress = Progress::new(100).with_candidates(4);
+    assert_eq!(progress.candidates_tried(), 0);
+    progress.inc(49);
+    assert_eq!(progress.candidates_tried(), 1);
+    progress.inc(51);
+    assert_eq!(progress.candidates_tried(), 4);
+    assert_eq!(Progress::new(0).with_candidates(4).candidates_tried(), 4);
+}
+
+#[test]
+fn test_progress_suffix(){
+    let progress = Progress::new(4000).with_grid(4, 1000).with_candidates(2);
+    progress.inc(2000);
+    assert_eq!(progress.suffix(), " of 4 accounts x 1000 indexes 1/2 candidates");
+    assert_eq!(Progress::new(4000).suffix(), "");
+    assert!(ProgressStyle::with_template(BAR_TEMPLATE).is_ok());
+}
+
+/**
+ * Throughput and timing statistics of a run
+ */
+pub struct Stats {
+    pub keys: usize,
+    pub elapsed: Duration,
+    pub threads: usize,
+    pub chunks: usize,
+}
+
+impl Stats {
+    pub fn keys_per_second(&self) -> f64 {
+        if self.elapsed.as_secs_f64() > 0.0 {
+            self.keys as f64 / self.elapsed.as_secs_f64()
+        } else {
+            0.0
+        }
+    }
+}
+
+#[test]
+fn test_stats(){
+    use rayon::prelude::*;
+
+    // 10 chunks of 25 indexes, the last two returning early after 5 indexes
+    let progress = Progress::new(250);
+    (0..10usize).into_par_iter().for_each(|chunk| {
+        let processed = if chunk >= 8 { 5 } else { 25 };
+        for _ in 0..processed {
+            progress.inc(1);
+        }
+    });
+    let stats = progress.stats(4, 10);
+    assert_eq!(stats.keys, 210);
+    assert_eq!(stats.threads, 4);
+    assert_eq!(stats.chunks, 10);
+
+    let stats = Stats { elapsed: Duration::from_millis(1500), ..stats };
+    assert_eq!(stats.keys_per_second(), 140.0);
+    let stats = Stats { elapsed: Duration::from_secs(0), ..stats };
+    assert_eq!(stats.keys_per_second(), 0.0);
+}
+
+/**
+ * Formats a duration as h:mm:ss
+ */
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+#[test]
+fn test_format_duration(){
+    assert_eq!(format_duration(Duration::from_secs(0)), "0:00:00");
+    assert_eq!(format_duration(Duration::from_secs(3725)), "1:02:05");
+}
@@ -0,0 +1,216 @@
+use crate::output;
+use hdifinder::{models::PassphraseCandidate, HdiFinderError};
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, IsTerminal, Read},
+};
+
+/**
+ * Largest secret file accepted, far above the longest mnemonic,
+ * so that pointing at a wrong file fails early
+ */
+const MAX_SECRET_FILE_SIZE: u64 = 4096;
+
+/**
+ * Largest list of mnemonics or passphrase candidates accepted
+ */
+const MAX_LIST_FILE_SIZE: u64 = 1 << 20;
+
+/**
+ * Number of attempts at typing the same passphrase twice
+ */
+const PASSPHRASE_ATTEMPTS: usize = 3;
+
+fn stdin_error(source: io::Error) -> HdiFinderError {
+    HdiFinderError::Io {
+        path: "stdin".to_string(),
+        source,
+    }
+}
+
+/**
+ * Reads a secret without it going through the command line: from a prompt
+ * with the echo disabled when stdin is a terminal, from stdin otherwise.
+ * The line break ending the input isn't part of the secret.
+ */
+pub fn read_secret(label: &str) -> Result<String, HdiFinderError> {
+    if io::stdin().is_terminal() {
+        return self::read_secret_line(label);
+    }
+    let mut secret = String::new();
+    io::stdin().lock().read_to_string(&mut secret).map_err(self::stdin_error)?;
+    Ok(self::strip_line_break(&secret).to_string())
+}
+
+/**
+ * Reads a single line of secret, from a prompt with the echo disabled
+ * when stdin is a terminal. Fails once stdin is exhausted.
+ */
+fn read_secret_line(label: &str) -> Result<String, HdiFinderError> {
+    let stdin = io::stdin();
+    if stdin.is_terminal() {
+        return rpassword::prompt_password(format!("{}: ", label)).map_err(self::stdin_error);
+    }
+    let mut secret = String::new();
+    if stdin.read_line(&mut secret).map_err(self::stdin_error)? == 0 {
+        return Err(self::stdin_error(io::Error::new(io::ErrorKind::UnexpectedEof, "no input left")));
+    }
+    Ok(self::strip_line_break(&secret).to_string())
+}
+
+/**
+ * Asks for the passphrase twice, as a typo in it silently leads to another wallet.
+ * Asks again when the entries differ, up to a few times.
+ */
+pub fn ask_passphrase() -> Result<String, HdiFinderError> {
+    for _ in 0..PASSPHRASE_ATTEMPTS {
+        let passphrase = self::read_secret_line("Enter passphrase")?;
+        let confirmation = self::read_secret_line("Confirm passphrase")?;
+        if passphrase == confirmation {
+            return Ok(passphrase);
+        }
+        output::error("The passphrases don't match");
+    }
+    Err(HdiFinderError::InvalidArgument(format!(
+        "The passphrases didn't match after {} attempts",
+        PASSPHRASE_ATTEMPTS
+    )))
+}
+
+fn strip_line_break(input: &str) -> &str {
+    let input = input.strip_suffix('\n').unwrap_or(input);
+    input.strip_suffix('\r').unwrap_or(input)
+}
+
+#[test]
+fn test_strip_line_break(){
+    assert_eq!(strip_line_break("erupt quit\n"), "erupt quit");
+    assert_eq!(strip_line_break("erupt quit\r\n"), "erupt quit");
+    assert_eq!(strip_line_break("erupt quit"), "erupt quit");
+    assert_eq!(strip_line_break("erupt quit \n\n"), "erupt quit \n");
+}
+
+/**
+ * Reads a secret from a file, as is.
+ * Warns when other users are allowed to read the file.
+ */
+pub fn read_secret_file(path: &str) -> Result<String, HdiFinderError> {
+    self::read_private_file(path, MAX_SECRET_FILE_SIZE)
+}
+
+fn read_private_file(path: &str, max_size: u64) -> Result<String, HdiFinderError> {
+    let io_error = |source| HdiFinderError::Io {
+        path: path.to_string(),
+        source,
+    };
+    let metadata = fs::metadata(path).map_err(io_error)?;
+    if metadata.len() > max_size {
+        return Err(HdiFinderError::InvalidArgument(format!(
+            "{} is {} bytes long, more than the {} bytes expected at most",
+            path,
+            metadata.len(),
+            max_size
+        )));
+    }
+    #[cfg(unix)]
+    {
+        let mode = metadata.permissions().mode() & 0o777;
+        if mode & 0o044 != 0 {
+            warning!(
+                true,
+                "Warning: {} can be read by other users (mode {:o}). Restrict it with chmod 600",
+                path, mode
+            );
+        }
+    }
+    fs::read_to_string(path).map_err(io_error)
+}
+
+/**
+ * Reads a passphrase from a file. Passphrases are byte-sensitive, so only
+ * the line break ending the file is dropped, unless it is to be kept.
+ */
+pub fn read_passphrase_file(path: &str, keep_trailing_newline: bool) -> Result<String, HdiFinderError> {
+    let content = self::read_secret_file(path)?;
+    if keep_trailing_newline {
+        return Ok(content);
+    }
+    Ok(self::strip_line_break(&content).to_string())
+}
+
+#[test]
+fn test_read_secret_file(){
+    let path = std::env::temp_dir().join(format!("hdifinder-secret-{}", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    fs::write(path, "  erupt quit sphere\n").unwrap();
+    assert_eq!(read_secret_file(path).unwrap(), "  erupt quit sphere\n");
+    assert_eq!(read_passphrase_file(path, false).unwrap(), "  erupt quit sphere");
+    assert_eq!(read_passphrase_file(path, true).unwrap(), "  erupt quit sphere\n");
+
+    fs::write(path, "erupt ".repeat(1000)).unwrap();
+    assert_eq!(
+        read_secret_file(path).unwrap_err().to_string(),
+        format!("{} is 6000 bytes long, more than the 4096 bytes expected at most", path)
+    );
+    fs::remove_file(path).unwrap();
+    assert!(matches!(read_secret_file(path), Err(HdiFinderError::Io { .. })));
+}
+
+/**
+ * Splits a list of passphrase candidates, one per line, numbered from 1.
+ * Lines are kept as is but for their line break, an empty one standing for
+ * the empty passphrase. Repeated candidates are only kept once.
+ */
+pub fn parse_passphrase_list(content: &str) -> Vec<PassphraseCandidate> {
+    let content = content.strip_suffix('\n').unwrap_or(content);
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut candidates: Vec<PassphraseCandidate> = Vec::new();
+    for (position, line) in content.split('\n').enumerate() {
+        let passphrase = line.strip_suffix('\r').unwrap_or(line);
+        if seen.insert(passphrase) {
+            candidates.push(PassphraseCandidate {
+                line: position + 1,
+                passphrase: passphrase.to_string(),
+            });
+        }
+    }
+    candidates
+}
+
+#[test]
+fn test_parse_passphrase_list(){
+    let passphrases = |content| -> Vec<(usize, String)> {
+        parse_passphrase_list(content)
+            .into_iter()
+            .map(|candidate| (candidate.line, candidate.passphrase))
+            .collect()
+    };
+    assert_eq!(
+        passphrases("summer\r\n Summer \n\nsummer\nwinter\n"),
+        vec![(1, "summer".to_string()), (2, " Summer ".to_string()), (3, String::new()), (5, "winter".to_string())]
+    );
+    assert_eq!(passphrases("summer"), vec![(1, "summer".to_string())]);
+    assert_eq!(passphrases(""), vec![(1, String::new())]);
+}
+
+/**
+ * Reads a file listing several secrets, as is
+ */
+pub fn read_secret_list(path: &str) -> Result<String, HdiFinderError> {
+    self::read_private_file(path, MAX_LIST_FILE_SIZE)
+}
+
+/**
+ * Reads a list of passphrase candidates from a file
+ */
+pub fn read_passphrase_list(path: &str) -> Result<Vec<PassphraseCandidate>, HdiFinderError> {
+    let content = self::read_secret_list(path)?;
+    if content.is_empty() {
+        return Err(HdiFinderError::InvalidArgument(format!("No passphrase candidate found in {}", path)));
+    }
+    Ok(self::parse_passphrase_list(&content))
+}
@@ -0,0 +1,341 @@
+use crate::{derivation, error::HdiFinderError, message};
+use bitcoin::{
+    blockdata::{opcodes, transaction::SigHashType},
+    consensus::encode,
+    util::{
+        bip143::SigHashCache,
+        bip32::ExtendedPrivKey,
+        ecdsa::{PrivateKey, PublicKey},
+        psbt::PartiallySignedTransaction,
+    },
+    Script, TxOut,
+};
+use secp256k1::{All, Message, Secp256k1};
+use std::{collections::HashSet, fs, ops::Range, path::Path};
+#[cfg(test)]
+use {
+    bitcoin::{network::constants::Network, Address, OutPoint, Transaction, TxIn},
+    hdpath::{AccountHDPath, Purpose},
+    secp256k1::Signature,
+    std::str::FromStr,
+};
+
+/**
+ * Magic bytes a PSBT starts with, telling a binary file from a base64 one
+ */
+const PSBT_MAGIC: &[u8] = b"psbt\xff";
+
+fn invalid_psbt(reason: impl ToString) -> HdiFinderError {
+    HdiFinderError::InvalidArgument(format!("Invalid PSBT: {}", reason.to_string()))
+}
+
+/**
+ * Reads the PSBT of --psbt: the path of a file, holding the PSBT in binary
+ * or in base64, or the base64 of the PSBT itself
+ */
+pub fn read_psbt(arg: &str) -> Result<PartiallySignedTransaction, HdiFinderError> {
+    let bytes = if Path::new(arg).is_file() {
+        let content = fs::read(arg).map_err(|source| HdiFinderError::Io { path: arg.to_string(), source })?;
+        if content.starts_with(PSBT_MAGIC) {
+            content
+        } else {
+            message::from_base64(String::from_utf8_lossy(&content).trim()).ok_or_else(|| invalid_psbt(format!("{} holds neither a binary PSBT nor base64", arg)))?
+        }
+    } else {
+        message::from_base64(arg.trim()).ok_or_else(|| invalid_psbt("neither a file nor base64"))?
+    };
+    encode::deserialize(&bytes).map_err(invalid_psbt)
+}
+
+/**
+ * Encodes a PSBT in base64, as wallets import it
+ */
+pub fn to_base64(psbt: &PartiallySignedTransaction) -> String {
+    message::to_base64(&encode::serialize(psbt))
+}
+
+/**
+ * Writes a PSBT to a file in base64
+ */
+pub fn write_psbt(path: &str, psbt: &PartiallySignedTransaction) -> Result<(), HdiFinderError> {
+    fs::write(path, format!("{}\n", to_base64(psbt))).map_err(|source| HdiFinderError::Io { path: path.to_string(), source })
+}
+
+/**
+ * Retrieves the output an input spends, from its witness UTXO or its previous
+ * transaction
+ */
+fn spent_output(psbt: &PartiallySignedTransaction, index: usize) -> Option<TxOut> {
+    let input = &psbt.inputs[index];
+    input.witness_utxo.clone().or_else(|| {
+        let previous_output = psbt.global.unsigned_tx.input[index].previous_output;
+        input
+            .non_witness_utxo
+            .as_ref()
+            .filter(|transaction| transaction.txid() == previous_output.txid)?
+            .output
+            .get(previous_output.vout as usize)
+            .cloned()
+    })
+}
+
+/**
+ * How the output spent by an input is signed for with a key
+ */
+enum Spending {
+    Legacy,
+    Witness,
+    // p2sh-p2wpkh, along with the redeem script
+    NestedWitness(Script),
+}
+
+/**
+ * Tells how a key signs for an output script, None when the script isn't one
+ * of the single key types the key has
+ */
+fn spending(key: &PublicKey, script_pubkey: &Script) -> Option<Spending> {
+    let witness_script = Script::new_v0_wpkh(&key.wpubkey_hash()?);
+    if *script_pubkey == Script::new_p2pkh(&key.pubkey_hash()) {
+        Some(Spending::Legacy)
+    } else if *script_pubkey == witness_script {
+        Some(Spending::Witness)
+    } else if *script_pubkey == Script::new_p2sh(&witness_script.script_hash()) {
+        Some(Spending::NestedWitness(witness_script))
+    } else {
+        None
+    }
+}
+
+fn is_taproot(script_pubkey: &Script) -> bool {
+    let bytes = script_pubkey.as_bytes();
+    bytes.len() == 34 && bytes[0] == opcodes::all::OP_PUSHNUM_1.into_u8() && bytes[1] == 32
+}
+
+/**
+ * Inputs of a PSBT that have been signed and the ones left untouched, with
+ * the reason why
+ */
+#[derive(Debug, Default, PartialEq)]
+pub struct SigningReport {
+    pub signed: Vec<usize>,
+    pub unsigned: Vec<(usize, String)>,
+}
+
+/**
+ * Signs the inputs of a PSBT spending the p2pkh, p2wpkh or p2sh-p2wpkh
+ * outputs of the keys, adding their partial signatures with the sighash type
+ * of the input, SIGHASH_ALL by default. Taproot inputs and the ones no key
+ * signs for are left as they are.
+ */
+pub fn sign(secp: &Secp256k1<All>, psbt: &mut PartiallySignedTransaction, keys: &[PrivateKey]) -> SigningReport {
+    let transaction = psbt.global.unsigned_tx.clone();
+    let mut cache = SigHashCache::new(&transaction);
+    let mut report = SigningReport::default();
+    for index in 0..psbt.inputs.len() {
+        let spent = match self::spent_output(psbt, index) {
+            Some(spent) => spent,
+            None => {
+                report.unsigned.push((index, "its previous output is missing".to_string()));
+                continue;
+            }
+        };
+        let input = &mut psbt.inputs[index];
+        if input.final_script_sig.is_some() || input.final_script_witness.is_some() {
+            report.unsigned.push((index, "it is already finalized".to_string()));
+            continue;
+        }
+        if self::is_taproot(&spent.script_pubkey) {
+            report.unsigned.push((index, "taproot inputs can't be signed".to_string()));
+            continue;
+        }
+        let signer = keys.iter().find_map(|key| {
+            let public_key = key.public_key(secp);
+            self::spending(&public_key, &spent.script_pubkey).map(|spending| (key, public_key, spending))
+        });
+        let (key, public_key, spending) = match signer {
+            Some(signer) => signer,
+            None => {
+                report.unsigned.push((index, "no key of the scan matches its script".to_string()));
+                continue;
+            }
+        };
+        let sighash_type = input.sighash_type.unwrap_or(SigHashType::All);
+        let sighash = match &spending {
+            // The amount isn't committed to by legacy signatures, which is
+            // only safe when it is checked against the previous transaction
+            Spending::Legacy if input.non_witness_utxo.is_none() => {
+                report.unsigned.push((index, "its previous transaction is missing".to_string()));
+                continue;
+            }
+            Spending::Legacy => transaction.signature_hash(index, &spent.script_pubkey, sighash_type.as_u32()),
+            Spending::Witness | Spending::NestedWitness(_) => {
+                cache.signature_hash(index, &Script::new_p2pkh(&public_key.pubkey_hash()), spent.value, sighash_type)
+            }
+        };
+        let hash = Message::from_slice(&sighash[..]).expect("a signature hash is 32 bytes long");
+        let mut signature = secp.sign_low_r(&hash, &key.key).serialize_der().to_vec();
+        signature.push(sighash_type.as_u32() as u8);
+        input.partial_sigs.insert(public_key, signature);
+        if let Spending::NestedWitness(redeem_script) = spending {
+            input.redeem_script.get_or_insert(redeem_script);
+        }
+        report.signed.push(index);
+    }
+    report
+}
+
+/**
+ * Derives the keys of a chain over a range of indexes, hardened or not,
+ * keeping the ones that sign for an input of the PSBT, as --sign-all-known does
+ */
+pub fn known_keys(
+    secp: &Secp256k1<All>,
+    psbt: &PartiallySignedTransaction,
+    chain_key: &ExtendedPrivKey,
+    range: Range<u32>,
+    hardened: bool,
+) -> Result<Vec<PrivateKey>, HdiFinderError> {
+    let scripts: HashSet<Script> = (0..psbt.inputs.len())
+        .filter_map(|index| self::spent_output(psbt, index))
+        .map(|spent| spent.script_pubkey)
+        .collect();
+    let mut keys = Vec::new();
+    for index in range {
+        let key = if hardened {
+            derivation::hardened_index_key(secp, chain_key, index)?.private_key
+        } else {
+            derivation::index_key(secp, chain_key, index)?.private_key
+        };
+        let public_key = key.public_key(secp);
+        if scripts.iter().any(|script| self::spending(&public_key, script).is_some()) {
+            keys.push(key);
+        }
+    }
+    Ok(keys)
+}
+
+#[cfg(test)]
+fn test_chain_key(purpose: Purpose) -> ExtendedPrivKey {
+    let secp = Secp256k1::new();
+    let seed = derivation::get_mnemonic("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb", None).unwrap().to_seed("");
+    let master = derivation::get_master_key(seed, Network::Regtest).unwrap();
+    derivation::chain_key(&secp, &master, &AccountHDPath::new(purpose, 1, 0), 0).unwrap()
+}
+
+/**
+ * Builds a regtest PSBT spending the outputs of a funding transaction, paying
+ * to the p2pkh, p2wpkh and p2sh-p2wpkh addresses of index 0 of the test
+ * mnemonic, to a foreign p2wpkh address and to a taproot output
+ */
+#[cfg(test)]
+fn test_psbt() -> PartiallySignedTransaction {
+    let secp = Secp256k1::new();
+    let public_key = |purpose| derivation::index_key(&secp, &test_chain_key(purpose), 0).unwrap().private_key.public_key(&secp);
+    let taproot = Script::from(
+        [vec![opcodes::all::OP_PUSHNUM_1.into_u8(), 32], vec![1; 32]].concat(),
+    );
+    let scripts = vec![
+        Address::p2pkh(&public_key(Purpose::Pubkey), Network::Regtest).script_pubkey(),
+        Address::p2wpkh(&public_key(Purpose::Witness), Network::Regtest).unwrap().script_pubkey(),
+        Address::p2shwpkh(&public_key(Purpose::ScriptHash), Network::Regtest).unwrap().script_pubkey(),
+        Address::from_str("bcrt1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk554dfr7").unwrap().script_pubkey(),
+        taproot,
+    ];
+    let funding = Transaction {
+        version: 2,
+        lock_time: 0,
+        input: vec![TxIn::default()],
+        output: scripts.into_iter().map(|script_pubkey| TxOut { value: 100_000, script_pubkey }).collect(),
+    };
+    let spending = Transaction {
+        version: 2,
+        lock_time: 0,
+        input: (0..funding.output.len() as u32)
+            .map(|vout| TxIn { previous_output: OutPoint::new(funding.txid(), vout), sequence: 0xFFFFFFFD, ..TxIn::default() })
+            .collect(),
+        output: vec![TxOut {
+            value: 490_000,
+            script_pubkey: Address::from_str("bcrt1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk554dfr7").unwrap().script_pubkey(),
+        }],
+    };
+    let mut psbt = PartiallySignedTransaction::from_unsigned_tx(spending).unwrap();
+    for (index, input) in psbt.inputs.iter_mut().enumerate() {
+        input.non_witness_utxo = Some(funding.clone());
+        if index > 0 {
+            input.witness_utxo = Some(funding.output[index].clone());
+        }
+    }
+    psbt
+}
+
+#[test]
+fn test_read_psbt(){
+    let psbt = test_psbt();
+    let encoded = to_base64(&psbt);
+    assert!(encoded.starts_with("cHNidP8"));
+    assert_eq!(read_psbt(&encoded).unwrap(), psbt);
+
+    let file = std::env::temp_dir().join(format!("hdifinder-test-psbt-{}", std::process::id()));
+    let path = file.to_str().unwrap();
+    write_psbt(path, &psbt).unwrap();
+    assert_eq!(read_psbt(path).unwrap(), psbt);
+    fs::write(&file, encode::serialize(&psbt)).unwrap();
+    assert_eq!(read_psbt(path).unwrap(), psbt);
+    fs::remove_file(&file).unwrap();
+
+    assert_eq!(read_psbt("not a psbt").unwrap_err().to_string(), "Invalid PSBT: neither a file nor base64");
+    assert!(read_psbt("cHNidP8=").unwrap_err().to_string().starts_with("Invalid PSBT: "));
+}
+
+#[test]
+fn test_sign(){
+    let secp = Secp256k1::new();
+    let key = |purpose| derivation::index_key(&secp, &test_chain_key(purpose), 0).unwrap().private_key;
+    let keys = vec![key(Purpose::Pubkey), key(Purpose::Witness), key(Purpose::ScriptHash)];
+    let mut psbt = test_psbt();
+    let report = sign(&secp, &mut psbt, &keys);
+    assert_eq!(report.signed, vec![0, 1, 2]);
+    assert_eq!(
+        report.unsigned,
+        vec![(3, "no key of the scan matches its script".to_string()), (4, "taproot inputs can't be signed".to_string())]
+    );
+    let transaction = &psbt.global.unsigned_tx;
+    for (index, key) in keys.iter().enumerate() {
+        let public_key = key.public_key(&secp);
+        let signature = &psbt.inputs[index].partial_sigs[&public_key];
+        assert_eq!(*signature.last().unwrap(), SigHashType::All.as_u32() as u8);
+        let spent = &psbt.inputs[index].witness_utxo.clone().unwrap_or_else(|| psbt.inputs[index].non_witness_utxo.as_ref().unwrap().output[index].clone());
+        let sighash = if index == 0 {
+            transaction.signature_hash(index, &spent.script_pubkey, SigHashType::All.as_u32())
+        } else {
+            SigHashCache::new(transaction).signature_hash(index, &Script::new_p2pkh(&public_key.pubkey_hash()), spent.value, SigHashType::All)
+        };
+        let signature = Signature::from_der(&signature[..signature.len() - 1]).unwrap();
+        assert!(secp.verify(&Message::from_slice(&sighash[..]).unwrap(), &signature, &public_key.key).is_ok());
+    }
+    assert_eq!(psbt.inputs[2].redeem_script, Some(Script::new_v0_wpkh(&keys[2].public_key(&secp).wpubkey_hash().unwrap())));
+    assert!(psbt.inputs[3].partial_sigs.is_empty() && psbt.inputs[4].partial_sigs.is_empty());
+
+    // The sighash type of the input is signed with
+    let mut psbt = test_psbt();
+    psbt.inputs[1].sighash_type = Some(SigHashType::AllPlusAnyoneCanPay);
+    sign(&secp, &mut psbt, &keys);
+    assert_eq!(*psbt.inputs[1].partial_sigs.values().next().unwrap().last().unwrap(), 0x81);
+
+    // Legacy inputs are only signed along with their previous transaction
+    let mut psbt = test_psbt();
+    psbt.inputs[0].non_witness_utxo = None;
+    psbt.inputs[0].witness_utxo = Some(psbt.inputs[1].non_witness_utxo.as_ref().unwrap().output[0].clone());
+    let report = sign(&secp, &mut psbt, &keys);
+    assert_eq!(report.unsigned[0], (0, "its previous transaction is missing".to_string()));
+}
+
+#[test]
+fn test_known_keys(){
+    let secp = Secp256k1::new();
+    let psbt = test_psbt();
+    let keys = known_keys(&secp, &psbt, &test_chain_key(Purpose::Witness), 0..5, false).unwrap();
+    assert_eq!(keys, vec![derivation::index_key(&secp, &test_chain_key(Purpose::Witness), 0).unwrap().private_key]);
+    assert!(known_keys(&secp, &psbt, &test_chain_key(Purpose::Witness), 1..5, false).unwrap().is_empty());
+    assert!(known_keys(&secp, &psbt, &test_chain_key(Purpose::Witness), 0..5, true).unwrap().is_empty());
+}
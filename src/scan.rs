@@ -0,0 +1,602 @@
+#[cfg(feature = "qr")]
+use crate::output::QrOutput;
+use crate::{
+    cli::{self, EXIT_FOUND, EXIT_NOT_FOUND},
+    output::{self, CandidateColumns, OutputFile, OutputWriter, SeedStatus},
+};
+use bitcoin::util::{
+    bip32::{ExtendedPrivKey, ExtendedPubKey},
+    ecdsa::PrivateKey,
+};
+use clap::ArgMatches;
+use hdifinder::{
+    address, derivation,
+    electrum::SeedVersion,
+    models::{ChainKey, ExecutionConf, MnemonicFix, OutputFormat, ScanSummary, SearchConfig, Seed, Swaps, WalletConf},
+    multisig,
+    progress::{self, Progress},
+    psbt,
+    search::{self, DerivationTrace, Scan},
+    state::{self, Checkpoint},
+    wallet::{self, MnemonicCandidate, Source},
+    HdiFinderError,
+};
+use hdpath::Purpose;
+use log::info;
+use secp256k1::{All, Secp256k1};
+use std::{
+    collections::HashSet,
+    convert::TryFrom,
+    mem,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/**
+ * Number of keys derived to measure the throughput in dry run mode
+ */
+const CALIBRATION_KEYS: usize = 2000;
+
+/**
+ * What the wallets are read from: the mnemonics scanned, or the seed or the
+ * extended key given in their place, along with the lines of the mnemonics
+ * file which have been skipped
+ */
+struct Secrets {
+    mnemonics: Vec<MnemonicCandidate>,
+    seed: Option<Seed>,
+    key: Option<ExtendedPrivKey>,
+    public_key: Option<ExtendedPubKey>,
+    electrum_seed: Option<(String, SeedVersion)>,
+    skipped: Vec<(usize, SeedStatus)>,
+}
+
+impl Secrets {
+    /**
+     * Reads the secret given by the arguments, the extended keys setting the
+     * purposes and the network of the config when they imply them
+     */
+    fn read(args: &ArgMatches, config: &mut SearchConfig) -> Result<Secrets, HdiFinderError> {
+        let shares_seed: Option<Seed> = if args.contains_id("slip39-share") || args.contains_id("slip39-file") {
+            Some(cli::read_slip39_shares(args, config)?)
+        } else {
+            None
+        };
+        let public_key: Option<ExtendedPubKey> = match args.get_one::<String>("xpub").map(String::as_str) {
+            Some(key) => Some(cli::read_xpub(args, config, key)?),
+            None => None,
+        };
+        let key: Option<ExtendedPrivKey> = match args.get_one::<String>("xprv").map(String::as_str) {
+            Some(key) => Some(cli::read_xprv(args, config, key)?),
+            None => None,
+        };
+        let seed: Option<Seed> = match args.get_one::<String>("seed").map(String::as_str) {
+            Some(hex) => Some(cli::read_seed(config, hex)?),
+            None if args.get_one::<String>("seed-type").map(String::as_str) == Some("aezeed") => Some(cli::read_aezeed(args, config)?),
+            None => shares_seed,
+        };
+        let mut skipped: Vec<(usize, SeedStatus)> = Vec::new();
+        let electrum_seed: Option<(String, SeedVersion)> = if args.get_one::<String>("seed-type").map(String::as_str) == Some("electrum") {
+            Some(cli::read_electrum_seed(args, config)?)
+        } else {
+            None
+        };
+        let mnemonics: Vec<MnemonicCandidate> = if seed.is_some() || key.is_some() || public_key.is_some() || electrum_seed.is_some() {
+            Vec::new()
+        } else if let Some(hex) = args.get_one::<String>("entropy").map(String::as_str) {
+            vec![MnemonicCandidate::new(cli::read_entropy(args, config, hex)?, None)]
+        } else if let Some(path) = args.get_one::<String>("mnemonics-file").map(String::as_str) {
+            let list = cli::read_mnemonics_file(args, config, path)?;
+            skipped.extend(list.skipped.into_iter().map(|skipped| (skipped.line, SeedStatus::Skipped(skipped.reason))));
+            list.valid
+                .into_iter()
+                .map(|(line, mnemonic)| MnemonicCandidate { line: Some(line), ..MnemonicCandidate::new(mnemonic, None) })
+                .collect()
+        } else {
+            let mnemonic = cli::read_mnemonic(args, config)?;
+            if mnemonic.trim().is_empty() {
+                return Err(HdiFinderError::InvalidArgument("No mnemonic found".to_string()));
+            }
+            // Whitespace, case and Unicode differences are fixed silently by
+            // the parsing, but may hint at a transcription mistake
+            if derivation::normalize_mnemonic(&mnemonic) != mnemonic {
+                warning!(
+                    config.format != OutputFormat::Text,
+                    "The mnemonic has been normalized (extra whitespace, uppercase letters, composed or invisible characters). Double check it if the address isn't found"
+                );
+            }
+            self::mnemonic_candidates(&mnemonic, config)?
+        };
+        Ok(Secrets { mnemonics, seed, key, public_key, electrum_seed, skipped })
+    }
+
+    /**
+     * Lists what the wallets are derived from: the key or the seed given, or
+     * every mnemonic scanned
+     */
+    fn sources(&self) -> Vec<Source> {
+        match (&self.public_key, &self.key, &self.seed, &self.electrum_seed) {
+            (Some(public_key), _, _, _) => vec![Source::PublicKey(public_key)],
+            (None, Some(key), _, _) => vec![Source::Key(key)],
+            (None, None, Some(seed), _) => vec![Source::Seed(seed)],
+            (None, None, None, Some((phrase, version))) => vec![Source::Electrum(phrase, *version)],
+            (None, None, None, None) => self.mnemonics.iter().map(Source::Mnemonic).collect(),
+        }
+    }
+}
+
+/**
+ * Lists the versions of the mnemonic phrase to scan, telling how a phrase
+ * failing the checksum is fixed. Without a fix to try, the words completing
+ * it with a valid checksum are listed to the user.
+ */
+fn mnemonic_candidates(phrase: &str, config: &SearchConfig) -> Result<Vec<MnemonicCandidate>, HdiFinderError> {
+    let machine_output = config.format != OutputFormat::Text;
+    let expanded = wallet::mnemonic_candidates(phrase, config)?;
+
+    if expanded.checksum_error.is_some() && config.fix_checksum {
+        let last_words = expanded.candidates.iter().filter(|candidate| matches!(candidate.fix, Some(MnemonicFix::LastWord(_)))).count();
+        notice!(machine_output, "The checksum doesn't match, trying the {} last words giving a valid one", last_words);
+    }
+
+    if let Some(swaps) = config.try_swaps {
+        if swaps == Swaps::All {
+            let words = derivation::normalize_mnemonic(phrase).split_whitespace().count();
+            warning!(
+                machine_output,
+                "Warning: --try-swaps=all swaps every pair of words, {} of them for {} words. Each swap giving a valid checksum is scanned as a wallet of its own",
+                words * words.saturating_sub(1) / 2,
+                words
+            );
+        }
+        let swapped = expanded.candidates.iter().filter(|candidate| matches!(candidate.fix, Some(MnemonicFix::Swap(..)))).count();
+        notice!(machine_output, "Swaps of words giving a valid checksum: {}", swapped);
+    }
+
+    match expanded.checksum_error {
+        Some(error) if expanded.candidates.is_empty() => {
+            let last_words: Vec<String> = derivation::last_word_candidates(phrase, config.language)
+                .iter()
+                .filter_map(|candidate| candidate.word_iter().last().map(|word| word.to_string()))
+                .collect();
+            notice!(
+                true,
+                "Last words giving a valid checksum: {}. Try them all with --fix-checksum",
+                last_words.join(" ")
+            );
+            Err(error)
+        }
+        _ => Ok(expanded.candidates),
+    }
+}
+
+/**
+ * Prints what a scan would do and extrapolates its duration from
+ * the throughput measured on a small calibration batch
+ */
+fn dry_run(
+    secp: &Secp256k1<All>,
+    config: &SearchConfig,
+    source: Source,
+    mnemonics: usize,
+    wallet_configs: &[WalletConf],
+    slices: usize,
+    scanned: usize,
+) -> Result<(), HdiFinderError> {
+    let threads = if config.threads == 0 { rayon::current_num_threads() } else { config.threads };
+    let range = config.end.saturating_sub(config.start);
+    let machine_output = config.format != OutputFormat::Text;
+
+    match source {
+        Source::Mnemonic(candidate) => notice!(
+            machine_output,
+            "mnemonic: {} words ({})",
+            candidate.mnemonic.word_count(),
+            derivation::language_name(candidate.mnemonic.language())
+        ),
+        Source::Seed(seed) => notice!(machine_output, "seed: {} bytes", seed.len()),
+        Source::Key(key) if key.depth == derivation::ACCOUNT_DEPTH => {
+            notice!(machine_output, "extended key: account {}", derivation::child_index(key.child_number))
+        }
+        Source::Key(_) => notice!(machine_output, "extended key: master"),
+        Source::PublicKey(key) if key.depth == derivation::CHAIN_DEPTH => {
+            notice!(machine_output, "extended public key: chain {} (watch-only)", derivation::child_index(key.child_number))
+        }
+        Source::PublicKey(key) => notice!(machine_output, "extended public key: account {} (watch-only)", derivation::child_index(key.child_number)),
+        Source::Electrum(_, version) => notice!(
+            machine_output,
+            "electrum seed: {}",
+            if version == SeedVersion::Segwit { "segwit" } else { "standard" }
+        ),
+    }
+    if mnemonics > 1 {
+        notice!(machine_output, "mnemonic candidates: {}", mnemonics);
+    }
+    notice!(machine_output, "network: {}", config.network);
+    if !config.passphrase_candidates.is_empty() {
+        notice!(machine_output, "passphrase candidates: {}", config.passphrase_candidates.len());
+    }
+    notice!(machine_output, "paths:");
+    // Every candidate is scanned under the same paths
+    let paths = wallet_configs.len() / (mnemonics.max(1) * config.passphrase_candidates.len().max(1));
+    for wallet_config in &wallet_configs[..paths] {
+        notice!(
+            machine_output,
+            "    {}/i{} ({})",
+            wallet_config.path(),
+            if wallet_config.hardened_index { "'" } else { "" },
+            if let Some(multisig) = &wallet_config.multisig {
+                multisig::address_type(multisig.nested)
+            } else if wallet_config.uncompressed {
+                address::P2PKH_UNCOMPRESSED
+            } else {
+                address::coin_address_type(wallet_config.account.purpose(), &address::address_params(wallet_config.coin, wallet_config.network))
+            }
+        );
+    }
+    notice!(machine_output, "range: {} to {} ({} indexes)", config.start, config.end.saturating_sub(1), range);
+    notice!(machine_output, "chunks: {} of up to {} indexes", slices, config.chunksize);
+    if config.verbose > 0 {
+        for slice in 0..slices {
+            if let Some(execution_config) = search::get_executor_config(config, slice) {
+                notice!(machine_output, "    {}..{}", execution_config.start, execution_config.end);
+            }
+        }
+    }
+    notice!(machine_output, "threads: {}", threads);
+    notice!(machine_output, "keys to derive: {}", scanned);
+    if range == 0 || wallet_configs.is_empty() {
+        notice!(machine_output, "estimated duration: {}", progress::format_duration(Duration::ZERO));
+        return Ok(());
+    }
+
+    // The calibration batch is spread over the wallets, as address types
+    // don't all cost the same to compute. It searches the targets of the
+    // scan, for the addresses to be computed whenever the scan computes them
+    let progress = Progress::new(CALIBRATION_KEYS);
+    let per_wallet = (CALIBRATION_KEYS / wallet_configs.len()).clamp(1, range);
+    let started = Instant::now();
+    for wallet_config in wallet_configs {
+        let execution_config = ExecutionConf {
+            start: config.start,
+            end: config.start + per_wallet,
+        };
+        let stop = AtomicBool::new(false);
+        search::scan_range(secp, &config.addresses, wallet_config, execution_config, false, &progress, &stop)?;
+        if progress.scanned() >= CALIBRATION_KEYS {
+            break;
+        }
+    }
+    let stats = progress.stats(1, wallet_configs.len());
+    let elapsed = started.elapsed();
+    notice!(
+        machine_output,
+        "calibration: {} keys in {:.2}s ({:.0} keys/s per thread)",
+        stats.keys,
+        elapsed.as_secs_f64(),
+        stats.keys_per_second()
+    );
+    let rate = stats.keys_per_second() * threads as f64;
+    if rate > 0.0 {
+        notice!(
+            machine_output,
+            "estimated duration: {}",
+            progress::format_duration(Duration::from_secs_f64(scanned as f64 / rate))
+        );
+    }
+    Ok(())
+}
+
+/**
+ * Signs the PSBT of --psbt with the keys of the matches, along with every key
+ * of the scanned range spending one of its inputs for --sign-all-known, and
+ * writes it to --psbt-out when an input has been signed. The inputs left
+ * unsigned are listed with the reason why.
+ */
+fn sign_psbt(secp: &Secp256k1<All>, config: &SearchConfig, wallet_configs: &[WalletConf], mut keys: Vec<PrivateKey>) -> Result<(), HdiFinderError> {
+    let (mut psbt, path) = match (config.psbt.clone(), &config.psbt_out) {
+        (Some(psbt), Some(path)) => (psbt, path),
+        _ => return Ok(()),
+    };
+    let machine_output = config.format != OutputFormat::Text;
+    if config.sign_all_known {
+        let range = match (u32::try_from(config.start), u32::try_from(config.end)) {
+            (Ok(start), Ok(end)) => start..end,
+            _ => {
+                return Err(HdiFinderError::InvalidArgument(format!(
+                    "--sign-all-known can't sign with the keys of the range {}..{}, beyond the indexes of a chain",
+                    config.start, config.end
+                )))
+            }
+        };
+        for wallet_config in wallet_configs {
+            if let ChainKey::Private(chain_key) = &wallet_config.chain_key {
+                keys.extend(psbt::known_keys(secp, &psbt, chain_key, range.clone(), wallet_config.hardened_index)?);
+            }
+        }
+    }
+    let report = psbt::sign(secp, &mut psbt, &keys);
+    for (index, reason) in &report.unsigned {
+        notice!(machine_output, "Input {} of the PSBT left unsigned: {}", index, reason);
+    }
+    if report.signed.is_empty() {
+        notice!(machine_output, "No input of the PSBT has been signed, {} has not been written", path);
+        return Ok(());
+    }
+    psbt::write_psbt(path, &psbt)?;
+    let inputs: Vec<String> = report.signed.iter().map(|index| index.to_string()).collect();
+    notice!(
+        machine_output,
+        "Signed input{} {} of the PSBT, written to {}",
+        if inputs.len() > 1 { "s" } else { "" },
+        inputs.join(", "),
+        path
+    );
+    Ok(())
+}
+
+/**
+ * Scans the wallets of the secret given for the targets of the config,
+ * returns the exit code to report
+ */
+pub fn run(args: &ArgMatches, mut config: SearchConfig) -> Result<i32, HdiFinderError> {
+    let mnemonics_file = args.get_one::<String>("mnemonics-file").map(String::as_str);
+    let mut secrets = Secrets::read(args, &mut config)?;
+    let mut seeds: Vec<(usize, SeedStatus)> = mem::take(&mut secrets.skipped);
+    if config.verbose > 0 && !config.dry_run && secrets.mnemonics.len() == 1 {
+        notice!(
+            config.format != OutputFormat::Text,
+            "Using a {} words {} mnemonic",
+            secrets.mnemonics[0].mnemonic.word_count(),
+            derivation::language_name(secrets.mnemonics[0].mnemonic.language())
+        );
+    }
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(config.threads)
+        .build()
+        .map_err(|e| HdiFinderError::Internal(format!("unable to start the worker threads ({})", e)))?;
+
+    // A single context is shared by every derivation of the scan
+    let secp = Secp256k1::new();
+    let root_sources = secrets.sources();
+    // The child mnemonics are scanned in place of the master seed
+    let children: Vec<MnemonicCandidate> = match config.bip85_children {
+        Some(count) => wallet::bip85_children(&secp, &config, root_sources[0], count)?,
+        None => Vec::new(),
+    };
+    if config.verbose > 0 && !children.is_empty() {
+        notice!(
+            config.format != OutputFormat::Text,
+            "Scanning {} BIP85 child mnemonics of {} words",
+            children.len(),
+            config.bip85_words
+        );
+    }
+    let sources: Vec<Source> = if children.is_empty() {
+        root_sources
+    } else {
+        children.iter().map(Source::Mnemonic).collect()
+    };
+    let mnemonic_count = if children.is_empty() { secrets.mnemonics.len() } else { children.len() };
+    let combinations = wallet::combinations(&config, &sources);
+    let wallet_configs = wallet::scan_wallet_configs(&pool, &secp, &config, &combinations)?;
+    let combination_count = combinations.len();
+
+    let checkpoint: Option<Checkpoint> = match config.state_file.clone() {
+        Some(path) => {
+            let fingerprint = wallet::state_fingerprint(&secp, &config, &sources, &wallet_configs)?;
+            if let Some(next) = state::resume(&path, &fingerprint)? {
+                notice!(config.format != OutputFormat::Text, "Resuming from index {}", next);
+                config.start = config.end.min(next.max(config.start));
+            }
+            Some(Checkpoint::new(
+                &path,
+                fingerprint,
+                config.start,
+                config.end,
+                config.chunksize,
+                wallet_configs.len(),
+            ))
+        }
+        None => None,
+    };
+
+    let slices = search::get_slices(&config);
+
+    let scanned: usize = (0..slices)
+        .filter_map(|slice| search::get_executor_config(&config, slice))
+        .map(|execution_config| execution_config.end - execution_config.start)
+        .sum::<usize>()
+        * wallet_configs.len();
+
+    let params = address::address_params(config.coin, config.network);
+    let mut address_types: Vec<&str> = config.purposes.iter().map(|purpose| address::coin_address_type(purpose, &params)).collect();
+    if config.layout.is_legacy() || (config.include_uncompressed && config.purposes.contains(&Purpose::Pubkey)) {
+        address_types.push(address::P2PKH_UNCOMPRESSED);
+    }
+    if let Some(multisig) = &config.multisig {
+        address_types = vec![multisig::address_type(multisig.nested)];
+    }
+    info!(
+        "network {}, address types {:?}, accounts {:?}, chains {:?}, {} targets, format {:?}",
+        config.network,
+        address_types,
+        config.accounts,
+        config.chains,
+        config.addresses.len(),
+        config.format
+    );
+    info!(
+        "range {}..{} in {} chunks of up to {} indexes, {} wallets, {} candidates, {} threads, {} keys",
+        config.start,
+        config.end,
+        slices,
+        config.chunksize,
+        wallet_configs.len(),
+        combination_count,
+        pool.current_num_threads(),
+        scanned
+    );
+
+    if config.dry_run {
+        self::dry_run(&secp, &config, sources[0], mnemonic_count, &wallet_configs, slices, scanned)?;
+        return Ok(0);
+    }
+
+    let candidates = config.passphrase_candidates.len().max(1);
+    // The scan is a grid of accounts by indexes when several accounts are given
+    let accounts = wallet_configs.iter().map(|wallet_config| wallet_config.account.account()).collect::<HashSet<u32>>().len();
+    let columns = CandidateColumns {
+        account: accounts > 1,
+        account_label: !config.named_accounts.is_empty(),
+        mnemonic_line: mnemonics_file.is_some(),
+        bip85_child: config.bip85_children.is_some(),
+        mnemonic_fix: secrets.mnemonics.iter().any(|candidate| candidate.fix.is_some()),
+        passphrase: !config.passphrase_candidates.is_empty(),
+        label: config.targets.values().any(|target| target.label.is_some()),
+        script: config.targets.values().any(|target| target.script.is_some()),
+        public_key: config.addresses.iter().any(|target| address::is_pubkey_target(target)),
+        multisig: config.multisig.is_some(),
+        hash160: config.addresses.iter().any(|target| address::is_hash160_target(target)),
+        wif: config.show_private,
+        account_xpub: config.show_xpub,
+        signature: config.sign_message.is_some(),
+        bip322_signature: config.sign_message_bip322.is_some(),
+    };
+    let writer = OutputWriter::new(config.format, config.stop_at_first, columns, config.redact).with_targets(config.targets.clone());
+    #[cfg(feature = "qr")]
+    let writer = if config.qr {
+        writer.with_qr(QrOutput::new(config.qr_private, config.qr_file.clone()))
+    } else {
+        writer
+    };
+    let writer = match &config.output {
+        Some(path) => writer.with_file(OutputFile::new(path.clone(), config.output_format, config.force_output)),
+        None => writer,
+    };
+    writer.begin()?;
+    let progress = Progress::new(scanned).with_candidates(combination_count);
+    let progress = if accounts > 1 {
+        progress.with_grid(accounts, config.end.saturating_sub(config.start))
+    } else {
+        progress
+    };
+    let done = AtomicBool::new(false);
+    let stop = AtomicBool::new(false);
+    let time_limit_reached = AtomicBool::new(false);
+    let completed: Vec<AtomicUsize> = wallet_configs.iter().map(|_| AtomicUsize::new(0)).collect();
+    let matched_lines: Mutex<HashSet<usize>> = Mutex::new(HashSet::new());
+    let matched_keys: Mutex<Vec<PrivateKey>> = Mutex::new(Vec::new());
+
+    // Every index is logged at the trace level, within the rate limit
+    let trace = match (config.trace_derivations, config.verbose) {
+        (Some(every), _) => Some(DerivationTrace::new(every)),
+        (None, verbose) if verbose >= 3 => Some(DerivationTrace::new(1)),
+        _ => None,
+    };
+    let scan = Scan {
+        secp: &secp,
+        config: &config,
+        wallet_configs: &wallet_configs,
+        progress: &progress,
+        stop: &stop,
+        checkpoint: checkpoint.as_ref(),
+        completed: Some(&completed),
+        observer: trace.as_ref().map(|trace| trace as &dyn search::Observer),
+    };
+
+    let found = thread::scope(|scope| {
+        if config.progress {
+            scope.spawn(|| progress.draw_until(&done));
+        }
+        if let Some(max_duration) = config.max_duration {
+            let (done, stop, time_limit_reached) = (&done, &stop, &time_limit_reached);
+            scope.spawn(move || {
+                let started = Instant::now();
+                while !done.load(Ordering::Relaxed) {
+                    let elapsed = started.elapsed();
+                    if elapsed >= max_duration {
+                        time_limit_reached.store(true, Ordering::Relaxed);
+                        stop.store(true, Ordering::Relaxed);
+                        break;
+                    }
+                    thread::sleep((max_duration - elapsed).min(Duration::from_millis(100)));
+                }
+            });
+        }
+        let found = scan.run(&pool, |result| {
+            if let Some(line) = result.mnemonic_line {
+                matched_lines.lock().map_err(|_| output::poisoned())?.insert(line);
+            }
+            if let Some(key) = result.private_key {
+                matched_keys.lock().map_err(|_| output::poisoned())?.push(key);
+            }
+            writer.found(result)?;
+            // Nobody reads the next matches, e.g. `| head -1`
+            if writer.is_closed() {
+                stop.store(true, Ordering::Relaxed);
+            }
+            Ok(())
+        });
+        done.store(true, Ordering::Relaxed);
+        found
+    })?;
+
+    if let Some(checkpoint) = &checkpoint {
+        if let Err(e) = checkpoint.save() {
+            notice!(config.format != OutputFormat::Text, "{}", e);
+        }
+    }
+
+    if time_limit_reached.load(Ordering::Relaxed) {
+        warning!(true, "Time limit reached, the scan has been stopped before the end of the range");
+    }
+
+    writer.finish(
+        &ScanSummary {
+            scanned,
+            start: config.start,
+            end: config.end,
+            address_types,
+            candidates,
+            mnemonics: mnemonic_count,
+            accounts,
+        },
+        &progress.stats(pool.current_num_threads(), scan.tasks()),
+    )?;
+
+    if let Some(path) = mnemonics_file {
+        let matched_lines = matched_lines.into_inner().map_err(|_| output::poisoned())?;
+        for candidate in &secrets.mnemonics {
+            let line = candidate.line.unwrap_or_default();
+            // every chunk of every wallet derived from the line
+            let fully_scanned = wallet_configs
+                .iter()
+                .zip(&completed)
+                .filter(|(wallet_config, _)| wallet_config.mnemonic_line == Some(line))
+                .all(|(_, chunks)| chunks.load(Ordering::Relaxed) == slices);
+            let status = if matched_lines.contains(&line) {
+                SeedStatus::Found
+            } else if fully_scanned {
+                SeedStatus::Scanned
+            } else {
+                SeedStatus::PartlyScanned
+            };
+            seeds.push((line, status));
+        }
+        seeds.sort_by_key(|(line, _)| *line);
+        notice!(config.format != OutputFormat::Text, "{}", output::text_seeds_summary(path, &seeds));
+    }
+
+    self::sign_psbt(&secp, &config, &wallet_configs, matched_keys.into_inner().map_err(|_| output::poisoned())?)?;
+
+    if found > 0 {
+        return Ok(EXIT_FOUND);
+    }
+    Ok(EXIT_NOT_FOUND)
+}
@@ -0,0 +1,1038 @@
+use crate::{
+    address, bip322, descriptor,
+    error::HdiFinderError,
+    derivation::{self, MAX_INDEX},
+    message,
+    models::{ChainKey, DerivedAddress, ExecutionConf, GridCell, MultisigKeys, SearchConfig, SearchResult, SignedMessage, WalletConf},
+    multisig,
+    progress::Progress,
+    state::Checkpoint,
+};
+use bitcoin::{
+    hashes::hex::ToHex,
+    util::bip32::{ChildNumber, DerivationPath},
+    Address,
+};
+use log::{debug, log_enabled, trace, Level};
+use rayon::{prelude::*, ThreadPool};
+use secp256k1::{All, Secp256k1};
+use std::{
+    collections::HashSet,
+    convert::TryInto,
+    ops::Range,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+#[cfg(test)]
+use {
+    crate::models::{Coin, OutputFormat},
+    crate::presets::Layout,
+    bitcoin::network::constants::Network,
+    hdpath::{AccountHDPath, Purpose},
+};
+
+#[cfg(test)]
+fn get_test_wallet_config(seed: [u8; 64], purpose: Purpose, change: u32) -> WalletConf {
+    let secp = Secp256k1::new();
+    let master = derivation::get_master_key(seed, Network::Bitcoin).unwrap();
+    let account = AccountHDPath::new(purpose, 0, 0);
+    let chain_key = ChainKey::Private(derivation::chain_key(&secp, &master, &account, change).unwrap());
+    WalletConf {
+        fingerprint: derivation::master_fingerprint(&secp, &master),
+        ..WalletConf::new(chain_key, account, change, Network::Bitcoin)
+    }
+}
+
+/**
+ * Scans a range of indexes of a wallet for the target addresses, as run
+ * by each of the parallel tasks of a search. Targets may also be public keys,
+ * as the hex of their compressed encoding: the derived keys are compared
+ * against them first, the addresses not being computed when only keys are
+ * searched. Hash160 targets, 40 hex digits, are compared against the hash of
+ * the derived keys the same way.
+ * Returns the matches found in the range, stopping at the first one if required,
+ * or an error when the range holds an index that can't be derived.
+ * `stop` is shared between the executors so that all of them bail out as soon
+ * as it is raised, either by the one finding the address when stopping at the
+ * first match or when the time limit is reached.
+ */
+pub fn scan_range(
+    secp: &Secp256k1<All>,
+    addresses: &HashSet<String>,
+    wallet_config: &WalletConf,
+    execution_config: ExecutionConf,
+    stop_at_first: bool,
+    progress: &Progress,
+    stop: &AtomicBool,
+) -> Result<Vec<SearchResult>, HdiFinderError> {
+    self::scan_range_with(secp, addresses, wallet_config, execution_config, stop_at_first, progress, stop, None)
+}
+
+/**
+ * Hook called by the executor with every index it checks, along with its
+ * full path and the address derived there, e.g. to sample what is derived.
+ * It is shared by the workers, and only slows the scan down when given.
+ */
+pub trait Observer: Sync {
+    fn checked(&self, path: &DerivationPath, derived: &DerivedAddress);
+}
+
+/**
+ * Same as `scan_range`, handing every index checked to `observer`. The
+ * address of every index is computed when one is given, even when only
+ * public keys are searched.
+ */
+#[allow(clippy::too_many_arguments)]
+pub fn scan_range_with(
+    secp: &Secp256k1<All>,
+    addresses: &HashSet<String>,
+    wallet_config: &WalletConf,
+    execution_config: ExecutionConf,
+    stop_at_first: bool,
+    progress: &Progress,
+    stop: &AtomicBool,
+    observer: Option<&dyn Observer>,
+) -> Result<Vec<SearchResult>, HdiFinderError> {
+    let start = execution_config.start;
+    let end = execution_config.end;
+    let mut results: Vec<SearchResult> = Vec::new();
+    let pubkey_targets = addresses.iter().any(|target| address::is_pubkey_target(target));
+    let hash160_targets = addresses.iter().any(|target| address::is_hash160_target(target));
+    let address_targets = addresses
+        .iter()
+        .any(|target| !address::is_pubkey_target(target) && !address::is_hash160_target(target));
+    let params = address::address_params(wallet_config.coin, wallet_config.network);
+
+    for i in start..end {
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        let index: u32 = match i.try_into() {
+            Ok(index) if index <= MAX_INDEX => index,
+            _ => {
+                return Err(HdiFinderError::InvalidRange(format!(
+                    "Index {} exceeds the BIP32 non-hardened limit of {}",
+                    i, MAX_INDEX
+                )))
+            }
+        };
+        let hd_path = match &wallet_config.chain_path {
+            Some(chain_path) => chain_path.child(wallet_config.index_child(index)),
+            None if wallet_config.hardened_index => wallet_config.path().child(ChildNumber::Hardened { index }),
+            None => DerivationPath::from(
+                wallet_config
+                    .account
+                    .address_at(wallet_config.change, index)
+                    .map_err(|(field, value)| HdiFinderError::InvalidPath(format!("{} {} is out of range", field, value)))?,
+            ),
+        };
+        let (mut public_key, mut private_key) = match &wallet_config.chain_key {
+            ChainKey::Private(chain_key) => {
+                let key = if wallet_config.hardened_index {
+                    derivation::hardened_index_key(secp, chain_key, index)?
+                } else {
+                    derivation::index_key(secp, chain_key, index)?
+                };
+                (derivation::get_public_key(secp, key), Some(key))
+            }
+            ChainKey::Public(_) if wallet_config.hardened_index => {
+                return Err(HdiFinderError::InvalidPath(format!("index {}' is hardened, which a public key can't derive", index)))
+            }
+            ChainKey::Public(chain_pubkey) => (derivation::index_pubkey(secp, chain_pubkey, index)?, None),
+        };
+        if wallet_config.uncompressed {
+            public_key.public_key.compressed = false;
+            if let Some(key) = private_key.as_mut() {
+                key.private_key.compressed = false;
+            }
+        }
+        let matched_key = if pubkey_targets {
+            Some(public_key.public_key.to_string()).filter(|key| addresses.contains(key))
+        } else {
+            None
+        };
+        let matched_hash = if hash160_targets {
+            Some(public_key.public_key.pubkey_hash().to_hex()).filter(|hash| addresses.contains(hash))
+        } else {
+            None
+        };
+        if matched_key.is_none() && matched_hash.is_none() && !address_targets && observer.is_none() {
+            progress.inc(1);
+            continue;
+        }
+        let (mut derived, multisig_keys) = match &wallet_config.multisig {
+            Some(multisig) => {
+                let (derived, keys) = multisig::address_at(secp, multisig, public_key.public_key, index, wallet_config.network)?;
+                let public_keys = keys.iter().map(|key| key.to_string()).collect();
+                (derived, Some(MultisigKeys { threshold: multisig.threshold, public_keys }))
+            }
+            None => (address::coin_address_compute(secp, public_key, wallet_config.account.purpose(), &params)?, None),
+        };
+        if wallet_config.uncompressed {
+            derived.address_type = address::P2PKH_UNCOMPRESSED;
+        }
+        progress.inc(1);
+        if let Some(observer) = observer {
+            observer.checked(&hd_path, &derived);
+        }
+
+        let target = match (&matched_key, &matched_hash) {
+            (Some(key), _) => key.clone(),
+            (None, Some(hash)) => hash.clone(),
+            (None, None) if addresses.contains(&derived.address) => derived.address.clone(),
+            (None, None) => continue,
+        };
+        let key_addresses = match (&matched_key, &matched_hash) {
+            (Some(_), _) => address::standard_addresses(secp, public_key, wallet_config.network, wallet_config.coin)?,
+            (None, Some(_)) => address::hash160_addresses(&public_key.public_key.pubkey_hash(), wallet_config.network, wallet_config.coin),
+            (None, None) => Vec::new(),
+        };
+        let bip322_message = match (&wallet_config.sign_message_bip322, private_key) {
+            (Some(message), Some(key)) if bip322::is_supported(derived.address_type) => {
+                let address = Address::from_str(&derived.address).map_err(|error| HdiFinderError::InvalidAddress(error.to_string()))?;
+                let signature = bip322::sign(secp, &key.private_key, &address, message)?;
+                Some(SignedMessage { message: message.clone(), signature })
+            }
+            _ => None,
+        };
+        results.push(SearchResult {
+            index: i,
+            account: wallet_config.account.account(),
+            chain: wallet_config.change,
+            derivation_path: hd_path.to_string(),
+            fingerprint: wallet_config.fingerprint.map(|fingerprint| fingerprint.to_string()),
+            origin: wallet_config.fingerprint.map(|fingerprint| derivation::key_origin(&fingerprint, &hd_path)),
+            target,
+            address: derived.address,
+            address_type: derived.address_type.to_string(),
+            network: wallet_config.network,
+            passphrase: wallet_config.passphrase.clone(),
+            mnemonic_line: wallet_config.mnemonic_line,
+            mnemonic_fix: wallet_config.mnemonic_fix.clone(),
+            watch_only: wallet_config.watch_only,
+            bip85_child: wallet_config.bip85_child,
+            account_label: wallet_config.account_label.map(str::to_string),
+            multisig: multisig_keys,
+            label: None,
+            script: None,
+            public_key: matched_key,
+            hash160: matched_hash,
+            key_addresses,
+            descriptor: wallet_config
+                .descriptor_key
+                .as_ref()
+                .map(|key| descriptor::chain_descriptor(wallet_config.account.purpose(), key)),
+            wif: private_key
+                .filter(|_| wallet_config.show_private)
+                .map(|key| key.private_key.to_string()),
+            account_xpub: wallet_config.account_xpub.clone(),
+            signed_message: match (&wallet_config.sign_message, private_key) {
+                (Some(message), Some(key)) => message::sign_message(secp, &key.private_key, derived.address_type, message)
+                    .map(|signature| SignedMessage { message: message.clone(), signature }),
+                _ => None,
+            },
+            bip322_message,
+            private_key: private_key.filter(|_| wallet_config.sign_psbt).map(|key| key.private_key),
+        });
+        if stop_at_first {
+            stop.store(true, Ordering::Relaxed);
+            break;
+        }
+    }
+    Ok(results)
+}
+
+#[test]
+fn test_scan_range_pubkey(){
+    let secp = Secp256k1::new();
+    let test_seed = derivation::get_mnemonic("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb", None).unwrap().to_seed("");
+    let wallet_config = get_test_wallet_config(test_seed, Purpose::Witness, 0);
+    let targets = HashSet::from(["02016653fa405f3ecedb3dc88a378dabf7cd4c1c1acf1430515e854a630254cbbe".to_string()]);
+    let results = scan_range(&secp, &targets, &wallet_config, ExecutionConf { start: 0, end: 10 }, true, &Progress::new(10), &AtomicBool::new(false)).unwrap();
+    // The key of m/44'/0'/0'/0/5 isn't one of the BIP84 account
+    assert!(results.is_empty());
+
+    let wallet_config = get_test_wallet_config(test_seed, Purpose::Pubkey, 0);
+    let results = scan_range(&secp, &targets, &wallet_config, ExecutionConf { start: 0, end: 10 }, true, &Progress::new(10), &AtomicBool::new(false)).unwrap();
+    assert_eq!(results[0].index, 5);
+    assert_eq!(results[0].address, "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    assert_eq!(results[0].public_key.as_deref(), Some("02016653fa405f3ecedb3dc88a378dabf7cd4c1c1acf1430515e854a630254cbbe"));
+    assert_eq!(results[0].key_addresses[2].address, "bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y");
+}
+
+#[test]
+fn test_scan_range_hash160(){
+    let secp = Secp256k1::new();
+    let test_seed = derivation::get_mnemonic("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb", None).unwrap().to_seed("");
+    let wallet_config = get_test_wallet_config(test_seed, Purpose::Pubkey, 0);
+    let targets = HashSet::from(["29b9c049a22a9e3b620fb327b05debaf67b42ed4".to_string()]);
+    let results = scan_range(&secp, &targets, &wallet_config, ExecutionConf { start: 0, end: 10 }, true, &Progress::new(10), &AtomicBool::new(false)).unwrap();
+    assert_eq!(results[0].index, 5);
+    assert_eq!(results[0].target, "29b9c049a22a9e3b620fb327b05debaf67b42ed4");
+    assert_eq!(results[0].hash160.as_deref(), Some("29b9c049a22a9e3b620fb327b05debaf67b42ed4"));
+    assert_eq!(results[0].public_key, None);
+    assert_eq!(results[0].key_addresses[0].address, "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK");
+    assert_eq!(results[0].key_addresses[1].address, "bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y");
+}
+
+#[test]
+fn test_scan_range_hardened_index(){
+    let secp = Secp256k1::new();
+    let test_seed = derivation::get_mnemonic("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb", None).unwrap().to_seed("");
+    let wallet_config = WalletConf { hardened_index: true, ..get_test_wallet_config(test_seed, Purpose::Pubkey, 0) };
+    let targets = HashSet::from(["1F9CpR8vnCnN7om7t1VGtzNevr9WDNpeRi".to_string(), "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK".to_string()]);
+    let results = scan_range(&secp, &targets, &wallet_config, ExecutionConf { start: 0, end: 10 }, false, &Progress::new(10), &AtomicBool::new(false)).unwrap();
+    // Only the address of the hardened index 5 is found, not the one of the normal index
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].address, "1F9CpR8vnCnN7om7t1VGtzNevr9WDNpeRi");
+    assert_eq!(results[0].derivation_path, "m/44'/0'/0'/0/5'");
+
+    let watch_only = WalletConf {
+        chain_key: match wallet_config.chain_key {
+            ChainKey::Private(ref chain_key) => ChainKey::Public(derivation::chain_pubkey(&secp, chain_key)),
+            ChainKey::Public(_) => unreachable!(),
+        },
+        hardened_index: true,
+        ..get_test_wallet_config(test_seed, Purpose::Pubkey, 0)
+    };
+    assert!(scan_range(&secp, &targets, &watch_only, ExecutionConf { start: 0, end: 10 }, false, &Progress::new(10), &AtomicBool::new(false)).is_err());
+}
+
+#[test]
+fn test_scan_range_uncompressed(){
+    let secp = Secp256k1::new();
+    let test_seed = derivation::get_mnemonic("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb", None).unwrap().to_seed("");
+    let master = derivation::get_master_key(test_seed, Network::Bitcoin).unwrap();
+    let chain_path = Layout::Bip32.chain_path(0, 0).unwrap();
+    let wallet_config = WalletConf {
+        chain_key: ChainKey::Private(master.derive_priv(&secp, &chain_path).unwrap()),
+        chain_path: Some(chain_path),
+        show_private: true,
+        ..get_test_wallet_config(test_seed, Purpose::Pubkey, 0)
+    };
+    // The addresses of m/0'/0/5, for its compressed and its uncompressed key
+    let targets = HashSet::from(["1MjK9HAPaWN2sJhZFTrnFuPwLG9mcH5dvD".to_string(), "1FKf1F6tihVZTpjRT8VYSYJVeS9EyA4ajn".to_string()]);
+    let results = scan_range(&secp, &targets, &wallet_config, ExecutionConf { start: 0, end: 10 }, false, &Progress::new(10), &AtomicBool::new(false)).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].address, "1MjK9HAPaWN2sJhZFTrnFuPwLG9mcH5dvD");
+    assert_eq!(results[0].derivation_path, "m/0'/0/5");
+    assert_eq!(results[0].wif.as_deref(), Some("KyTXKGuCfPUSgagUcGFrjCthJB6XQgFFjJ5xUJaCNBbjZWaEMA3C"));
+
+    let wallet_config = WalletConf { uncompressed: true, ..wallet_config };
+    let results = scan_range(&secp, &targets, &wallet_config, ExecutionConf { start: 0, end: 10 }, false, &Progress::new(10), &AtomicBool::new(false)).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].address, "1FKf1F6tihVZTpjRT8VYSYJVeS9EyA4ajn");
+    assert_eq!(results[0].address_type, "p2pkh-uncompressed");
+    assert_eq!(results[0].wif.as_deref(), Some("5JKhQAwsg4HBoaTci4ukthktGfzSU7dJqYxxAMkKSC1cvenjwaT"));
+}
+
+#[test]
+fn test_scan_range_multisig(){
+    let secp = Secp256k1::new();
+    let test_seed = derivation::get_mnemonic("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb", None).unwrap().to_seed("");
+    let master = derivation::get_master_key(test_seed, Network::Bitcoin).unwrap();
+    // The p2sh-p2wsh cosigners of a 2-of-3 wallet, at m/48'/0'/0'/1'
+    let cosigners: Vec<_> = [
+        "xpub6DkFAXWQ2dHxnMKoSBogHrw1rgNJKR4umdbnNVNTYeCGcduxWnNUHgGptqEQWPKRmeW4Zn4FHSbLMBKEWYaMDYu47Ytg6DdFnPNt8hwn5mE",
+        "xpub6FQya7zGhR92giSkXpPgPHpq85nUnqabbbNuJiae1zndR3B6Nq2QCoSWBkdLF7bkifSYSNvyTfhg4KBvKyJ94HXuEaeWZsabMnTyJiPz21N",
+    ]
+    .iter()
+    .map(|key| multisig::parse_cosigner(key, Network::Bitcoin).unwrap())
+    .collect();
+    let chain_path = multisig::account_path(0, 0, true).child(ChildNumber::Normal { index: 0 });
+    let wallet_config = WalletConf {
+        chain_key: ChainKey::Private(master.derive_priv(&secp, &chain_path).unwrap()),
+        chain_path: Some(chain_path),
+        multisig: Some(crate::models::Multisig {
+            threshold: 2,
+            cosigners: multisig::chain_keys(&secp, &cosigners, 0).unwrap(),
+            sorted: true,
+            key_position: 0,
+            nested: true,
+        }),
+        ..get_test_wallet_config(test_seed, Purpose::Custom(48), 0)
+    };
+    let targets = HashSet::from(["3MoJYHQoH271ouUXUjbttupHNwzBk18hWT".to_string()]);
+    let results = scan_range(&secp, &targets, &wallet_config, ExecutionConf { start: 0, end: 10 }, false, &Progress::new(10), &AtomicBool::new(false)).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].derivation_path, "m/48'/0'/0'/1'/0/3");
+    assert_eq!(results[0].address_type, "p2shwsh");
+    assert_eq!(
+        results[0].multisig,
+        Some(MultisigKeys {
+            threshold: 2,
+            public_keys: vec![
+                "029bc7c96a0351c2d9e4ff44c0fe63f09d223c842086b47f8b02d26d678ed6ffe0".to_string(),
+                "02ce91d065a554becda779dd2a8f1f7d3f4a3e21f18438c334e4b066167a6c5f3b".to_string(),
+                "02f0f61749145b59de0d9e82613f69204c2a6bf39acbb07f6c0fff50f237700a27".to_string(),
+            ],
+        })
+    );
+}
+
+#[test]
+fn test_scan_range(){
+    let secp = Secp256k1::new();
+
+    let test_address = "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK";
+    let test_mnemonic_phrase = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let test_seed = derivation::get_mnemonic(test_mnemonic_phrase, None).unwrap().to_seed("");
+    let progress = Progress::new(0);
+
+    let test_wallet_config = get_test_wallet_config(test_seed, Purpose::Pubkey, 0);
+
+    let execution_config =  ExecutionConf{
+        start: 10,
+        end: 25
+    };
+
+    assert!(scan_range(&secp,&HashSet::from([test_address.to_string()]),&test_wallet_config,execution_config,true,&progress,&AtomicBool::new(false)).unwrap().is_empty());
+
+    let execution_config =  ExecutionConf{
+        start: 0,
+        end: 10
+    };
+    
+    let result = scan_range(&secp,&HashSet::from([test_address.to_string()]),&test_wallet_config,execution_config.clone(),true,&progress,&AtomicBool::new(false)).unwrap();
+
+    assert!(!result.is_empty());
+    assert_eq!(result[0].index,5);
+    assert_eq!(result[0].address,test_address);
+    assert_eq!(result[0].derivation_path,"m/44'/0'/0'/0/5");
+
+    // Several targets are compared against each derived address
+    let test_addresses = HashSet::from([
+        "1BMJBmhLLdPyVr3NzstjopExv4BtGJbLXe".to_string(),
+        test_address.to_string(),
+        "15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3".to_string(),
+    ]);
+    let result = scan_range(&secp,&test_addresses,&test_wallet_config,execution_config.clone(),true,&progress,&AtomicBool::new(false)).unwrap();
+    assert_eq!(result.len(),1);
+    assert_eq!(result[0].index,5);
+    assert_eq!(result[0].address,test_address);
+
+    // All the matches of the range are reported when not stopping at the first one
+    let full_progress = Progress::new(20);
+    let result = scan_range(&secp,&test_addresses,&test_wallet_config,ExecutionConf{ start: 0, end: 20 },false,&full_progress,&AtomicBool::new(false)).unwrap();
+    assert_eq!(full_progress.scanned(),20);
+    assert_eq!(result.len(),2);
+    assert_eq!(result[0].index,5);
+    assert_eq!(result[1].index,15);
+    assert_eq!(result[1].address,"15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3");
+
+    // Addresses of other types are only searched under their own purpose
+    let test_segwit_address = "bc1q9xuuqjdz920rkcs0kvnmqh0t4anmgtk5u60h0y";
+    assert!(scan_range(&secp,&HashSet::from([test_segwit_address.to_string()]),&test_wallet_config,execution_config.clone(),true,&progress,&AtomicBool::new(false)).unwrap().is_empty());
+
+    let test_taproot_address = "bc1pa4ugps024jserz0wqt5z7tqh629cz04x3jaxs7vvssnhu6uxfx4qu8kxwc";
+    let test_taproot_wallet_config = get_test_wallet_config(test_seed, Purpose::Custom(86), 0);
+    let result = scan_range(&secp,&HashSet::from([test_taproot_address.to_string()]),&test_taproot_wallet_config,execution_config.clone(),true,&progress,&AtomicBool::new(false)).unwrap();
+    assert_eq!(result[0].index,5);
+    assert_eq!(result[0].address_type,"p2tr");
+
+    // Change addresses are only found when scanning the internal chain
+    let test_change_address = "1EukGhGZ3ai3zoG8mH86irCX18zTbthDZ5";
+    assert!(scan_range(&secp,&HashSet::from([test_change_address.to_string()]),&test_wallet_config,execution_config.clone(),true,&progress,&AtomicBool::new(false)).unwrap().is_empty());
+    let test_change_wallet_config = get_test_wallet_config(test_seed, Purpose::Pubkey, 1);
+    let result = scan_range(&secp,&HashSet::from([test_change_address.to_string()]),&test_change_wallet_config,execution_config,true,&progress,&AtomicBool::new(false)).unwrap();
+    assert_eq!(result[0].index,3);
+    assert_eq!(result[0].address,test_change_address);
+
+}
+
+#[test]
+fn test_scan_range_stops_on_match(){
+    let secp = Secp256k1::new();
+    let test_address = "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK";
+    let test_mnemonic_phrase = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let test_seed = derivation::get_mnemonic(test_mnemonic_phrase, None).unwrap().to_seed("");
+    let test_wallet_config = get_test_wallet_config(test_seed, Purpose::Pubkey, 0);
+    let addresses = HashSet::from([test_address.to_string()]);
+    let found = AtomicBool::new(false);
+
+    // The chunk holding the address raises the flag on its match
+    let progress = Progress::new(10);
+    let result = scan_range(&secp,&addresses,&test_wallet_config,ExecutionConf{ start: 0, end: 10 },true,&progress,&found).unwrap();
+    assert_eq!(result[0].index,5);
+    assert_eq!(progress.scanned(),6);
+    assert!(found.load(Ordering::Relaxed));
+
+    // Any other chunk bails out without deriving up to its end
+    let progress = Progress::new(1000);
+    let result = scan_range(&secp,&addresses,&test_wallet_config,ExecutionConf{ start: 10, end: 1010 },true,&progress,&found).unwrap();
+    assert!(result.is_empty());
+    assert_eq!(progress.scanned(),0);
+
+    // A public chain key derives the same addresses
+    let test_watch_only_wallet_config = WalletConf{
+        chain_key: match test_wallet_config.chain_key {
+            ChainKey::Private(ref chain_key) => ChainKey::Public(derivation::chain_pubkey(&secp, chain_key)),
+            ChainKey::Public(_) => unreachable!(),
+        },
+        account: AccountHDPath::new(Purpose::Pubkey, 0, 0),
+        change: 0,
+        network: Network::Bitcoin,
+        passphrase: None,
+        mnemonic_line: None,
+        mnemonic_fix: None,
+        watch_only: true,
+        bip85_child: None,
+        chain_path: None,
+        account_label: None,
+        hardened_index: false,
+        uncompressed: false,
+        multisig: None,
+        descriptor_key: None,
+        show_private: false,
+        account_xpub: None,
+        fingerprint: None,
+        sign_message: None,
+        sign_message_bip322: None,
+        sign_psbt: false,
+    };
+    let result = scan_range(&secp,&addresses,&test_watch_only_wallet_config,ExecutionConf{ start: 0, end: 10 },true,&Progress::new(10),&AtomicBool::new(false)).unwrap();
+    assert_eq!(result[0].index,5);
+    assert_eq!(result[0].derivation_path,"m/44'/0'/0'/0/5");
+
+    // The flag isn't raised when every match has to be reported
+    let stop = AtomicBool::new(false);
+    let result = scan_range(&secp,&addresses,&test_wallet_config,ExecutionConf{ start: 0, end: 10 },false,&Progress::new(10),&stop).unwrap();
+    assert_eq!(result.len(),1);
+    assert!(!stop.load(Ordering::Relaxed));
+
+    // but every chunk bails out once it is raised
+    let progress = Progress::new(10);
+    scan_range(&secp,&addresses,&test_wallet_config,ExecutionConf{ start: 10, end: 20 },false,&progress,&found).unwrap();
+    assert_eq!(progress.scanned(),0);
+}
+
+#[test]
+fn test_scan_range_index_limit(){
+    let secp = Secp256k1::new();
+    let test_wallet_config = get_test_wallet_config([0; 64], Purpose::Pubkey, 0);
+    let result = scan_range(&secp,&HashSet::new(),&test_wallet_config,ExecutionConf{ start: 0x80000000, end: 0x80000001 },false,&Progress::new(1),&AtomicBool::new(false));
+    assert_eq!(result.unwrap_err().to_string(), "Index 2147483648 exceeds the BIP32 non-hardened limit of 2147483647");
+}
+
+#[test]
+fn test_scan_range_with_observer(){
+    struct Collect(Mutex<Vec<String>>);
+    impl Observer for Collect {
+        fn checked(&self, path: &DerivationPath, derived: &DerivedAddress) {
+            self.0.lock().unwrap().push(format!("{} {}", path, derived.address));
+        }
+    }
+
+    let secp = Secp256k1::new();
+    let test_seed = derivation::get_mnemonic("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb", None).unwrap().to_seed("");
+    let test_wallet_config = get_test_wallet_config(test_seed, Purpose::Pubkey, 0);
+    let collect = Collect(Mutex::new(Vec::new()));
+    // no address target, the addresses are computed for the observer only
+    let result = scan_range_with(&secp,&HashSet::new(),&test_wallet_config,ExecutionConf{ start: 14, end: 16 },false,&Progress::new(2),&AtomicBool::new(false),Some(&collect)).unwrap();
+    assert!(result.is_empty());
+    assert_eq!(collect.0.into_inner().unwrap()[1], "m/44'/0'/0'/0/15 15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3");
+}
+
+/**
+ * Target of the derivation trace, which can be enabled on its own
+ */
+pub const TRACE_TARGET: &str = "derivations";
+
+/**
+ * Most lines the derivation trace writes per second, the lines above it
+ * being dropped so that a long scan doesn't fill the disk
+ */
+const TRACE_LINES_PER_SECOND: usize = 100;
+
+/**
+ * Logs one in `every` of the indexes checked at the trace level, with their
+ * path and address, rate limited. Does nothing when the trace level of
+ * its target is disabled.
+ */
+pub struct DerivationTrace {
+    every: usize,
+    checked: AtomicUsize,
+    // Start of the current second and the lines written during it
+    window: Mutex<(Instant, usize)>,
+}
+
+impl DerivationTrace {
+    pub fn new(every: usize) -> DerivationTrace {
+        DerivationTrace {
+            every: every.max(1),
+            checked: AtomicUsize::new(0),
+            window: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    /**
+     * Whether the next index checked is written, once in `every` and
+     * within the rate limit
+     */
+    fn sampled(&self) -> bool {
+        if self.checked.fetch_add(1, Ordering::Relaxed) % self.every != 0 {
+            return false;
+        }
+        let mut window = self.window.lock().expect("no worker panics while tracing");
+        if window.0.elapsed() >= Duration::from_secs(1) {
+            *window = (Instant::now(), 0);
+        }
+        window.1 += 1;
+        window.1 <= TRACE_LINES_PER_SECOND
+    }
+}
+
+impl Observer for DerivationTrace {
+    fn checked(&self, path: &DerivationPath, derived: &DerivedAddress) {
+        if log_enabled!(target: TRACE_TARGET, Level::Trace) && self.sampled() {
+            trace!(target: TRACE_TARGET, "{} {} {}", path, derived.address_type, derived.address);
+        }
+    }
+}
+
+#[test]
+fn test_derivation_trace(){
+    let trace = DerivationTrace::new(3);
+    let sampled: Vec<bool> = (0..7).map(|_| trace.sampled()).collect();
+    assert_eq!(sampled, vec![true, false, false, true, false, false, true]);
+
+    // every index sampled, the lines above the limit dropped
+    let trace = DerivationTrace::new(1);
+    assert_eq!((0..TRACE_LINES_PER_SECOND * 2).filter(|_| trace.sampled()).count(), TRACE_LINES_PER_SECOND);
+    assert_eq!(DerivationTrace::new(0).every, 1);
+}
+
+/**
+ * Looks for a single address in a range of indexes of a wallet.
+ * Convenience over `scan_range` for callers that don't need to share
+ * the work between threads nor to follow its progress.
+ */
+pub fn find_address(wallet: &WalletConf, target: &str, range: Range<u32>) -> Result<Option<SearchResult>, HdiFinderError> {
+    let secp = Secp256k1::new();
+    let execution_config = ExecutionConf {
+        start: range.start as usize,
+        end: range.end as usize,
+    };
+    let progress = Progress::new(range.len());
+    let results = self::scan_range(
+        &secp,
+        &HashSet::from([target.to_string()]),
+        wallet,
+        execution_config,
+        true,
+        &progress,
+        &AtomicBool::new(false),
+    )?;
+    Ok(results.into_iter().next())
+}
+
+#[test]
+fn test_find_address(){
+    let test_mnemonic_phrase = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let test_seed = derivation::get_mnemonic(test_mnemonic_phrase, None).unwrap().to_seed("");
+    let test_wallet_config = get_test_wallet_config(test_seed, Purpose::Pubkey, 0);
+
+    let result = find_address(&test_wallet_config, "15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3", 0..20).unwrap().unwrap();
+    assert_eq!(result.index, 15);
+    assert_eq!(result.derivation_path, "m/44'/0'/0'/0/15");
+    assert!(find_address(&test_wallet_config, "15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3", 0..15).unwrap().is_none());
+    assert!(find_address(&test_wallet_config, "15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3", 0x80000000..0x80000001).is_err());
+}
+
+/**
+ * Retrieves the number of chunks the index range is split into.
+ * The last chunk holds the remainder when the range isn't a multiple
+ * of the chunksize.
+ */
+pub fn get_slices(config: &SearchConfig) -> usize {
+    if config.chunksize == 0 {
+        return 0;
+    }
+    config.end.saturating_sub(config.start).div_ceil(config.chunksize)
+}
+
+/**
+ * Executor configuration builder. Will be called before each
+ * thread iteration.
+ * Returns None when the iteration lies beyond the end of the range.
+ */
+pub fn get_executor_config(config: &SearchConfig, iteration: usize) -> Option<ExecutionConf> {
+    if config.chunksize == 0 {
+        return None;
+    }
+    let start = iteration.checked_mul(config.chunksize)?.checked_add(config.start)?;
+    if start >= config.end {
+        return None;
+    }
+    Some(ExecutionConf {
+        start,
+        end: config.end.min(start.saturating_add(config.chunksize)),
+    })
+}
+
+/**
+ * Plans the cell of the wallet × index grid a task of a scan covers. The
+ * cells are enumerated chunk first: the same chunk of indexes of every
+ * wallet, e.g of every account, before the next one, so that the low
+ * indexes of all the accounts are scanned before the high indexes of any.
+ * Each task derives from the chain key of its wallet, computed once per
+ * account, and its indexes follow each other.
+ * Returns None when the task lies beyond the grid.
+ */
+pub fn get_grid_cell(config: &SearchConfig, wallets: usize, task: usize) -> Option<GridCell> {
+    if wallets == 0 {
+        return None;
+    }
+    Some(GridCell {
+        wallet: task % wallets,
+        execution_config: self::get_executor_config(config, task / wallets)?,
+    })
+}
+
+#[cfg(test)]
+pub(crate) fn test_search_config(start: usize, end: usize, chunksize: usize) -> SearchConfig {
+    SearchConfig {
+        start,
+        end,
+        chunksize,
+        passphrase: String::new(),
+        passphrase_candidates: Vec::new(),
+        redact: false,
+        fix_checksum: false,
+        try_swaps: None,
+        language: None,
+        addresses: HashSet::new(),
+        targets: std::collections::HashMap::new(),
+        stop_at_first: true,
+        format: OutputFormat::Text,
+        network: Network::Bitcoin,
+        coin: Coin::Bitcoin,
+        purposes: vec![Purpose::Pubkey],
+        accounts: 0..=0,
+        chains: vec![0],
+        layout: Layout::Bip44,
+        named_accounts: Vec::new(),
+        hardened_index: false,
+        include_uncompressed: false,
+        multisig: None,
+        progress: false,
+        threads: 0,
+        pubkey_only: false,
+        state_file: None,
+        max_duration: None,
+        dry_run: false,
+        verbose: 0,
+        trace_derivations: None,
+        bip85_children: None,
+        bip85_words: 12,
+        emit_descriptor: false,
+        with_private: false,
+        show_private: false,
+        show_xpub: false,
+        sign_message: None,
+        sign_message_bip322: None,
+        psbt: None,
+        psbt_out: None,
+        sign_all_known: false,
+        qr: false,
+        qr_private: false,
+        qr_file: None,
+        output: None,
+        output_format: OutputFormat::Text,
+        force_output: false,
+    }
+}
+
+#[test]
+fn test_get_executor_config(){
+    // (end, chunksize) combinations whose chunks must cover [0, end) exactly once
+    for (end, chunksize) in [(10100, 2500), (10000, 2500), (10, 2500), (97, 10), (13, 1), (1, 1), (2500, 2500)] {
+        let config = test_search_config(0, end, chunksize);
+        let mut indexes: Vec<usize> = Vec::new();
+        for slice in 0..get_slices(&config) {
+            let execution_config = get_executor_config(&config, slice).unwrap();
+            assert!(execution_config.start < execution_config.end);
+            assert!(execution_config.end - execution_config.start <= chunksize);
+            indexes.extend(execution_config.start..execution_config.end);
+        }
+        assert_eq!(indexes, (0..end).collect::<Vec<usize>>(), "end {} chunksize {}", end, chunksize);
+    }
+
+    let config = test_search_config(0, 10100, 2500);
+    assert_eq!(get_slices(&config), 5);
+    assert_eq!(get_executor_config(&config, 4).unwrap().start, 10000);
+    assert_eq!(get_executor_config(&config, 4).unwrap().end, 10100);
+
+    // Chunk boundaries are offset by the start index
+    let config = test_search_config(500000, 501000, 300);
+    let mut indexes: Vec<usize> = Vec::new();
+    for slice in 0..get_slices(&config) {
+        let execution_config = get_executor_config(&config, slice).unwrap();
+        indexes.extend(execution_config.start..execution_config.end);
+    }
+    assert_eq!(indexes, (500000..501000).collect::<Vec<usize>>());
+    assert_eq!(get_executor_config(&config, 0).unwrap().start, 500000);
+    assert_eq!(get_executor_config(&config, 3).unwrap().end, 501000);
+
+    // Out of range iterations don't produce any range
+    assert!(get_executor_config(&config, 4).is_none());
+    assert!(get_executor_config(&config, usize::MAX).is_none());
+    assert!(get_executor_config(&test_search_config(0, 10, 0), 0).is_none());
+    assert!(get_executor_config(&test_search_config(usize::MAX - 1, usize::MAX, 10), 1).is_none());
+}
+
+#[test]
+fn test_get_executor_config_grid(){
+    for start in [0, 1, 7, 100] {
+        for end in [0, 1, 7, 13, 100, 101] {
+            for chunksize in [0, 1, 2, 3, 7, 100, 1000] {
+                let config = test_search_config(start, end, chunksize);
+                let slices = get_slices(&config);
+                let mut next = start;
+                for slice in 0..slices {
+                    let execution_config = get_executor_config(&config, slice).unwrap();
+                    // contiguous with the previous range, so never overlapping it
+                    assert_eq!(execution_config.start, next);
+                    assert!(execution_config.start < execution_config.end);
+                    assert!(execution_config.end <= end);
+                    next = execution_config.end;
+                }
+                assert!(get_executor_config(&config, slices).is_none());
+                if chunksize > 0 && start < end {
+                    assert_eq!(next, end, "start {} end {} chunksize {}", start, end, chunksize);
+                } else {
+                    assert_eq!(slices, 0);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_get_grid_cell(){
+    // Every (wallet, index) cell of the grid is covered exactly once
+    let config = test_search_config(0, 10, 4);
+    let wallets = 3;
+    let tasks = get_slices(&config) * wallets;
+    let mut cells: Vec<(usize, usize)> = Vec::new();
+    for task in 0..tasks {
+        let cell = get_grid_cell(&config, wallets, task).unwrap();
+        cells.extend((cell.execution_config.start..cell.execution_config.end).map(|index| (cell.wallet, index)));
+    }
+    assert_eq!(cells.len(), 30);
+    cells.sort();
+    cells.dedup();
+    assert_eq!(cells.len(), 30);
+
+    // The first chunk of every wallet comes before the second one of any
+    let firsts: Vec<(usize, usize)> = (0..wallets)
+        .map(|task| get_grid_cell(&config, wallets, task).unwrap())
+        .map(|cell| (cell.wallet, cell.execution_config.start))
+        .collect();
+    assert_eq!(firsts, vec![(0, 0), (1, 0), (2, 0)]);
+    assert_eq!(get_grid_cell(&config, wallets, 3).unwrap().execution_config.start, 4);
+    assert!(get_grid_cell(&config, wallets, tasks).is_none());
+    assert!(get_grid_cell(&config, 0, 0).is_none());
+}
+
+/**
+ * A scan of the index range of several wallets. The work is split into tasks
+ * of one chunk of indexes for one wallet, the cells of `get_grid_cell`, so
+ * that the whole wallet × index grid is spread across the threads.
+ */
+pub struct Scan<'a> {
+    pub secp: &'a Secp256k1<All>,
+    pub config: &'a SearchConfig,
+    pub wallet_configs: &'a [WalletConf],
+    pub progress: &'a Progress,
+    pub stop: &'a AtomicBool,
+    pub checkpoint: Option<&'a Checkpoint>,
+    // Chunks fully scanned, counted per wallet when following each of them
+    pub completed: Option<&'a [AtomicUsize]>,
+    // Hook handed every index checked
+    pub observer: Option<&'a dyn Observer>,
+}
+
+impl Scan<'_> {
+    pub fn tasks(&self) -> usize {
+        self::get_slices(self.config) * self.wallet_configs.len()
+    }
+
+    fn run_task(&self, task: usize) -> Result<Vec<SearchResult>, HdiFinderError> {
+        let GridCell { wallet, execution_config } = match self::get_grid_cell(self.config, self.wallet_configs.len(), task) {
+            Some(cell) => cell,
+            None => return Ok(Vec::new()),
+        };
+        let wallet_config = &self.wallet_configs[wallet];
+        let (start, end) = (execution_config.start, execution_config.end);
+        debug!("chunk {}..{} of {} started", start, end, wallet_config.path());
+        let started = Instant::now();
+        let results = self::scan_range_with(
+            self.secp,
+            &self.config.addresses,
+            wallet_config,
+            execution_config,
+            self.config.stop_at_first,
+            self.progress,
+            self.stop,
+            self.observer,
+        )?;
+        debug!(
+            "chunk {}..{} of {} finished in {:.3}s, {} matches",
+            start,
+            end,
+            wallet_config.path(),
+            started.elapsed().as_secs_f64(),
+            results.len()
+        );
+        // A chunk cut short by a match elsewhere or by the time limit hasn't been fully scanned
+        if !self.stop.load(Ordering::Relaxed) {
+            if let Some(checkpoint) = self.checkpoint {
+                checkpoint.complete(task / self.wallet_configs.len())?;
+            }
+            if let Some(completed) = self.completed {
+                completed[wallet].fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        Ok(results)
+    }
+
+    /**
+     * Runs every task on the pool and hands the matches to `found`, whose
+     * failure stops the scan. Returns the number of matches.
+     */
+    pub fn run<F>(&self, pool: &ThreadPool, found: F) -> Result<usize, HdiFinderError>
+    where
+        F: Fn(&SearchResult) -> Result<(), HdiFinderError> + Sync,
+    {
+        let tasks = self.tasks();
+        pool.install(|| {
+            if self.config.stop_at_first {
+                // Workers stop as soon as one of them raises the match flag. The
+                // match is handed over once the pool has drained.
+                let (sender, receiver) = mpsc::channel();
+                (0..tasks).into_par_iter().try_for_each_with(sender, |sender, task| {
+                    for result in self.run_task(task)? {
+                        sender
+                            .send(result)
+                            .map_err(|_| HdiFinderError::Internal("the matches receiver has been dropped".to_string()))?;
+                    }
+                    Ok::<(), HdiFinderError>(())
+                })?;
+                match receiver.try_iter().next() {
+                    Some(result) => {
+                        found(&result)?;
+                        Ok(1)
+                    }
+                    None => Ok(0),
+                }
+            } else {
+                (0..tasks)
+                    .into_par_iter()
+                    .map(|task| {
+                        let results = self.run_task(task)?;
+                        for result in &results {
+                            found(result)?;
+                        }
+                        Ok(results.len())
+                    })
+                    .sum()
+            }
+        })
+    }
+}
+
+#[test]
+fn test_scan_run(){
+    let test_mnemonic_phrase = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let test_seed = derivation::get_mnemonic(test_mnemonic_phrase, None).unwrap().to_seed("");
+    let secp = Secp256k1::new();
+    let wallet_configs = [
+        get_test_wallet_config(test_seed, Purpose::Pubkey, 0),
+        get_test_wallet_config(test_seed, Purpose::Pubkey, 1),
+    ];
+    let mut config = test_search_config(0, 20, 4);
+    config.addresses = HashSet::from([
+        "15Wbvv7V9yWLCr3pxmPSFsAS3NSyQyqeA3".to_string(),
+        "1EukGhGZ3ai3zoG8mH86irCX18zTbthDZ5".to_string(),
+    ]);
+    config.stop_at_first = false;
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+    let progress = Progress::new(40);
+    let completed = [AtomicUsize::new(0), AtomicUsize::new(0)];
+    let scan = Scan {
+        secp: &secp,
+        config: &config,
+        wallet_configs: &wallet_configs,
+        progress: &progress,
+        stop: &AtomicBool::new(false),
+        checkpoint: None,
+        completed: Some(&completed),
+        observer: None,
+    };
+    assert_eq!(scan.tasks(), 10);
+
+    let found = Mutex::new(Vec::new());
+    let matches = scan
+        .run(&pool, |result| {
+            found.lock().unwrap().push(result.derivation_path.clone());
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(matches, 2);
+    assert_eq!(progress.scanned(), 40);
+    // the 5 chunks of each wallet
+    assert_eq!(completed.iter().map(|chunks| chunks.load(Ordering::Relaxed)).collect::<Vec<usize>>(), vec![5, 5]);
+    let mut found = found.into_inner().unwrap();
+    found.sort();
+    assert_eq!(found, vec!["m/44'/0'/0'/0/15", "m/44'/0'/0'/1/3"]);
+}
+
+#[test]
+fn test_scan_run_grid(){
+    let test_mnemonic_phrase = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let test_seed = derivation::get_mnemonic(test_mnemonic_phrase, None).unwrap().to_seed("");
+    let secp = Secp256k1::new();
+    let master = derivation::get_master_key(test_seed, Network::Bitcoin).unwrap();
+    let account_wallet = |account: u32| {
+        let account = AccountHDPath::new(Purpose::Pubkey, 0, account);
+        WalletConf {
+            chain_key: ChainKey::Private(derivation::chain_key(&secp, &master, &account, 0).unwrap()),
+            account,
+            ..get_test_wallet_config(test_seed, Purpose::Pubkey, 0)
+        }
+    };
+    let mut config = test_search_config(0, 10, 4);
+    // planted at m/44'/0'/2'/0/7
+    config.addresses = HashSet::from(["1BMJBmhLLdPyVr3NzstjopExv4BtGJbLXe".to_string()]);
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+    let run = |wallet_configs: &[WalletConf]| {
+        let progress = Progress::new(10 * wallet_configs.len());
+        let scan = Scan {
+            secp: &secp,
+            config: &config,
+            wallet_configs,
+            progress: &progress,
+            stop: &AtomicBool::new(false),
+            checkpoint: None,
+            completed: None,
+            observer: None,
+        };
+        let found = Mutex::new(Vec::new());
+        scan.run(&pool, |result| {
+            found.lock().unwrap().push((result.account, result.index));
+            Ok(())
+        })
+        .unwrap();
+        found.into_inner().unwrap()
+    };
+
+    // The index range of the first account alone doesn't hold it
+    assert!(run(&[account_wallet(0)]).is_empty());
+    let grid: Vec<WalletConf> = (0..4).map(account_wallet).collect();
+    assert_eq!(run(&grid), vec![(2, 7)]);
+}
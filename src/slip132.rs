@@ -0,0 +1,221 @@
+use crate::error::HdiFinderError;
+use bitcoin::{
+    network::constants::Network,
+    util::{base58, bip32::ExtendedPubKey},
+};
+use hdpath::Purpose;
+
+/**
+ * Version bytes of an extended key encoding (SLIP-132). Wallets export
+ * their account keys with the version matching the address type, which
+ * the BIP32 parsing only accepts as xpub/xprv and tpub/tprv.
+ */
+struct Version {
+    prefix: &'static str,
+    bytes: [u8; 4],
+    private: bool,
+    mainnet: bool,
+    // None for the plain BIP32 versions, which don't tell the address type
+    purpose: Option<Purpose>,
+    multisig: bool,
+}
+
+const fn version(prefix: &'static str, bytes: [u8; 4], private: bool, mainnet: bool, purpose: Option<Purpose>, multisig: bool) -> Version {
+    Version {
+        prefix,
+        bytes,
+        private,
+        mainnet,
+        purpose,
+        multisig,
+    }
+}
+
+const VERSIONS: [Version; 20] = [
+    version("xpub", [0x04, 0x88, 0xb2, 0x1e], false, true, None, false),
+    version("xprv", [0x04, 0x88, 0xad, 0xe4], true, true, None, false),
+    version("ypub", [0x04, 0x9d, 0x7c, 0xb2], false, true, Some(Purpose::ScriptHash), false),
+    version("yprv", [0x04, 0x9d, 0x78, 0x78], true, true, Some(Purpose::ScriptHash), false),
+    version("zpub", [0x04, 0xb2, 0x47, 0x46], false, true, Some(Purpose::Witness), false),
+    version("zprv", [0x04, 0xb2, 0x43, 0x0c], true, true, Some(Purpose::Witness), false),
+    version("Ypub", [0x02, 0x95, 0xb4, 0x3f], false, true, None, true),
+    version("Yprv", [0x02, 0x95, 0xb0, 0x05], true, true, None, true),
+    version("Zpub", [0x02, 0xaa, 0x7e, 0xd3], false, true, None, true),
+    version("Zprv", [0x02, 0xaa, 0x7a, 0x99], true, true, None, true),
+    version("tpub", [0x04, 0x35, 0x87, 0xcf], false, false, None, false),
+    version("tprv", [0x04, 0x35, 0x83, 0x94], true, false, None, false),
+    version("upub", [0x04, 0x4a, 0x52, 0x62], false, false, Some(Purpose::ScriptHash), false),
+    version("uprv", [0x04, 0x4a, 0x4e, 0x28], true, false, Some(Purpose::ScriptHash), false),
+    version("vpub", [0x04, 0x5f, 0x1c, 0xf6], false, false, Some(Purpose::Witness), false),
+    version("vprv", [0x04, 0x5f, 0x18, 0xbc], true, false, Some(Purpose::Witness), false),
+    version("Upub", [0x02, 0x42, 0x89, 0xef], false, false, None, true),
+    version("Uprv", [0x02, 0x42, 0x85, 0xb5], true, false, None, true),
+    version("Vpub", [0x02, 0x57, 0x54, 0x83], false, false, None, true),
+    version("Vprv", [0x02, 0x57, 0x50, 0x48], true, false, None, true),
+];
+
+/**
+ * Lists the prefixes of the single signature keys, public or private
+ */
+fn supported_prefixes(private: bool) -> Vec<&'static str> {
+    VERSIONS
+        .iter()
+        .filter(|version| version.private == private && !version.multisig)
+        .map(|version| version.prefix)
+        .collect()
+}
+
+/**
+ * Re-encodes an extended key with the BIP32 version bytes (xpub, xprv, tpub
+ * or tprv), along with the purpose its SLIP-132 prefix stands for.
+ * Keys which aren't valid base58check are given back as is for the BIP32
+ * parsing to report the error.
+ */
+pub fn to_bip32(key: &str, private: bool) -> Result<(String, Option<Purpose>), HdiFinderError> {
+    let key = key.trim();
+    let mut data = match base58::from_check(key) {
+        Ok(data) if data.len() > 4 => data,
+        _ => return Ok((key.to_string(), None)),
+    };
+    let kind = if private { "private" } else { "public" };
+    let version = VERSIONS.iter().find(|version| data[..4] == version.bytes);
+    let version = match version {
+        Some(version) if version.private == private => version,
+        Some(version) => {
+            return Err(HdiFinderError::InvalidArgument(format!(
+                "{} is a {} key, an extended {} key is expected",
+                version.prefix,
+                if version.private { "private" } else { "public" },
+                kind
+            )))
+        }
+        None => {
+            return Err(HdiFinderError::InvalidArgument(format!(
+                "Unsupported extended {} key version {}. Supported prefixes are {}",
+                kind,
+                key.chars().take(4).collect::<String>(),
+                supported_prefixes(private).join(", ")
+            )))
+        }
+    };
+    if version.multisig {
+        return Err(HdiFinderError::InvalidArgument(format!(
+            "{} is a multisig key prefix, which only --cosigner-xpub takes. Supported prefixes are {}",
+            version.prefix,
+            supported_prefixes(private).join(", ")
+        )));
+    }
+    let bip32 = VERSIONS
+        .iter()
+        .find(|bip32| bip32.purpose.is_none() && !bip32.multisig && bip32.private == private && bip32.mainnet == version.mainnet)
+        .expect("VERSIONS holds the BIP32 versions of both networks");
+    data[..4].copy_from_slice(&bip32.bytes);
+    Ok((base58::check_encode_slice(&data), version.purpose.clone()))
+}
+
+#[test]
+fn test_to_bip32(){
+    // BIP84 account key of "abandon abandon ... about"
+    let zpub = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+    let (xpub, purpose) = to_bip32(zpub, false).unwrap();
+    assert!(xpub.starts_with("xpub"));
+    assert_eq!(purpose, Some(Purpose::Witness));
+    assert_eq!(to_bip32(&xpub, false).unwrap(), (xpub.clone(), None));
+
+    assert_eq!(
+        to_bip32(zpub, true).unwrap_err().to_string(),
+        "zpub is a public key, an extended private key is expected"
+    );
+    // version bytes unknown
+    let mut data = base58::from_check(zpub).unwrap();
+    data[..4].copy_from_slice(&[0x04, 0x00, 0x00, 0x00]);
+    assert!(to_bip32(&base58::check_encode_slice(&data), false)
+        .unwrap_err()
+        .to_string()
+        .ends_with("Supported prefixes are xpub, ypub, zpub, tpub, upub, vpub"));
+    data[..4].copy_from_slice(&[0x02, 0xaa, 0x7e, 0xd3]);
+    assert!(to_bip32(&base58::check_encode_slice(&data), false)
+        .unwrap_err()
+        .to_string()
+        .starts_with("Zpub is a multisig key prefix, which only --cosigner-xpub takes."));
+    // left for the BIP32 parsing to reject
+    assert_eq!(to_bip32("zpub", false).unwrap(), ("zpub".to_string(), None));
+}
+
+/**
+ * Re-encodes the extended public key of a multisig cosigner with the BIP32
+ * version bytes. Wallets export them as Zpub or Ypub (Vpub or Upub on
+ * testnet) as well as xpub, the single signature prefixes being refused.
+ */
+pub fn cosigner_to_bip32(key: &str) -> Result<String, HdiFinderError> {
+    let key = key.trim();
+    let mut data = match base58::from_check(key) {
+        Ok(data) if data.len() > 4 => data,
+        _ => return Ok(key.to_string()),
+    };
+    let supported: Vec<&str> = VERSIONS
+        .iter()
+        .filter(|version| !version.private && (version.multisig || version.purpose.is_none()))
+        .map(|version| version.prefix)
+        .collect();
+    let version = match VERSIONS.iter().find(|version| data[..4] == version.bytes) {
+        Some(version) if supported.contains(&version.prefix) => version,
+        _ => {
+            return Err(HdiFinderError::InvalidArgument(format!(
+                "Unsupported cosigner key version {}. Supported prefixes are {}",
+                key.chars().take(4).collect::<String>(),
+                supported.join(", ")
+            )))
+        }
+    };
+    let bip32 = VERSIONS
+        .iter()
+        .find(|bip32| bip32.purpose.is_none() && !bip32.multisig && !bip32.private && bip32.mainnet == version.mainnet)
+        .expect("VERSIONS holds the BIP32 versions of both networks");
+    data[..4].copy_from_slice(&bip32.bytes);
+    Ok(base58::check_encode_slice(&data))
+}
+
+#[test]
+fn test_cosigner_to_bip32(){
+    let xpub = "xpub6DkFAXWQ2dHxq2vatrt9qyA3bXYU4ToWQwCHbf5XB2mSTexcHZCeKS1VZYcPoBd5X8yVcbXFHJR9R8UCVpt82VX1VhR28mCyxUFL4r6KFrf";
+    let mut data = base58::from_check(xpub).unwrap();
+    data[..4].copy_from_slice(&[0x02, 0xaa, 0x7e, 0xd3]);
+    let zpub = base58::check_encode_slice(&data);
+    assert!(zpub.starts_with("Zpub"));
+    assert_eq!(cosigner_to_bip32(&zpub).unwrap(), xpub);
+    assert_eq!(cosigner_to_bip32(xpub).unwrap(), xpub);
+
+    data[..4].copy_from_slice(&[0x04, 0xb2, 0x47, 0x46]);
+    assert_eq!(
+        cosigner_to_bip32(&base58::check_encode_slice(&data)).unwrap_err().to_string(),
+        "Unsupported cosigner key version zpub. Supported prefixes are xpub, Ypub, Zpub, tpub, Upub, Vpub"
+    );
+}
+
+/**
+ * Encodes an extended public key with the SLIP-132 version of the address
+ * type standard for a purpose, e.g zpub for BIP84 on mainnet. None for the
+ * purposes without one, whose keys wallets export as xpub or tpub.
+ */
+pub fn from_bip32(key: &ExtendedPubKey, purpose: &Purpose) -> Option<String> {
+    let mainnet = key.network == Network::Bitcoin;
+    let version = VERSIONS
+        .iter()
+        .find(|version| !version.private && !version.multisig && version.mainnet == mainnet && version.purpose.as_ref() == Some(purpose))?;
+    let mut data = key.encode();
+    data[..4].copy_from_slice(&version.bytes);
+    Some(base58::check_encode_slice(&data))
+}
+
+#[test]
+fn test_from_bip32(){
+    let zpub = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+    let key: ExtendedPubKey = to_bip32(zpub, false).unwrap().0.parse().unwrap();
+    assert_eq!(from_bip32(&key, &Purpose::Witness).unwrap(), zpub);
+    assert!(from_bip32(&key, &Purpose::ScriptHash).unwrap().starts_with("ypub"));
+    assert_eq!(from_bip32(&key, &Purpose::Pubkey), None);
+    assert_eq!(from_bip32(&key, &Purpose::Custom(86)), None);
+    let testnet = ExtendedPubKey { network: Network::Testnet, ..key };
+    assert!(from_bip32(&testnet, &Purpose::Witness).unwrap().starts_with("vpub"));
+}
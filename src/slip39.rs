@@ -0,0 +1,439 @@
+use crate::error::HdiFinderError;
+use bitcoin::hashes::{hmac, sha256, Hash, HashEngine};
+use std::collections::BTreeMap;
+
+/**
+ * SLIP-39 splits a master secret into shares (Shamir's secret sharing),
+ * themselves split into groups: the secret is recovered from a threshold of
+ * groups, each group being recovered from a threshold of its member shares.
+ * The master secret is used as is as the BIP32 seed.
+ */
+const WORDLIST: &str = include_str!("slip39_wordlist.txt");
+
+const RADIX_BITS: usize = 10;
+// Identifier, extendable flag and iteration exponent, then the group and
+// member indexes and thresholds
+const HEADER_WORDS: usize = 4;
+const CHECKSUM_WORDS: usize = 3;
+const MIN_SECRET_LEN: usize = 16;
+const MIN_WORDS: usize = HEADER_WORDS + CHECKSUM_WORDS + (MIN_SECRET_LEN * 8).div_ceil(RADIX_BITS);
+
+const SECRET_INDEX: u8 = 255;
+const DIGEST_INDEX: u8 = 254;
+const DIGEST_LEN: usize = 4;
+
+const ROUND_COUNT: u8 = 4;
+const BASE_ITERATION_COUNT: u32 = 10000;
+
+const CHECKSUM_GENERATOR: [u32; 10] = [
+    0xE0E040, 0x1C1C080, 0x3838100, 0x7070200, 0xE0E0009, 0x1C0C2412, 0x38086C24, 0x3090FC48, 0x21B1F890, 0x3F3F120,
+];
+
+/**
+ * A share of a SLIP-39 split master secret, as encoded by its words
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Share {
+    identifier: u16,
+    extendable: bool,
+    iteration_exponent: u8,
+    group_index: u8,
+    group_threshold: u8,
+    group_count: u8,
+    member_index: u8,
+    member_threshold: u8,
+    value: Vec<u8>,
+}
+
+fn shares_error(reason: String) -> HdiFinderError {
+    HdiFinderError::Shares(reason)
+}
+
+fn word_index(word: &str) -> Option<u16> {
+    WORDLIST.lines().position(|candidate| candidate == word).map(|index| index as u16)
+}
+
+/**
+ * Computes the Reed-Solomon checksum over GF(1024) of the share words,
+ * customized by the kind of share
+ */
+fn rs1024_polymod(customization: &[u8], words: &[u16]) -> u32 {
+    let mut checksum: u32 = 1;
+    for value in customization.iter().map(|byte| *byte as u32).chain(words.iter().map(|word| *word as u32)) {
+        let top = checksum >> 20;
+        checksum = ((checksum & 0xFFFFF) << 10) ^ value;
+        for (i, generator) in CHECKSUM_GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+fn customization(extendable: bool) -> &'static [u8] {
+    if extendable {
+        b"shamir_extendable"
+    } else {
+        b"shamir"
+    }
+}
+
+/**
+ * Parses the words of a share, checking its checksum
+ */
+pub fn parse_share(phrase: &str) -> Result<Share, HdiFinderError> {
+    let words: Vec<String> = phrase.split_whitespace().map(|word| word.to_lowercase()).collect();
+    if words.len() < MIN_WORDS {
+        return Err(shares_error(format!("the share has {} words, a share has at least {} words", words.len(), MIN_WORDS)));
+    }
+    let mut indexes: Vec<u16> = Vec::with_capacity(words.len());
+    for (position, word) in words.iter().enumerate() {
+        match self::word_index(word) {
+            Some(index) => indexes.push(index),
+            None => return Err(shares_error(format!("word {} \"{}\" isn't a SLIP-39 word", position + 1, word))),
+        }
+    }
+
+    let header = indexes[..HEADER_WORDS].iter().fold(0u64, |header, index| (header << RADIX_BITS) | *index as u64);
+    let extendable = (header >> 24) & 1 == 1;
+    if self::rs1024_polymod(self::customization(extendable), &indexes) != 1 {
+        return Err(shares_error("the share checksum is invalid, a word may be wrong".to_string()));
+    }
+
+    let value_words = &indexes[HEADER_WORDS..indexes.len() - CHECKSUM_WORDS];
+    let value_bits = value_words.len() * RADIX_BITS;
+    let padding = value_bits % 16;
+    if padding > 8 {
+        return Err(shares_error(format!("the share has {} words, which don't encode a secret", words.len())));
+    }
+    let mut bits: Vec<bool> = Vec::with_capacity(value_bits);
+    for word in value_words {
+        bits.extend((0..RADIX_BITS).rev().map(|bit| (word >> bit) & 1 == 1));
+    }
+    if bits[..padding].iter().any(|bit| *bit) {
+        return Err(shares_error("the share padding isn't made of zeros".to_string()));
+    }
+    let value: Vec<u8> = bits[padding..]
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0u8, |value, bit| (value << 1) | *bit as u8))
+        .collect();
+
+    let share = Share {
+        identifier: (header >> 25) as u16,
+        extendable,
+        iteration_exponent: ((header >> 20) & 0xF) as u8,
+        group_index: ((header >> 16) & 0xF) as u8,
+        group_threshold: ((header >> 12) & 0xF) as u8 + 1,
+        group_count: ((header >> 8) & 0xF) as u8 + 1,
+        member_index: ((header >> 4) & 0xF) as u8,
+        member_threshold: (header & 0xF) as u8 + 1,
+        value,
+    };
+    if share.group_threshold > share.group_count {
+        return Err(shares_error(format!(
+            "the share requires {} groups out of {}",
+            share.group_threshold, share.group_count
+        )));
+    }
+    Ok(share)
+}
+
+#[test]
+fn test_parse_share(){
+    let share = parse_share("shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed").unwrap();
+    assert_eq!((share.group_threshold, share.group_count), (1, 1));
+    assert_eq!((share.member_index, share.member_threshold), (2, 2));
+    assert_eq!(share.value.len(), 16);
+
+    assert_eq!(
+        parse_share("shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding army").unwrap_err().to_string(),
+        "Invalid SLIP-39 shares: word 20 \"army\" isn't a SLIP-39 word"
+    );
+    assert_eq!(
+        parse_share("shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding axis").unwrap_err().to_string(),
+        "Invalid SLIP-39 shares: the share checksum is invalid, a word may be wrong"
+    );
+    assert_eq!(
+        parse_share("shadow pistol academic always").unwrap_err().to_string(),
+        "Invalid SLIP-39 shares: the share has 4 words, a share has at least 20 words"
+    );
+}
+
+/**
+ * Exponentials and logarithms of GF(256) with the generator 3, over the
+ * Rijndael polynomial
+ */
+fn gf256_tables() -> ([u8; 255], [u8; 256]) {
+    let mut exp = [0u8; 255];
+    let mut log = [0u8; 256];
+    let mut value: u16 = 1;
+    for (i, power) in exp.iter_mut().enumerate() {
+        *power = value as u8;
+        log[value as usize] = i as u8;
+        value = (value << 1) ^ value;
+        if value & 0x100 != 0 {
+            value ^= 0x11B;
+        }
+    }
+    (exp, log)
+}
+
+/**
+ * Evaluates at `x` the polynomials going through the points of the shares,
+ * byte by byte (Lagrange interpolation over GF(256))
+ */
+fn interpolate(points: &[(u8, &[u8])], x: u8) -> Vec<u8> {
+    if let Some((_, value)) = points.iter().find(|(index, _)| *index == x) {
+        return value.to_vec();
+    }
+    let (exp, log) = self::gf256_tables();
+    let log_product: usize = points.iter().map(|(index, _)| log[(index ^ x) as usize] as usize).sum();
+    let mut result = vec![0u8; points[0].1.len()];
+    for (index, value) in points {
+        let log_others: usize = points
+            .iter()
+            .filter(|(other, _)| other != index)
+            .map(|(other, _)| log[(other ^ index) as usize] as usize)
+            .sum();
+        let log_basis = (log_product + 255 * points.len() - log[(index ^ x) as usize] as usize - log_others) % 255;
+        for (byte, share_byte) in result.iter_mut().zip(value.iter()) {
+            if *share_byte != 0 {
+                *byte ^= exp[(log[*share_byte as usize] as usize + log_basis) % 255];
+            }
+        }
+    }
+    result
+}
+
+#[test]
+fn test_interpolate(){
+    // f(x) = 2x + 1, 3 standing for the addition
+    let points: [(u8, &[u8]); 2] = [(1, &[3]), (2, &[5])];
+    assert_eq!(interpolate(&points, 0), vec![1]);
+    assert_eq!(interpolate(&points, 2), vec![5]);
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut engine = hmac::HmacEngine::<sha256::Hash>::new(key);
+    engine.input(data);
+    hmac::Hmac::<sha256::Hash>::from_engine(engine).into_inner()
+}
+
+/**
+ * Recovers the secret shared by the points, checking its digest when more
+ * than one share is needed
+ */
+fn recover_secret(threshold: u8, points: &[(u8, &[u8])]) -> Option<Vec<u8>> {
+    if threshold == 1 {
+        return Some(points[0].1.to_vec());
+    }
+    let secret = self::interpolate(points, SECRET_INDEX);
+    let digest_share = self::interpolate(points, DIGEST_INDEX);
+    let digest = self::hmac_sha256(&digest_share[DIGEST_LEN..], &secret);
+    if digest[..DIGEST_LEN] == digest_share[..DIGEST_LEN] {
+        Some(secret)
+    } else {
+        None
+    }
+}
+
+/**
+ * Derives the PBKDF2-HMAC-SHA256 key of a Feistel round, never longer than a
+ * single block
+ */
+fn round_function(round: u8, passphrase: &[u8], iterations: u32, salt: &[u8], half: &[u8]) -> Vec<u8> {
+    let mut password = vec![round];
+    password.extend(passphrase);
+    let mut block_salt = salt.to_vec();
+    block_salt.extend(half);
+    block_salt.extend(1u32.to_be_bytes());
+    let mut block = self::hmac_sha256(&password, &block_salt);
+    let mut key = block;
+    for _ in 1..iterations {
+        block = self::hmac_sha256(&password, &block);
+        for (byte, round_byte) in key.iter_mut().zip(block.iter()) {
+            *byte ^= round_byte;
+        }
+    }
+    key[..half.len()].to_vec()
+}
+
+/**
+ * Decrypts the encrypted master secret with the passphrase, through the
+ * four rounds of the Feistel network
+ */
+fn decrypt(encrypted: &[u8], passphrase: &[u8], share: &Share) -> Vec<u8> {
+    let mut salt: Vec<u8> = Vec::new();
+    if !share.extendable {
+        salt.extend(b"shamir");
+        salt.extend(share.identifier.to_be_bytes());
+    }
+    let iterations = (BASE_ITERATION_COUNT << share.iteration_exponent) / ROUND_COUNT as u32;
+    let (left, right) = encrypted.split_at(encrypted.len() / 2);
+    let (mut left, mut right) = (left.to_vec(), right.to_vec());
+    for round in (0..ROUND_COUNT).rev() {
+        let key = self::round_function(round, passphrase, iterations, &salt, &right);
+        let mixed: Vec<u8> = left.iter().zip(key.iter()).map(|(byte, key_byte)| byte ^ key_byte).collect();
+        left = right;
+        right = mixed;
+    }
+    right.extend(left);
+    right
+}
+
+/**
+ * Tells which of the groups are complete, for the error reporting shares
+ * missing
+ */
+fn describe_groups(groups: &BTreeMap<u8, Vec<&Share>>, first: &Share) -> String {
+    if first.group_count == 1 {
+        let given = groups.values().next().map_or(0, |members| members.len());
+        return format!("{} of the {} shares required are given", given, first.member_threshold);
+    }
+    let complete = groups.values().filter(|members| members.len() >= members[0].member_threshold as usize).count();
+    let details: Vec<String> = groups
+        .iter()
+        .map(|(index, members)| format!("group {} has {} of its {} shares", index + 1, members.len(), members[0].member_threshold))
+        .collect();
+    format!(
+        "{} of the {} groups required are complete ({})",
+        complete,
+        first.group_threshold,
+        details.join(", ")
+    )
+}
+
+/**
+ * Combines the shares into the master secret, decrypted with the passphrase
+ */
+pub fn combine(shares: &[Share], passphrase: &str) -> Result<Vec<u8>, HdiFinderError> {
+    let first = match shares.first() {
+        Some(first) => first,
+        None => return Err(shares_error("no share given".to_string())),
+    };
+    if passphrase.chars().any(|c| !(' '..='~').contains(&c)) {
+        return Err(shares_error("a SLIP-39 passphrase is made of printable ASCII characters only".to_string()));
+    }
+    for (position, share) in shares.iter().enumerate() {
+        if share.identifier != first.identifier || share.extendable != first.extendable {
+            return Err(shares_error(format!(
+                "share {} doesn't belong to the same secret as share 1 (identifier {} instead of {})",
+                position + 1,
+                share.identifier,
+                first.identifier
+            )));
+        }
+        if share.iteration_exponent != first.iteration_exponent
+            || share.group_threshold != first.group_threshold
+            || share.group_count != first.group_count
+            || share.value.len() != first.value.len()
+        {
+            return Err(shares_error(format!("share {} doesn't match the parameters of share 1", position + 1)));
+        }
+    }
+
+    let mut groups: BTreeMap<u8, Vec<&Share>> = BTreeMap::new();
+    for (position, share) in shares.iter().enumerate() {
+        let members = groups.entry(share.group_index).or_default();
+        if let Some(member) = members.first() {
+            if member.member_threshold != share.member_threshold {
+                return Err(shares_error(format!(
+                    "share {} requires {} shares of group {} while another share requires {}",
+                    position + 1,
+                    share.member_threshold,
+                    share.group_index + 1,
+                    member.member_threshold
+                )));
+            }
+        }
+        match members.iter().find(|member| member.member_index == share.member_index) {
+            // The same share given twice
+            Some(member) if member.value == share.value => continue,
+            Some(_) => {
+                return Err(shares_error(format!(
+                    "share {} and another share are both share {} of group {}",
+                    position + 1,
+                    share.member_index + 1,
+                    share.group_index + 1
+                )))
+            }
+            None => members.push(share),
+        }
+    }
+
+    let complete: Vec<(u8, Vec<&Share>)> = groups
+        .iter()
+        .filter(|(_, members)| members.len() >= members[0].member_threshold as usize)
+        .map(|(index, members)| (*index, members.clone()))
+        .collect();
+    if complete.len() < first.group_threshold as usize {
+        return Err(shares_error(format!("not enough shares, {}", self::describe_groups(&groups, first))));
+    }
+
+    let digest_error = || shares_error("the shares don't combine into a valid secret, a share may be wrong or come from another secret".to_string());
+    let mut group_secrets: Vec<(u8, Vec<u8>)> = Vec::new();
+    for (index, members) in complete.iter().take(first.group_threshold as usize) {
+        let threshold = members[0].member_threshold;
+        let points: Vec<(u8, &[u8])> = members
+            .iter()
+            .take(threshold as usize)
+            .map(|member| (member.member_index, member.value.as_slice()))
+            .collect();
+        group_secrets.push((*index, self::recover_secret(threshold, &points).ok_or_else(digest_error)?));
+    }
+    let points: Vec<(u8, &[u8])> = group_secrets.iter().map(|(index, secret)| (*index, secret.as_slice())).collect();
+    let encrypted = self::recover_secret(first.group_threshold, &points).ok_or_else(digest_error)?;
+    Ok(self::decrypt(&encrypted, passphrase.as_bytes(), first))
+}
+
+#[cfg(test)]
+fn combine_phrases(phrases: &[&str], passphrase: &str) -> Result<String, HdiFinderError> {
+    let shares: Vec<Share> = phrases.iter().map(|phrase| parse_share(phrase)).collect::<Result<_, _>>()?;
+    Ok(combine(&shares, passphrase)?.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+#[test]
+fn test_combine(){
+    // SLIP-39 test vectors, encrypted with the passphrase "TREZOR"
+    let single = "duckling enlarge academic academic agency result length solution fridge kidney coal piece deal husband erode duke ajar critical decision keyboard";
+    assert_eq!(combine_phrases(&[single], "TREZOR").unwrap(), "bb54aac4b89dc868ba37d9cc21b2cece");
+    assert_ne!(combine_phrases(&[single], "").unwrap(), "bb54aac4b89dc868ba37d9cc21b2cece");
+    assert_eq!(
+        combine_phrases(&["theory painting academic academic armed sweater year military elder discuss acne wildlife boring employer fused large satoshi bundle carbon diagnose anatomy hamster leaves tracks paces beyond phantom capital marvel lips brave detect luck"], "TREZOR").unwrap(),
+        "989baf9dcaad5b10ca33dfd8cc75e42477025dce88ae83e75a230086a0e00e92"
+    );
+
+    let first = "shadow pistol academic always adequate wildlife fancy gross oasis cylinder mustang wrist rescue view short owner flip making coding armed";
+    let second = "shadow pistol academic acid actress prayer class unknown daughter sweater depict flip twice unkind craft early superior advocate guest smoking";
+    assert_eq!(combine_phrases(&[first, second], "TREZOR").unwrap(), "b43ceb7e57a0ea8766221624d01b0864");
+    assert_eq!(combine_phrases(&[second, first, second], "TREZOR").unwrap(), "b43ceb7e57a0ea8766221624d01b0864");
+    assert_eq!(
+        combine_phrases(&[first], "TREZOR").unwrap_err().to_string(),
+        "Invalid SLIP-39 shares: not enough shares, 1 of the 2 shares required are given"
+    );
+    assert!(combine_phrases(&[first, single], "TREZOR")
+        .unwrap_err()
+        .to_string()
+        .starts_with("Invalid SLIP-39 shares: share 2 doesn't belong to the same secret as share 1"));
+
+    // A share of another member altered: the digest no longer matches
+    let mut altered = parse_share(second).unwrap();
+    altered.value[0] ^= 1;
+    assert!(combine(&[parse_share(first).unwrap(), altered], "TREZOR")
+        .unwrap_err()
+        .to_string()
+        .starts_with("Invalid SLIP-39 shares: the shares don't combine into a valid secret"));
+
+    // Two groups of a 2 of 3 groups split, one of them lacking a share
+    let mut group = parse_share(first).unwrap();
+    group.group_threshold = 2;
+    group.group_count = 3;
+    let mut other = group.clone();
+    other.group_index = 2;
+    other.member_threshold = 1;
+    assert_eq!(
+        combine(&[group, other], "").unwrap_err().to_string(),
+        "Invalid SLIP-39 shares: not enough shares, 1 of the 2 groups required are complete (group 1 has 1 of its 2 shares, group 3 has 1 of its 1 shares)"
+    );
+}
@@ -0,0 +1,269 @@
+use crate::error::HdiFinderError;
+use bitcoin::{hashes::sha256, hashes::Hash, network::constants::Network};
+use std::{fs, io, sync::Mutex};
+
+/**
+ * Number of chunks scanned between two writes of the state file
+ */
+const CHECKPOINT_INTERVAL: usize = 10;
+
+/**
+ * Progress of a scan as persisted in a state file.
+ * Every index below `next` has been scanned.
+ */
+#[derive(Debug, PartialEq)]
+pub struct ScanState {
+    pub fingerprint: String,
+    pub next: usize,
+}
+
+/**
+ * Identifies a scan so that a state file is never resumed with another wallet.
+ * Every source scanned and every passphrase tried is part of it, a state file
+ * of other candidates resuming at a chunk they haven't been scanned up to.
+ * The end of the range isn't part of it, which allows extending a finished scan.
+ */
+pub fn fingerprint(master_fingerprints: &[String], passphrases: &[&str], paths: &[String], network: Network, start: usize) -> String {
+    let passphrase_hashes: Vec<String> = passphrases
+        .iter()
+        .map(|passphrase| sha256::Hash::hash(passphrase.as_bytes()).to_string())
+        .collect();
+    let identity = format!(
+        "{}|{}|{}|{}|{}",
+        master_fingerprints.join(","),
+        passphrase_hashes.join(","),
+        paths.join(","),
+        network,
+        start
+    );
+    sha256::Hash::hash(identity.as_bytes()).to_string()
+}
+
+#[test]
+fn test_fingerprint(){
+    let paths = vec!["m/44'/0'/0'/0".to_string()];
+    let master = vec!["d34db33f".to_string()];
+    let reference = fingerprint(&master, &[""], &paths, Network::Bitcoin, 0);
+    assert_eq!(reference.len(), 64);
+    assert_eq!(reference, fingerprint(&master, &[""], &paths, Network::Bitcoin, 0));
+
+    assert_ne!(reference, fingerprint(&["0badc0de".to_string()], &[""], &paths, Network::Bitcoin, 0));
+    assert_ne!(reference, fingerprint(&master, &["secret"], &paths, Network::Bitcoin, 0));
+    assert_ne!(reference, fingerprint(&master, &[""], &["m/84'/0'/0'/0".to_string()], Network::Bitcoin, 0));
+    assert_ne!(reference, fingerprint(&master, &[""], &paths, Network::Testnet, 0));
+    assert_ne!(reference, fingerprint(&master, &[""], &paths, Network::Bitcoin, 500));
+
+    // Every candidate counts, not only the first one
+    let masters = vec!["d34db33f".to_string(), "0badc0de".to_string()];
+    let candidates = fingerprint(&masters, &["", "secret"], &paths, Network::Bitcoin, 0);
+    assert_ne!(candidates, fingerprint(&[masters[0].clone(), "feedface".to_string()], &["", "secret"], &paths, Network::Bitcoin, 0));
+    assert_ne!(candidates, fingerprint(&masters, &["", "hidden"], &paths, Network::Bitcoin, 0));
+    assert_ne!(candidates, fingerprint(&masters, &[""], &paths, Network::Bitcoin, 0));
+}
+
+/**
+ * Reads the index a scan resumes from, None when the state file doesn't
+ * exist yet. A state file saved by another scan is refused.
+ */
+pub fn resume(path: &str, fingerprint: &str) -> Result<Option<usize>, HdiFinderError> {
+    match self::load(path)? {
+        Some(saved) if saved.fingerprint != fingerprint => Err(HdiFinderError::InvalidArgument(format!(
+            "State file {} belongs to another scan (mnemonic, passphrase, paths, network or start index differ)",
+            path
+        ))),
+        Some(saved) => Ok(Some(saved.next)),
+        None => Ok(None),
+    }
+}
+
+#[test]
+fn test_resume(){
+    let path = std::env::temp_dir().join(format!("hdifinder-resume-{}", std::process::id()));
+    let path = path.to_str().unwrap();
+    let paths = vec!["m/44'/0'/0'/0".to_string()];
+    let masters = vec!["d34db33f".to_string(), "0badc0de".to_string()];
+    let saved = fingerprint(&masters, &["", "secret"], &paths, Network::Bitcoin, 0);
+
+    assert_eq!(resume(path, &saved).unwrap(), None);
+    save(path, &ScanState { fingerprint: saved.clone(), next: 1200 }).unwrap();
+    assert_eq!(resume(path, &saved).unwrap(), Some(1200));
+
+    // The second mnemonic or a passphrase candidate changed
+    let other_mnemonic = fingerprint(&[masters[0].clone(), "feedface".to_string()], &["", "secret"], &paths, Network::Bitcoin, 0);
+    let other_passphrase = fingerprint(&masters, &["", "hidden"], &paths, Network::Bitcoin, 0);
+    for fingerprint in [other_mnemonic, other_passphrase] {
+        assert!(resume(path, &fingerprint).unwrap_err().to_string().starts_with(&format!("State file {} belongs to another scan", path)));
+    }
+    fs::remove_file(path).unwrap();
+}
+
+/**
+ * Reads a state file, returns None when it doesn't exist yet
+ */
+pub fn load(path: &str) -> Result<Option<ScanState>, HdiFinderError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(source) => {
+            return Err(HdiFinderError::Io {
+                path: path.to_string(),
+                source,
+            })
+        }
+    };
+
+    let mut fingerprint = None;
+    let mut next = None;
+    for line in content.lines() {
+        match line.split_once('=') {
+            Some(("fingerprint", value)) => fingerprint = Some(value.to_string()),
+            Some(("next", value)) => next = value.parse::<usize>().ok(),
+            _ => {}
+        }
+    }
+    match (fingerprint, next) {
+        (Some(fingerprint), Some(next)) => Ok(Some(ScanState { fingerprint, next })),
+        _ => Err(HdiFinderError::Io {
+            path: path.to_string(),
+            source: io::Error::new(io::ErrorKind::InvalidData, "the state file is corrupted"),
+        }),
+    }
+}
+
+/**
+ * Writes a state file. The content is written aside first and then moved
+ * over the previous file so that an interruption never leaves a partial file.
+ */
+pub fn save(path: &str, state: &ScanState) -> Result<(), HdiFinderError> {
+    let tmp_path = format!("{}.tmp", path);
+    fs::write(&tmp_path, format!("fingerprint={}\nnext={}\n", state.fingerprint, state.next))
+        .and_then(|_| fs::rename(&tmp_path, path))
+        .map_err(|source| HdiFinderError::Io {
+            path: path.to_string(),
+            source,
+        })
+}
+
+#[test]
+fn test_save_load(){
+    let path = std::env::temp_dir().join(format!("hdifinder-state-{}", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    assert_eq!(load(path).unwrap(), None);
+    let state = ScanState { fingerprint: "abc".to_string(), next: 2500 };
+    save(path, &state).unwrap();
+    assert_eq!(load(path).unwrap(), Some(state));
+
+    fs::write(path, "next=12\n").unwrap();
+    assert!(crate::error::cause_chain(&load(path).unwrap_err()).ends_with("the state file is corrupted"));
+    fs::remove_file(path).unwrap();
+}
+
+/**
+ * Tracks the chunks completed by the workers and periodically persists
+ * the contiguous scanned range. Chunks complete out of order, so only
+ * the leading chunks that are fully scanned for every wallet count.
+ */
+pub struct Checkpoint {
+    path: String,
+    fingerprint: String,
+    start: usize,
+    end: usize,
+    chunksize: usize,
+    tasks_per_chunk: usize,
+    completed: Mutex<CheckpointProgress>,
+}
+
+struct CheckpointProgress {
+    tasks_done: Vec<usize>,
+    contiguous: usize,
+    saved: usize,
+}
+
+impl Checkpoint {
+    pub fn new(path: &str, fingerprint: String, start: usize, end: usize, chunksize: usize, tasks_per_chunk: usize) -> Checkpoint {
+        let chunks = end.saturating_sub(start).div_ceil(chunksize);
+        Checkpoint {
+            path: path.to_string(),
+            fingerprint,
+            start,
+            end,
+            chunksize,
+            tasks_per_chunk,
+            completed: Mutex::new(CheckpointProgress {
+                tasks_done: vec![0; chunks],
+                contiguous: 0,
+                saved: 0,
+            }),
+        }
+    }
+
+    /**
+     * Records a task fully scanned for a chunk, saving the state every few chunks
+     */
+    pub fn complete(&self, chunk: usize) -> Result<(), HdiFinderError> {
+        let mut completed = self.completed.lock().map_err(|_| HdiFinderError::Internal("a worker panicked while recording a chunk".to_string()))?;
+        completed.tasks_done[chunk] += 1;
+        while completed.contiguous < completed.tasks_done.len()
+            && completed.tasks_done[completed.contiguous] == self.tasks_per_chunk
+        {
+            completed.contiguous += 1;
+        }
+        if completed.contiguous >= completed.saved + CHECKPOINT_INTERVAL {
+            completed.saved = completed.contiguous;
+            save(&self.path, &self.state(completed.contiguous))?;
+        }
+        Ok(())
+    }
+
+    /**
+     * Saves the contiguous scanned range, once the workers are done
+     */
+    pub fn save(&self) -> Result<(), HdiFinderError> {
+        let completed = self.completed.lock().map_err(|_| HdiFinderError::Internal("a worker panicked while recording a chunk".to_string()))?;
+        save(&self.path, &self.state(completed.contiguous))
+    }
+
+    fn state(&self, contiguous: usize) -> ScanState {
+        ScanState {
+            fingerprint: self.fingerprint.clone(),
+            next: self.end.min(self.start + contiguous * self.chunksize),
+        }
+    }
+}
+
+#[test]
+fn test_checkpoint(){
+    let path = std::env::temp_dir().join(format!("hdifinder-checkpoint-{}", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    // 25 chunks of 10 indexes from 100, 2 wallets scanned per chunk
+    let checkpoint = Checkpoint::new(path, "abc".to_string(), 100, 345, 10, 2);
+    checkpoint.complete(1).unwrap();
+    checkpoint.complete(1).unwrap();
+    checkpoint.complete(0).unwrap();
+    checkpoint.save().unwrap();
+    // chunk 0 is only scanned for one of the wallets
+    assert_eq!(load(path).unwrap().unwrap().next, 100);
+
+    checkpoint.complete(0).unwrap();
+    checkpoint.save().unwrap();
+    assert_eq!(load(path).unwrap().unwrap().next, 120);
+
+    // the state is written on its own every few chunks
+    for chunk in 2..12 {
+        checkpoint.complete(chunk).unwrap();
+        checkpoint.complete(chunk).unwrap();
+    }
+    fs::remove_file(path).unwrap();
+    for chunk in 12..25 {
+        checkpoint.complete(chunk).unwrap();
+        checkpoint.complete(chunk).unwrap();
+    }
+    assert_eq!(load(path).unwrap().unwrap().next, 300);
+
+    // the last chunk ends with the range
+    checkpoint.save().unwrap();
+    assert_eq!(load(path).unwrap(), Some(ScanState { fingerprint: "abc".to_string(), next: 345 }));
+    fs::remove_file(path).unwrap();
+}
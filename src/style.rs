@@ -0,0 +1,77 @@
+use std::{
+    env,
+    io::{self, IsTerminal},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/**
+ * Set by --no-color
+ */
+static NO_COLOR: AtomicBool = AtomicBool::new(false);
+
+/**
+ * What a message tells, which its color stands for
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Style {
+    Found,
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/**
+ * Turns the colors off for the rest of the run, as --no-color does
+ */
+pub fn disable() {
+    NO_COLOR.store(true, Ordering::Relaxed);
+}
+
+/**
+ * Tells whether the text printed to a stream is colored: only terminals get
+ * colors, unless --no-color or the NO_COLOR environment variable is set
+ */
+pub fn enabled(stream: Stream) -> bool {
+    if NO_COLOR.load(Ordering::Relaxed) || env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        return false;
+    }
+    match stream {
+        Stream::Stdout => io::stdout().is_terminal(),
+        Stream::Stderr => io::stderr().is_terminal(),
+    }
+}
+
+fn colored(text: &str, style: Style) -> String {
+    let color = match style {
+        Style::Found => 32,
+        Style::Warning => 33,
+        Style::Error => 31,
+    };
+    format!("\x1b[{}m{}\x1b[0m", color, text)
+}
+
+/**
+ * Colors a text printed to a stream when the stream gets colors
+ */
+pub fn paint(text: &str, style: Style, stream: Stream) -> String {
+    if self::enabled(stream) {
+        self::colored(text, style)
+    } else {
+        text.to_string()
+    }
+}
+
+#[test]
+fn test_paint(){
+    assert_eq!(colored("address found", Style::Found), "\x1b[32maddress found\x1b[0m");
+    assert_eq!(colored("Warning", Style::Warning), "\x1b[33mWarning\x1b[0m");
+    assert_eq!(colored("Invalid mnemonic", Style::Error), "\x1b[31mInvalid mnemonic\x1b[0m");
+    disable();
+    assert!(!enabled(Stream::Stdout) && !enabled(Stream::Stderr));
+    assert_eq!(paint("address found", Style::Found, Stream::Stdout), "address found");
+}
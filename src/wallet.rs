@@ -0,0 +1,761 @@
+use crate::{
+    aezeed, derivation, descriptor,
+    electrum::{self, SeedVersion},
+    error::HdiFinderError,
+    models::{AccountXpub, ChainKey, MnemonicFix, Multisig, PassphraseCandidate, SearchConfig, Seed, Swaps, WalletConf},
+    multisig, slip132, state,
+};
+use bip39::Mnemonic;
+use bitcoin::util::bip32::{ChildNumber, DerivationPath, ExtendedPrivKey, ExtendedPubKey};
+use hdpath::{AccountHDPath, Purpose};
+use rayon::{prelude::*, ThreadPool};
+use secp256k1::{All, Secp256k1};
+#[cfg(test)]
+use {
+    crate::{presets::Layout, search},
+    bitcoin::network::constants::Network,
+    std::str::FromStr,
+};
+
+/**
+ * Mnemonic scanned, along with where it comes from when scanning several
+ */
+pub struct MnemonicCandidate {
+    pub mnemonic: Mnemonic,
+    pub line: Option<usize>,
+    pub fix: Option<MnemonicFix>,
+    // The child index of a BIP85 child mnemonic, scanned without a passphrase
+    pub bip85_child: Option<u32>,
+}
+
+impl MnemonicCandidate {
+    pub fn new(mnemonic: Mnemonic, fix: Option<MnemonicFix>) -> MnemonicCandidate {
+        MnemonicCandidate { mnemonic, line: None, fix, bip85_child: None }
+    }
+}
+
+/**
+ * What the wallets are derived from
+ */
+#[derive(Clone, Copy)]
+pub enum Source<'a> {
+    Mnemonic(&'a MnemonicCandidate),
+    Seed(&'a Seed),
+    Key(&'a ExtendedPrivKey),
+    PublicKey(&'a ExtendedPubKey),
+    Electrum(&'a str, SeedVersion),
+}
+
+/**
+ * Versions of a mnemonic phrase to scan, along with the checksum error of a
+ * phrase failing it, its candidates then being the fixed ones only
+ */
+pub struct MnemonicCandidates {
+    pub candidates: Vec<MnemonicCandidate>,
+    pub checksum_error: Option<HdiFinderError>,
+}
+
+/**
+ * Lists the versions of the mnemonic phrase to scan, along with how they have
+ * been fixed. A phrase failing the checksum only goes through fixed: its last word
+ * replaced with --fix-checksum or two of its words swapped with --try-swaps.
+ */
+pub fn mnemonic_candidates(phrase: &str, config: &SearchConfig) -> Result<MnemonicCandidates, HdiFinderError> {
+    let mut candidates: Vec<MnemonicCandidate> = Vec::new();
+    let checksum_error = match derivation::get_mnemonic(phrase, config.language) {
+        Ok(mnemonic) => {
+            candidates.push(MnemonicCandidate::new(mnemonic, None));
+            None
+        }
+        // The fixes are only tried on BIP39 phrases
+        Err(HdiFinderError::Mnemonic { error, .. })
+            if !config.fix_checksum && config.try_swaps.is_none() && electrum::seed_version(phrase).is_some() =>
+        {
+            return Err(HdiFinderError::Mnemonic {
+                reason: "the phrase is an Electrum seed, not a BIP39 mnemonic. Give --seed-type electrum".to_string(),
+                error,
+            })
+        }
+        Err(HdiFinderError::Mnemonic { error, .. })
+            if !config.fix_checksum && config.try_swaps.is_none() && aezeed::is_cipher_seed(phrase) =>
+        {
+            return Err(HdiFinderError::Mnemonic {
+                reason: "the phrase is an LND aezeed cipher seed, not a BIP39 mnemonic. Give --seed-type aezeed".to_string(),
+                error,
+            })
+        }
+        Err(error @ HdiFinderError::Mnemonic { error: bip39::Error::InvalidChecksum, .. }) => Some(error),
+        Err(error) => return Err(error),
+    };
+
+    if checksum_error.is_some() && config.fix_checksum {
+        candidates.extend(derivation::last_word_candidates(phrase, config.language).into_iter().map(|mnemonic| {
+            let word = mnemonic.word_iter().last().unwrap_or_default().to_string();
+            MnemonicCandidate::new(mnemonic, Some(MnemonicFix::LastWord(word)))
+        }));
+    }
+
+    if let Some(swaps) = config.try_swaps {
+        candidates.extend(
+            derivation::swap_candidates(phrase, config.language, swaps == Swaps::All)
+                .into_iter()
+                .map(|(first, second, mnemonic)| MnemonicCandidate::new(mnemonic, Some(MnemonicFix::Swap(first, second)))),
+        );
+    }
+
+    Ok(MnemonicCandidates { candidates, checksum_error })
+}
+
+#[test]
+fn test_mnemonic_candidates() {
+    let mut config = search::test_search_config(0, 10, 10);
+    let phrase = "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb";
+    let expanded = mnemonic_candidates(phrase, &config).unwrap();
+    assert_eq!(expanded.candidates.len(), 1);
+    assert!(expanded.candidates[0].fix.is_none());
+    assert!(expanded.checksum_error.is_none());
+
+    // the last word "verb" mistyped as "very"
+    let mistyped = phrase.replace(" verb", " very");
+    let expanded = mnemonic_candidates(&mistyped, &config).unwrap();
+    assert!(expanded.candidates.is_empty());
+    assert!(matches!(
+        expanded.checksum_error,
+        Some(HdiFinderError::Mnemonic { error: bip39::Error::InvalidChecksum, .. })
+    ));
+
+    config.fix_checksum = true;
+    let expanded = mnemonic_candidates(&mistyped, &config).unwrap();
+    assert_eq!(expanded.candidates.len(), 8);
+    assert!(expanded.checksum_error.is_some());
+    let fixed = expanded.candidates.iter().find(|candidate| candidate.fix == Some(MnemonicFix::LastWord("verb".to_string()))).unwrap();
+    assert_eq!(fixed.mnemonic.to_string(), phrase);
+
+    // "quit" and "tenant" are only found swapped when trying every pair
+    let swapped = phrase.replacen("quit", "tenant", 1).replacen(" tenant verb", " quit verb", 1);
+    config.fix_checksum = false;
+    config.try_swaps = Some(Swaps::Adjacent);
+    let expanded = mnemonic_candidates(&swapped, &config).unwrap();
+    assert!(expanded.candidates.iter().all(|candidate| candidate.fix != Some(MnemonicFix::Swap(1, 22))));
+    config.try_swaps = Some(Swaps::All);
+    let expanded = mnemonic_candidates(&swapped, &config).unwrap();
+    let fixed = expanded.candidates.iter().find(|candidate| candidate.fix == Some(MnemonicFix::Swap(1, 22))).unwrap();
+    assert_eq!(fixed.mnemonic.to_string(), phrase);
+
+    // The other seed types are told apart when no fix is tried
+    config.try_swaps = None;
+    match mnemonic_candidates("cycle rocket west magnet parrot shuffle foot correct salt library feed song", &config) {
+        Err(HdiFinderError::Mnemonic { reason, .. }) => assert!(reason.contains("Give --seed-type electrum")),
+        _ => panic!("an Electrum seed must be refused"),
+    }
+}
+
+/**
+ * Builds the wallets scanned for a mnemonic and a passphrase, or for a seed
+ * or an extended key given as is: one per account, purpose and chain, and
+ * per key format for the legacy layouts, and the p2pkh ones of
+ * --include-uncompressed, whose keys may be uncompressed
+ */
+pub fn wallet_configs(
+    secp: &Secp256k1<All>,
+    config: &SearchConfig,
+    source: Source,
+    passphrase: Option<&PassphraseCandidate>,
+) -> Result<Vec<WalletConf>, HdiFinderError> {
+    let (root, candidate) = match source {
+        Source::PublicKey(_) | Source::Electrum(..) if config.layout.is_legacy() => {
+            return Err(HdiFinderError::InvalidArgument(
+                "The legacy presets derive their chains from a BIP32 master private key".to_string(),
+            ))
+        }
+        Source::PublicKey(_) | Source::Electrum(..) if config.multisig.is_some() => {
+            return Err(HdiFinderError::InvalidArgument(
+                "--cosigner-xpub derives the key of the seed from a BIP32 master private key".to_string(),
+            ))
+        }
+        Source::PublicKey(key) => return self::watch_only_wallet_configs(secp, config, key),
+        Source::Electrum(phrase, version) => return self::electrum_wallet_configs(secp, config, phrase, version, passphrase),
+        Source::Mnemonic(candidate) => {
+            let seed = candidate.mnemonic.to_seed(match passphrase {
+                Some(passphrase) => passphrase.passphrase.as_str(),
+                None if candidate.bip85_child.is_some() => "",
+                None => config.passphrase.as_str(),
+            });
+            (derivation::get_master_key(seed, config.network)?, Some(candidate))
+        }
+        Source::Seed(seed) => (derivation::get_master_key(*seed, config.network)?, None),
+        Source::Key(key) if key.depth != 0 && config.layout.is_legacy() => {
+            return Err(HdiFinderError::InvalidArgument(
+                "The legacy presets derive their chains from the master key, not from an account level key".to_string(),
+            ))
+        }
+        Source::Key(key) if key.depth != 0 && config.multisig.is_some() => {
+            return Err(HdiFinderError::InvalidArgument(
+                "--cosigner-xpub derives the BIP48 account of the seed from the master key, not from an account level key".to_string(),
+            ))
+        }
+        Source::Key(key) => (*key, None),
+    };
+    if let Some(multisig) = &config.multisig {
+        return self::multisig_wallet_configs(secp, config, multisig, &root, candidate, passphrase);
+    }
+    // An account level key is scanned under each purpose, the levels above
+    // it being unknown, while a master key is scanned as well under the
+    // named accounts of the preset
+    let mut accounts: Vec<(u32, Purpose, Option<&'static str>)> = Vec::new();
+    if root.depth == derivation::ACCOUNT_DEPTH {
+        let account = derivation::child_index(root.child_number);
+        accounts.extend(config.purposes.iter().map(|purpose| (account, purpose.clone(), None)));
+    } else {
+        for account in config.accounts.clone() {
+            accounts.extend(config.purposes.iter().map(|purpose| (account, purpose.clone(), None)));
+        }
+        accounts.extend(config.named_accounts.iter().map(|named| (named.index, named.purpose.clone(), Some(named.name))));
+    }
+    let mut wallet_configs: Vec<WalletConf> = Vec::new();
+    for (account, purpose, account_label) in accounts {
+        for change in &config.chains {
+            // The legacy layouts have no purpose nor coin level, their
+            // account standing for the account' level of m/account'/chain
+            let chain_path = config.layout.chain_path(account, *change);
+            let account = AccountHDPath::new(purpose, derivation::coin_type_for(config.coin, config.network), account);
+            let (chain_key, account_path) = match (&chain_path, config.layout.account_path(account.account())) {
+                (Some(path), Some(account_path)) => {
+                    let chain_key = root.derive_priv(secp, path).map_err(|source| HdiFinderError::Derivation {
+                        target: path.to_string(),
+                        source,
+                    })?;
+                    (chain_key, account_path)
+                }
+                _ => (derivation::root_chain_key(secp, &root, &account, *change)?, DerivationPath::from(&account)),
+            };
+            let descriptor_key = if config.emit_descriptor {
+                Some(descriptor::chain_key_expression(secp, &root, &account_path, *change, config.with_private)?)
+            } else {
+                None
+            };
+            let account_xpub = if config.show_xpub {
+                Some(derivation::account_xpub(secp, &root, &account_path, account.purpose())?)
+            } else {
+                None
+            };
+            let key_formats: &[bool] = if config.layout.is_legacy() || (config.include_uncompressed && account.purpose() == &Purpose::Pubkey) {
+                &[false, true]
+            } else {
+                &[false]
+            };
+            for uncompressed in key_formats {
+                wallet_configs.push(WalletConf {
+                    chain_key: if config.pubkey_only {
+                        ChainKey::Public(derivation::chain_pubkey(secp, &chain_key))
+                    } else {
+                        ChainKey::Private(chain_key)
+                    },
+                    account: account.clone(),
+                    change: *change,
+                    network: config.network,
+                    coin: config.coin,
+                    passphrase: passphrase.cloned(),
+                    mnemonic_line: candidate.and_then(|candidate| candidate.line),
+                    mnemonic_fix: candidate.and_then(|candidate| candidate.fix.clone()),
+                    watch_only: false,
+                    bip85_child: candidate.and_then(|candidate| candidate.bip85_child),
+                    chain_path: chain_path.clone(),
+                    account_label,
+                    hardened_index: config.hardened_index,
+                    uncompressed: *uncompressed,
+                    multisig: None,
+                    // Descriptors only express the compressed keys of an xpub
+                    descriptor_key: descriptor_key.clone().filter(|_| !uncompressed),
+                    show_private: config.show_private,
+                    account_xpub: account_xpub.clone(),
+                    fingerprint: derivation::master_fingerprint(secp, &root),
+                    sign_message: config.sign_message.clone(),
+                    sign_message_bip322: config.sign_message_bip322.clone(),
+                    sign_psbt: config.psbt.is_some(),
+                });
+            }
+        }
+    }
+    Ok(wallet_configs)
+}
+
+#[test]
+fn test_wallet_configs() {
+    let secp = Secp256k1::new();
+    let mut config = search::test_search_config(0, 10, 10);
+    config.accounts = 0..=1;
+    config.chains = vec![0, 1];
+    let mnemonic = derivation::get_mnemonic("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb", None).unwrap();
+    let candidate = MnemonicCandidate { line: Some(3), ..MnemonicCandidate::new(mnemonic, None) };
+
+    // One wallet per account and chain
+    let wallets = wallet_configs(&secp, &config, Source::Mnemonic(&candidate), None).unwrap();
+    let paths: Vec<String> = wallets.iter().map(|wallet| wallet.path().to_string()).collect();
+    assert_eq!(paths, vec!["m/44'/0'/0'/0", "m/44'/0'/0'/1", "m/44'/0'/1'/0", "m/44'/0'/1'/1"]);
+    assert!(wallets.iter().all(|wallet| wallet.mnemonic_line == Some(3) && !wallet.watch_only));
+    let found = search::find_address(&wallets[0], "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", 0..10).unwrap().unwrap();
+    assert_eq!(found.origin.as_deref(), Some("[67b4602d/44'/0'/0'/0/5]"));
+
+    // The passphrase candidate replaces the passphrase of the config
+    let passphrase = PassphraseCandidate { line: 1, passphrase: "secret".to_string() };
+    let wallets = wallet_configs(&secp, &config, Source::Mnemonic(&candidate), Some(&passphrase)).unwrap();
+    assert_eq!(wallets[0].passphrase, Some(passphrase));
+    assert!(search::find_address(&wallets[0], "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", 0..10).unwrap().is_none());
+
+    // The uncompressed keys double the p2pkh wallets
+    config.include_uncompressed = true;
+    let wallets = wallet_configs(&secp, &config, Source::Mnemonic(&candidate), None).unwrap();
+    assert_eq!(wallets.len(), 8);
+    assert_eq!(wallets.iter().filter(|wallet| wallet.uncompressed).count(), 4);
+
+    // An account level key is only scanned under its own account
+    config.include_uncompressed = false;
+    let master = derivation::get_master_key(candidate.mnemonic.to_seed(""), Network::Bitcoin).unwrap();
+    let account = master.derive_priv(&secp, &DerivationPath::from_str("m/44'/0'/0'").unwrap()).unwrap();
+    let wallets = wallet_configs(&secp, &config, Source::Key(&account), None).unwrap();
+    assert_eq!(wallets.len(), 2);
+    assert!(wallets.iter().all(|wallet| wallet.account.account() == 0 && wallet.fingerprint.is_none()));
+    config.layout = Layout::Bip32;
+    assert!(matches!(
+        wallet_configs(&secp, &config, Source::Key(&account), None),
+        Err(HdiFinderError::InvalidArgument(_))
+    ));
+}
+
+/**
+ * Builds the wallets of the multisig wallet the seed is a cosigner of: one per
+ * account and chain, the key of the seed derived under its BIP48 path and the
+ * keys of the other cosigners under their account keys
+ */
+fn multisig_wallet_configs(
+    secp: &Secp256k1<All>,
+    config: &SearchConfig,
+    multisig: &Multisig,
+    root: &ExtendedPrivKey,
+    candidate: Option<&MnemonicCandidate>,
+    passphrase: Option<&PassphraseCandidate>,
+) -> Result<Vec<WalletConf>, HdiFinderError> {
+    let coin_type = derivation::coin_type_for(config.coin, config.network);
+    let mut wallet_configs: Vec<WalletConf> = Vec::new();
+    for account in config.accounts.clone() {
+        let account_path = multisig::account_path(coin_type, account, multisig.nested);
+        let account_xpub = if config.show_xpub {
+            Some(derivation::account_xpub(secp, root, &account_path, &Purpose::Custom(48))?)
+        } else {
+            None
+        };
+        for change in &config.chains {
+            let chain_path = account_path.child(ChildNumber::Normal { index: *change });
+            let chain_key = root.derive_priv(secp, &chain_path).map_err(|source| HdiFinderError::Derivation {
+                target: chain_path.to_string(),
+                source,
+            })?;
+            wallet_configs.push(WalletConf {
+                chain_key: if config.pubkey_only {
+                    ChainKey::Public(derivation::chain_pubkey(secp, &chain_key))
+                } else {
+                    ChainKey::Private(chain_key)
+                },
+                account: AccountHDPath::new(Purpose::Custom(48), coin_type, account),
+                change: *change,
+                network: config.network,
+                coin: config.coin,
+                passphrase: passphrase.cloned(),
+                mnemonic_line: candidate.and_then(|candidate| candidate.line),
+                mnemonic_fix: candidate.and_then(|candidate| candidate.fix.clone()),
+                watch_only: false,
+                bip85_child: candidate.and_then(|candidate| candidate.bip85_child),
+                chain_path: Some(chain_path),
+                account_label: None,
+                hardened_index: false,
+                uncompressed: false,
+                multisig: Some(Multisig {
+                    cosigners: multisig::chain_keys(secp, &multisig.cosigners, *change)?,
+                    ..multisig.clone()
+                }),
+                descriptor_key: None,
+                show_private: config.show_private,
+                account_xpub: account_xpub.clone(),
+                fingerprint: derivation::master_fingerprint(secp, root),
+                sign_message: None,
+                sign_message_bip322: None,
+                sign_psbt: false,
+            });
+        }
+    }
+    Ok(wallet_configs)
+}
+
+/**
+ * Derives the BIP85 child mnemonics scanned in place of the master seed they
+ * are derived from, the passphrase applying to the master seed
+ */
+pub fn bip85_children(secp: &Secp256k1<All>, config: &SearchConfig, source: Source, count: u32) -> Result<Vec<MnemonicCandidate>, HdiFinderError> {
+    let master = match source {
+        Source::Mnemonic(candidate) => {
+            derivation::get_master_key(candidate.mnemonic.to_seed(config.passphrase.as_str()), config.network)?
+        }
+        Source::Seed(seed) => derivation::get_master_key(*seed, config.network)?,
+        Source::Key(key) if key.depth == 0 => *key,
+        Source::Key(_) => {
+            return Err(HdiFinderError::InvalidArgument(
+                "--bip85-children derives the child mnemonics from a master key, not from an account level key".to_string(),
+            ))
+        }
+        Source::PublicKey(_) | Source::Electrum(..) => {
+            return Err(HdiFinderError::InvalidArgument(
+                "--bip85-children derives the child mnemonics from a BIP32 master private key".to_string(),
+            ))
+        }
+    };
+    (0..count)
+        .map(|index| {
+            let mnemonic = derivation::bip85_mnemonic(secp, &master, config.bip85_words, index)?;
+            Ok(MnemonicCandidate { bip85_child: Some(index), ..MnemonicCandidate::new(mnemonic, None) })
+        })
+        .collect()
+}
+
+#[test]
+fn test_bip85_children() {
+    let secp = Secp256k1::new();
+    let config = search::test_search_config(0, 10, 10);
+    let master = ExtendedPrivKey::from_str("xprv9s21ZrQH143K2LBWUUQRFXhucrQqBpKdRRxNVq2zBqsx8HVqFk2uYo8kmbaLLHRdqtQpUm98uKfu3vca1LqdGhUtyoFnCNkfmXRyPXLjbKb").unwrap();
+    let children = bip85_children(&secp, &config, Source::Key(&master), 2).unwrap();
+    assert_eq!(children.len(), 2);
+    assert_eq!(children[0].mnemonic.to_string(), "girl mad pet galaxy egg matter matrix prison refuse sense ordinary nose");
+    assert_eq!(children[1].bip85_child, Some(1));
+    assert_ne!(children[0].mnemonic, children[1].mnemonic);
+
+    // The children are scanned without the passphrase of the master seed
+    let mut config = config;
+    config.passphrase = "secret".to_string();
+    let with_passphrase = wallet_configs(&secp, &config, Source::Mnemonic(&children[0]), None).unwrap();
+    config.passphrase = String::new();
+    let without_passphrase = wallet_configs(&secp, &config, Source::Mnemonic(&children[0]), None).unwrap();
+    assert_eq!(with_passphrase[0].fingerprint, without_passphrase[0].fingerprint);
+
+    let account = master.derive_priv(&secp, &DerivationPath::from_str("m/44'/0'/0'").unwrap()).unwrap();
+    assert!(bip85_children(&secp, &config, Source::Key(&account), 2).is_err());
+    let public_key = ExtendedPubKey::from_private(&secp, &account);
+    assert!(bip85_children(&secp, &config, Source::PublicKey(&public_key), 2).is_err());
+}
+
+/**
+ * Builds the wallets scanned for an extended public key, deriving public keys
+ * only. The purpose and coin levels being above the key, it is scanned under
+ * each purpose.
+ */
+fn watch_only_wallet_configs(secp: &Secp256k1<All>, config: &SearchConfig, key: &ExtendedPubKey) -> Result<Vec<WalletConf>, HdiFinderError> {
+    let (accounts, chains) = if key.depth == derivation::CHAIN_DEPTH {
+        (config.accounts.clone(), vec![derivation::child_index(key.child_number)])
+    } else {
+        let account = derivation::child_index(key.child_number);
+        (account..=account, config.chains.clone())
+    };
+    let mut wallet_configs: Vec<WalletConf> = Vec::new();
+    for account in accounts {
+        for purpose in &config.purposes {
+            for change in &chains {
+                let account = AccountHDPath::new(purpose.clone(), derivation::coin_type_for(config.coin, config.network), account);
+                let chain_key = derivation::xpub_chain_key(secp, key, *change)?;
+                let key_formats: &[bool] = if config.include_uncompressed && *purpose == Purpose::Pubkey { &[false, true] } else { &[false] };
+                for uncompressed in key_formats {
+                    wallet_configs.push(WalletConf {
+                        chain_key: ChainKey::Public(chain_key),
+                        change: *change,
+                        network: config.network,
+                        coin: config.coin,
+                        passphrase: None,
+                        mnemonic_line: None,
+                        mnemonic_fix: None,
+                        watch_only: true,
+                        bip85_child: None,
+                        chain_path: None,
+                        account_label: None,
+                        hardened_index: config.hardened_index,
+                        uncompressed: *uncompressed,
+                        multisig: None,
+                        descriptor_key: if config.emit_descriptor && !uncompressed {
+                            Some(self::watch_only_key_expression(key, *change))
+                        } else {
+                            None
+                        },
+                        show_private: false,
+                        account_xpub: if config.show_xpub {
+                            self::watch_only_account_xpub(key, &account)
+                        } else {
+                            None
+                        },
+                        fingerprint: None,
+                        sign_message: None,
+                        sign_message_bip322: None,
+                        sign_psbt: false,
+                        account: account.clone(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(wallet_configs)
+}
+
+#[test]
+fn test_watch_only_wallet_configs() {
+    let secp = Secp256k1::new();
+    let mut config = search::test_search_config(0, 10, 10);
+    config.chains = vec![0, 1];
+    config.emit_descriptor = true;
+    config.show_xpub = true;
+    let mnemonic = derivation::get_mnemonic("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb", None).unwrap();
+    let master = derivation::get_master_key(mnemonic.to_seed(""), Network::Bitcoin).unwrap();
+    let account = master.derive_priv(&secp, &DerivationPath::from_str("m/44'/0'/0'").unwrap()).unwrap();
+    let account_xpub = ExtendedPubKey::from_private(&secp, &account);
+
+    let wallets = wallet_configs(&secp, &config, Source::PublicKey(&account_xpub), None).unwrap();
+    assert_eq!(wallets.len(), 2);
+    assert!(wallets.iter().all(|wallet| wallet.watch_only && matches!(wallet.chain_key, ChainKey::Public(_))));
+    assert_eq!(wallets[1].descriptor_key, Some(format!("{}/1", account_xpub)));
+    assert_eq!(wallets[0].account_xpub.as_ref().map(|xpub| xpub.xpub.clone()), Some(account_xpub.to_string()));
+    let found = search::find_address(&wallets[0], "14odE5c1eXuphR24fXMtzDfsXMLCmFTFgK", 0..10).unwrap().unwrap();
+    assert_eq!(found.index, 5);
+
+    // A chain level key is the chain itself, under each account of the range
+    config.accounts = 0..=1;
+    let chain_xpub = ExtendedPubKey::from_private(&secp, &account.derive_priv(&secp, &[ChildNumber::Normal { index: 0 }]).unwrap());
+    let wallets = wallet_configs(&secp, &config, Source::PublicKey(&chain_xpub), None).unwrap();
+    assert_eq!(wallets.len(), 2);
+    assert!(wallets.iter().all(|wallet| wallet.change == 0 && wallet.account_xpub.is_none()));
+    assert_eq!(wallets[0].descriptor_key, Some(chain_xpub.to_string()));
+
+    config.layout = Layout::Bip32;
+    assert!(wallet_configs(&secp, &config, Source::PublicKey(&chain_xpub), None).is_err());
+}
+
+/**
+ * Builds the key expression of a chain below an extended public key. The
+ * origin of the key is unknown, and a chain level key is the chain itself.
+ */
+fn watch_only_key_expression(key: &ExtendedPubKey, change: u32) -> String {
+    if key.depth == derivation::CHAIN_DEPTH {
+        key.to_string()
+    } else {
+        format!("{}/{}", key, change)
+    }
+}
+
+/**
+ * Gives the extended public key of the account of a watch-only scan, the
+ * key itself when it is an account level key. Neither the master fingerprint
+ * nor the account key of a chain level key are known.
+ */
+fn watch_only_account_xpub(key: &ExtendedPubKey, account: &AccountHDPath) -> Option<AccountXpub> {
+    if key.depth != derivation::ACCOUNT_DEPTH {
+        return None;
+    }
+    Some(AccountXpub {
+        path: DerivationPath::from(account).to_string(),
+        fingerprint: None,
+        xpub: key.to_string(),
+        slip132: slip132::from_bip32(key, account.purpose()),
+    })
+}
+
+/**
+ * Builds the wallets scanned for an Electrum seed, one per chain, under the
+ * paths and for the address type of the seed version
+ */
+fn electrum_wallet_configs(
+    secp: &Secp256k1<All>,
+    config: &SearchConfig,
+    phrase: &str,
+    version: SeedVersion,
+    passphrase: Option<&PassphraseCandidate>,
+) -> Result<Vec<WalletConf>, HdiFinderError> {
+    let seed = electrum::to_seed(phrase, passphrase.map_or(config.passphrase.as_str(), |candidate| candidate.passphrase.as_str()));
+    let master = derivation::get_master_key(seed, config.network)?;
+    let purpose = electrum::purpose(version)
+        .ok_or_else(|| HdiFinderError::Internal("two-factor Electrum seeds aren't supported".to_string()))?;
+    let mut wallet_configs: Vec<WalletConf> = Vec::new();
+    for change in &config.chains {
+        let path = electrum::chain_path(version, *change);
+        let chain_key = master.derive_priv(secp, &path).map_err(|source| HdiFinderError::Derivation {
+            target: path.to_string(),
+            source,
+        })?;
+        let account_path: DerivationPath = path.as_ref()[..path.as_ref().len() - 1].to_vec().into();
+        let descriptor_key = if config.emit_descriptor {
+            Some(descriptor::chain_key_expression(secp, &master, &account_path, *change, config.with_private)?)
+        } else {
+            None
+        };
+        wallet_configs.push(WalletConf {
+            chain_key: if config.pubkey_only {
+                ChainKey::Public(derivation::chain_pubkey(secp, &chain_key))
+            } else {
+                ChainKey::Private(chain_key)
+            },
+            account: AccountHDPath::new(purpose.clone(), derivation::coin_type_for(config.coin, config.network), 0),
+            change: *change,
+            network: config.network,
+            coin: config.coin,
+            passphrase: passphrase.cloned(),
+            mnemonic_line: None,
+            mnemonic_fix: None,
+            watch_only: false,
+            bip85_child: None,
+            chain_path: Some(path),
+            account_label: None,
+            hardened_index: config.hardened_index,
+            uncompressed: false,
+            multisig: None,
+            descriptor_key,
+            show_private: config.show_private,
+            account_xpub: if config.show_xpub {
+                Some(derivation::account_xpub(secp, &master, &account_path, &purpose)?)
+            } else {
+                None
+            },
+            fingerprint: derivation::master_fingerprint(secp, &master),
+            sign_message: config.sign_message.clone(),
+            sign_message_bip322: config.sign_message_bip322.clone(),
+            sign_psbt: config.psbt.is_some(),
+        });
+    }
+    Ok(wallet_configs)
+}
+
+/**
+ * Pairs each source with each passphrase candidate, or with the passphrase of
+ * the config alone when no candidate is given
+ */
+pub fn combinations<'a>(config: &'a SearchConfig, sources: &[Source<'a>]) -> Vec<(Source<'a>, Option<&'a PassphraseCandidate>)> {
+    let passphrases: Vec<Option<&PassphraseCandidate>> = if config.passphrase_candidates.is_empty() {
+        vec![None]
+    } else {
+        config.passphrase_candidates.iter().map(Some).collect()
+    };
+    sources
+        .iter()
+        .flat_map(|source| passphrases.iter().map(move |passphrase| (*source, *passphrase)))
+        .collect()
+}
+
+/**
+ * Builds the wallets of every combination of a source and a passphrase.
+ * Stretching the seed is the costly part of trying a candidate, so the
+ * candidates are spread over the threads of the pool.
+ */
+pub fn scan_wallet_configs(
+    pool: &ThreadPool,
+    secp: &Secp256k1<All>,
+    config: &SearchConfig,
+    combinations: &[(Source, Option<&PassphraseCandidate>)],
+) -> Result<Vec<WalletConf>, HdiFinderError> {
+    Ok(pool
+        .install(|| {
+            combinations
+                .par_iter()
+                .map(|(source, passphrase)| self::wallet_configs(secp, config, *source, *passphrase))
+                .collect::<Result<Vec<Vec<WalletConf>>, HdiFinderError>>()
+        })?
+        .into_iter()
+        .flatten()
+        .collect())
+}
+
+#[test]
+fn test_scan_wallet_configs() {
+    let secp = Secp256k1::new();
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+    let mut config = search::test_search_config(0, 10, 10);
+    config.passphrase_candidates = ["", "secret", "Summer 2017"]
+        .iter()
+        .enumerate()
+        .map(|(line, passphrase)| PassphraseCandidate { line: line + 1, passphrase: passphrase.to_string() })
+        .collect();
+    let phrases = [
+        "erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb",
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+    ];
+    let candidates: Vec<MnemonicCandidate> = phrases
+        .iter()
+        .map(|phrase| MnemonicCandidate::new(derivation::get_mnemonic(phrase, None).unwrap(), None))
+        .collect();
+    let sources: Vec<Source> = candidates.iter().map(Source::Mnemonic).collect();
+    let combinations = combinations(&config, &sources);
+    assert_eq!(combinations.len(), 6);
+
+    // The wallets keep the order of the combinations
+    let wallets = scan_wallet_configs(&pool, &secp, &config, &combinations).unwrap();
+    assert_eq!(wallets.len(), 6);
+    let lines: Vec<usize> = wallets.iter().filter_map(|wallet| wallet.passphrase.as_ref().map(|candidate| candidate.line)).collect();
+    assert_eq!(lines, vec![1, 2, 3, 1, 2, 3]);
+    assert_eq!(wallets[0].fingerprint, derivation::master_fingerprint(&secp, &derivation::get_master_key(candidates[0].mnemonic.to_seed(""), Network::Bitcoin).unwrap()));
+
+    config.passphrase_candidates.clear();
+    assert_eq!(self::combinations(&config, &sources).len(), 2);
+}
+
+/**
+ * Gives the fingerprint of the scan saved along with its state file: the
+ * master keys of every source and the passphrases tried, along with the paths
+ * of the wallets, so that a state file only resumes the scan it was saved by
+ */
+pub fn state_fingerprint(secp: &Secp256k1<All>, config: &SearchConfig, sources: &[Source], wallet_configs: &[WalletConf]) -> Result<String, HdiFinderError> {
+    let paths: Vec<String> = wallet_configs
+        .iter()
+        // The format of the standard paths is kept for the state files saved before
+        .map(|wallet_config| match &wallet_config.chain_path {
+            Some(path) => path.to_string(),
+            None => format!("{}/{}", wallet_config.account, wallet_config.change),
+        })
+        // A hardened scan doesn't resume the normal one of the same paths
+        .map(|path| if config.hardened_index { format!("{}/*'", path) } else { path })
+        .collect();
+    let key_fingerprints = sources
+        .iter()
+        .map(|source| {
+            let key_fingerprint = match *source {
+                Source::Mnemonic(candidate) => {
+                    derivation::get_master_key(candidate.mnemonic.to_seed(&config.passphrase), config.network)?.fingerprint(secp)
+                }
+                Source::Seed(seed) => derivation::get_master_key(*seed, config.network)?.fingerprint(secp),
+                Source::Key(key) => key.fingerprint(secp),
+                Source::PublicKey(key) => key.fingerprint(),
+                Source::Electrum(phrase, _) => {
+                    derivation::get_master_key(electrum::to_seed(phrase, &config.passphrase), config.network)?.fingerprint(secp)
+                }
+            };
+            Ok(key_fingerprint.to_string())
+        })
+        .collect::<Result<Vec<String>, HdiFinderError>>()?;
+    let passphrases: Vec<&str> = if config.passphrase_candidates.is_empty() {
+        vec![config.passphrase.as_str()]
+    } else {
+        config.passphrase_candidates.iter().map(|candidate| candidate.passphrase.as_str()).collect()
+    };
+    Ok(state::fingerprint(&key_fingerprints, &passphrases, &paths, config.network, config.start))
+}
+
+#[test]
+fn test_state_fingerprint() {
+    let secp = Secp256k1::new();
+    let mut config = search::test_search_config(0, 10, 10);
+    let mnemonic = derivation::get_mnemonic("erupt quit sphere taxi air decade vote mixed life elevator mammal search empower rabbit barely indoor crush grid slide correct scatter deal tenant verb", None).unwrap();
+    let candidate = MnemonicCandidate::new(mnemonic, None);
+    let sources = vec![Source::Mnemonic(&candidate)];
+    let wallets = wallet_configs(&secp, &config, sources[0], None).unwrap();
+    let reference = state_fingerprint(&secp, &config, &sources, &wallets).unwrap();
+    assert_eq!(
+        reference,
+        state::fingerprint(&["67b4602d".to_string()], &[""], &[format!("{}/0", wallets[0].account)], Network::Bitcoin, 0)
+    );
+
+    config.hardened_index = true;
+    assert_ne!(reference, state_fingerprint(&secp, &config, &sources, &wallets).unwrap());
+    config.hardened_index = false;
+    config.passphrase = "secret".to_string();
+    assert_ne!(reference, state_fingerprint(&secp, &config, &sources, &wallets).unwrap());
+}